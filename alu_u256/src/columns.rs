@@ -0,0 +1,82 @@
+use alloc::vec::Vec;
+use core::borrow::{Borrow, BorrowMut};
+use core::mem::{size_of, transmute};
+use valida_bigint::{FieldOpCols, Limbs, LtCols, NUM_LIMBS};
+use valida_derive::AlignedBorrow;
+use valida_machine::Word;
+use valida_util::indices_arr;
+
+/// Each row of the trace is one operation on 256-bit integers, split into bytes with the
+/// least significant first.
+#[derive(AlignedBorrow)]
+pub struct U256Cols<T> {
+    /// The operation, or none of them for padding rows
+    pub is_add: T,
+    pub is_mul: T,
+    pub is_modmul: T,
+
+    /// CPU clock of the instruction
+    pub clk: T,
+    /// Addresses of the operands, as seen by the CPU
+    pub x_addr: Word<T>,
+    pub y_addr: Word<T>,
+    /// The same addresses, as field elements
+    pub x_ptr: T,
+    pub y_ptr: T,
+
+    /// The operands, and the modulus of a modular multiplication
+    pub x: Limbs<T>,
+    pub y: Limbs<T>,
+    pub m: Limbs<T>,
+
+    /// Witnessed output, which is written over `x`
+    pub output: Limbs<T>,
+
+    /// The carry out of each limb of an addition or a multiplication, split into bytes.
+    /// The last carry of an addition is the carry out of the sum.
+    pub carry_low: [T; NUM_LIMBS],
+    pub carry_high: [T; NUM_LIMBS],
+
+    /// Whether the modulus is zero, which makes the output of a modular multiplication zero
+    pub m_is_zero: T,
+    /// The operands reduced modulo a nonzero `m`, as the sum of each with zero
+    pub x_mod: FieldOpCols<T>,
+    pub y_mod: FieldOpCols<T>,
+    /// The product of the reduced operands modulo `m`, which is less than `m`
+    pub modmul: FieldOpCols<T>,
+    pub modmul_lt: LtCols<T>,
+
+    /// The value written to the destination operand: the carry of an addition, and the
+    /// address of the output otherwise
+    pub dst: Word<T>,
+}
+
+impl<T: Copy> U256Cols<T> {
+    /// The columns that hold bytes in every row.
+    pub fn range_checked(&self) -> Vec<T> {
+        self.output
+            .iter()
+            .chain(&self.carry_low)
+            .chain(&self.carry_high)
+            .copied()
+            .collect()
+    }
+
+    /// The columns that hold bytes in modular multiplications.
+    pub fn modmul_range_checked(&self) -> Vec<T> {
+        self.x_mod
+            .range_checked()
+            .chain(self.y_mod.range_checked())
+            .chain(self.modmul.range_checked())
+            .chain([self.modmul_lt.diff])
+            .collect()
+    }
+}
+
+pub const NUM_U256_COLS: usize = size_of::<U256Cols<u8>>();
+pub const U256_COL_MAP: U256Cols<usize> = make_col_map();
+
+const fn make_col_map() -> U256Cols<usize> {
+    let indices_arr = indices_arr::<NUM_U256_COLS>();
+    unsafe { transmute::<[usize; NUM_U256_COLS], U256Cols<usize>>(indices_arr) }
+}
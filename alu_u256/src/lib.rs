@@ -0,0 +1,388 @@
+#![no_std]
+
+extern crate alloc;
+
+use crate::columns::{U256Cols, NUM_U256_COLS, U256_COL_MAP};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::transmute;
+use valida_bigint::{range_check_values, word, FieldOperation, Limbs, NUM_LIMBS, NUM_WORDS, U256};
+use valida_bus::{MachineWithGeneralBus, MachineWithMemBus, MachineWithRangeBus8};
+use valida_cpu::MachineWithCpuChip;
+use valida_machine::{instructions, Chip, Instruction, Interaction, Operands, Word};
+use valida_opcodes::{U256ADD, U256MODMUL, U256MUL};
+use valida_range::MachineWithRangeChip;
+
+use p3_air::VirtualPairCol;
+use p3_field::{AbstractField, Field, PrimeField32};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_maybe_rayon::prelude::*;
+use valida_machine::StarkConfig;
+
+pub mod columns;
+pub mod stark;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Operation {
+    Add,
+    Mul,
+    /// Multiplication modulo the given modulus
+    ModMul(U256),
+}
+
+/// An operation on 256-bit integers, as executed by a `U256*` instruction.
+#[derive(Clone, Debug)]
+pub struct U256Operation {
+    pub operation: Operation,
+    pub clk: u32,
+    /// Address of the first operand, which is overwritten by the output
+    pub x_addr: Word<u8>,
+    /// Address of the second operand, followed by the modulus if there is one
+    pub y_addr: Word<u8>,
+    pub x: U256,
+    pub y: U256,
+}
+
+#[derive(Default)]
+pub struct U256Chip {
+    pub operations: Vec<U256Operation>,
+}
+
+impl<M, SC> Chip<M, SC> for U256Chip
+where
+    M: MachineWithGeneralBus<SC::Val> + MachineWithMemBus<SC::Val> + MachineWithRangeBus8<SC::Val>,
+    SC: StarkConfig,
+{
+    fn generate_trace(&self, _machine: &M) -> RowMajorMatrix<SC::Val> {
+        let num_rows = self.operations.len().next_power_of_two();
+        let rows = (0..num_rows)
+            .into_par_iter()
+            .map(|i| self.op_to_row(self.operations.get(i)))
+            .collect::<Vec<_>>();
+        RowMajorMatrix::new(rows.into_iter().flatten().collect(), NUM_U256_COLS)
+    }
+
+    fn global_sends(&self, machine: &M) -> Vec<Interaction<SC::Val>> {
+        let clk = VirtualPairCol::single_main(U256_COL_MAP.clk);
+        let is_static_initial = VirtualPairCol::constant(SC::Val::zero());
+        let is_real = VirtualPairCol::sum_main(vec![
+            U256_COL_MAP.is_add,
+            U256_COL_MAP.is_mul,
+            U256_COL_MAP.is_modmul,
+        ]);
+        let is_modmul = VirtualPairCol::single_main(U256_COL_MAP.is_modmul);
+        let memory = |is_read: bool,
+                      ptr: usize,
+                      offset: usize,
+                      limbs: &Limbs<usize>,
+                      count: &VirtualPairCol<SC::Val>| {
+            let mut sends = vec![];
+            for i in 0..NUM_WORDS {
+                let addr = VirtualPairCol::new_main(
+                    vec![(ptr, SC::Val::one())],
+                    SC::Val::from_canonical_usize(offset + 4 * i),
+                );
+                let mut fields = vec![
+                    VirtualPairCol::constant(SC::Val::from_bool(is_read)),
+                    clk.clone(),
+                    addr,
+                    is_static_initial.clone(),
+                ];
+                fields.extend(word(limbs, i).0.map(VirtualPairCol::single_main));
+                sends.push(Interaction {
+                    fields,
+                    count: count.clone(),
+                    argument_index: machine.mem_bus(),
+                });
+            }
+            sends
+        };
+
+        let mut sends = vec![];
+        sends.extend(memory(
+            true,
+            U256_COL_MAP.x_ptr,
+            0,
+            &U256_COL_MAP.x,
+            &is_real,
+        ));
+        sends.extend(memory(
+            true,
+            U256_COL_MAP.y_ptr,
+            0,
+            &U256_COL_MAP.y,
+            &is_real,
+        ));
+        sends.extend(memory(
+            true,
+            U256_COL_MAP.y_ptr,
+            NUM_LIMBS,
+            &U256_COL_MAP.m,
+            &is_modmul,
+        ));
+        sends.extend(memory(
+            false,
+            U256_COL_MAP.x_ptr,
+            0,
+            &U256_COL_MAP.output,
+            &is_real,
+        ));
+
+        let range_checked = [
+            (U256_COL_MAP.range_checked(), is_real),
+            (U256_COL_MAP.modmul_range_checked(), is_modmul),
+        ];
+        for (cols, count) in range_checked {
+            for col in cols {
+                sends.push(Interaction {
+                    fields: vec![VirtualPairCol::single_main(col)],
+                    count: count.clone(),
+                    argument_index: machine.range_bus(),
+                });
+            }
+        }
+        sends
+    }
+
+    fn global_receives(&self, machine: &M) -> Vec<Interaction<SC::Val>> {
+        let opcode = VirtualPairCol::new_main(
+            vec![
+                (U256_COL_MAP.is_add, SC::Val::from_canonical_u32(U256ADD)),
+                (U256_COL_MAP.is_mul, SC::Val::from_canonical_u32(U256MUL)),
+                (
+                    U256_COL_MAP.is_modmul,
+                    SC::Val::from_canonical_u32(U256MODMUL),
+                ),
+            ],
+            SC::Val::zero(),
+        );
+        let x_addr = U256_COL_MAP.x_addr.0.map(VirtualPairCol::single_main);
+        let y_addr = U256_COL_MAP.y_addr.0.map(VirtualPairCol::single_main);
+        let dst = U256_COL_MAP.dst.0.map(VirtualPairCol::single_main);
+        let clk = VirtualPairCol::single_main(U256_COL_MAP.clk);
+
+        let mut fields = vec![opcode];
+        fields.extend(x_addr);
+        fields.extend(y_addr);
+        fields.extend(dst);
+        fields.push(clk);
+
+        let receive = Interaction {
+            fields,
+            count: VirtualPairCol::sum_main(vec![
+                U256_COL_MAP.is_add,
+                U256_COL_MAP.is_mul,
+                U256_COL_MAP.is_modmul,
+            ]),
+            argument_index: machine.general_bus(),
+        };
+        vec![receive]
+    }
+}
+
+impl U256Chip {
+    /// The row of an operation. Padding rows are all zero.
+    fn op_to_row<F: PrimeField32>(&self, op: Option<&U256Operation>) -> [F; NUM_U256_COLS] {
+        let mut row = [F::zero(); NUM_U256_COLS];
+        let cols: &mut U256Cols<F> = unsafe { transmute(&mut row) };
+        if let Some(op) = op {
+            populate(cols, op);
+        }
+        row
+    }
+}
+
+/// Fills in the row of an operation, and returns its output along with the value written
+/// to the destination operand.
+fn populate<F: PrimeField32>(cols: &mut U256Cols<F>, op: &U256Operation) -> (U256, Word<u8>) {
+    cols.clk = F::from_canonical_u32(op.clk);
+    cols.x_addr = op.x_addr.transform(F::from_canonical_u8);
+    cols.y_addr = op.y_addr.transform(F::from_canonical_u8);
+    // The row is filled in as the instruction runs, so an address beyond the field must
+    // not panic. It is reduced the same way as the constraint on its bytes.
+    cols.x_ptr = F::from_wrapped_u32(op.x_addr.into());
+    cols.y_ptr = F::from_wrapped_u32(op.y_addr.into());
+    cols.x = op.x.to_le_bytes().map(F::from_canonical_u8);
+    cols.y = op.y.to_le_bytes().map(F::from_canonical_u8);
+
+    let x = op.x.to_le_bytes();
+    let y = op.y.to_le_bytes();
+    let mut carry_limbs = |column: &dyn Fn(usize) -> u32| {
+        let mut output = [0; NUM_LIMBS];
+        let mut carry = 0;
+        for (k, limb) in output.iter_mut().enumerate() {
+            let total = column(k) + carry;
+            *limb = total as u8;
+            carry = total >> 8;
+            cols.carry_low[k] = F::from_canonical_u32(carry & 0xFF);
+            cols.carry_high[k] = F::from_canonical_u32(carry >> 8);
+        }
+        (U256::from_le_bytes(output), carry)
+    };
+
+    let (output, dst) = match op.operation {
+        Operation::Add => {
+            cols.is_add = F::one();
+            let (output, carry) = carry_limbs(&|k| x[k] as u32 + y[k] as u32);
+            (output, Word::from(carry))
+        }
+        Operation::Mul => {
+            cols.is_mul = F::one();
+            let (output, _) =
+                carry_limbs(&|k| (0..=k).map(|i| x[i] as u32 * y[k - i] as u32).sum());
+            (output, op.x_addr)
+        }
+        Operation::ModMul(m) => {
+            cols.is_modmul = F::one();
+            cols.m = m.to_le_bytes().map(F::from_canonical_u8);
+            let output = if m.is_zero() {
+                cols.m_is_zero = F::one();
+                U256::ZERO
+            } else {
+                let x = cols
+                    .x_mod
+                    .populate(op.x, U256::ZERO, m, FieldOperation::Add);
+                let y = cols
+                    .y_mod
+                    .populate(op.y, U256::ZERO, m, FieldOperation::Add);
+                let output = cols.modmul.populate(x, y, m, FieldOperation::Mul);
+                cols.modmul_lt.populate(output, m);
+                output
+            };
+            (output, op.x_addr)
+        }
+    };
+    cols.output = output.to_le_bytes().map(F::from_canonical_u8);
+    cols.dst = dst.transform(F::from_canonical_u8);
+    (output, dst)
+}
+
+pub trait MachineWithU256Chip<F: Field>: MachineWithCpuChip<F> {
+    fn u256(&self) -> &U256Chip;
+    fn u256_mut(&mut self) -> &mut U256Chip;
+}
+
+instructions!(
+    U256AddInstruction,
+    U256MulInstruction,
+    U256ModMulInstruction
+);
+
+impl<M, F> Instruction<M, F> for U256AddInstruction
+where
+    M: MachineWithU256Chip<F> + MachineWithRangeChip<F, 256>,
+    F: PrimeField32,
+{
+    const OPCODE: u32 = U256ADD;
+
+    /// `U256ADD a(fp), b(fp), c(fp)` adds the integer at the address in `c(fp)` to the
+    /// integer at the address in `b(fp)`, which it overwrites with the sum modulo `2^256`.
+    /// The carry out of the sum is written to `a(fp)`. An integer takes up 8 cells, with the
+    /// least significant first.
+    fn execute(state: &mut M, ops: Operands<i32>) {
+        execute(state, <Self as Instruction<M, F>>::OPCODE, ops);
+    }
+}
+
+impl<M, F> Instruction<M, F> for U256MulInstruction
+where
+    M: MachineWithU256Chip<F> + MachineWithRangeChip<F, 256>,
+    F: PrimeField32,
+{
+    const OPCODE: u32 = U256MUL;
+
+    /// `U256MUL a(fp), b(fp), c(fp)` multiplies the integer at the address in `b(fp)` by
+    /// the integer at the address in `c(fp)`, and overwrites the first with the product
+    /// modulo `2^256`. The address in `b(fp)` is written to `a(fp)`.
+    fn execute(state: &mut M, ops: Operands<i32>) {
+        execute(state, <Self as Instruction<M, F>>::OPCODE, ops);
+    }
+}
+
+impl<M, F> Instruction<M, F> for U256ModMulInstruction
+where
+    M: MachineWithU256Chip<F> + MachineWithRangeChip<F, 256>,
+    F: PrimeField32,
+{
+    const OPCODE: u32 = U256MODMUL;
+
+    /// `U256MODMUL a(fp), b(fp), c(fp)` multiplies the integer at the address in `b(fp)` by
+    /// the integer at the address in `c(fp)`, modulo the integer in the 8 cells that follow
+    /// the second, and overwrites the first with the result. The address in `b(fp)` is
+    /// written to `a(fp)`. As with the EVM's `MULMOD`, the operands need not be less than
+    /// the modulus, and the result is zero for a zero modulus.
+    fn execute(state: &mut M, ops: Operands<i32>) {
+        execute(state, <Self as Instruction<M, F>>::OPCODE, ops);
+    }
+}
+
+fn execute<M, F>(state: &mut M, opcode: u32, ops: Operands<i32>)
+where
+    M: MachineWithU256Chip<F> + MachineWithRangeChip<F, 256>,
+    F: PrimeField32,
+{
+    let clk = state.cpu().clock;
+    let pc = state.cpu().pc;
+    let fp = state.cpu().fp;
+    let read_addr_1 = (fp as i32 + ops.b()) as u32;
+    let read_addr_2 = (fp as i32 + ops.c()) as u32;
+    let write_addr = (fp as i32 + ops.a()) as u32;
+
+    let x_addr = state
+        .mem_mut()
+        .read(clk, read_addr_1, true, pc, opcode, 0, "");
+    let y_addr = state
+        .mem_mut()
+        .read(clk, read_addr_2, true, pc, opcode, 1, "");
+
+    let x = read_u256(state, clk, x_addr.into());
+    let y = read_u256(state, clk, y_addr.into());
+    let operation = match opcode {
+        U256ADD => Operation::Add,
+        U256MUL => Operation::Mul,
+        _ => {
+            let y_base: u32 = y_addr.into();
+            let m = read_u256(state, clk, y_base.wrapping_add(4 * NUM_WORDS as u32));
+            Operation::ModMul(m)
+        }
+    };
+    let op = U256Operation {
+        operation,
+        clk,
+        x_addr,
+        y_addr,
+        x,
+        y,
+    };
+
+    let mut row = [F::zero(); NUM_U256_COLS];
+    let cols: &mut U256Cols<F> = unsafe { transmute(&mut row) };
+    let (output, dst) = populate(cols, &op);
+    range_check_values(state, cols.range_checked());
+    if let Operation::ModMul(_) = operation {
+        range_check_values(state, cols.modmul_range_checked());
+    }
+
+    let base: u32 = x_addr.into();
+    for (i, word) in output.to_words().into_iter().enumerate() {
+        state
+            .mem_mut()
+            .coprocessor_write(clk, base.wrapping_add(4 * i as u32), word);
+    }
+    state.mem_mut().write(clk, write_addr, dst, true);
+
+    state.u256_mut().operations.push(op);
+    state.cpu_mut().push_bus_op_with_memory(None, opcode, ops);
+}
+
+fn read_u256<M, F>(state: &mut M, clk: u32, addr: u32) -> U256
+where
+    M: MachineWithCpuChip<F>,
+    F: Field,
+{
+    U256::from_words(core::array::from_fn(|i| {
+        state
+            .mem_mut()
+            .coprocessor_read(clk, addr.wrapping_add(4 * i as u32))
+    }))
+}
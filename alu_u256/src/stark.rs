@@ -0,0 +1,127 @@
+use crate::columns::{U256Cols, NUM_U256_COLS};
+use crate::U256Chip;
+use core::borrow::Borrow;
+use valida_bigint::{constant_limbs, FieldOperation, Limbs, NUM_LIMBS, U256};
+use valida_machine::Word;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, PrimeField};
+use p3_matrix::MatrixRowSlices;
+
+impl<F> BaseAir<F> for U256Chip {
+    fn width(&self) -> usize {
+        NUM_U256_COLS
+    }
+}
+
+impl<F, AB> Air<AB> for U256Chip
+where
+    F: PrimeField,
+    AB: AirBuilder<F = F>,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local: &U256Cols<AB::Var> = main.row_slice(0).borrow();
+
+        let base = [1 << 24, 1 << 16, 1 << 8, 1].map(AB::Expr::from_canonical_u32);
+        let limb_base = AB::Expr::from_canonical_u32(1 << 8);
+
+        let is_add: AB::Expr = local.is_add.into();
+        builder.assert_bool(local.is_add);
+        builder.assert_bool(local.is_mul);
+        builder.assert_bool(local.is_modmul);
+        builder.assert_bool(is_add.clone() + local.is_mul + local.is_modmul);
+        builder.assert_eq(local.x_ptr, reduce::<AB>(&base, local.x_addr));
+        builder.assert_eq(local.y_ptr, reduce::<AB>(&base, local.y_addr));
+
+        let carry: [AB::Expr; NUM_LIMBS] = core::array::from_fn(|i| {
+            let high: AB::Expr = local.carry_high[i].into();
+            high * limb_base.clone() + local.carry_low[i]
+        });
+        let carry_in = |i: usize| {
+            if i == 0 {
+                AB::Expr::zero()
+            } else {
+                carry[i - 1].clone()
+            }
+        };
+
+        // The sum, with a carry from each limb to the next as in `Add32Chip`. The limbs
+        // are bytes, so the carries are bits.
+        for (i, carry_out) in carry.iter().enumerate() {
+            builder.when(local.is_add).assert_eq(
+                local.x[i] + local.y[i] + carry_in(i),
+                carry_out.clone() * limb_base.clone() + local.output[i],
+            );
+        }
+
+        // The low half of the product, summing the products of limbs of the same weight
+        // and carrying the excess to the next limb.
+        for (k, carry_out) in carry.iter().enumerate() {
+            let products: AB::Expr = (0..=k).map(|i| local.x[i] * local.y[k - i]).sum();
+            builder.when(local.is_mul).assert_eq(
+                products + carry_in(k),
+                carry_out.clone() * limb_base.clone() + local.output[k],
+            );
+        }
+
+        // A zero modulus makes the output zero.
+        builder.assert_bool(local.m_is_zero);
+        builder.when(local.m_is_zero).assert_one(local.is_modmul);
+        for i in 0..NUM_LIMBS {
+            builder.when(local.m_is_zero).assert_zero(local.m[i]);
+            builder.when(local.m_is_zero).assert_zero(local.output[i]);
+        }
+
+        // Otherwise the operands are reduced, so that the quotient of their product fits
+        // in its limbs, and the output is the product modulo `m`. Since the output is less
+        // than `m`, the modulus is nonzero.
+        let is_modmul: AB::Expr = local.is_modmul.into();
+        let is_reduced = is_modmul - local.m_is_zero;
+        let limbs = |x: &Limbs<AB::Var>| -> Limbs<AB::Expr> { x.map(Into::into) };
+        let zero = constant_limbs(U256::ZERO);
+        let m = limbs(&local.m);
+        for (cols, value) in [(&local.x_mod, &local.x), (&local.y_mod, &local.y)] {
+            cols.eval(
+                &mut builder.when(is_reduced.clone()),
+                &limbs(value),
+                &zero,
+                &m,
+                FieldOperation::Add,
+            );
+        }
+        let result = limbs(&local.modmul.result);
+        local.modmul.eval(
+            &mut builder.when(is_reduced.clone()),
+            &limbs(&local.x_mod.result),
+            &limbs(&local.y_mod.result),
+            &m,
+            FieldOperation::Mul,
+        );
+        local
+            .modmul_lt
+            .eval(&mut builder.when(is_reduced.clone()), &result, &m);
+        for (output, result) in local.output.into_iter().zip(result) {
+            builder.when(is_reduced.clone()).assert_eq(output, result);
+        }
+
+        // An addition writes its carry to the destination, and the other operations write
+        // the address of the output.
+        let not_add = AB::Expr::one() - is_add.clone();
+        for i in 0..3 {
+            builder.assert_eq(local.dst[i], not_add.clone() * local.x_addr[i]);
+        }
+        builder.assert_eq(
+            local.dst[3],
+            is_add * local.carry_low[NUM_LIMBS - 1] + not_add * local.x_addr[3],
+        );
+    }
+}
+
+fn reduce<AB: AirBuilder>(base: &[AB::Expr], input: Word<AB::Var>) -> AB::Expr {
+    input
+        .into_iter()
+        .enumerate()
+        .map(|(i, x)| base[i].clone() * x)
+        .sum()
+}
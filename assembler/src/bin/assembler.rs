@@ -1,7 +1,7 @@
 use clap::{arg, command};
 use std::fs::File;
 use std::io::{self, Read, Write};
-use valida_assembler::assemble;
+use valida_assembler::{assemble_object, assemble_relocatable_object};
 
 fn main() {
     let matches = command!()
@@ -11,6 +11,12 @@ fn main() {
         .arg(arg!(
             -o --output <FILE> "The machine code output file"
         ))
+        .arg(arg!(
+            --elf "Write an ELF executable with the data sections, rather than only the code"
+        ))
+        .arg(arg!(
+            --relocatable "Write a relocatable ELF object for valida-ld to link"
+        ))
         .get_matches();
 
     // Read assembly code from input file, or from stdin if no file is specified
@@ -27,7 +33,22 @@ fn main() {
     }
 
     // Write machine code to file, or stdout if no file is specified
-    let machine_code = assemble(&assembly_code).expect("Failed to assemble code");
+    let result = match matches.get_flag("relocatable") {
+        true => assemble_relocatable_object(&assembly_code),
+        false => assemble_object(&assembly_code),
+    };
+    let machine_code = match result {
+        Ok(object) if matches.get_flag("relocatable") => object.to_relocatable_elf(),
+        Ok(object) if matches.get_flag("elf") => object.to_elf(),
+        Ok(object) => object.code,
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("error: {}\n", error);
+            }
+            eprintln!("Failed to assemble code");
+            std::process::exit(1);
+        }
+    };
     if let Some(filepath) = matches.get_one::<String>("output") {
         File::create(filepath)
             .expect("Failed to open output file")
@@ -0,0 +1,361 @@
+use crate::{DataSection, Object, RelocationTarget, Section};
+use byteorder::{LittleEndian, WriteBytesExt};
+
+const ELF_HEADER_SIZE: u16 = 52;
+const PROGRAM_HEADER_SIZE: u16 = 32;
+const SECTION_HEADER_SIZE: u16 = 40;
+const SYMBOL_SIZE: u32 = 16;
+const RELA_SIZE: u32 = 12;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+const SHF_WRITE: u32 = 1;
+const SHF_ALLOC: u32 = 2;
+const SHF_EXECINSTR: u32 = 4;
+const SHF_INFO_LINK: u32 = 0x40;
+const SHN_UNDEF: u16 = 0;
+const SHN_ABS: u16 = 0xfff1;
+
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+const STT_NOTYPE: u8 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_SECTION: u8 = 3;
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+
+/// The index of each section header, after the null header at index 0.
+const TEXT_INDEX: u16 = 1;
+const SYMTAB_INDEX: u32 = 5;
+const STRTAB_INDEX: u32 = 6;
+const SHSTRTAB_INDEX: u16 = 7;
+
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u32,
+    address: u32,
+    offset: u32,
+    size: u32,
+    link: u32,
+    info: u32,
+    alignment: u32,
+    entry_size: u32,
+}
+
+/// A string table, which starts with an empty string.
+struct StringTable(Vec<u8>);
+
+impl StringTable {
+    fn new() -> Self {
+        Self(vec![0])
+    }
+
+    fn add(&mut self, name: &str) -> u32 {
+        let offset = self.0.len() as u32;
+        self.0.extend(name.as_bytes());
+        self.0.push(0);
+        offset
+    }
+}
+
+/// The bytes of a data section as stored in the file. `valida_elf` reads each word into a
+/// memory cell most significant byte first, so the bytes of each word are reversed.
+fn section_contents(data: &DataSection) -> Vec<u8> {
+    let mut bytes = data.bytes.clone();
+    bytes.resize(bytes.len().next_multiple_of(4), 0);
+    for word in bytes.chunks_exact_mut(4) {
+        word.reverse();
+    }
+    bytes
+}
+
+fn pad(file: &mut Vec<u8>) {
+    file.resize(file.len().next_multiple_of(4), 0);
+}
+
+/// The index of a section's header.
+fn section_index(section: Section) -> u16 {
+    match section {
+        Section::Text => TEXT_INDEX,
+        Section::Rodata => TEXT_INDEX + 1,
+        Section::Data => TEXT_INDEX + 2,
+        Section::Bss => TEXT_INDEX + 3,
+        Section::Absolute => SHN_ABS,
+        Section::Undefined => SHN_UNDEF,
+    }
+}
+
+/// Write an object as a 32-bit little-endian ELF file. An executable has code at address 0,
+/// and every section that is loaded into memory also gets a `PT_LOAD` segment. A relocatable
+/// object instead has a symbol for each section, symbol values relative to their sections,
+/// and a `.rela` section for each section with relocations.
+pub(crate) fn write_elf(object: &Object, relocatable: bool) -> Vec<u8> {
+    let mut section_names = StringTable::new();
+    let mut headers = vec![SectionHeader {
+        name: 0,
+        kind: 0,
+        flags: 0,
+        address: 0,
+        offset: 0,
+        size: 0,
+        link: 0,
+        info: 0,
+        alignment: 0,
+        entry_size: 0,
+    }];
+    let text = object.code.clone();
+    let rodata = section_contents(&object.rodata);
+    let data = section_contents(&object.data);
+    let loaded = [
+        (".text", SHF_EXECINSTR, 0, &text),
+        (".rodata", 0, object.rodata.address, &rodata),
+        (".data", SHF_WRITE, object.data.address, &data),
+    ];
+    let segments = match relocatable {
+        true => 0,
+        false => {
+            loaded
+                .iter()
+                .filter(|(_, _, _, bytes)| !bytes.is_empty())
+                .count()
+                + !object.bss.bytes.is_empty() as usize
+        }
+    };
+    let base = |address: u32| if relocatable { 0 } else { address };
+
+    let mut file = vec![0; ELF_HEADER_SIZE as usize + segments * PROGRAM_HEADER_SIZE as usize];
+    for (name, flags, address, bytes) in loaded {
+        headers.push(SectionHeader {
+            name: section_names.add(name),
+            kind: SHT_PROGBITS,
+            flags: SHF_ALLOC | flags,
+            address: base(address),
+            offset: file.len() as u32,
+            size: bytes.len() as u32,
+            link: 0,
+            info: 0,
+            alignment: 4,
+            entry_size: 0,
+        });
+        file.extend(bytes);
+        pad(&mut file);
+    }
+    headers.push(SectionHeader {
+        name: section_names.add(".bss"),
+        kind: SHT_NOBITS,
+        flags: SHF_ALLOC | SHF_WRITE,
+        address: base(object.bss.address),
+        offset: file.len() as u32,
+        size: object.bss.bytes.len() as u32,
+        link: 0,
+        info: 0,
+        alignment: 4,
+        entry_size: 0,
+    });
+
+    // Local symbols must come before global ones
+    let mut symbols: Vec<_> = object.symbols.iter().collect();
+    symbols.sort_by_key(|symbol| symbol.global);
+    let mut symbol_names = StringTable::new();
+    let symtab_offset = file.len() as u32;
+    file.extend([0; SYMBOL_SIZE as usize]);
+    // Relocations refer to sections through their symbols
+    let sections = [Section::Text, Section::Rodata, Section::Data, Section::Bss];
+    let section_symbols = if relocatable { &sections[..] } else { &[] };
+    for &section in section_symbols {
+        file.extend([0; 12]);
+        file.push(STB_LOCAL << 4 | STT_SECTION);
+        file.push(0);
+        file.write_u16::<LittleEndian>(section_index(section))
+            .unwrap();
+    }
+    for symbol in &symbols {
+        let kind = match symbol.section {
+            Section::Text => STT_FUNC,
+            Section::Rodata | Section::Data | Section::Bss => STT_OBJECT,
+            Section::Absolute | Section::Undefined => STT_NOTYPE,
+        };
+        let value = match object.section(symbol.section) {
+            Some(data) if relocatable => symbol.value - data.address,
+            _ => symbol.value,
+        };
+        let binding = if symbol.global { STB_GLOBAL } else { STB_LOCAL };
+        file.write_u32::<LittleEndian>(symbol_names.add(&symbol.name))
+            .unwrap();
+        file.write_u32::<LittleEndian>(value).unwrap();
+        file.write_u32::<LittleEndian>(symbol.size).unwrap();
+        file.push(binding << 4 | kind);
+        file.push(0);
+        file.write_u16::<LittleEndian>(section_index(symbol.section))
+            .unwrap();
+    }
+    headers.push(SectionHeader {
+        name: section_names.add(".symtab"),
+        kind: SHT_SYMTAB,
+        flags: 0,
+        address: 0,
+        offset: symtab_offset,
+        size: file.len() as u32 - symtab_offset,
+        link: STRTAB_INDEX,
+        info: 1
+            + section_symbols.len() as u32
+            + symbols.iter().filter(|symbol| !symbol.global).count() as u32,
+        alignment: 4,
+        entry_size: SYMBOL_SIZE,
+    });
+    let strtab_name = section_names.add(".strtab");
+    let shstrtab_name = section_names.add(".shstrtab");
+    // Each section with relocations, and the name of its `.rela` section
+    let relocated: Vec<(Section, u32)> = [
+        (Section::Text, ".rela.text"),
+        (Section::Rodata, ".rela.rodata"),
+        (Section::Data, ".rela.data"),
+    ]
+    .into_iter()
+    .filter(|&(section, _)| {
+        object
+            .relocations
+            .iter()
+            .any(|relocation| relocation.section == section)
+    })
+    .map(|(section, name)| (section, section_names.add(name)))
+    .collect();
+    for (name, table) in [
+        (strtab_name, symbol_names.0),
+        (shstrtab_name, section_names.0.clone()),
+    ] {
+        headers.push(SectionHeader {
+            name,
+            kind: SHT_STRTAB,
+            flags: 0,
+            address: 0,
+            offset: file.len() as u32,
+            size: table.len() as u32,
+            link: 0,
+            info: 0,
+            alignment: 1,
+            entry_size: 0,
+        });
+        file.extend(table);
+    }
+    pad(&mut file);
+    for (section, name) in relocated {
+        let offset = file.len() as u32;
+        for relocation in &object.relocations {
+            if relocation.section != section {
+                continue;
+            }
+            let symbol = match &relocation.target {
+                // The section symbols are in the same order as the sections
+                RelocationTarget::Section(target) => section_index(*target) as usize,
+                RelocationTarget::Symbol(name) => {
+                    let index = symbols.iter().position(|symbol| symbol.name == *name);
+                    1 + section_symbols.len() + index.unwrap()
+                }
+            };
+            file.write_u32::<LittleEndian>(relocation.offset).unwrap();
+            file.write_u32::<LittleEndian>((symbol as u32) << 8 | relocation.kind.elf_type())
+                .unwrap();
+            file.write_i32::<LittleEndian>(relocation.addend).unwrap();
+        }
+        headers.push(SectionHeader {
+            name,
+            kind: SHT_RELA,
+            flags: SHF_INFO_LINK,
+            address: 0,
+            offset,
+            size: file.len() as u32 - offset,
+            link: SYMTAB_INDEX,
+            info: section_index(section) as u32,
+            alignment: 4,
+            entry_size: RELA_SIZE,
+        });
+    }
+
+    let section_headers_offset = file.len() as u32;
+    for header in &headers {
+        for field in [
+            header.name,
+            header.kind,
+            header.flags,
+            header.address,
+            header.offset,
+            header.size,
+            header.link,
+            header.info,
+            header.alignment,
+            header.entry_size,
+        ] {
+            file.write_u32::<LittleEndian>(field).unwrap();
+        }
+    }
+
+    // Execution starts at `_start`, or at the first instruction if there is no such label
+    let entry = object
+        .symbols
+        .iter()
+        .find(|symbol| symbol.name == "_start" && symbol.section == Section::Text)
+        .filter(|_| !relocatable)
+        .map_or(0, |symbol| symbol.value);
+
+    let mut header = vec![0x7f, b'E', b'L', b'F', 1, 1, 1];
+    header.resize(16, 0);
+    let kind = if relocatable { 1 } else { 2 }; // ET_REL or ET_EXEC
+    header.write_u16::<LittleEndian>(kind).unwrap();
+    header.write_u16::<LittleEndian>(0).unwrap(); // EM_NONE
+    header.write_u32::<LittleEndian>(1).unwrap(); // EV_CURRENT
+    header.write_u32::<LittleEndian>(entry).unwrap();
+    let program_headers_offset = if relocatable { 0 } else { ELF_HEADER_SIZE };
+    header
+        .write_u32::<LittleEndian>(program_headers_offset as u32)
+        .unwrap();
+    header
+        .write_u32::<LittleEndian>(section_headers_offset)
+        .unwrap();
+    header.write_u32::<LittleEndian>(0).unwrap();
+    for field in [
+        ELF_HEADER_SIZE,
+        PROGRAM_HEADER_SIZE,
+        segments as u16,
+        SECTION_HEADER_SIZE,
+        headers.len() as u16,
+        SHSTRTAB_INDEX,
+    ] {
+        header.write_u16::<LittleEndian>(field).unwrap();
+    }
+
+    let mut flags = [PF_R | PF_X, PF_R, PF_R | PF_W, PF_R | PF_W].into_iter();
+    for section in &headers[TEXT_INDEX as usize..SYMTAB_INDEX as usize] {
+        let flags = flags.next().unwrap();
+        if section.size == 0 || relocatable {
+            continue;
+        }
+        let file_size = if section.kind == SHT_NOBITS {
+            0
+        } else {
+            section.size
+        };
+        for field in [
+            PT_LOAD,
+            section.offset,
+            section.address,
+            section.address,
+            file_size,
+            section.size,
+            flags,
+            4,
+        ] {
+            header.write_u32::<LittleEndian>(field).unwrap();
+        }
+    }
+    file[..header.len()].copy_from_slice(&header);
+    file
+}
@@ -0,0 +1,53 @@
+use core::fmt;
+use pest::error::{Error, LineColLocation};
+use pest::RuleType;
+
+/// An error in assembly source, and where it was found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssemblyError {
+    /// The 1-based line number.
+    pub line: usize,
+    /// The 1-based column, in characters.
+    pub column: usize,
+    /// The source line containing the error.
+    pub snippet: String,
+    pub message: String,
+}
+
+impl AssemblyError {
+    pub fn new(line: usize, column: usize, snippet: &str, message: String) -> Self {
+        Self {
+            line,
+            column,
+            snippet: snippet.to_string(),
+            message,
+        }
+    }
+
+    /// Convert a syntax error from parsing the single source line `snippet`.
+    pub(crate) fn from_pest<R: RuleType>(line: usize, snippet: &str, error: Error<R>) -> Self {
+        let column = match error.line_col {
+            LineColLocation::Pos((_, column)) | LineColLocation::Span((_, column), _) => column,
+        };
+        Self::new(line, column, snippet, error.variant.message().to_string())
+    }
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Keep tabs in the marker line, so that it lines up with the snippet
+        let indent: String = self
+            .snippet
+            .chars()
+            .take(self.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        write!(
+            f,
+            "{}:{}: {}\n{}\n{}^",
+            self.line, self.column, self.message, self.snippet, indent
+        )
+    }
+}
+
+impl std::error::Error for AssemblyError {}
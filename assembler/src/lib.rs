@@ -1,161 +1,1073 @@
 use byteorder::{LittleEndian, WriteBytesExt};
+use pest::iterators::Pair;
 use pest::Parser;
 use pest_derive::*;
 use std::collections::HashMap;
 use valida_opcodes::*;
 
+mod elf;
+mod error;
+mod macros;
+mod pseudo;
+
+pub use error::AssemblyError;
+pub use pseudo::{pseudo_instruction_format, PSEUDO_INSTRUCTIONS};
+
 #[derive(Parser)]
 #[grammar = "grammar/assembly.pest"]
 pub struct AssemblyParser;
 
-pub fn assemble(input: &str) -> Result<Vec<u8>, String> {
-    let parsed = AssemblyParser::parse(Rule::assembly, input).unwrap();
-    // First pass: Record label locations
-    let mut label_locations = HashMap::new();
-    let mut pc = 0;
-    for pair in parsed.clone() {
-        match pair.as_rule() {
-            Rule::label => {
-                let label_name = pair.as_str().trim().trim_end_matches(':');
-                label_locations.insert(label_name, BYTES_PER_INSTR as i32 * pc);
-            }
-            Rule::instruction => {
-                pc += 1;
-            }
-            _ => {}
-        }
-    }
-
-    // Second pass: Generate machine code and replace labels with PC locations
-    let mut vec: Vec<u8> = Vec::new();
-    for pair in parsed {
-        match pair.as_rule() {
-            Rule::instruction => {
-                let mut inner_pairs = pair.into_inner();
-                let mnemonic = inner_pairs.next().unwrap().as_str();
-                let mut operands: Vec<i32> = inner_pairs
-                    .filter_map(|p| {
-                        if p.as_rule() == Rule::WHITESPACE {
-                            return None;
-                        }
-                        let op_str = p.as_str();
-                        let ret = if op_str.ends_with("(fp)") {
-                            // Extract the numeric value from the string and convert to i32
-                            op_str.trim_end_matches("(fp)").parse::<i32>().unwrap()
-                        } else if label_locations.contains_key(op_str) {
-                            // If operand is a label reference, replace with PC location
-                            *label_locations.get(op_str).unwrap()
-                        } else {
-                            // Otherwise, use the operand as-is
-                            op_str.parse::<i32>().unwrap()
-                        };
-                        Some(ret)
-                    })
-                    .collect();
-
-                // Convert mnemonic to opcode
-                let opcode = match mnemonic {
-                    // Core CPU
-                    "lw" => LOAD32,
-                    "loadu8" => LOADU8,
-                    "loads8" => LOADS8,
-                    "sw" => STORE32,
-                    "storeu8" => STOREU8,
-                    "jal" => JAL,
-                    "jalv" => JALV,
-                    "beq" | "beqi" => BEQ,
-                    "bne" | "bnei" => BNE,
-                    "imm32" => IMM32,
-                    "stop" => STOP,
-
-                    // Nondeterministic input
-                    "advread" => READ_ADVICE,
-
-                    // U32 ALU
-                    "add" | "addi" => ADD32,
-                    "sub" | "subi" => SUB32,
-                    "mul" | "muli" => MUL32,
-                    "mulhs" | "mulhsi" => MULHS32,
-                    "mulhu" | "mulhui" => MULHU32,
-                    "div" | "divi" => DIV32,
-                    "sdiv" | "sdivi" => SDIV32,
-                    "ilt" | "lt" | "lti" => LT32,
-                    "ilte" | "lte" | "ltei" => LTE32,
-                    "islt" | "slt" | "slti" => SLT32,
-                    "isle" | "sle" | "slei" => SLE32,
-                    "shl" | "shli" => SHL32,
-                    "shr" | "shri" => SHR32,
-                    "sra" | "srai" => SRA32,
-                    "and" | "andi" => AND32,
-                    "or" | "ori" => OR32,
-                    "xor" | "xori" => XOR32,
-                    "ne" | "nei" => NE32,
-                    "eq" | "eqi" => EQ32,
-
-                    // Native field
-                    "feadd" => ADD,
-                    "fesub" => SUB,
-                    "femul" => MUL,
-
-                    // Output
-                    "write" => WRITE,
-
-                    _ => panic!("Unknown mnemonic"),
-                };
+/// Where an instruction's assembly operands go among its five machine operands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperandLayout {
+    /// (a, 0, c, 0, 0)
+    Load,
+    /// (0, b, c, 0, 0)
+    Store,
+    /// (a, b, c, d, e)
+    Full,
+    /// (0, 0, 0, 0, 0)
+    Nullary,
+    /// (a, b, c, 0, 0)
+    Binary,
+    /// (a, b, c, 0, 1), with an immediate c
+    ImmediateC,
+    /// (a, b, c, 1, 0), with an immediate b
+    ImmediateB,
+    /// (a, 0, 0, 0, 0)
+    Unary,
+    /// (a, b, 0, 0, 0)
+    Pair,
+}
 
-                // Insert zero operands if necessary
-                match mnemonic {
-                    "lw" | "loadu8" | "loads8" => {
-                        // (a, 0, c, 0, 0)
-                        operands.insert(1, 0);
-                        operands.extend(vec![0; 2]);
-                    }
-                    "sw" | "storeu8" => {
-                        // (0, b, c, 0, 0)
-                        operands.insert(0, 0);
-                        operands.extend(vec![0; 2]);
-                    }
-                    "imm32" | "write" => {
-                        // (a, b, c, d, e)
-                    }
-                    "stop" => {
-                        // (0, 0, 0, 0, 0)
-                        operands.extend(vec![0; 5]);
+impl OperandLayout {
+    pub fn num_operands(self) -> usize {
+        match self {
+            OperandLayout::Load | OperandLayout::Store | OperandLayout::Pair => 2,
+            OperandLayout::Full => 5,
+            OperandLayout::Nullary => 0,
+            OperandLayout::Binary | OperandLayout::ImmediateC | OperandLayout::ImmediateB => 3,
+            OperandLayout::Unary => 1,
+        }
+    }
+
+    /// Insert zero operands and immediate flags where necessary.
+    pub fn arrange(self, mut operands: Vec<i32>) -> Vec<i32> {
+        match self {
+            OperandLayout::Load => {
+                operands.insert(1, 0);
+                operands.extend(vec![0; 2]);
+            }
+            OperandLayout::Store => {
+                operands.insert(0, 0);
+                operands.extend(vec![0; 2]);
+            }
+            OperandLayout::Full => {}
+            OperandLayout::Nullary => operands.extend(vec![0; 5]),
+            OperandLayout::Binary => operands.extend(vec![0; 2]),
+            OperandLayout::ImmediateC => operands.extend(vec![0, 1]),
+            OperandLayout::ImmediateB => operands.extend(vec![1, 0]),
+            OperandLayout::Unary => operands.extend(vec![0; 4]),
+            OperandLayout::Pair => operands.extend(vec![0; 3]),
+        }
+        operands
+    }
+
+    /// The machine operand that each assembly operand goes to.
+    pub fn slots(self) -> &'static [usize] {
+        match self {
+            OperandLayout::Load => &[0, 2],
+            OperandLayout::Store => &[1, 2],
+            OperandLayout::Full => &[0, 1, 2, 3, 4],
+            OperandLayout::Nullary => &[],
+            OperandLayout::Binary | OperandLayout::ImmediateC | OperandLayout::ImmediateB => {
+                &[0, 1, 2]
+            }
+            OperandLayout::Unary => &[0],
+            OperandLayout::Pair => &[0, 1],
+        }
+    }
+
+    /// The inverse of `arrange`: the assembly operands of an instruction, if its zero
+    /// operands and immediate flags fit this layout.
+    pub fn extract(self, operands: &[i32; 5]) -> Option<Vec<i32>> {
+        let [a, b, c, d, e] = *operands;
+        match self {
+            OperandLayout::Load => (b == 0 && d == 0 && e == 0).then(|| vec![a, c]),
+            OperandLayout::Store => (a == 0 && d == 0 && e == 0).then(|| vec![b, c]),
+            OperandLayout::Full => Some(vec![a, b, c, d, e]),
+            OperandLayout::Nullary => (*operands == [0; 5]).then(Vec::new),
+            OperandLayout::Binary => (d == 0 && e == 0).then(|| vec![a, b, c]),
+            OperandLayout::ImmediateC => (d == 0 && e == 1).then(|| vec![a, b, c]),
+            OperandLayout::ImmediateB => (d == 1 && e == 0).then(|| vec![a, b, c]),
+            OperandLayout::Unary => (b == 0 && c == 0 && d == 0 && e == 0).then(|| vec![a]),
+            OperandLayout::Pair => (c == 0 && d == 0 && e == 0).then(|| vec![a, b]),
+        }
+    }
+}
+
+/// Every mnemonic, with its opcode and the layout of its operands.
+pub const INSTRUCTIONS: &[(&str, u32, OperandLayout)] = &[
+    // Core CPU
+    ("lw", LOAD32, OperandLayout::Load),
+    ("loadu8", LOADU8, OperandLayout::Load),
+    ("loads8", LOADS8, OperandLayout::Load),
+    ("sw", STORE32, OperandLayout::Store),
+    ("storeu8", STOREU8, OperandLayout::Store),
+    ("jal", JAL, OperandLayout::Binary),
+    ("jalv", JALV, OperandLayout::Binary),
+    ("beq", BEQ, OperandLayout::Binary),
+    ("beqi", BEQ, OperandLayout::ImmediateC),
+    ("bne", BNE, OperandLayout::Binary),
+    ("bnei", BNE, OperandLayout::ImmediateC),
+    ("imm32", IMM32, OperandLayout::Full),
+    ("stop", STOP, OperandLayout::Nullary),
+    ("loadfp", LOADFP, OperandLayout::Pair),
+    // Nondeterministic input
+    ("advread", READ_ADVICE, OperandLayout::Unary),
+    // U32 ALU
+    ("add", ADD32, OperandLayout::Binary),
+    ("addi", ADD32, OperandLayout::ImmediateC),
+    ("sub", SUB32, OperandLayout::Binary),
+    ("subi", SUB32, OperandLayout::ImmediateC),
+    ("mul", MUL32, OperandLayout::Binary),
+    ("muli", MUL32, OperandLayout::ImmediateC),
+    ("mulhs", MULHS32, OperandLayout::Binary),
+    ("mulhsi", MULHS32, OperandLayout::ImmediateC),
+    ("mulhu", MULHU32, OperandLayout::Binary),
+    ("mulhui", MULHU32, OperandLayout::ImmediateC),
+    ("div", DIV32, OperandLayout::Binary),
+    ("divi", DIV32, OperandLayout::ImmediateC),
+    ("sdiv", SDIV32, OperandLayout::Binary),
+    ("sdivi", SDIV32, OperandLayout::ImmediateC),
+    ("lt", LT32, OperandLayout::Binary),
+    ("lti", LT32, OperandLayout::ImmediateC),
+    ("ilt", LT32, OperandLayout::ImmediateB),
+    ("lte", LTE32, OperandLayout::Binary),
+    ("ltei", LTE32, OperandLayout::ImmediateC),
+    ("ilte", LTE32, OperandLayout::ImmediateB),
+    ("slt", SLT32, OperandLayout::Binary),
+    ("slti", SLT32, OperandLayout::ImmediateC),
+    ("islt", SLT32, OperandLayout::ImmediateB),
+    ("sle", SLE32, OperandLayout::Binary),
+    ("slei", SLE32, OperandLayout::ImmediateC),
+    ("isle", SLE32, OperandLayout::ImmediateB),
+    ("shl", SHL32, OperandLayout::Binary),
+    ("shli", SHL32, OperandLayout::ImmediateC),
+    ("shr", SHR32, OperandLayout::Binary),
+    ("shri", SHR32, OperandLayout::ImmediateC),
+    ("sra", SRA32, OperandLayout::Binary),
+    ("srai", SRA32, OperandLayout::ImmediateC),
+    ("and", AND32, OperandLayout::Binary),
+    ("andi", AND32, OperandLayout::ImmediateC),
+    ("or", OR32, OperandLayout::Binary),
+    ("ori", OR32, OperandLayout::ImmediateC),
+    ("xor", XOR32, OperandLayout::Binary),
+    ("xori", XOR32, OperandLayout::ImmediateC),
+    ("ne", NE32, OperandLayout::Binary),
+    ("nei", NE32, OperandLayout::ImmediateC),
+    ("eq", EQ32, OperandLayout::Binary),
+    ("eqi", EQ32, OperandLayout::ImmediateC),
+    // Native field
+    ("feadd", ADD, OperandLayout::Binary),
+    ("fesub", SUB, OperandLayout::Binary),
+    ("femul", MUL, OperandLayout::Binary),
+    // Output
+    ("write", WRITE, OperandLayout::Full),
+    // Host calls
+    ("ecall", ECALL, OperandLayout::Binary),
+    // Precompiles
+    ("keccakf", KECCAKF, OperandLayout::Binary),
+    ("sha256compress", SHA256COMPRESS, OperandLayout::Binary),
+    ("poseidon2", POSEIDON2, OperandLayout::Binary),
+    ("secp256k1add", SECP256K1ADD, OperandLayout::Binary),
+    ("secp256k1double", SECP256K1DOUBLE, OperandLayout::Binary),
+    ("ed25519add", ED25519ADD, OperandLayout::Binary),
+    ("ed25519double", ED25519DOUBLE, OperandLayout::Binary),
+    ("u256add", U256ADD, OperandLayout::Binary),
+    ("u256mul", U256MUL, OperandLayout::Binary),
+    ("u256modmul", U256MODMUL, OperandLayout::Binary),
+];
+
+/// The opcode of a mnemonic and the layout of its operands.
+pub fn instruction_format(mnemonic: &str) -> Option<(u32, OperandLayout)> {
+    INSTRUCTIONS
+        .iter()
+        .find(|(name, _, _)| *name == mnemonic)
+        .map(|&(_, opcode, layout)| (opcode, layout))
+}
+
+/// The 1-based column at which a parsed item starts.
+fn column(pair: &Pair<Rule>) -> usize {
+    pair.as_span().start_pos().line_col().1
+}
+
+/// Parse a decimal, hexadecimal (`0x`) or binary (`0b`) integer. Values up to `u32::MAX`
+/// are accepted, and wrap around to negative numbers.
+fn parse_constant(text: &str) -> Result<i32, String> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let prefix = digits.get(..2).map(str::to_ascii_lowercase);
+    let value = match prefix.as_deref() {
+        Some("0x") => i64::from_str_radix(&digits[2..], 16),
+        Some("0b") => i64::from_str_radix(&digits[2..], 2),
+        _ => digits.parse::<i64>(),
+    };
+    let value = value.map(|value| if negative { -value } else { value });
+    match value {
+        Ok(value) if i32::MIN as i64 <= value && value <= u32::MAX as i64 => Ok(value as i32),
+        _ => Err(format!("invalid integer `{}`", text)),
+    }
+}
+
+fn parse_term(pair: &Pair<Rule>, label_locations: &HashMap<&str, i32>) -> Result<i32, String> {
+    match pair.as_rule() {
+        Rule::constant => parse_constant(pair.as_str()),
+        Rule::character => match parse_string(pair.as_str())?[..] {
+            [byte] => Ok(byte as i32),
+            _ => Err(format!("invalid character `{}`", pair.as_str())),
+        },
+        // If the operand is a label reference, replace it with the PC location
+        _ => label_locations
+            .get(pair.as_str())
+            .copied()
+            .ok_or_else(|| format!("undefined label `{}`", pair.as_str())),
+    }
+}
+
+/// Evaluate a sum of terms such as `table+8` or `SIZE-1`. Like the machine, arithmetic
+/// wraps around.
+fn evaluate(expression: &Pair<Rule>, label_locations: &HashMap<&str, i32>) -> Result<i32, String> {
+    let mut value = 0i32;
+    let mut subtract = false;
+    for pair in expression.clone().into_inner() {
+        if pair.as_rule() == Rule::operator {
+            subtract = pair.as_str() == "-";
+            continue;
+        }
+        let term = parse_term(&pair, label_locations)?;
+        value = match subtract {
+            true => value.wrapping_sub(term),
+            false => value.wrapping_add(term),
+        };
+    }
+    Ok(value)
+}
+
+/// The section or undefined symbol that an operand is relative to, if any. The difference
+/// of two labels in the same section doesn't change when the section moves, but anything
+/// else that refers to labels must be one address plus a constant for the linker to
+/// relocate it.
+fn relocation_target(
+    pair: &Pair<Rule>,
+    label_sections: &HashMap<&str, Section>,
+) -> Result<Option<RelocationTarget>, String> {
+    let expression = pair.clone().into_inner().next().unwrap();
+    let mut counts: Vec<(RelocationTarget, i32)> = Vec::new();
+    let mut sign = 1;
+    for term in expression.clone().into_inner() {
+        match term.as_rule() {
+            Rule::operator => {
+                sign = if term.as_str() == "-" { -1 } else { 1 };
+                continue;
+            }
+            Rule::jump_label => {}
+            _ => continue,
+        }
+        let target = match label_sections.get(term.as_str()) {
+            Some(Section::Absolute) | None => continue,
+            Some(Section::Undefined) => RelocationTarget::Symbol(term.as_str().to_string()),
+            Some(&section) => RelocationTarget::Section(section),
+        };
+        match counts.iter_mut().find(|(counted, _)| *counted == target) {
+            Some((_, count)) => *count += sign,
+            None => counts.push((target, sign)),
+        }
+    }
+    counts.retain(|(_, count)| *count != 0);
+    match &counts[..] {
+        [] => Ok(None),
+        [(_, 1)] => Ok(counts.pop().map(|(target, _)| target)),
+        _ => Err(format!("`{}` can't be relocated", expression.as_str())),
+    }
+}
+
+fn parse_operand(pair: &Pair<Rule>, label_locations: &HashMap<&str, i32>) -> Result<i32, String> {
+    let value = pair.clone().into_inner().next().unwrap();
+    match value.as_rule() {
+        Rule::string => Err(format!("expected a number, found {}", value.as_str())),
+        _ => evaluate(&value, label_locations),
+    }
+}
+
+/// The name in a `.globl` or `.equ` directive.
+fn parse_name<'i>(pair: &Pair<'i, Rule>) -> Result<&'i str, (usize, String)> {
+    let terms: Vec<Pair<Rule>> = pair
+        .clone()
+        .into_inner()
+        .flat_map(|expression| expression.into_inner())
+        .collect();
+    match &terms[..] {
+        [term] if term.as_rule() == Rule::jump_label => Ok(term.as_str()),
+        _ => Err((
+            column(pair),
+            format!("expected a name, found `{}`", pair.as_str()),
+        )),
+    }
+}
+
+/// The bytes of a string literal, with its quotes removed and escapes replaced.
+fn parse_string(literal: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut chars = literal[1..literal.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            bytes.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
+            continue;
+        }
+        // The grammar ensures that a backslash is followed by another character
+        bytes.push(match chars.next().unwrap() {
+            'n' => b'\n',
+            't' => b'\t',
+            'r' => b'\r',
+            '0' => 0,
+            '\\' => b'\\',
+            '"' => b'"',
+            '\'' => b'\'',
+            c => return Err(format!("unknown escape `\\{}`", c)),
+        });
+    }
+    Ok(bytes)
+}
+
+/// An opcode and its five operands.
+type EncodedInstruction = (u32, Vec<i32>);
+
+/// An operand that the linker relocates: its offset from the start of the encoded
+/// instruction, how it is relocated, what it refers to, and its value.
+type OperandRelocation = (u32, RelocationKind, RelocationTarget, i32);
+
+/// Encode an instruction, or the instructions that a pseudo-instruction expands to, or
+/// describe what is wrong with it and the column where the problem is. When assembling an
+/// object for the linker, `label_sections` gives the section of each label, and the
+/// operands that refer to addresses are returned.
+fn encode_instruction(
+    pair: Pair<Rule>,
+    label_locations: &HashMap<&str, i32>,
+    label_sections: Option<&HashMap<&str, Section>>,
+    pc: u32,
+) -> Result<(Vec<EncodedInstruction>, Vec<OperandRelocation>), (usize, String)> {
+    let mut inner_pairs = pair.into_inner();
+    let mnemonic_pair = inner_pairs.next().unwrap();
+    let mnemonic = mnemonic_pair.as_str();
+    let num_operands = match instruction_format(mnemonic) {
+        Some((_, layout)) => layout.num_operands(),
+        None => match pseudo_instruction_format(mnemonic) {
+            Some((num_operands, _)) => num_operands,
+            None => {
+                return Err((
+                    column(&mnemonic_pair),
+                    format!("unknown mnemonic `{}`", mnemonic),
+                ))
+            }
+        },
+    };
+
+    let operand_pairs: Vec<Pair<Rule>> = inner_pairs.collect();
+    check_arity(&mnemonic_pair, operand_pairs.len(), num_operands)?;
+    let operands = operand_pairs
+        .iter()
+        .map(|pair| parse_operand(pair, label_locations).map_err(|message| (column(pair), message)))
+        .collect::<Result<Vec<i32>, _>>()?;
+
+    let mut relocations = Vec::new();
+    for (index, pair) in operand_pairs.iter().enumerate() {
+        let Some(label_sections) = label_sections else {
+            break;
+        };
+        let Some(target) =
+            relocation_target(pair, label_sections).map_err(|message| (column(pair), message))?
+        else {
+            continue;
+        };
+        let place = match instruction_format(mnemonic) {
+            Some((_, layout)) => Some((0, layout.slots()[index], RelocationKind::Word)),
+            None => pseudo::address_operand(mnemonic)
+                .filter(|&(operand, _, _, _)| operand == index)
+                .map(|(_, instruction, slot, kind)| (instruction, slot, kind)),
+        };
+        let Some((instruction, slot, kind)) = place else {
+            return Err((
+                column(pair),
+                format!("`{}` can't be an address", pair.as_str()),
+            ));
+        };
+        let offset = BYTES_PER_INSTR * instruction + 4 * (slot as u32 + 1);
+        relocations.push((offset, kind, target, operands[index]));
+    }
+
+    let instructions = match instruction_format(mnemonic) {
+        Some(_) => vec![(mnemonic, operands)],
+        None => pseudo::expand(mnemonic, &operands, pc),
+    };
+    let instructions = instructions
+        .into_iter()
+        .map(|(mnemonic, operands)| {
+            let (opcode, layout) = instruction_format(mnemonic).unwrap();
+            (opcode, layout.arrange(operands))
+        })
+        .collect();
+    Ok((instructions, relocations))
+}
+
+/// Check the number of operands of an instruction or directive.
+fn check_arity(name: &Pair<Rule>, found: usize, expected: usize) -> Result<(), (usize, String)> {
+    if found == expected {
+        return Ok(());
+    }
+    Err((
+        column(name),
+        format!(
+            "expected {} operand{} for `{}`, found {}",
+            expected,
+            if expected == 1 { "" } else { "s" },
+            name.as_str(),
+            found
+        ),
+    ))
+}
+
+/// The section that code, data or a symbol is in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Section {
+    #[default]
+    Text,
+    /// Read-only data
+    Rodata,
+    Data,
+    /// Zero-initialized data, which takes no space in the executable
+    Bss,
+    /// Symbols defined with `.equ`, which are numbers rather than addresses
+    Absolute,
+    /// Symbols that an object uses but doesn't define, for the linker to resolve
+    Undefined,
+}
+
+/// The first data section starts here, so that a null pointer never points at data.
+pub const DATA_START: u32 = 0x1000;
+
+/// The contents of a data section, byte by byte in the order of their addresses.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DataSection {
+    pub address: u32,
+    pub bytes: Vec<u8>,
+}
+
+/// A label or `.equ` definition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ObjectSymbol {
+    pub name: String,
+    pub section: Section,
+    /// The address of a label, in bytes, or the value of an `.equ` definition.
+    pub value: u32,
+    /// The distance to the next symbol in the same section, or to the end of the section.
+    pub size: u32,
+    /// Whether the symbol was named by `.globl`.
+    pub global: bool,
+}
+
+/// How a relocated value is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelocationKind {
+    /// A little-endian 32-bit word: an instruction operand or a `.word`
+    Word,
+    /// The last four operands of `imm32`, which hold the bytes of the value, most
+    /// significant first
+    Imm32,
+}
+
+impl RelocationKind {
+    /// The `r_type` of the relocation in an ELF object.
+    pub fn elf_type(self) -> u32 {
+        match self {
+            RelocationKind::Word => 1,
+            RelocationKind::Imm32 => 2,
+        }
+    }
+
+    pub fn from_elf_type(elf_type: u32) -> Option<Self> {
+        match elf_type {
+            1 => Some(RelocationKind::Word),
+            2 => Some(RelocationKind::Imm32),
+            _ => None,
+        }
+    }
+}
+
+/// What a relocated value is relative to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RelocationTarget {
+    /// The start of a section of the same object
+    Section(Section),
+    /// A symbol defined in another object
+    Symbol(String),
+}
+
+/// A value that the linker fills in once it knows where sections and symbols are: the
+/// address of the target plus the addend.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Relocation {
+    pub section: Section,
+    /// The offset of the value from the start of its section, in bytes
+    pub offset: u32,
+    pub kind: RelocationKind,
+    pub target: RelocationTarget,
+    pub addend: i32,
+}
+
+/// Assembled code and data, with the symbols defined in the source. Code starts at address
+/// 0, and data after `DATA_START`. Relocations are only recorded by
+/// `assemble_relocatable_object`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Object {
+    pub code: Vec<u8>,
+    pub rodata: DataSection,
+    pub data: DataSection,
+    pub bss: DataSection,
+    pub symbols: Vec<ObjectSymbol>,
+    pub relocations: Vec<Relocation>,
+}
+
+impl Object {
+    pub fn section(&self, section: Section) -> Option<&DataSection> {
+        match section {
+            Section::Rodata => Some(&self.rodata),
+            Section::Data => Some(&self.data),
+            Section::Bss => Some(&self.bss),
+            Section::Text | Section::Absolute | Section::Undefined => None,
+        }
+    }
+
+    fn section_mut(&mut self, section: Section) -> Option<&mut DataSection> {
+        match section {
+            Section::Rodata => Some(&mut self.rodata),
+            Section::Data => Some(&mut self.data),
+            Section::Bss => Some(&mut self.bss),
+            Section::Text | Section::Absolute | Section::Undefined => None,
+        }
+    }
+
+    /// A relocation of the value at `offset` in `section`, which was assembled as `value`.
+    fn relocation(
+        &self,
+        section: Section,
+        offset: u32,
+        kind: RelocationKind,
+        target: RelocationTarget,
+        value: i32,
+    ) -> Relocation {
+        let base = match &target {
+            RelocationTarget::Section(section) => {
+                self.section(*section).map_or(0, |data| data.address)
+            }
+            RelocationTarget::Symbol(_) => 0,
+        };
+        Relocation {
+            section,
+            offset,
+            kind,
+            target,
+            addend: value.wrapping_sub(base as i32),
+        }
+    }
+
+    /// Write the object as a Valida ELF executable.
+    pub fn to_elf(&self) -> Vec<u8> {
+        elf::write_elf(self, false)
+    }
+
+    /// Write the object as a relocatable Valida ELF object, for `valida-ld` to link.
+    pub fn to_relocatable_elf(&self) -> Vec<u8> {
+        elf::write_elf(self, true)
+    }
+}
+
+/// A `.word` or `.byte` value, written into its section once every label has an address.
+struct Fixup<'i> {
+    section: Section,
+    offset: usize,
+    size: usize,
+    argument: Pair<'i, Rule>,
+    line: usize,
+    source: &'i str,
+}
+
+/// Everything the first pass collects from the source. Data is laid out, but values that
+/// may refer to labels are filled in later.
+#[derive(Default)]
+struct Listing<'i> {
+    section: Section,
+    num_instructions: u32,
+    object: Object,
+    alignments: HashMap<Section, u32>,
+    /// Label names, in order of definition, with their sections and offsets
+    labels: Vec<(&'i str, Section, u32)>,
+    /// Instructions, with the program counter of the first instruction they encode to
+    instructions: Vec<(usize, &'i str, Pair<'i, Rule>, u32)>,
+    fixups: Vec<Fixup<'i>>,
+    /// `.equ` definitions: the name and value
+    equs: Vec<(usize, &'i str, Pair<'i, Rule>, Pair<'i, Rule>)>,
+    globals: Vec<(usize, &'i str, Pair<'i, Rule>)>,
+}
+
+impl<'i> Listing<'i> {
+    fn label(&mut self, pair: Pair<'i, Rule>) -> Result<(), (usize, String)> {
+        let name = pair.clone().into_inner().next().unwrap().as_str();
+        if self.labels.iter().any(|(label, _, _)| *label == name) {
+            return Err((column(&pair), format!("duplicate label `{}`", name)));
+        }
+        let offset = match self.object.section(self.section) {
+            Some(section) => section.bytes.len() as u32,
+            None => BYTES_PER_INSTR * self.num_instructions,
+        };
+        self.labels.push((name, self.section, offset));
+        Ok(())
+    }
+
+    fn instruction(
+        &mut self,
+        line: usize,
+        source: &'i str,
+        pair: Pair<'i, Rule>,
+    ) -> Result<(), (usize, String)> {
+        if self.section != Section::Text {
+            return Err((column(&pair), "instructions must be in `.text`".to_string()));
+        }
+        // A pseudo-instruction may take several instructions
+        let mnemonic = pair.clone().into_inner().next().unwrap().as_str();
+        let length = pseudo_instruction_format(mnemonic).map_or(1, |(_, length)| length);
+        self.instructions
+            .push((line, source, pair, self.num_instructions));
+        self.num_instructions += length;
+        Ok(())
+    }
+
+    fn directive(
+        &mut self,
+        line: usize,
+        source: &'i str,
+        pair: Pair<'i, Rule>,
+    ) -> Result<(), (usize, String)> {
+        let mut inner_pairs = pair.into_inner();
+        let name_pair = inner_pairs.next().unwrap();
+        let name = name_pair.as_str();
+        let arguments: Vec<Pair<Rule>> = inner_pairs.collect();
+        let section = match name {
+            ".text" => Some(Section::Text),
+            ".rodata" => Some(Section::Rodata),
+            ".data" => Some(Section::Data),
+            ".bss" => Some(Section::Bss),
+            _ => None,
+        };
+        if let Some(section) = section {
+            check_arity(&name_pair, arguments.len(), 0)?;
+            self.section = section;
+            return Ok(());
+        }
+        match name {
+            ".globl" => {
+                for argument in arguments {
+                    parse_name(&argument)?;
+                    self.globals.push((line, source, argument));
+                }
+                return Ok(());
+            }
+            ".equ" => {
+                check_arity(&name_pair, arguments.len(), 2)?;
+                let mut arguments = arguments.into_iter();
+                let name = arguments.next().unwrap();
+                parse_name(&name)?;
+                self.equs
+                    .push((line, source, name, arguments.next().unwrap()));
+                return Ok(());
+            }
+            ".word" | ".byte" | ".ascii" | ".asciz" | ".align" | ".zero" => {}
+            _ => {
+                return Err((column(&name_pair), format!("unknown directive `{}`", name)));
+            }
+        }
+
+        let current = self.section;
+        if current == Section::Bss && !matches!(name, ".align" | ".zero") {
+            return Err((
+                column(&name_pair),
+                "`.bss` can only be filled with `.zero`".to_string(),
+            ));
+        }
+        let Some(section) = self.object.section_mut(current) else {
+            return Err((
+                column(&name_pair),
+                format!("`{}` is only allowed in a data section", name),
+            ));
+        };
+        match name {
+            ".word" | ".byte" => {
+                let size = if name == ".word" { 4 } else { 1 };
+                for argument in arguments {
+                    self.fixups.push(Fixup {
+                        section: current,
+                        offset: section.bytes.len(),
+                        size,
+                        argument,
+                        line,
+                        source,
+                    });
+                    section.bytes.extend(vec![0; size]);
+                }
+            }
+            ".ascii" | ".asciz" => {
+                for argument in arguments {
+                    let literal = argument.clone().into_inner().next().unwrap();
+                    if literal.as_rule() != Rule::string {
+                        return Err((
+                            column(&argument),
+                            format!("expected a string, found `{}`", literal.as_str()),
+                        ));
                     }
-                    "add" | "sub" | "mul" | "mulhs" | "mulhu" | "div" | "lt" | "lte" | "shl"
-                    | "shr" | "sra" | "beq" | "bne" | "and" | "or" | "xor" | "ne" | "eq"
-                    | "jal" | "jalv" | "slt" | "sle" => {
-                        // (a, b, c, 0, 0)
-                        operands.extend(vec![0; 2]);
+                    let bytes = parse_string(literal.as_str())
+                        .map_err(|message| (column(&argument), message))?;
+                    section.bytes.extend(bytes);
+                    if name == ".asciz" {
+                        section.bytes.push(0);
                     }
-                    "addi" | "subi" | "muli" | "mulhsi" | "mulhui" | "divi" | "sdivi" | "lti"
-                    | "ltei" | "shli" | "shri" | "srai" | "beqi" | "bnei" | "andi" | "ori"
-                    | "xori" | "nei" | "eqi" | "slti" | "slei" => {
-                        // (a, b, c, 0, 1)
-                        operands.extend(vec![0, 1]);
+                }
+            }
+            _ => {
+                check_arity(&name_pair, arguments.len(), 1)?;
+                let count = parse_operand(&arguments[0], &HashMap::new())
+                    .ok()
+                    .filter(|count| *count >= 0)
+                    .ok_or_else(|| {
+                        (
+                            column(&arguments[0]),
+                            format!("expected a size, found `{}`", arguments[0].as_str()),
+                        )
+                    })? as u32;
+                let len = section.bytes.len() as u64;
+                let (len, alignment) = if name == ".zero" {
+                    (len + count as u64, 1)
+                } else if count.is_power_of_two() {
+                    (len.next_multiple_of(count as u64), count)
+                } else {
+                    return Err((
+                        column(&arguments[0]),
+                        format!("alignment {} is not a power of two", count),
+                    ));
+                };
+                // Check before the section grows, as a single count can ask for gigabytes.
+                if self.data_end(current, len, alignment) > u32::MAX as u64 {
+                    return Err((
+                        column(&arguments[0]),
+                        format!(
+                            "`{} {}` runs past the end of memory",
+                            name,
+                            arguments[0].as_str()
+                        ),
+                    ));
+                }
+                let section = self.object.section_mut(current).unwrap();
+                section.bytes.resize(len as usize, 0);
+                if name == ".align" {
+                    let alignment = self.alignments.entry(current).or_insert(4);
+                    *alignment = count.max(*alignment);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The address just past the data sections, placed as in `finish`, if `resized` were
+    /// `len` bytes long and aligned to at least `alignment`.
+    fn data_end(&self, resized: Section, len: u64, alignment: u32) -> u64 {
+        let mut address = DATA_START as u64;
+        for section in [Section::Rodata, Section::Data, Section::Bss] {
+            let mut section_alignment = self.alignments.get(&section).copied().unwrap_or(4);
+            let mut section_len = self
+                .object
+                .section(section)
+                .map_or(0, |data| data.bytes.len() as u64);
+            if section == resized {
+                section_alignment = section_alignment.max(alignment);
+                section_len = len;
+            }
+            address = address.next_multiple_of(section_alignment as u64) + section_len;
+        }
+        address
+    }
+
+    /// Place the data sections, and fill in every value that depends on a label. For an
+    /// object that will be linked, labels that aren't defined become undefined symbols, and
+    /// every value that depends on where a section is placed gets a relocation.
+    fn finish(mut self, relocatable: bool, errors: &mut Vec<AssemblyError>) -> Object {
+        let mut address = DATA_START;
+        for section in [Section::Rodata, Section::Data, Section::Bss] {
+            let alignment = self.alignments.get(&section).copied().unwrap_or(4);
+            let data = self.object.section_mut(section).unwrap();
+            data.address = align(address, alignment);
+            address = data.address + data.bytes.len() as u32;
+        }
+
+        let mut label_locations = HashMap::new();
+        let mut label_sections = HashMap::new();
+        for &(name, section, offset) in &self.labels {
+            let base = self.object.section(section).map_or(0, |data| data.address);
+            label_locations.insert(name, (base + offset) as i32);
+            label_sections.insert(name, section);
+        }
+        let mut equs = HashMap::new();
+        for (line, source, name, value) in &self.equs {
+            let result = match label_locations.contains_key(name.as_str()) {
+                true => Err((column(name), format!("duplicate label `{}`", name.as_str()))),
+                false => parse_operand(value, &label_locations)
+                    .and_then(|number| {
+                        // An `.equ` defined as an address moves with the section of the address
+                        let target = match relocatable {
+                            true => relocation_target(value, &label_sections)?,
+                            false => None,
+                        };
+                        match target {
+                            Some(RelocationTarget::Section(section)) => Ok((number, section)),
+                            _ => Ok((number, Section::Absolute)),
+                        }
+                    })
+                    .map_err(|message| (column(value), message)),
+            };
+            match result {
+                Ok((value, section)) => {
+                    label_locations.insert(name.as_str(), value);
+                    label_sections.insert(name.as_str(), section);
+                    equs.insert(name.as_str(), (value, section));
+                }
+                Err((column, message)) => {
+                    errors.push(AssemblyError::new(*line, column, source, message))
+                }
+            }
+        }
+
+        // An object that will be linked may use labels that other objects define
+        let mut undefined = Vec::new();
+        if relocatable {
+            let arguments = self.fixups.iter().map(|fixup| &fixup.argument);
+            let instructions = self.instructions.iter().map(|(_, _, pair, _)| pair);
+            let globals = self.globals.iter().map(|(_, _, name)| name);
+            for pair in arguments.chain(instructions).chain(globals) {
+                for term in pair.clone().into_inner().flatten() {
+                    let name = term.as_str();
+                    if term.as_rule() == Rule::jump_label && !label_locations.contains_key(name) {
+                        label_locations.insert(name, 0);
+                        label_sections.insert(name, Section::Undefined);
+                        undefined.push(name);
                     }
-                    "ilt" | "ilte" | "islt" | "isle" => {
-                        // (a, b, c, 1, 0)
-                        operands.extend(vec![1, 0]);
+                }
+            }
+        }
+
+        for fixup in &self.fixups {
+            let value = parse_operand(&fixup.argument, &label_locations).and_then(|value| {
+                let target = match relocatable {
+                    true => relocation_target(&fixup.argument, &label_sections)?,
+                    false => None,
+                };
+                match (fixup.size, target) {
+                    (4, target) => Ok((value.to_le_bytes().to_vec(), target, value)),
+                    (_, Some(_)) => Err("an address doesn't fit in a byte".to_string()),
+                    (_, None) => match value {
+                        -128..=255 => Ok((vec![value as u8], None, value)),
+                        _ => Err(format!("{} doesn't fit in a byte", value)),
+                    },
+                }
+            });
+            match value {
+                Ok((bytes, target, value)) => {
+                    if let Some(target) = target {
+                        let relocation = self.object.relocation(
+                            fixup.section,
+                            fixup.offset as u32,
+                            RelocationKind::Word,
+                            target,
+                            value,
+                        );
+                        self.object.relocations.push(relocation);
                     }
-                    "advread" => {
-                        // (a, 0, 0, 0, 0)
-                        operands.extend(vec![0; 4]);
+                    let data = self.object.section_mut(fixup.section).unwrap();
+                    data.bytes[fixup.offset..fixup.offset + fixup.size].copy_from_slice(&bytes);
+                }
+                Err(message) => errors.push(AssemblyError::new(
+                    fixup.line,
+                    column(&fixup.argument),
+                    fixup.source,
+                    message,
+                )),
+            }
+        }
+
+        // Generate machine code and replace labels with PC locations
+        for (line, source, pair, pc) in self.instructions {
+            let sections = relocatable.then_some(&label_sections);
+            match encode_instruction(pair, &label_locations, sections, pc) {
+                Ok((instructions, relocations)) => {
+                    for (offset, kind, target, value) in relocations {
+                        let offset = BYTES_PER_INSTR * pc + offset;
+                        let relocation =
+                            self.object
+                                .relocation(Section::Text, offset, kind, target, value);
+                        self.object.relocations.push(relocation);
                     }
-                    _ => {
-                        panic!("Unknown mnemonic {}", mnemonic);
+                    // Write opcode and operands
+                    let code = &mut self.object.code;
+                    for (opcode, operands) in instructions {
+                        code.write_u32::<LittleEndian>(opcode).unwrap();
+                        for operand in operands {
+                            code.write_i32::<LittleEndian>(operand).unwrap();
+                        }
                     }
-                };
-
-                // Write opcode and operands
-                vec.write_u32::<LittleEndian>(opcode).unwrap();
-                for operand in operands {
-                    vec.write_i32::<LittleEndian>(operand).unwrap();
                 }
+                Err((column, message)) => {
+                    errors.push(AssemblyError::new(line, column, source, message));
+                }
+            }
+        }
+
+        let mut globals = Vec::new();
+        for (line, source, name) in &self.globals {
+            if label_locations.contains_key(name.as_str()) {
+                globals.push(name.as_str());
+            } else {
+                let message = format!("undefined label `{}`", name.as_str());
+                errors.push(AssemblyError::new(*line, column(name), source, message));
+            }
+        }
+
+        // Local labels, such as the `.LBB0_1` that compilers emit for branches, are not symbols
+        let mut symbols: Vec<(&str, Section, u32)> = self
+            .labels
+            .iter()
+            .filter(|(name, _, _)| !name.starts_with(".L"))
+            .map(|&(name, section, _)| (name, section, label_locations[name] as u32))
+            .collect();
+        symbols.sort_by_key(|&(_, section, address)| (section, address));
+        for (index, &(name, section, address)) in symbols.iter().enumerate() {
+            let end = match symbols.get(index + 1) {
+                Some(&(_, next_section, next_address)) if next_section == section => next_address,
+                _ => match self.object.section(section) {
+                    Some(data) => data.address + data.bytes.len() as u32,
+                    None => self.object.code.len() as u32,
+                },
+            };
+            self.object.symbols.push(ObjectSymbol {
+                name: name.to_string(),
+                section,
+                value: address,
+                size: end - address,
+                global: globals.contains(&name),
+            });
+        }
+        for (_, _, name, _) in &self.equs {
+            if let Some(&(value, section)) = equs.get(name.as_str()) {
+                self.object.symbols.push(ObjectSymbol {
+                    name: name.as_str().to_string(),
+                    section,
+                    value: value as u32,
+                    size: 0,
+                    global: globals.contains(&name.as_str()),
+                });
             }
-            _ => {}
         }
+        for name in undefined {
+            self.object.symbols.push(ObjectSymbol {
+                name: name.to_string(),
+                section: Section::Undefined,
+                value: 0,
+                size: 0,
+                global: true,
+            });
+        }
+        self.object
     }
+}
+
+/// Round an address up to a multiple of `alignment`, which is a power of two.
+fn align(address: u32, alignment: u32) -> u32 {
+    (address + alignment - 1) & !(alignment - 1)
+}
+
+fn assemble_listing(input: &str, relocatable: bool) -> Result<Object, Vec<AssemblyError>> {
+    let mut errors = Vec::new();
+
+    let expanded = macros::expand_macros(input, &mut errors);
+
+    // Parse each line on its own, so that a syntax error doesn't hide errors on later lines
+    let mut lines = Vec::new();
+    for (line, source) in &expanded {
+        match AssemblyParser::parse(Rule::line, source) {
+            Ok(pairs) => lines.push((*line, source.as_str(), pairs)),
+            Err(error) => errors.push(AssemblyError::from_pest(*line, source, error)),
+        }
+    }
+
+    // First pass: Record label locations and lay out data
+    let mut listing = Listing::default();
+    for (line, source, pairs) in lines {
+        for pair in pairs {
+            let result = match pair.as_rule() {
+                Rule::label => listing.label(pair),
+                Rule::instruction => listing.instruction(line, source, pair),
+                Rule::directive => listing.directive(line, source, pair),
+                _ => Ok(()),
+            };
+            if let Err((column, message)) = result {
+                errors.push(AssemblyError::new(line, column, source, message));
+            }
+        }
+    }
+
+    // Second pass: Fill in values that refer to labels, and generate machine code
+    let object = listing.finish(relocatable, &mut errors);
+
+    if errors.is_empty() {
+        Ok(object)
+    } else {
+        errors.sort_by_key(|error| (error.line, error.column));
+        Err(errors)
+    }
+}
+
+/// Assemble source into code, data and symbols, or report every error found in it.
+pub fn assemble_object(input: &str) -> Result<Object, Vec<AssemblyError>> {
+    assemble_listing(input, false)
+}
+
+/// Assemble source into an object for the linker, with relocations for every value that
+/// depends on where its sections are placed. Labels that are used but not defined become
+/// undefined symbols rather than errors.
+pub fn assemble_relocatable_object(input: &str) -> Result<Object, Vec<AssemblyError>> {
+    assemble_listing(input, true)
+}
+
+/// Assemble source into machine code, or report every error found in it. A flat binary has
+/// no room for data, so the data sections are dropped; use `assemble_elf` to keep them.
+pub fn assemble(input: &str) -> Result<Vec<u8>, Vec<AssemblyError>> {
+    assemble_object(input).map(|object| object.code)
+}
+
+/// Assemble source into a Valida ELF executable, or report every error found in it.
+pub fn assemble_elf(input: &str) -> Result<Vec<u8>, Vec<AssemblyError>> {
+    assemble_object(input).map(|object| object.to_elf())
+}
 
-    Ok(vec)
+/// Assemble source into a relocatable Valida ELF object, or report every error found in it.
+pub fn assemble_relocatable(input: &str) -> Result<Vec<u8>, Vec<AssemblyError>> {
+    assemble_relocatable_object(input).map(|object| object.to_relocatable_elf())
 }
@@ -0,0 +1,195 @@
+use crate::{check_arity, column, parse_name, AssemblyError, AssemblyParser, Rule};
+use pest::Parser;
+use std::collections::HashMap;
+
+/// Macros may invoke other macros, up to this depth.
+const MAX_DEPTH: usize = 64;
+
+/// A `.macro` definition.
+struct Macro {
+    parameters: Vec<String>,
+    body: Vec<String>,
+}
+
+/// What a line means to the macro expander.
+enum MacroLine {
+    /// `.macro name, parameters...`, with the column of the directive
+    Definition(usize, String, Macro),
+    /// A use of a macro: any label before it, the macro and the arguments
+    Invocation(Option<String>, String, Vec<String>),
+    /// `.endm` outside a definition, with its column
+    End(usize),
+    Other,
+}
+
+/// Whether a line, ignoring any comment, is the directive `name`.
+fn is_directive(source: &str, name: &str) -> bool {
+    source.split(';').next().unwrap().split_whitespace().next() == Some(name)
+}
+
+struct Expander {
+    macros: HashMap<String, Macro>,
+    lines: Vec<(usize, String)>,
+    /// The number of expansions so far, which replaces `\@` in a macro body
+    expansions: usize,
+}
+
+impl Expander {
+    fn classify(&self, source: &str) -> Result<MacroLine, (usize, String)> {
+        // Syntax errors are reported when the line is assembled
+        let Ok(pairs) = AssemblyParser::parse(Rule::line, source) else {
+            return Ok(MacroLine::Other);
+        };
+        let mut label = None;
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::label => label = Some(pair.as_str().to_string()),
+                Rule::directive => {
+                    let mut inner_pairs = pair.into_inner();
+                    let name_pair = inner_pairs.next().unwrap();
+                    match name_pair.as_str() {
+                        ".macro" => {
+                            let names = inner_pairs
+                                .map(|pair| parse_name(&pair).map(str::to_string))
+                                .collect::<Result<Vec<String>, _>>()?;
+                            let Some((name, parameters)) = names.split_first() else {
+                                return Err((
+                                    column(&name_pair),
+                                    "expected a name for `.macro`".to_string(),
+                                ));
+                            };
+                            let definition = Macro {
+                                parameters: parameters.to_vec(),
+                                body: vec![],
+                            };
+                            return Ok(MacroLine::Definition(
+                                column(&name_pair),
+                                name.clone(),
+                                definition,
+                            ));
+                        }
+                        ".endm" => return Ok(MacroLine::End(column(&name_pair))),
+                        _ => {}
+                    }
+                }
+                Rule::instruction => {
+                    let mut inner_pairs = pair.into_inner();
+                    let mnemonic_pair = inner_pairs.next().unwrap();
+                    let Some(definition) = self.macros.get(mnemonic_pair.as_str()) else {
+                        continue;
+                    };
+                    let arguments: Vec<String> =
+                        inner_pairs.map(|pair| pair.as_str().to_string()).collect();
+                    check_arity(&mnemonic_pair, arguments.len(), definition.parameters.len())?;
+                    return Ok(MacroLine::Invocation(
+                        label,
+                        mnemonic_pair.as_str().to_string(),
+                        arguments,
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(MacroLine::Other)
+    }
+
+    /// Add a line, replacing it with the body of the macro it invokes, if any.
+    fn expand(
+        &mut self,
+        line: usize,
+        source: String,
+        depth: usize,
+        errors: &mut Vec<AssemblyError>,
+    ) {
+        let result = match self.classify(&source) {
+            Ok(MacroLine::Definition(column, _, _)) => {
+                Err((column, "`.macro` inside a macro".to_string()))
+            }
+            Ok(MacroLine::End(column)) => Err((column, "`.endm` without `.macro`".to_string())),
+            Ok(MacroLine::Invocation(..)) if depth == MAX_DEPTH => {
+                Err((1, "macros are nested too deeply".to_string()))
+            }
+            Ok(MacroLine::Invocation(label, name, arguments)) => {
+                if let Some(label) = label {
+                    self.lines.push((line, label));
+                }
+                let definition = &self.macros[&name];
+                // Replace longer parameters first, so that `\a` doesn't replace part of `\ab`
+                let mut substitutions: Vec<(String, &str)> = definition
+                    .parameters
+                    .iter()
+                    .map(|parameter| format!("\\{}", parameter))
+                    .zip(arguments.iter().map(String::as_str))
+                    .collect();
+                substitutions.sort_by_key(|(parameter, _)| std::cmp::Reverse(parameter.len()));
+                let counter = self.expansions.to_string();
+                let body: Vec<String> = definition
+                    .body
+                    .iter()
+                    .map(|body_line| {
+                        let mut body_line = body_line.replace("\\@", &counter);
+                        for (parameter, argument) in &substitutions {
+                            body_line = body_line.replace(parameter, argument);
+                        }
+                        body_line
+                    })
+                    .collect();
+                self.expansions += 1;
+                for body_line in body {
+                    self.expand(line, body_line, depth + 1, errors);
+                }
+                Ok(())
+            }
+            Ok(MacroLine::Other) => {
+                self.lines.push((line, source.clone()));
+                Ok(())
+            }
+            Err(error) => Err(error),
+        };
+        if let Err((column, message)) = result {
+            errors.push(AssemblyError::new(line, column, &source, message));
+        }
+    }
+}
+
+/// Collect `.macro` definitions, and replace each use of a macro with its body, with `\name`
+/// replaced by the argument for the parameter `name`. Every resulting line keeps the number
+/// of the source line it came from, so that errors in an expansion point at its use.
+pub(crate) fn expand_macros(input: &str, errors: &mut Vec<AssemblyError>) -> Vec<(usize, String)> {
+    let mut expander = Expander {
+        macros: HashMap::new(),
+        lines: vec![],
+        expansions: 0,
+    };
+    // The definition being read: its line, name and body so far
+    let mut definition: Option<(usize, String, Macro)> = None;
+    for (index, source) in input.lines().enumerate() {
+        let line = index + 1;
+        if let Some((start, name, mut body)) = definition.take() {
+            if is_directive(source, ".endm") {
+                expander.macros.insert(name, body);
+            } else {
+                body.body.push(source.to_string());
+                definition = Some((start, name, body));
+            }
+            continue;
+        }
+        match expander.classify(source) {
+            Ok(MacroLine::Definition(_, name, body)) => definition = Some((line, name, body)),
+            Ok(_) => expander.expand(line, source.to_string(), 0, errors),
+            Err((column, message)) => {
+                errors.push(AssemblyError::new(line, column, source, message));
+            }
+        }
+    }
+    if let Some((start, _, _)) = definition {
+        let source = input.lines().nth(start - 1).unwrap();
+        errors.push(AssemblyError::new(
+            start,
+            1,
+            source,
+            "`.macro` without `.endm`".to_string(),
+        ));
+    }
+    expander.lines
+}
@@ -0,0 +1,93 @@
+use crate::RelocationKind;
+use valida_opcodes::BYTES_PER_INSTR;
+
+/// Every pseudo-instruction, with its number of operands and the number of instructions it
+/// expands to.
+pub const PSEUDO_INSTRUCTIONS: &[(&str, usize, u32)] = &[
+    ("nop", 0, 1),
+    ("mov", 2, 1),
+    ("li", 2, 1),
+    ("not", 2, 1),
+    ("neg", 2, 1),
+    ("gt", 3, 1),
+    ("gte", 3, 1),
+    ("sgt", 3, 1),
+    ("sge", 3, 1),
+    ("j", 1, 1),
+    ("blt", 4, 2),
+    ("ble", 4, 2),
+    ("bgt", 4, 2),
+    ("bge", 4, 2),
+    ("call", 2, 2),
+    ("ret", 0, 1),
+];
+
+/// The number of operands of a pseudo-instruction, and the number of instructions it
+/// expands to.
+pub fn pseudo_instruction_format(mnemonic: &str) -> Option<(usize, u32)> {
+    PSEUDO_INSTRUCTIONS
+        .iter()
+        .find(|(name, _, _)| *name == mnemonic)
+        .map(|&(_, num_operands, length)| (num_operands, length))
+}
+
+/// Expand the pseudo-instruction at `pc` into the mnemonics and operands of the
+/// instructions that implement it:
+///
+/// - `nop` jumps to the next instruction, and `j label` jumps to `label`.
+/// - `mov a(fp), b(fp)` copies a cell, and `li a(fp), value` loads a 32-bit value.
+/// - `not` and `neg` take the bitwise and two's complement negation of a cell.
+/// - `gt`, `gte`, `sgt` and `sge` are `lt`, `lte`, `slt` and `sle` with their operands
+///   swapped.
+/// - `blt label, b(fp), c(fp), t(fp)` branches if b < c, keeping the comparison in t, and
+///   likewise for `ble`, `bgt` and `bge`. Comparisons are unsigned.
+/// - `call label, frame` calls a function whose frame starts `frame` bytes from the
+///   caller's, storing the return address at 0(fp) and the way back to the caller's frame
+///   at 8(fp) of the callee's frame. `ret` returns from such a call.
+pub(crate) fn expand(mnemonic: &str, operands: &[i32], pc: u32) -> Vec<(&'static str, Vec<i32>)> {
+    match (mnemonic, operands) {
+        ("nop", []) => {
+            let next = BYTES_PER_INSTR * (pc + 1);
+            vec![("beq", vec![next as i32, 0, 0])]
+        }
+        ("mov", &[a, b]) => vec![("addi", vec![a, b, 0])],
+        ("li", &[a, value]) => {
+            // imm32 takes the bytes of the value, most significant first
+            let bytes = value.to_be_bytes().map(|byte| byte as i32);
+            vec![("imm32", [&[a], &bytes[..]].concat())]
+        }
+        ("not", &[a, b]) => vec![("xori", vec![a, b, -1])],
+        ("neg", &[a, b]) => vec![("muli", vec![a, b, -1])],
+        ("gt", &[a, b, c]) => vec![("lt", vec![a, c, b])],
+        ("gte", &[a, b, c]) => vec![("lte", vec![a, c, b])],
+        ("sgt", &[a, b, c]) => vec![("slt", vec![a, c, b])],
+        ("sge", &[a, b, c]) => vec![("sle", vec![a, c, b])],
+        ("j", &[target]) => vec![("beq", vec![target, 0, 0])],
+        ("blt", &[target, b, c, t]) => vec![("lt", vec![t, b, c]), ("bnei", vec![target, t, 0])],
+        ("ble", &[target, b, c, t]) => vec![("lte", vec![t, b, c]), ("bnei", vec![target, t, 0])],
+        ("bgt", &[target, b, c, t]) => vec![("lt", vec![t, c, b]), ("bnei", vec![target, t, 0])],
+        ("bge", &[target, b, c, t]) => vec![("lte", vec![t, c, b]), ("bnei", vec![target, t, 0])],
+        ("call", &[target, frame]) => {
+            let offset = frame.wrapping_neg().to_be_bytes().map(|byte| byte as i32);
+            vec![
+                ("imm32", [&[frame + 8], &offset[..]].concat()),
+                ("jal", vec![frame, target, frame]),
+            ]
+        }
+        ("ret", []) => vec![("jalv", vec![-4, 0, 8])],
+        _ => unreachable!("`{}` with {} operands", mnemonic, operands.len()),
+    }
+}
+
+/// The operand of a pseudo-instruction that may be an address, and where it ends up: the
+/// index of the instruction in the expansion, the machine operand, and how the linker
+/// relocates it.
+pub(crate) fn address_operand(mnemonic: &str) -> Option<(usize, u32, usize, RelocationKind)> {
+    match mnemonic {
+        "j" => Some((0, 0, 0, RelocationKind::Word)),
+        "blt" | "ble" | "bgt" | "bge" => Some((0, 1, 0, RelocationKind::Word)),
+        "call" => Some((0, 1, 1, RelocationKind::Word)),
+        "li" => Some((1, 0, 1, RelocationKind::Imm32)),
+        _ => None,
+    }
+}
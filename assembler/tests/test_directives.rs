@@ -0,0 +1,162 @@
+use valida_assembler::{assemble, assemble_object, ObjectSymbol, Section, DATA_START};
+use valida_elf::{load_elf_object_file, load_elf_symbols, Symbol};
+use valida_machine::{ProgramROM, Word};
+
+const PROGRAM: &str = r#"	.globl	main
+	.equ	COUNT, 3
+	.rodata
+greeting:
+	.asciz	"hi\n"
+	.align	8
+table:
+	.word	main, end, COUNT, -1
+	.data
+counter:
+	.byte	1, 255, -128
+	.bss
+buffer:
+	.zero	16
+	.text
+main:
+	imm32	-4(fp), 0, 0, 0, 0
+	addi	-8(fp), -4(fp), table
+.LBB0_1:
+	beq	.LBB0_1, 0(fp), 0(fp)
+end:
+	stop
+"#;
+
+#[test]
+fn lays_out_data() {
+    let object = assemble_object(PROGRAM).unwrap();
+    assert_eq!(object.code, assemble(PROGRAM).unwrap());
+    assert_eq!(object.code.len(), 4 * 24);
+
+    // `table` is aligned to 8 bytes, after the 4 bytes of `greeting`
+    assert_eq!(object.rodata.address, DATA_START);
+    let mut rodata = b"hi\n\0\0\0\0\0".to_vec();
+    for word in [0, 72, 3, -1] {
+        rodata.extend(i32::to_le_bytes(word));
+    }
+    assert_eq!(object.rodata.bytes, rodata);
+    assert_eq!(object.data.address, DATA_START + 24);
+    assert_eq!(object.data.bytes, [1, 255, 128]);
+    assert_eq!(object.bss.address, DATA_START + 28);
+    assert_eq!(object.bss.bytes, [0; 16]);
+
+    // The address of `table` is an immediate operand
+    assert_eq!(
+        &object.code[24 + 12..24 + 16],
+        (DATA_START + 8).to_le_bytes()
+    );
+
+    let symbol = |name: &str| object.symbols.iter().find(|symbol| symbol.name == name);
+    let main = ObjectSymbol {
+        name: "main".to_string(),
+        section: Section::Text,
+        value: 0,
+        size: 72,
+        global: true,
+    };
+    assert_eq!(symbol("main"), Some(&main));
+    assert_eq!(symbol("table").unwrap().value, DATA_START + 8);
+    assert_eq!(symbol("table").unwrap().size, 16);
+    assert_eq!(symbol("COUNT").unwrap().section, Section::Absolute);
+    assert_eq!(symbol("COUNT").unwrap().value, 3);
+    assert_eq!(symbol(".LBB0_1"), None);
+}
+
+#[test]
+fn writes_loadable_elf() {
+    let object = assemble_object(PROGRAM).unwrap();
+    let elf = object.to_elf();
+
+    let program = load_elf_object_file(elf.clone()).unwrap();
+    let instructions = |rom: &ProgramROM<i32>| -> Vec<(u32, [i32; 5])> {
+        rom.0
+            .iter()
+            .map(|instruction| (instruction.opcode, instruction.operands.0))
+            .collect()
+    };
+    assert_eq!(
+        instructions(&program.code),
+        instructions(&ProgramROM::from_machine_code(&object.code))
+    );
+    assert_eq!(program.initial_program_counter, 0);
+    // The byte at the lowest address is the least significant
+    assert_eq!(program.data[&DATA_START], Word([0, b'\n', b'i', b'h']));
+    assert_eq!(program.data[&(DATA_START + 16)], Word::from(3));
+    assert_eq!(program.data[&(DATA_START + 20)], Word::from(u32::MAX));
+    assert_eq!(program.data[&(DATA_START + 24)], Word([0, 128, 255, 1]));
+
+    assert_eq!(
+        load_elf_symbols(&elf),
+        [
+            Symbol {
+                name: "main".to_string(),
+                address: 0,
+                size: 72,
+            },
+            Symbol {
+                name: "end".to_string(),
+                address: 72,
+                size: 24,
+            },
+        ]
+    );
+}
+
+#[test]
+fn reports_directive_errors() {
+    let program = r#"	.globl	missing
+	.word	1
+	.data
+	stop
+	.byte	256
+	.ascii	5
+	.align	3
+	.frob
+	.bss
+	.word	0
+"#;
+    let errors = assemble_object(program).unwrap_err();
+    let found: Vec<(usize, usize, &str)> = errors
+        .iter()
+        .map(|error| (error.line, error.column, error.message.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (1, 9, "undefined label `missing`"),
+            (2, 2, "`.word` is only allowed in a data section"),
+            (4, 2, "instructions must be in `.text`"),
+            (5, 8, "256 doesn't fit in a byte"),
+            (6, 9, "expected a string, found `5`"),
+            (7, 9, "alignment 3 is not a power of two"),
+            (8, 2, "unknown directive `.frob`"),
+            (10, 2, "`.bss` can only be filled with `.zero`"),
+        ]
+    );
+}
+
+#[test]
+fn rejects_data_past_the_end_of_memory() {
+    // `.bss` starts at 0x80000000, so it can't hold 0x80000000 bytes
+    let program = "\t.data
+\t.align\t0x40000000
+\t.byte\t1
+\t.bss
+\t.align\t0x40000000
+\t.zero\t1
+\t.zero\t0x7fffffff
+";
+    let errors = assemble_object(program).unwrap_err();
+    let found: Vec<(usize, usize, &str)> = errors
+        .iter()
+        .map(|error| (error.line, error.column, error.message.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [(7, 8, "`.zero 0x7fffffff` runs past the end of memory")]
+    );
+}
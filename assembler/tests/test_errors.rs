@@ -0,0 +1,45 @@
+use valida_assembler::{assemble, AssemblyError};
+
+const INVALID_PROGRAM: &str = "main:
+\taddi\t-4(fp), 0
+\tfoo\t1, 2
+\tjal\t-8(fp), nowhere, -8
+\timm32\t-4(fp), 0, 0, 0, 99999999999
+\taddi\t-4(fp), $, 0
+main:
+\tstop
+";
+
+#[test]
+fn reports_every_error() {
+    let errors = assemble(INVALID_PROGRAM).unwrap_err();
+    let found: Vec<(usize, usize, &str)> = errors
+        .iter()
+        .map(|error| (error.line, error.column, error.message.as_str()))
+        .collect();
+    assert_eq!(
+        found[..4],
+        [
+            (2, 2, "expected 3 operands for `addi`, found 2"),
+            (3, 2, "unknown mnemonic `foo`"),
+            (4, 14, "undefined label `nowhere`"),
+            (5, 25, "invalid integer `99999999999`"),
+        ]
+    );
+    // A syntax error, reported by the parser
+    assert_eq!((errors[4].line, errors[4].column), (6, 15));
+    assert_eq!(
+        (errors[5].line, errors[5].message.as_str()),
+        (7, "duplicate label `main`")
+    );
+    assert_eq!(errors.len(), 6);
+}
+
+#[test]
+fn error_display_points_at_column() {
+    let error = AssemblyError::new(3, 2, "\tfoo\t1, 2", "unknown mnemonic `foo`".to_string());
+    assert_eq!(
+        error.to_string(),
+        "3:2: unknown mnemonic `foo`\n\tfoo\t1, 2\n\t^"
+    );
+}
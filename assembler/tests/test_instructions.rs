@@ -0,0 +1,81 @@
+use valida_assembler::{assemble, assemble_object, INSTRUCTIONS};
+use valida_opcodes::Opcode;
+
+/// The opcode and operands of each instruction in machine code.
+fn decode(code: &[u8]) -> Vec<(u32, [i32; 5])> {
+    let word = |bytes: &[u8]| u32::from_le_bytes(bytes.try_into().unwrap());
+    code.chunks_exact(24)
+        .map(|instruction| {
+            let mut operands = [0; 5];
+            for (i, operand) in operands.iter_mut().enumerate() {
+                *operand = word(&instruction[4 + 4 * i..8 + 4 * i]) as i32;
+            }
+            (word(&instruction[..4]), operands)
+        })
+        .collect()
+}
+
+#[test]
+fn assembles_every_opcode() {
+    for value in 0..=u16::MAX as u32 {
+        let Ok(opcode) = Opcode::try_from(value) else {
+            continue;
+        };
+        let Some((mnemonic, _, layout)) = INSTRUCTIONS.iter().find(|(_, op, _)| *op == value)
+        else {
+            panic!("No mnemonic for {:?}", opcode);
+        };
+        let operands = vec!["1"; layout.num_operands()].join(", ");
+        let code = assemble(&format!("\t{}\t{}\n", mnemonic, operands)).unwrap();
+        assert_eq!(decode(&code)[0].0, value, "{}", mnemonic);
+    }
+}
+
+#[test]
+fn assembles_literals_and_expressions() {
+    let program = r"	.equ	SIZE, 0x10
+	.equ	LAST, SIZE-4
+	.data
+table:
+	.word	table+LAST, 'A', 0b101
+	.text
+start:
+	imm32	-4(fp), 0xff, 0B11, '\n', 'z'
+	addi	-8(fp), -4(fp), -0x80000000
+	addi	LAST(fp), -4(fp), 0xffffffff
+	beq	start+24, 0(fp), 0(fp)
+	loadfp	-12(fp), SIZE - 4 + 1
+";
+    let object = assemble_object(program).unwrap();
+    assert_eq!(
+        decode(&object.code),
+        [
+            (7, [-4, 255, 3, 10, 122]),
+            (100, [-8, -4, i32::MIN, 0, 1]),
+            (100, [12, -4, -1, 0, 1]),
+            (5, [24, 0, 0, 0, 0]),
+            (10, [-12, 13, 0, 0, 0]),
+        ]
+    );
+    let table = object.data.address as i32 + 12;
+    let mut data = table.to_le_bytes().to_vec();
+    data.extend(65i32.to_le_bytes());
+    data.extend(5i32.to_le_bytes());
+    assert_eq!(object.data.bytes, data);
+}
+
+#[test]
+fn reports_invalid_literals() {
+    let errors = assemble("\taddi\t-4(fp), 0x100000000, 2\n\t.equ\tSIZE+1, 2\n").unwrap_err();
+    let found: Vec<(usize, usize, &str)> = errors
+        .iter()
+        .map(|error| (error.line, error.column, error.message.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (1, 15, "invalid integer `0x100000000`"),
+            (2, 7, "expected a name, found `SIZE+1`"),
+        ]
+    );
+}
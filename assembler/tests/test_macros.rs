@@ -0,0 +1,91 @@
+use valida_assembler::assemble;
+
+#[test]
+fn expands_pseudo_instructions() {
+    let program = "main:
+	li	-4(fp), 0x12345678
+	mov	-8(fp), -4(fp)
+	call	fib, -28
+	nop
+	j	main
+	bgt	main, -4(fp), -8(fp), -12(fp)
+	gte	-12(fp), -4(fp), -8(fp)
+	not	-16(fp), -4(fp)
+	neg	-16(fp), -4(fp)
+fib:
+	ret
+";
+    // `call` takes two instructions, so `nop` is the 5th instruction
+    let expanded = "main:
+	imm32	-4(fp), 0x12, 0x34, 0x56, 0x78
+	addi	-8(fp), -4(fp), 0
+	imm32	-20(fp), 0, 0, 0, 28
+	jal	-28(fp), fib, -28
+	beq	120, 0(fp), 0(fp)
+	beq	main, 0(fp), 0(fp)
+	lt	-12(fp), -8(fp), -4(fp)
+	bnei	main, -12(fp), 0
+	lte	-12(fp), -8(fp), -4(fp)
+	xori	-16(fp), -4(fp), -1
+	muli	-16(fp), -4(fp), -1
+fib:
+	jalv	-4(fp), 0(fp), 8(fp)
+";
+    assert_eq!(assemble(program).unwrap(), assemble(expanded).unwrap());
+}
+
+#[test]
+fn expands_macros() {
+    let program = r"	.macro	swap, a, b, tmp
+	mov	\tmp, \a
+	mov	\a, \b
+	mov	\b, \tmp
+	.endm
+	.macro	spin
+loop\@:
+	j	loop\@
+	.endm
+start:	swap	-4(fp), -8(fp), -12(fp)
+	spin
+	spin
+";
+    let expanded = "start:
+	addi	-12(fp), -4(fp), 0
+	addi	-4(fp), -8(fp), 0
+	addi	-8(fp), -12(fp), 0
+loop1:
+	beq	loop1, 0(fp), 0(fp)
+loop2:
+	beq	loop2, 0(fp), 0(fp)
+";
+    assert_eq!(assemble(program).unwrap(), assemble(expanded).unwrap());
+}
+
+#[test]
+fn reports_macro_errors() {
+    let program = r"	.macro	twice, x
+	\x
+	\x
+	.endm
+	twice	1, 2
+	.endm
+	twice	foo
+	.macro	unfinished
+";
+    let errors = assemble(program).unwrap_err();
+    let found: Vec<(usize, usize, &str)> = errors
+        .iter()
+        .map(|error| (error.line, error.column, error.message.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (5, 2, "expected 1 operand for `twice`, found 2"),
+            (6, 2, "`.endm` without `.macro`"),
+            // Errors in an expansion are reported where the macro is used
+            (7, 2, "unknown mnemonic `foo`"),
+            (7, 2, "unknown mnemonic `foo`"),
+            (8, 1, "`.macro` without `.endm`"),
+        ]
+    );
+}
@@ -0,0 +1,83 @@
+use valida_assembler::{
+    assemble_object, assemble_relocatable_object, ObjectSymbol, Relocation, RelocationKind,
+    RelocationTarget, Section,
+};
+
+#[test]
+fn records_relocations() {
+    let program = "	li	-4(fp), table+4
+	call	print, -16
+	.data
+table:
+	.word	table, end-table
+end:
+";
+    assert!(assemble_object(program).is_err());
+    let object = assemble_relocatable_object(program).unwrap();
+    let relocation = |section, offset, kind, target, addend| Relocation {
+        section,
+        offset,
+        kind,
+        target,
+        addend,
+    };
+    assert_eq!(
+        object.relocations,
+        [
+            // The difference of two labels doesn't change when `.data` moves
+            relocation(
+                Section::Data,
+                0,
+                RelocationKind::Word,
+                RelocationTarget::Section(Section::Data),
+                0
+            ),
+            // The bytes of the value are the last four operands of `imm32`
+            relocation(
+                Section::Text,
+                8,
+                RelocationKind::Imm32,
+                RelocationTarget::Section(Section::Data),
+                4
+            ),
+            // `call` expands to `imm32` and then `jal`, whose second operand is the target
+            relocation(
+                Section::Text,
+                2 * 24 + 8,
+                RelocationKind::Word,
+                RelocationTarget::Symbol("print".to_string()),
+                0
+            ),
+        ]
+    );
+    assert!(object.symbols.contains(&ObjectSymbol {
+        name: "print".to_string(),
+        section: Section::Undefined,
+        value: 0,
+        size: 0,
+        global: true,
+    }));
+}
+
+#[test]
+fn reports_relocation_errors() {
+    let program = "	mov	-8(fp), table
+	.data
+table:
+	.word	table+table
+	.byte	table
+";
+    let errors = assemble_relocatable_object(program).unwrap_err();
+    let found: Vec<(usize, usize, &str)> = errors
+        .iter()
+        .map(|error| (error.line, error.column, error.message.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (1, 14, "`table` can't be an address"),
+            (4, 8, "`table+table` can't be relocated"),
+            (5, 8, "an address doesn't fit in a byte"),
+        ]
+    );
+}
@@ -1,21 +1,32 @@
-use clap::Parser;
+use clap::{Arg, ArgMatches, Command, Parser};
+use reedline::{DefaultPrompt, DefaultPromptSegment, Reedline, Signal};
 use std::fs;
 use std::fs::File;
-use std::io::{stdout, Write};
+use std::io::{stdin, stdout, BufWriter, IsTerminal, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
 
-use valida_basic::BasicMachine;
+use valida_basic::dap::DapServer;
+use valida_basic::debugger::{
+    parse_number, Breakpoint, Condition, Debugger, Operand, StopReason, Watchpoint,
+};
+use valida_basic::gdb::GdbServer;
+use valida_basic::trace::MemoryAccessKind;
+use valida_basic::{BasicMachine, Snapshot};
 
 use p3_baby_bear::BabyBear;
 
 use p3_fri::{FriConfig, TwoAdicFriPcs, TwoAdicFriPcsConfig};
 use valida_cpu::MachineWithCpuChip;
 use valida_machine::{
-    AdviceProvider, GlobalAdviceProvider, Machine, MachineProof, ProgramROM, StdinAdviceProvider,
-    StoppingFlag,
+    AdviceProvider, FixedAdviceProvider, GlobalAdviceProvider, Machine, MachineProof, ProgramROM,
+    RecordingAdviceProvider, StdinAdviceProvider,
 };
 use valida_memory::MachineWithMemoryChip;
+use valida_opcodes::BYTES_PER_INSTR;
 
 use valida_elf::{load_executable_file, Program};
+use valida_host_call::{DefaultHostCallHandler, MachineWithHostCallChip};
 use valida_program::MachineWithProgramChip;
 use valida_static_data::MachineWithStaticDataChip;
 
@@ -34,12 +45,15 @@ use valida_machine::StarkConfigImpl;
 use valida_machine::__internal::p3_commit::ExtensionMmcs;
 use valida_output::MachineWithOutputChip;
 
-use reedline_repl_rs::clap::{Arg, ArgMatches, Command};
-use reedline_repl_rs::{Repl, Result};
+/// REPL commands print the message they return, whether it is an error or not.
+type Result<T> = std::result::Result<T, String>;
+
+type Handler = fn(ArgMatches, &mut Context) -> Result<Option<String>>;
 
 #[derive(Parser, Clone)]
 struct Args {
-    /// Command option either "run" or "prove" or "verify" or "interactive"
+    /// Command option either "run" or "prove" or "verify" or "profile" or "interactive" or
+    /// "gdbserver" or "dap"
     #[arg(name = "Action Option")]
     action: String,
 
@@ -47,9 +61,10 @@ struct Args {
     #[arg(name = "PROGRAM FILE")]
     program: String,
 
-    /// The output file for run or prove, or the input file for verify
+    /// The output file for run or prove, the input file for verify, or the folded stack
+    /// output file for profile. Not used by interactive, gdbserver or dap
     #[arg(name = "ACTION FILE")]
-    action_file: String,
+    action_file: Option<String>,
 
     /// Stack height (which is also the initial frame pointer value)
     #[arg(long, default_value = "16777216")]
@@ -58,61 +73,108 @@ struct Args {
     /// Advice file
     #[arg(name = "Advice file")]
     advice: Option<String>,
+
+    /// Snapshot file to resume execution from ("run", "interactive", "gdbserver" and "dap" only)
+    #[arg(long)]
+    restore: Option<String>,
+
+    /// Write the instruction-level execution trace to this file, as JSON Lines
+    #[arg(long)]
+    trace_out: Option<String>,
+
+    /// Save the advice bytes the program consumed to this file, so that the same execution
+    /// can be proven or verified later by passing it as the advice file
+    #[arg(long)]
+    record_advice: Option<String>,
+
+    /// Input file read by the guest through host calls
+    #[arg(long)]
+    input: Option<String>,
+
+    /// Number of functions to list in the profile table
+    #[arg(long, default_value = "20")]
+    top: usize,
+
+    /// TCP port that gdbserver listens on, on the loopback interface
+    #[arg(long, default_value = "1234")]
+    port: u16,
+
+    /// File of REPL commands to run before the interactive prompt ("interactive" only)
+    #[arg(long)]
+    script: Option<String>,
+}
+
+fn host_call_handler(args: &Args) -> DefaultHostCallHandler {
+    match &args.input {
+        Some(file_name) => DefaultHostCallHandler::new(
+            fs::read(file_name)
+                .expect(format!("Failed to read input file: {}", file_name).as_str()),
+        ),
+        None => DefaultHostCallHandler::default(),
+    }
+}
+
+fn read_snapshot(file_name: &str) -> Snapshot {
+    let bytes =
+        fs::read(file_name).expect(format!("Failed to read snapshot file: {}", file_name).as_str());
+    Snapshot::from_bytes(&bytes).expect("Snapshot deserialization failed")
 }
 
 struct Context {
-    machine_: BasicMachine<BabyBear>,
+    debugger_: Debugger<BabyBear>,
     args_: Args,
-    breakpoints_: Vec<u32>,
-    stopped_: StoppingFlag,
     last_fp_: u32,
     recorded_current_fp_: u32,
     last_fp_size_: u32,
-    advice: GlobalAdviceProvider,
+    /// The command files being run, innermost last, so that a file can't source itself
+    sourcing_: Vec<PathBuf>,
 }
 
 impl Context {
     fn new(args: &Args) -> Context {
+        let program_file = fs::read(&args.program)
+            .expect(format!("Failed to read executable file: {}", &args.program).as_str());
+        let mut debugger = Debugger::load(
+            &program_file,
+            GlobalAdviceProvider::new(&args.advice),
+            args.stack_height,
+        )
+        .unwrap_or_else(|error| panic!("Invalid executable file {}: {}", args.program, error));
+        debugger
+            .machine
+            .host_call_mut()
+            .set_handler(host_call_handler(args));
         let mut context = Context {
-            machine_: BasicMachine::<BabyBear>::default(),
+            debugger_: debugger,
             args_: (*args).clone(),
-            breakpoints_: Vec::new(),
-            stopped_: StoppingFlag::DidNotStop,
             last_fp_: args.stack_height,
             recorded_current_fp_: args.stack_height,
             last_fp_size_: 0,
-            advice: GlobalAdviceProvider::new(&args.advice),
+            sourcing_: vec![],
         };
 
-        let Program {
-            code,
-            data,
-            initial_program_counter,
-        } = load_executable_file(
-            fs::read(&args.program)
-                .expect(format!("Failed to read executable file: {}", &args.program).as_str()),
-        );
-
-        context.machine_.program_mut().set_program_rom(&code);
-        context.machine_.static_data_mut().load(data);
-        context.machine_.cpu_mut().pc = initial_program_counter;
-        context.machine_.cpu_mut().fp = args.stack_height;
-        context.machine_.cpu_mut().save_register_state();
+        if let Some(snapshot_file) = &args.restore {
+            context.restore_snapshot(&read_snapshot(snapshot_file));
+        }
 
         context
     }
 
-    fn step(&mut self) -> (StoppingFlag, u32) {
-        // do not execute if already stopped
-        if self.stopped_ == StoppingFlag::DidStop {
-            return (StoppingFlag::DidStop, 0);
-        }
-        let state = self.machine_.step(&mut self.advice);
-        let pc = self.machine_.cpu().pc;
-        let fp = self.machine_.cpu().fp;
+    fn restore_snapshot(&mut self, snapshot: &Snapshot) {
+        self.debugger_.restore(snapshot);
+        self.last_fp_ = snapshot.fp;
+        self.recorded_current_fp_ = snapshot.fp;
+        self.last_fp_size_ = 0;
+    }
 
-        let instruction = self.machine_.program().program_rom.get_instruction(pc);
-        println!("{:4} : {:?}", pc, instruction.to_string());
+    fn step(&mut self) -> (StopReason, u32) {
+        let state = self.debugger_.step();
+        if state == StopReason::Exited {
+            return (state, 0);
+        }
+        let pc = self.debugger_.machine.cpu().pc;
+        let fp = self.debugger_.machine.cpu().fp;
+        self.print_instruction(pc);
 
         // check if fp is changed
         if fp != self.recorded_current_fp_ {
@@ -125,22 +187,107 @@ impl Context {
 
         (state, pc)
     }
+
+    /// Reset the frame bookkeeping after execution moved backwards.
+    fn moved_back(&mut self) {
+        let pc = self.debugger_.machine.cpu().pc;
+        let fp = self.debugger_.machine.cpu().fp;
+        self.print_instruction(pc);
+
+        self.last_fp_ = fp;
+        self.recorded_current_fp_ = fp;
+        self.last_fp_size_ = 0;
+    }
+
+    fn print_instruction(&self, pc: u32) {
+        let instruction = self
+            .debugger_
+            .machine
+            .program()
+            .program_rom
+            .get_instruction(pc);
+        match self.debugger_.location(pc) {
+            Some(location) => println!("{:4} : {:?}  <{}>", pc, instruction.to_string(), location),
+            None => println!("{:4} : {:?}", pc, instruction.to_string()),
+        }
+    }
 }
 
-fn init_context(_args: ArgMatches, _context: &mut Context) -> Result<Option<String>> {
-    Ok(Some(String::from("created machine")))
+fn describe_stop(reason: StopReason, context: &Context) -> Option<String> {
+    match reason {
+        StopReason::Step | StopReason::Interrupted => None,
+        StopReason::Exited => Some(String::from("Execution stopped")),
+        StopReason::StartOfHistory => {
+            Some(String::from("Reached the start of the recorded history"))
+        }
+        StopReason::Breakpoint(index) => Some(format!(
+            "Execution stopped at breakpoint {}, PC: {}",
+            index,
+            context.debugger_.machine.cpu().pc
+        )),
+        StopReason::Watchpoint(index, access) => {
+            let kind = match access.kind {
+                MemoryAccessKind::Read => "read",
+                MemoryAccessKind::Write => "write",
+            };
+            Some(format!(
+                "Execution stopped at watchpoint {}: {} of {} at 0x{:x}",
+                index, kind, access.value, access.addr
+            ))
+        }
+    }
+}
+
+fn reset(_args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    // Reload the program from disk, keeping the breakpoints, the watchpoints and the
+    // command files being run
+    let breakpoints = std::mem::take(&mut context.debugger_.breakpoints);
+    let watchpoints = std::mem::take(&mut context.debugger_.watchpoints);
+    let sourcing = std::mem::take(&mut context.sourcing_);
+    *context = Context::new(&context.args_.clone());
+    context.debugger_.breakpoints = breakpoints;
+    context.debugger_.watchpoints = watchpoints;
+    context.sourcing_ = sourcing;
+    Ok(Some(format!("Reloaded {}", context.args_.program)))
+}
+
+/// Parse an optional count argument, such as the number of steps.
+fn parse_count(args: &ArgMatches, name: &str, default: u32) -> Result<u32> {
+    match args.get_one::<String>(name) {
+        Some(count) => count
+            .parse::<u32>()
+            .map_err(|_| format!("Expected a number, got {}", count)),
+        None => Ok(default),
+    }
+}
+
+/// Resolve an address such as `0x1000`, `fp-8` or `[fp-8]`. Frame addresses are resolved
+/// against the current fp.
+fn resolve_address(context: &Context, text: &str) -> Result<u32> {
+    let operand = match text.starts_with('[') {
+        true => Operand::parse(text),
+        false => Operand::parse(&format!("[{}]", text)),
+    };
+    match operand {
+        Some(Operand::Cell(addr)) => Ok(addr),
+        Some(Operand::FrameCell(offset)) => {
+            let fp = context.debugger_.machine.cpu().fp;
+            Ok(fp.wrapping_add(offset as u32))
+        }
+        _ => Err(format!("Invalid address: {}", text)),
+    }
 }
 
 fn status(_args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
     // construct machine status
     let mut status = String::new();
     status.push_str("FP: ");
-    status.push_str(&context.machine_.cpu().fp.to_string());
+    status.push_str(&context.debugger_.machine.cpu().fp.to_string());
     status.push_str(", PC: ");
-    status.push_str(&context.machine_.cpu().pc.to_string());
-    status.push_str(match context.stopped_ {
-        StoppingFlag::DidStop => ", Stopped",
-        StoppingFlag::DidNotStop => ", Running",
+    status.push_str(&context.debugger_.machine.cpu().pc.to_string());
+    status.push_str(match context.debugger_.is_stopped() {
+        true => ", Stopped",
+        false => ", Running",
     });
     Ok(Some(status))
 }
@@ -155,12 +302,12 @@ fn show_frame(args: ArgMatches, context: &mut Context) -> Result<Option<String>>
         false => 6,
     };
     let mut frame = String::new();
-    let fp = context.machine_.cpu().fp as i32;
+    let fp = context.debugger_.machine.cpu().fp as i32;
     frame.push_str(format!("FP: {:x}\n", fp).as_str());
     for i in 0..size {
         let offset = i * -4;
         let read_addr = (fp + offset) as u32;
-        let string_val = context.machine_.mem().examine(read_addr);
+        let string_val = context.debugger_.machine.mem().examine(read_addr);
         let frameslot_addr = format!("0x{:8} | {:3}(fp)", read_addr, offset);
         let frameslot = format!("{:>7}", frameslot_addr);
         let frame_str = format!("\n{} : {}", frameslot, string_val);
@@ -174,7 +321,7 @@ fn last_frame(_: ArgMatches, context: &mut Context) -> Result<Option<String>> {
     let mut frame = String::new();
 
     let lfp = context.last_fp_;
-    let fp = context.machine_.cpu().fp as i32;
+    let fp = context.debugger_.machine.cpu().fp as i32;
     let last_size = context.last_fp_size_ as i32;
     frame += &format!("Last FP   : 0x{:x}, Frame size: {}\n", lfp, last_size).as_str();
     frame += &format!("Current FP: 0x{:x}\n", fp).as_str();
@@ -183,7 +330,7 @@ fn last_frame(_: ArgMatches, context: &mut Context) -> Result<Option<String>> {
     for i in (-10..(last_size / 4) + 1).rev() {
         let offset = (i * 4) as i32;
         let read_addr = (fp + offset) as u32;
-        let string_val = context.machine_.mem().examine(read_addr);
+        let string_val = context.debugger_.machine.mem().examine(read_addr);
         let frameslot_addr = format!("{}(fp)", offset);
         let frameslot = format!("0x{:<7x} | {:>7}", read_addr, frameslot_addr);
         let frame_str = format!("\n{} : {}", frameslot, string_val);
@@ -193,52 +340,168 @@ fn last_frame(_: ArgMatches, context: &mut Context) -> Result<Option<String>> {
 }
 
 fn list_instrs(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
-    let pc = context.machine_.cpu().pc;
-
-    let program_rom = &context.machine_.program().program_rom;
-    let total_size = program_rom.0.len();
-
-    let print_size_arg = args.get_one::<String>("size");
+    let pc = context.debugger_.machine.cpu().pc;
+    let print_size = parse_count(&args, "size", 10)?;
+    Ok(Some(format_instructions(context, pc, pc + print_size)))
+}
 
-    let print_size = match print_size_arg {
-        Some(size) => size.parse::<u32>().unwrap(),
-        None => 10,
-    };
+/// List the instructions from pc `from` up to, but not including, `to`.
+fn format_instructions(context: &Context, from: u32, to: u32) -> String {
+    let program_rom = &context.debugger_.machine.program().program_rom;
+    let total_size = program_rom.0.len() as u32;
 
     let mut formatted = String::new();
-    for i in 0..print_size {
-        let cur_pc = pc + i;
-        if cur_pc >= total_size as u32 {
-            break;
+    for cur_pc in from..to.min(total_size) {
+        // Label the first instruction of each function
+        let address = cur_pc * BYTES_PER_INSTR;
+        if let Some(symbol) = context.debugger_.debug_info.function_at(address) {
+            if symbol.address == address {
+                formatted.push_str(format!("{}:\n", symbol.name).as_str());
+            }
         }
         let instruction = program_rom.get_instruction(cur_pc);
-        formatted.push_str(format!("{:4} : {:?}\n", cur_pc, instruction.to_string()).as_str());
+        formatted.push_str(format!("{:4} : {:?}", cur_pc, instruction.to_string()).as_str());
+        if let Some(entry) = context.debugger_.debug_info.line_at(address) {
+            formatted.push_str(format!("  ; {}:{}", entry.file, entry.line).as_str());
+        }
+        formatted.push('\n');
     }
-    Ok(Some(formatted))
+    formatted
+}
+
+fn disassemble(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    let resolve = |location: &String| {
+        context
+            .debugger_
+            .resolve_location(location)
+            .map(|pcs| pcs[0])
+    };
+    let from = resolve(args.get_one::<String>("from").unwrap())?;
+    let to = match args.get_one::<String>("to") {
+        Some(to) => resolve(to)?,
+        // Without an end, disassemble the whole function
+        None => match context
+            .debugger_
+            .debug_info
+            .function_at(from * BYTES_PER_INSTR)
+        {
+            Some(symbol) if symbol.size > 0 => (symbol.address + symbol.size) / BYTES_PER_INSTR,
+            _ => from + 10,
+        },
+    };
+    Ok(Some(format_instructions(context, from, to)))
 }
 
 fn set_bp(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
-    let pc = args
-        .get_one::<String>("pc")
-        .unwrap()
-        .parse::<u32>()
-        .unwrap();
-    context.breakpoints_.push(pc);
-    let message = format!("Breakpoint set at pc: {}", pc);
-    Ok(Some(message))
+    let location = args.get_one::<String>("location").unwrap();
+    let pcs = match context.debugger_.resolve_location(location) {
+        Ok(pcs) => pcs,
+        Err(message) => return Ok(Some(message)),
+    };
+    let condition = match args.get_many::<String>("condition") {
+        Some(words) => {
+            let words: Vec<&String> = words.collect();
+            let condition = match words.split_first() {
+                Some((first, rest)) if first.as_str() == "if" => Condition::parse(
+                    &rest
+                        .iter()
+                        .map(|word| word.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+                _ => None,
+            };
+            match condition {
+                Some(condition) => Some(condition),
+                None => {
+                    return Ok(Some(String::from(
+                        "Expected a condition like: if [fp-8] == 5",
+                    )))
+                }
+            }
+        }
+        None => None,
+    };
+    let mut message = String::new();
+    for pc in pcs {
+        context
+            .debugger_
+            .breakpoints
+            .push(Breakpoint { pc, condition });
+        message += &format!(
+            "Breakpoint {} set at pc: {}",
+            context.debugger_.breakpoints.len() - 1,
+            pc
+        );
+        if let Some(location) = context.debugger_.location(pc) {
+            message += &format!(" <{}>", location);
+        }
+        message.push('\n');
+    }
+    Ok(Some(message.trim_end().to_string()))
+}
+
+fn watch(
+    args: ArgMatches,
+    context: &mut Context,
+    kind: Option<MemoryAccessKind>,
+) -> Result<Option<String>> {
+    let start = resolve_address(context, args.get_one::<String>("addr").unwrap())?;
+    let size = match args.get_one::<String>("size") {
+        Some(size) => match parse_number(size) {
+            Some(size) if size > 0 => size,
+            _ => return Ok(Some(format!("Invalid size: {}", size))),
+        },
+        None => 4,
+    };
+    let end = start.saturating_add(size);
+    context
+        .debugger_
+        .watchpoints
+        .push(Watchpoint { start, end, kind });
+    Ok(Some(format!(
+        "Watchpoint {} set on 0x{:x}..0x{:x}",
+        context.debugger_.watchpoints.len() - 1,
+        start,
+        end
+    )))
+}
+
+fn watch_writes(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    watch(args, context, Some(MemoryAccessKind::Write))
 }
+
+fn watch_reads(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    watch(args, context, Some(MemoryAccessKind::Read))
+}
+
+fn watch_accesses(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    watch(args, context, None)
+}
+
+fn backtrace(_: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    let mut frames = String::new();
+    for (depth, frame) in context.debugger_.backtrace().iter().enumerate() {
+        let location = context
+            .debugger_
+            .location(frame.pc)
+            .unwrap_or(String::from("??"));
+        frames += &format!(
+            "#{:<3} {:4} in {} (fp: 0x{:x})\n",
+            depth, frame.pc, location, frame.fp
+        );
+    }
+    Ok(Some(frames.trim_end().to_string()))
+}
+
 fn show_memory(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
-    let addr = args
-        .get_one::<String>("addr")
-        .unwrap()
-        .parse::<u32>()
-        .unwrap();
+    let addr = resolve_address(context, args.get_one::<String>("addr").unwrap())?;
+    let count = parse_count(&args, "count", 8)?;
 
-    // show memory at address, by default show 20 cells
     let mut memory = String::new();
-    for i in 0..8 {
-        let read_addr = addr + i * 4;
-        let string_val = context.machine_.mem().examine(read_addr);
+    for i in 0..count {
+        let read_addr = addr.wrapping_add(i * 4);
+        let string_val = context.debugger_.machine.mem().examine(read_addr);
         let memory_str = format!("0x{:<8x} : {}\n", read_addr, string_val);
         memory += &memory_str;
     }
@@ -246,45 +509,249 @@ fn show_memory(args: ArgMatches, context: &mut Context) -> Result<Option<String>
     Ok(Some(memory))
 }
 
+fn save_snapshot(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    let file_name = args.get_one::<String>("file").unwrap();
+    let snapshot = context
+        .debugger_
+        .machine
+        .snapshot(context.debugger_.advice.position());
+    match fs::write(file_name, snapshot.to_bytes()) {
+        Ok(_) => Ok(Some(format!(
+            "Saved snapshot at clock {} to {}",
+            snapshot.clock, file_name
+        ))),
+        Err(e) => Ok(Some(format!("Failed to write snapshot: {}", e))),
+    }
+}
+
+fn restore_snapshot(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    let file_name = args.get_one::<String>("file").unwrap();
+    let snapshot = match fs::read(file_name).map(|bytes| Snapshot::from_bytes(&bytes)) {
+        Ok(Ok(snapshot)) => snapshot,
+        Ok(Err(e)) => return Ok(Some(format!("Invalid snapshot: {}", e))),
+        Err(e) => return Ok(Some(format!("Failed to read snapshot: {}", e))),
+    };
+    context.restore_snapshot(&snapshot);
+    Ok(Some(format!(
+        "Restored snapshot at clock {}, PC: {}",
+        snapshot.clock, snapshot.pc
+    )))
+}
+
 fn run_until(_args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
-    let mut message = String::new();
     loop {
         let (stop, pc) = context.step();
-        if stop == StoppingFlag::DidStop {
-            message.push_str("Execution stopped");
-            break;
+        if stop != StopReason::Step {
+            return Ok(describe_stop(stop, context));
         }
-        if context.breakpoints_.contains(&pc) {
-            let bp_index = context.breakpoints_.iter().position(|&x| x == pc).unwrap();
-            message = format!("Execution stopped at breakpoint {}, PC: {}", bp_index, pc);
-            break;
+        if let Some(bp_index) = context.debugger_.breakpoint_hit(pc) {
+            return Ok(describe_stop(StopReason::Breakpoint(bp_index), context));
         }
     }
-    Ok(Some(message))
 }
 
-fn step(_args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
-    let (stop, _) = context.step();
-    if stop == StoppingFlag::DidStop {
-        context.stopped_ = StoppingFlag::DidStop;
-        Ok(Some(String::from("Execution stopped")))
-    } else {
-        Ok(None)
+fn step(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    let num_steps = parse_count(&args, "num_steps", 1)?;
+    for i in 0..num_steps {
+        let (stop, pc) = context.step();
+        if stop != StopReason::Step {
+            return Ok(describe_stop(stop, context));
+        }
+        // Stepping onto a breakpoint only stops early if there are steps left
+        if i + 1 < num_steps {
+            if let Some(bp_index) = context.debugger_.breakpoint_hit(pc) {
+                return Ok(describe_stop(StopReason::Breakpoint(bp_index), context));
+            }
+        }
     }
+    Ok(None)
 }
 
-fn repl_run(args: &Args) {
-    // instantiate repl
-    let mut repl = Repl::new(Context::new(args))
-        .with_name("REPL")
-        .with_version("v0.1.0")
-        .with_description("Valida VM REPL")
-        .with_banner("Start by using keywords")
-        .with_command(Command::new("x").about("read machine state"), status)
+fn reverse_step(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    let num_steps = parse_count(&args, "num_steps", 1)?;
+    for _ in 0..num_steps {
+        let stop = context.debugger_.step_back();
+        if stop != StopReason::Step {
+            return Ok(describe_stop(stop, context));
+        }
+        context.moved_back();
+    }
+    Ok(None)
+}
+
+fn reverse_continue(_args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    let stop = context.debugger_.reverse_cont();
+    context.moved_back();
+    Ok(describe_stop(stop, context))
+}
+
+/// The format of `x/FMT`, e.g. `4xw`: a count, then letters for how to display each unit
+/// (`x` hex, `d` signed, `u` unsigned, `c` ASCII) and its size (`b`, `h` or `w`).
+struct ExamineFormat {
+    count: u32,
+    display: char,
+    size: u32,
+}
+
+impl ExamineFormat {
+    fn parse(text: &str) -> Result<Self> {
+        let letters = text.trim_start_matches(|c: char| c.is_ascii_digit());
+        let digits = &text[..text.len() - letters.len()];
+        let mut parsed = ExamineFormat {
+            count: 1,
+            display: 'x',
+            size: 4,
+        };
+        if !digits.is_empty() {
+            parsed.count = digits
+                .parse()
+                .map_err(|_| format!("Invalid count: {}", digits))?;
+        }
+        for letter in letters.chars() {
+            match letter {
+                'x' | 'd' | 'u' | 'c' => parsed.display = letter,
+                'b' => parsed.size = 1,
+                'h' => parsed.size = 2,
+                'w' => parsed.size = 4,
+                _ => return Err(format!("Invalid format letter: {}", letter)),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Format one unit, given its bytes in memory order.
+    fn display(&self, bytes: &[u8]) -> String {
+        let value = bytes
+            .iter()
+            .rev()
+            .fold(0u32, |value, &byte| value << 8 | byte as u32);
+        let unused_bits = 32 - 8 * self.size;
+        match self.display {
+            'x' => format!("0x{:0width$x}", value, width = 2 * self.size as usize),
+            'd' => (((value << unused_bits) as i32) >> unused_bits).to_string(),
+            'u' => value.to_string(),
+            _ => bytes
+                .iter()
+                .map(|&byte| match byte.is_ascii_graphic() || byte == b' ' {
+                    true => byte as char,
+                    false => '.',
+                })
+                .collect(),
+        }
+    }
+}
+
+/// `x` shows the machine state, and `x/FMT addr` examines memory as in gdb.
+fn examine(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    if !args.contains_id("addr") {
+        return status(args, context);
+    }
+    let addr = resolve_address(context, args.get_one::<String>("addr").unwrap())?;
+    let format = ExamineFormat::parse(args.get_one::<String>("format").map_or("", String::as_str))?;
+
+    // Show 16 bytes per line
+    let per_line = 16 / format.size;
+    let mut lines = vec![];
+    for line_start in (0..format.count).step_by(per_line as usize) {
+        let units: Vec<String> = (line_start..format.count.min(line_start + per_line))
+            .map(|i| {
+                let unit_addr = addr.wrapping_add(i * format.size);
+                let bytes: Vec<u8> = (0..format.size)
+                    .map(|j| context.debugger_.read_byte(unit_addr.wrapping_add(j)))
+                    .collect();
+                format.display(&bytes)
+            })
+            .collect();
+        let line_addr = addr.wrapping_add(line_start * format.size);
+        lines.push(format!("0x{:<8x} : {}", line_addr, units.join(" ")));
+    }
+    Ok(Some(lines.join("\n")))
+}
+
+fn set_state(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    let value = |args: &ArgMatches| {
+        let value = args.get_one::<String>("value").unwrap();
+        parse_number(value).ok_or_else(|| format!("Invalid value: {}", value))
+    };
+    let pc = context.debugger_.machine.cpu().pc;
+    let fp = context.debugger_.machine.cpu().fp;
+    match args.subcommand() {
+        Some(("pc", args)) => {
+            let pc = value(args)?;
+            context.debugger_.set_registers(pc, fp);
+            Ok(Some(format!("PC: {}", pc)))
+        }
+        Some(("fp", args)) => {
+            let fp = value(args)?;
+            context.debugger_.set_registers(pc, fp);
+            context.recorded_current_fp_ = fp;
+            Ok(Some(format!("FP: 0x{:x}", fp)))
+        }
+        Some(("mem", args)) => {
+            let addr = resolve_address(context, args.get_one::<String>("addr").unwrap())?;
+            let value = value(args)?;
+            for (i, byte) in value.to_le_bytes().into_iter().enumerate() {
+                context
+                    .debugger_
+                    .write_byte(addr.wrapping_add(i as u32), byte);
+            }
+            Ok(Some(format!("0x{:<8x} : {}", addr, value)))
+        }
+        _ => unreachable!("set requires a subcommand"),
+    }
+}
+
+fn push_advice(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    let Some(("push", args)) = args.subcommand() else {
+        unreachable!("advice requires a subcommand");
+    };
+    let bytes = args
+        .get_many::<String>("bytes")
+        .unwrap()
+        .map(|byte| match parse_number(byte) {
+            Some(value) if value <= 0xff => Ok(value as u8),
+            _ => Err(format!("Invalid byte: {}", byte)),
+        })
+        .collect::<Result<Vec<u8>>>()?;
+    context.debugger_.advice.push(&bytes);
+    Ok(Some(format!("Pushed {} advice bytes", bytes.len())))
+}
+
+fn source(args: ArgMatches, context: &mut Context) -> Result<Option<String>> {
+    run_script_file(context, args.get_one::<String>("file").unwrap())?;
+    Ok(None)
+}
+
+/// The REPL's commands, each parsed by clap and run by its handler.
+#[derive(Default)]
+struct Commands(Vec<(Command, Handler)>);
+
+impl Commands {
+    fn with_command(mut self, command: Command, handler: Handler) -> Self {
+        self.0.push((command, handler));
+        self
+    }
+
+    fn find(self, name: &str) -> Option<(Command, Handler)> {
+        self.0
+            .into_iter()
+            .find(|(command, _)| command.get_name() == name)
+    }
+}
+
+fn commands() -> Commands {
+    Commands::default()
+        .with_command(
+            Command::new("x")
+                .arg(Arg::new("format").long("format").hide(true))
+                .arg(Arg::new("addr").required(false))
+                .about("read machine state, or examine memory, e.g. x/4xw fp-16"),
+            examine,
+        )
         .with_command(
             Command::new("s")
                 .arg(Arg::new("num_steps").required(false))
-                .about("step assembly"),
+                .about("step one or more instructions"),
             step,
         )
         .with_command(
@@ -299,14 +766,52 @@ fn repl_run(args: &Args) {
         )
         .with_command(
             Command::new("b")
-                .arg(Arg::new("pc").required(false))
-                .about("set break point at"),
+                .arg(Arg::new("location").required(true))
+                .arg(
+                    Arg::new("condition")
+                        .required(false)
+                        .num_args(1..)
+                        .allow_hyphen_values(true),
+                )
+                .about("set break point at a pc, function or file:line, e.g. b 12 if [fp-8] == 5"),
             set_bp,
         )
+        .with_command(
+            Command::new("watch")
+                .arg(Arg::new("addr").required(true))
+                .arg(Arg::new("size").required(false))
+                .about("stop when memory at an address, e.g. 0x1000 or fp-8, is written"),
+            watch_writes,
+        )
+        .with_command(
+            Command::new("rwatch")
+                .arg(Arg::new("addr").required(true))
+                .arg(Arg::new("size").required(false))
+                .about("stop when memory at an address is read"),
+            watch_reads,
+        )
+        .with_command(
+            Command::new("awatch")
+                .arg(Arg::new("addr").required(true))
+                .arg(Arg::new("size").required(false))
+                .about("stop when memory at an address is read or written"),
+            watch_accesses,
+        )
+        .with_command(Command::new("bt").about("show the call stack"), backtrace)
         .with_command(
             Command::new("r").about("run until stop or breakpoint"),
             run_until,
         )
+        .with_command(
+            Command::new("rs")
+                .arg(Arg::new("num_steps").required(false))
+                .about("step backwards"),
+            reverse_step,
+        )
+        .with_command(
+            Command::new("rc").about("run backwards until a breakpoint or watchpoint"),
+            reverse_continue,
+        )
         .with_command(
             Command::new("l")
                 .about("list instruction at current PC")
@@ -316,15 +821,221 @@ fn repl_run(args: &Args) {
         .with_command(
             Command::new("m")
                 .arg(Arg::new("addr").required(true))
-                .about("show memory at address"),
+                .arg(Arg::new("count").required(false))
+                .about("show memory cells at address"),
             show_memory,
         )
         .with_command(
-            Command::new("reset").about("reset machine state!"),
-            init_context,
-        );
+            Command::new("save")
+                .arg(Arg::new("file").required(true))
+                .about("save a snapshot of the machine state"),
+            save_snapshot,
+        )
+        .with_command(
+            Command::new("restore")
+                .arg(Arg::new("file").required(true))
+                .about("restore the machine state from a snapshot"),
+            restore_snapshot,
+        )
+        .with_command(
+            Command::new("disas")
+                .arg(Arg::new("from").required(true))
+                .arg(Arg::new("to").required(false))
+                .about("disassemble from a pc or function up to another, or a whole function"),
+            disassemble,
+        )
+        .with_command(
+            Command::new("set")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("pc")
+                        .arg(Arg::new("value").required(true))
+                        .about("set the pc"),
+                )
+                .subcommand(
+                    Command::new("fp")
+                        .arg(Arg::new("value").required(true))
+                        .about("set the fp"),
+                )
+                .subcommand(
+                    Command::new("mem")
+                        .arg(Arg::new("addr").required(true))
+                        .arg(Arg::new("value").required(true).allow_hyphen_values(true))
+                        .about("write a word to memory, e.g. set mem fp-8 5"),
+                )
+                .about("change the pc, fp or memory"),
+            set_state,
+        )
+        .with_command(
+            Command::new("advice")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("push")
+                        .arg(Arg::new("bytes").required(true).num_args(1..))
+                        .about("insert bytes to be read next from the advice tape"),
+                )
+                .about("feed advice to the program"),
+            push_advice,
+        )
+        .with_command(
+            Command::new("source")
+                .arg(Arg::new("file").required(true))
+                .about("run commands from a file"),
+            source,
+        )
+        .with_command(
+            Command::new("reset").about("reload the program and start over"),
+            reset,
+        )
+}
+
+fn help(command_name: Option<&String>) -> String {
+    match command_name {
+        Some(name) => match commands().find(name) {
+            Some((mut command, _)) => command.render_help().to_string(),
+            None => format!("Unknown command: {}", name),
+        },
+        None => commands()
+            .0
+            .iter()
+            .map(|(command, _)| {
+                let about = command.get_about().map(|about| about.to_string());
+                format!("{:<8} {}", command.get_name(), about.unwrap_or_default())
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// Parse and run one REPL command, returning the message to print.
+fn run_command(context: &mut Context, line: &str) -> Option<String> {
+    let mut words: Vec<String> = line.split_whitespace().map(String::from).collect();
+    match words.first() {
+        None => return None,
+        Some(word) if word.starts_with('#') => return None,
+        _ => {}
+    }
+    // As in gdb, `x/4xw addr` passes the format as part of the command name
+    if let Some(format) = words[0].strip_prefix("x/").map(String::from) {
+        words.splice(0..1, [String::from("x"), String::from("--format"), format]);
+    }
+    if words[0] == "help" {
+        return Some(help(words.get(1)));
+    }
+    let Some((command, handler)) = commands().find(&words[0]) else {
+        return Some(format!("Unknown command: {}. Try help", words[0]));
+    };
+    let result = command
+        .try_get_matches_from(&words)
+        .map_err(|e| e.to_string().trim_end().to_string())
+        .and_then(|matches| handler(matches, context));
+    match result {
+        Ok(output) => output,
+        Err(error) => Some(error),
+    }
+}
+
+/// Run REPL commands, one per line, printing their output. Lines starting with `#` are
+/// comments.
+fn run_script(context: &mut Context, script: &str) {
+    for line in script.lines() {
+        if let Some(output) = run_command(context, line) {
+            println!("{}", output);
+        }
+    }
+}
+
+/// Run the REPL commands in a file, unless the file is already being run.
+fn run_script_file(context: &mut Context, file_name: &str) -> Result<()> {
+    let read_error = |e: std::io::Error| format!("Failed to read command file: {}", e);
+    let script = fs::read_to_string(file_name).map_err(read_error)?;
+    let path = fs::canonicalize(file_name).map_err(read_error)?;
+    if context.sourcing_.contains(&path) {
+        return Err(format!("Command file {} is already being run", file_name));
+    }
+    context.sourcing_.push(path);
+    run_script(context, &script);
+    context.sourcing_.pop();
+    Ok(())
+}
+
+fn repl_run(args: &Args) {
+    let mut context = Context::new(args);
+    if let Some(script_file) = &args.script {
+        if let Err(e) = run_script_file(&mut context, script_file) {
+            println!("{}", e);
+        }
+    }
+    // Commands piped in run without the line editor, which needs a terminal
+    if !stdin().is_terminal() {
+        for line in stdin().lines().map_while(|line| line.ok()) {
+            if let Some(output) = run_command(&mut context, &line) {
+                println!("{}", output);
+            }
+        }
+        return;
+    }
+
+    println!("Valida VM REPL v0.1.0");
+    println!("Start by using keywords, or type help for a list of commands");
+    let mut line_editor = Reedline::create();
+    let prompt = DefaultPrompt::new(
+        DefaultPromptSegment::Basic(String::from("REPL")),
+        DefaultPromptSegment::Empty,
+    );
+    loop {
+        match line_editor.read_line(&prompt) {
+            Ok(Signal::Success(line)) => {
+                if let Some(output) = run_command(&mut context, &line) {
+                    println!("{}", output);
+                }
+            }
+            // Ctrl-C or Ctrl-D
+            Ok(_) => break,
+            Err(e) => {
+                println!("{}", e);
+                break;
+            }
+        }
+    }
+}
+
+fn load_debugger(args: &Args, advice: GlobalAdviceProvider) -> Debugger<BabyBear> {
+    let program_file = fs::read(&args.program)
+        .expect(format!("Failed to read executable file: {}", &args.program).as_str());
+    let mut debugger = Debugger::<BabyBear>::load(&program_file, advice, args.stack_height)
+        .unwrap_or_else(|error| panic!("Invalid executable file {}: {}", args.program, error));
+    debugger
+        .machine
+        .host_call_mut()
+        .set_handler(host_call_handler(args));
+    if let Some(snapshot_file) = &args.restore {
+        debugger.restore(&read_snapshot(snapshot_file));
+    }
+    debugger
+}
 
-    let _ = repl.run();
+fn gdb_run(args: &Args) {
+    let debugger = load_debugger(args, GlobalAdviceProvider::new(&args.advice));
+    let listener = TcpListener::bind(("127.0.0.1", args.port)).expect("Failed to listen for GDB");
+    println!("Listening for GDB on port {}", args.port);
+    GdbServer::new(debugger)
+        .serve(&listener)
+        .expect("GDB connection failed");
+}
+
+fn dap_run(args: &Args) {
+    // Stdin and stdout carry the protocol, so advice can't be read from stdin, and the
+    // server sends the guest's output to the client instead of printing it
+    let advice = match &args.advice {
+        Some(_) => GlobalAdviceProvider::new(&args.advice),
+        None => FixedAdviceProvider::empty().into(),
+    };
+    let mut server = DapServer::new(load_debugger(args, advice));
+    server.set_host_call_handler(host_call_handler(args));
+    server
+        .serve(&mut stdin().lock(), &mut stdout().lock())
+        .expect("DAP session failed");
 }
 
 fn main() {
@@ -334,24 +1045,69 @@ fn main() {
         repl_run(&args);
         return;
     }
+    if args.action == "gdbserver" {
+        gdb_run(&args);
+        return;
+    }
+    if args.action == "dap" {
+        dap_run(&args);
+        return;
+    }
+    let Some(action_file_name) = args.action_file.clone() else {
+        stdout()
+            .write("An ACTION FILE is required for this action\n".as_bytes())
+            .unwrap();
+        return;
+    };
 
     let mut machine = BasicMachine::<BabyBear>::default();
     let Program {
         code,
         data,
         initial_program_counter,
+        symbols,
     } = load_executable_file(
         fs::read(&args.program)
             .expect(format!("Failed to read executable file: {}", &args.program).as_str()),
-    );
+    )
+    .unwrap_or_else(|error| panic!("Invalid executable file {}: {}", args.program, error));
     machine.program_mut().set_program_rom(&code);
     machine.cpu_mut().fp = args.stack_height;
     machine.cpu_mut().pc = initial_program_counter;
     machine.cpu_mut().save_register_state();
     machine.static_data_mut().load(data);
+    machine
+        .host_call_mut()
+        .set_handler(host_call_handler(&args));
 
-    // Run the program
-    machine.run(&code, &mut GlobalAdviceProvider::new(&args.advice));
+    // Run the program, or resume it from a snapshot
+    let mut advice = RecordingAdviceProvider::new(GlobalAdviceProvider::new(&args.advice));
+    match &args.restore {
+        Some(snapshot_file) => {
+            if args.action != "run" && args.action != "profile" {
+                stdout()
+                    .write("A restored execution can only be run, not proven\n".as_bytes())
+                    .unwrap();
+                return;
+            }
+            let snapshot = read_snapshot(snapshot_file);
+            machine.restore(&snapshot);
+            advice.inner_mut().advance_to(snapshot.advice_position);
+            machine.resume(&mut advice);
+        }
+        None => machine.run(&code, &mut advice),
+    }
+
+    if let Some(record_file) = &args.record_advice {
+        fs::write(record_file, advice.recorded()).expect("Failed to write recorded advice");
+    }
+
+    if let Some(trace_file) = &args.trace_out {
+        let file = File::create(trace_file).expect("Failed to create trace file");
+        machine
+            .write_execution_trace(&mut BufWriter::new(file))
+            .expect("Failed to write execution trace");
+    }
 
     type Val = BabyBear;
     type Challenge = BinomialExtensionField<Val, 5>;
@@ -399,7 +1155,7 @@ fn main() {
 
     if args.action == "run" {
         let mut action_file;
-        match File::create(args.action_file) {
+        match File::create(&action_file_name) {
             Ok(file) => {
                 action_file = file;
             }
@@ -409,9 +1165,12 @@ fn main() {
             }
         }
         action_file.write_all(&machine.output().bytes()).unwrap();
+        if let Some(code) = machine.host_call().exit_code {
+            std::process::exit(code as i32);
+        }
     } else if args.action == "prove" {
         let mut action_file;
-        match File::create(args.action_file) {
+        match File::create(&action_file_name) {
             Ok(file) => {
                 action_file = file;
             }
@@ -426,8 +1185,24 @@ fn main() {
         ciborium::into_writer(&proof, &mut bytes).expect("Proof serialization failed");
         action_file.write(&bytes).expect("Writing proof failed");
         stdout().write("Proof successful\n".as_bytes()).unwrap();
+    } else if args.action == "profile" {
+        let profile = machine.profile(&symbols);
+        match File::create(&action_file_name) {
+            Ok(file) => {
+                profile
+                    .write_folded(&mut BufWriter::new(file))
+                    .expect("Writing folded stacks failed");
+            }
+            Err(e) => {
+                stdout().write(e.to_string().as_bytes()).unwrap();
+                return ();
+            }
+        }
+        profile
+            .write_top(&mut stdout(), args.top)
+            .expect("Writing profile failed");
     } else if args.action == "verify" {
-        let bytes = std::fs::read(args.action_file).expect("File reading failed");
+        let bytes = std::fs::read(&action_file_name).expect("File reading failed");
         let proof: MachineProof<MyConfig> =
             ciborium::from_reader(bytes.as_slice()).expect("Proof deserialization failed");
         let verification_result = machine.verify(&config, &proof);
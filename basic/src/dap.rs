@@ -0,0 +1,632 @@
+use crate::debugger::{parse_number, Breakpoint, Condition, Debugger, Frame, Operand, StopReason};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use p3_field::{PrimeField32, TwoAdicField};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, ErrorKind, Write};
+use std::sync::Mutex;
+use valida_cpu::MachineWithCpuChip;
+use valida_host_call::{
+    calls, errors, DefaultHostCallHandler, HostCall, HostCallHandler, HostCallOutcome,
+    MachineWithHostCallChip,
+};
+use valida_memory::MachineWithMemoryChip;
+use valida_opcodes::BYTES_PER_INSTR;
+use valida_output::MachineWithOutputChip;
+
+/// The guest is single-threaded, so every request refers to this thread.
+const THREAD_ID: u64 = 1;
+
+/// How far around the frame pointer to show memory cells in a frame's locals. Cells above
+/// the frame pointer hold the return address, the caller's frame offset and the arguments;
+/// the innermost frame's size is unknown, so its locals are shown down to this far below.
+const FRAME_VIEW_BYTES: u32 = 64;
+
+/// Bytes the guest has written to stdout or stderr, with the category of the `output`
+/// event that will carry them.
+type GuestOutput = Arc<Mutex<Vec<(&'static str, Vec<u8>)>>>;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// A server for the Debug Adapter Protocol, which lets editors debug a guest program. It
+/// exchanges `Content-Length` framed JSON messages over any reader and writer, usually
+/// stdin and stdout.
+///
+/// The program is loaded before the server starts, so `launch` only decides whether to
+/// stop on entry. Requests are handled one at a time, so a running guest cannot be paused.
+/// As in the GDB stub, instruction references are byte addresses, i.e. the instruction
+/// index times `BYTES_PER_INSTR`.
+///
+/// The protocol may use the host's stdout, so the guest's writes to stdout and stderr are
+/// sent to the client as `output` events instead.
+pub struct DapServer<F: PrimeField32 + TwoAdicField> {
+    debugger: Debugger<F>,
+    seq: u64,
+    stop_on_entry: bool,
+    /// Breakpoints by source path, as each `setBreakpoints` request replaces those of one
+    /// source.
+    source_breakpoints: BTreeMap<String, Vec<Breakpoint>>,
+    function_breakpoints: Vec<Breakpoint>,
+    instruction_breakpoints: Vec<Breakpoint>,
+    /// Events to send after the response to the current request.
+    events: Vec<Value>,
+    guest_output: GuestOutput,
+}
+
+/// Passes host calls on to another handler, except for writes to stdout and stderr, which
+/// are captured.
+struct CapturingHostCallHandler<H: HostCallHandler> {
+    inner: H,
+    output: GuestOutput,
+}
+
+impl<H: HostCallHandler> HostCallHandler for CapturingHostCallHandler<H> {
+    fn handle(&mut self, call: &mut HostCall) -> HostCallOutcome {
+        if call.number() != calls::WRITE {
+            return self.inner.handle(call);
+        }
+        let (fd, ptr, len) = (call.arg(0), call.arg(1), call.arg(2));
+        let category = match fd {
+            1 => "stdout",
+            2 => "stderr",
+            _ => return HostCallOutcome::Return(errors::EBADF),
+        };
+        let bytes = call.read_bytes(ptr, len);
+        self.output.lock().unwrap().push((category, bytes));
+        HostCallOutcome::Return(len)
+    }
+}
+
+/// Read one message, or `None` at the end of the input.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = vec![0; content_length.unwrap()];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = serde_json::to_vec(message)?;
+    write!(writer, "Content-Length: {}\r\n\r\n", body.len())?;
+    writer.write_all(&body)?;
+    writer.flush()
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let group = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(group >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn parse_condition(condition: &Value) -> Result<Option<Condition>, String> {
+    match condition.as_str() {
+        None | Some("") => Ok(None),
+        Some(text) => Condition::parse(text)
+            .map(Some)
+            .ok_or_else(|| format!("Invalid condition: {}", text)),
+    }
+}
+
+/// The source file and line of the instruction about to execute.
+fn current_line<F: PrimeField32 + TwoAdicField>(debugger: &Debugger<F>) -> Option<(String, u32)> {
+    debugger
+        .debug_info
+        .line_at(debugger.machine.cpu().pc * BYTES_PER_INSTR)
+        .map(|entry| (entry.file.clone(), entry.line))
+}
+
+impl<F: PrimeField32 + TwoAdicField> DapServer<F> {
+    pub fn new(debugger: Debugger<F>) -> Self {
+        let mut server = Self {
+            debugger,
+            seq: 0,
+            stop_on_entry: false,
+            source_breakpoints: BTreeMap::new(),
+            function_breakpoints: vec![],
+            instruction_breakpoints: vec![],
+            events: vec![],
+            guest_output: GuestOutput::default(),
+        };
+        server.set_host_call_handler(DefaultHostCallHandler::default());
+        server
+    }
+
+    /// Install a handler for the guest's host calls, other than its writes to stdout and
+    /// stderr.
+    pub fn set_host_call_handler<H: HostCallHandler + 'static>(&mut self, handler: H) {
+        let handler = CapturingHostCallHandler {
+            inner: handler,
+            output: self.guest_output.clone(),
+        };
+        self.debugger.machine.host_call_mut().set_handler(handler);
+    }
+
+    pub fn debugger(&self) -> &Debugger<F> {
+        &self.debugger
+    }
+
+    pub fn into_debugger(self) -> Debugger<F> {
+        self.debugger
+    }
+
+    /// Serve requests until the client disconnects or the input ends.
+    pub fn serve<R: BufRead, W: Write>(
+        &mut self,
+        reader: &mut R,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        while let Some(message) = read_message(reader)? {
+            if message["type"] != "request" {
+                continue;
+            }
+            let command = message["command"].as_str().unwrap_or("").to_string();
+            let result = self.handle_request(&command, &message["arguments"]);
+
+            self.seq += 1;
+            let mut response = json!({
+                "seq": self.seq,
+                "type": "response",
+                "request_seq": message["seq"],
+                "command": command,
+                "success": result.is_ok(),
+            });
+            match result {
+                Ok(body) => response["body"] = body,
+                Err(error) => response["message"] = Value::String(error),
+            }
+            write_message(writer, &response)?;
+
+            for mut event in core::mem::take(&mut self.events) {
+                self.seq += 1;
+                event["seq"] = json!(self.seq);
+                write_message(writer, &event)?;
+            }
+            if command == "disconnect" || command == "terminate" {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, command: &str, arguments: &Value) -> Result<Value, String> {
+        match command {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsFunctionBreakpoints": true,
+                "supportsConditionalBreakpoints": true,
+                "supportsInstructionBreakpoints": true,
+                "supportsStepBack": true,
+                "supportsReadMemoryRequest": true,
+            })),
+            "launch" | "attach" => {
+                self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+                self.send_event("initialized", json!({}));
+                Ok(json!({}))
+            }
+            "setBreakpoints" => Ok(self.set_breakpoints(arguments)),
+            "setFunctionBreakpoints" => Ok(self.set_function_breakpoints(arguments)),
+            "setInstructionBreakpoints" => Ok(self.set_instruction_breakpoints(arguments)),
+            "setExceptionBreakpoints" => Ok(json!({ "breakpoints": [] })),
+            "configurationDone" => {
+                if self.stop_on_entry {
+                    self.send_stopped("entry", None);
+                } else {
+                    let reason = self.debugger.cont();
+                    self.report_stop(reason);
+                }
+                Ok(json!({}))
+            }
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => self.scopes(arguments),
+            "variables" => self.variables(arguments),
+            "evaluate" => self.evaluate(arguments),
+            "readMemory" => self.read_memory(arguments),
+            "continue" => {
+                let reason = self.debugger.cont();
+                self.report_stop(reason);
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "next" | "stepIn" | "stepOut" => {
+                let reason = self.step(command, arguments);
+                self.report_stop(reason);
+                Ok(json!({}))
+            }
+            "stepBack" => {
+                let reason = self.debugger.step_back();
+                self.report_stop(reason);
+                Ok(json!({}))
+            }
+            "reverseContinue" => {
+                let reason = self.debugger.reverse_cont();
+                self.report_stop(reason);
+                Ok(json!({}))
+            }
+            "disconnect" | "terminate" => Ok(json!({})),
+            _ => Err(format!("Unsupported request: {}", command)),
+        }
+    }
+
+    fn send_event(&mut self, event: &str, body: Value) {
+        self.events.push(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }));
+    }
+
+    fn send_stopped(&mut self, reason: &str, description: Option<&str>) {
+        let mut body = json!({
+            "reason": reason,
+            "threadId": THREAD_ID,
+            "allThreadsStopped": true,
+        });
+        if let Some(description) = description {
+            body["description"] = json!(description);
+        }
+        self.send_event("stopped", body);
+    }
+
+    /// Send what the guest has written since the last stop.
+    fn send_guest_output(&mut self) {
+        let output = core::mem::take(&mut *self.guest_output.lock().unwrap());
+        for (category, bytes) in output {
+            self.send_event(
+                "output",
+                json!({
+                    "category": category,
+                    "output": String::from_utf8_lossy(&bytes),
+                }),
+            );
+        }
+    }
+
+    fn report_stop(&mut self, reason: StopReason) {
+        self.send_guest_output();
+        match reason {
+            StopReason::Step => self.send_stopped("step", None),
+            StopReason::Breakpoint(_) => self.send_stopped("breakpoint", None),
+            StopReason::Watchpoint(..) => self.send_stopped("data breakpoint", None),
+            StopReason::StartOfHistory => {
+                self.send_stopped("step", Some("Reached the start of the recorded history"))
+            }
+            StopReason::Interrupted => self.send_stopped("pause", None),
+            StopReason::Exited => {
+                let output = self.debugger.machine.output().bytes();
+                if !output.is_empty() {
+                    self.send_event(
+                        "output",
+                        json!({
+                            "category": "stdout",
+                            "output": String::from_utf8_lossy(&output),
+                        }),
+                    );
+                }
+                let exit_code = self.debugger.machine.host_call().exit_code.unwrap_or(0);
+                self.send_event("exited", json!({ "exitCode": exit_code }));
+                self.send_event("terminated", json!({}));
+            }
+        }
+    }
+
+    /// Step by source line, or by instruction if there is no line information or the client
+    /// asks for instruction granularity. `next` steps over calls and `stepOut` runs until the
+    /// current function returns.
+    fn step(&mut self, command: &str, arguments: &Value) -> StopReason {
+        let by_instruction = arguments["granularity"] == "instruction";
+        let start_fp = self.debugger.machine.cpu().fp;
+        let start_line = current_line(&self.debugger);
+        let same_line = |debugger: &Debugger<F>| {
+            !by_instruction && start_line.is_some() && current_line(debugger) == start_line
+        };
+        match command {
+            "stepIn" => self.step_while(same_line),
+            // The stack grows down, so a lower frame pointer belongs to a callee
+            "next" => self
+                .step_while(|debugger| debugger.machine.cpu().fp < start_fp || same_line(debugger)),
+            _ => self.step_while(|debugger| debugger.machine.cpu().fp <= start_fp),
+        }
+    }
+
+    /// Execute at least one instruction, then keep going while `keep_going` holds, stopping
+    /// early at breakpoints and watchpoints.
+    fn step_while<P: Fn(&Debugger<F>) -> bool>(&mut self, keep_going: P) -> StopReason {
+        loop {
+            match self.debugger.step() {
+                StopReason::Step => {}
+                reason => return reason,
+            }
+            if let Some(index) = self.debugger.breakpoint_hit(self.debugger.machine.cpu().pc) {
+                return StopReason::Breakpoint(index);
+            }
+            if !keep_going(&self.debugger) {
+                return StopReason::Step;
+            }
+        }
+    }
+
+    fn update_breakpoints(&mut self) {
+        self.debugger.breakpoints = self
+            .source_breakpoints
+            .values()
+            .flatten()
+            .chain(&self.function_breakpoints)
+            .chain(&self.instruction_breakpoints)
+            .cloned()
+            .collect();
+    }
+
+    /// Add breakpoints at the resolved pcs to `breakpoints`, and describe the result to
+    /// the client.
+    fn add_breakpoints(
+        pcs: Result<Vec<u32>, String>,
+        condition: &Value,
+        breakpoints: &mut Vec<Breakpoint>,
+    ) -> Value {
+        let resolved = pcs.and_then(|pcs| Ok((pcs, parse_condition(condition)?)));
+        match resolved {
+            Ok((pcs, condition)) => {
+                let address = pcs[0] * BYTES_PER_INSTR;
+                breakpoints.extend(pcs.into_iter().map(|pc| Breakpoint { pc, condition }));
+                json!({
+                    "verified": true,
+                    "instructionReference": format!("{:#x}", address),
+                })
+            }
+            Err(message) => json!({ "verified": false, "message": message }),
+        }
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Value) -> Value {
+        let path = arguments["source"]["path"]
+            .as_str()
+            .unwrap_or("")
+            .to_string();
+        // The line table may name the file relative to the compilation directory, so fall
+        // back to matching the file name alone.
+        let file_name = path.rsplit('/').next().unwrap_or("").to_string();
+        let mut breakpoints = vec![];
+        let mut results = vec![];
+        for requested in arguments["breakpoints"].as_array().into_iter().flatten() {
+            let line = requested["line"].as_u64().unwrap_or(0);
+            let pcs = self
+                .debugger
+                .resolve_location(&format!("{}:{}", path, line))
+                .or_else(|_| {
+                    self.debugger
+                        .resolve_location(&format!("{}:{}", file_name, line))
+                });
+            let mut result = Self::add_breakpoints(pcs, &requested["condition"], &mut breakpoints);
+            result["line"] = json!(line);
+            results.push(result);
+        }
+        self.source_breakpoints.insert(path, breakpoints);
+        self.update_breakpoints();
+        json!({ "breakpoints": results })
+    }
+
+    fn set_function_breakpoints(&mut self, arguments: &Value) -> Value {
+        let mut breakpoints = vec![];
+        let mut results = vec![];
+        for requested in arguments["breakpoints"].as_array().into_iter().flatten() {
+            let name = requested["name"].as_str().unwrap_or("");
+            let pcs = self.debugger.resolve_location(name);
+            results.push(Self::add_breakpoints(
+                pcs,
+                &requested["condition"],
+                &mut breakpoints,
+            ));
+        }
+        self.function_breakpoints = breakpoints;
+        self.update_breakpoints();
+        json!({ "breakpoints": results })
+    }
+
+    fn set_instruction_breakpoints(&mut self, arguments: &Value) -> Value {
+        let mut breakpoints = vec![];
+        let mut results = vec![];
+        for requested in arguments["breakpoints"].as_array().into_iter().flatten() {
+            let reference = requested["instructionReference"].as_str().unwrap_or("");
+            let offset = requested["offset"].as_i64().unwrap_or(0);
+            let pcs = parse_number(reference)
+                .map(|address| vec![(address as i64 + offset) as u32 / BYTES_PER_INSTR])
+                .ok_or_else(|| format!("Invalid instruction reference: {}", reference));
+            results.push(Self::add_breakpoints(
+                pcs,
+                &requested["condition"],
+                &mut breakpoints,
+            ));
+        }
+        self.instruction_breakpoints = breakpoints;
+        self.update_breakpoints();
+        json!({ "breakpoints": results })
+    }
+
+    /// Stack frames are identified by their index in the backtrace, innermost first, so
+    /// frame ids are only valid until execution resumes.
+    fn frame(&self, frame_id: &Value) -> Result<Frame, String> {
+        let index = frame_id.as_u64().unwrap_or(0) as usize;
+        self.debugger
+            .backtrace()
+            .get(index)
+            .copied()
+            .ok_or_else(|| format!("No frame {}", index))
+    }
+
+    fn stack_trace(&self) -> Value {
+        let frames: Vec<Value> = self
+            .debugger
+            .backtrace()
+            .iter()
+            .enumerate()
+            .map(|(id, frame)| {
+                let address = frame.pc * BYTES_PER_INSTR;
+                let mut value = json!({
+                    "id": id,
+                    "name": self
+                        .debugger
+                        .function_name(frame.pc)
+                        .unwrap_or_else(|| format!("{:#x}", address)),
+                    "line": 0,
+                    "column": 0,
+                    "instructionPointerReference": format!("{:#x}", address),
+                });
+                if let Some(entry) = self.debugger.debug_info.line_at(address) {
+                    let name = entry.file.rsplit('/').next().unwrap_or(&entry.file);
+                    value["source"] = json!({ "name": name, "path": entry.file });
+                    value["line"] = json!(entry.line);
+                    value["column"] = json!(1);
+                }
+                value
+            })
+            .collect();
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+
+    /// Each frame has two scopes, its registers and its locals, with variable references
+    /// `2 * id + 1` and `2 * id + 2`.
+    fn scopes(&self, arguments: &Value) -> Result<Value, String> {
+        let id = arguments["frameId"].as_u64().unwrap_or(0);
+        self.frame(&arguments["frameId"])?;
+        Ok(json!({
+            "scopes": [
+                {
+                    "name": "Registers",
+                    "presentationHint": "registers",
+                    "variablesReference": 2 * id + 1,
+                    "expensive": false,
+                },
+                {
+                    "name": "Locals",
+                    "presentationHint": "locals",
+                    "variablesReference": 2 * id + 2,
+                    "expensive": false,
+                },
+            ]
+        }))
+    }
+
+    fn variables(&self, arguments: &Value) -> Result<Value, String> {
+        let reference = arguments["variablesReference"].as_u64().unwrap_or(0);
+        if reference == 0 {
+            return Err("Invalid variables reference".to_string());
+        }
+        let index = (reference - 1) / 2;
+        let frames = self.debugger.backtrace();
+        let frame = *frames
+            .get(index as usize)
+            .ok_or_else(|| format!("No frame {}", index))?;
+        let variables: Vec<Value> = if reference % 2 == 1 {
+            vec![
+                json!({
+                    "name": "pc",
+                    "value": frame.pc.to_string(),
+                    "variablesReference": 0,
+                }),
+                json!({
+                    "name": "fp",
+                    "value": format!("{:#x}", frame.fp),
+                    "memoryReference": format!("{:#x}", frame.fp),
+                    "variablesReference": 0,
+                }),
+            ]
+        } else {
+            // A frame's locals end where its callee's frame starts
+            let lower = match index.checked_sub(1) {
+                Some(callee) => frames[callee as usize].fp,
+                None => frame.fp.saturating_sub(FRAME_VIEW_BYTES),
+            };
+            let upper = frame.fp.saturating_add(FRAME_VIEW_BYTES);
+            // A corrupted frame pointer can put the callee's frame above this one
+            let lower = lower.min(upper);
+            self.debugger
+                .machine
+                .mem()
+                .cells
+                .range(lower..upper)
+                .map(|(&addr, &value)| {
+                    let value: u32 = value.into();
+                    json!({
+                        "name": format!("{}(fp)", addr.wrapping_sub(frame.fp) as i32),
+                        "value": (value as i32).to_string(),
+                        "memoryReference": format!("{:#x}", addr),
+                        "variablesReference": 0,
+                    })
+                })
+                .collect()
+        };
+        Ok(json!({ "variables": variables }))
+    }
+
+    /// Evaluate an operand such as `[fp-8]`, relative to the selected frame.
+    fn evaluate(&self, arguments: &Value) -> Result<Value, String> {
+        let expression = arguments["expression"].as_str().unwrap_or("");
+        let operand =
+            Operand::parse(expression).ok_or_else(|| format!("Cannot evaluate {}", expression))?;
+        let frame = self.frame(&arguments["frameId"])?;
+        let value = match operand {
+            Operand::Pc => Some(frame.pc),
+            Operand::Fp => Some(frame.fp),
+            Operand::FrameCell(offset) => self
+                .debugger
+                .read_cell(frame.fp.wrapping_add(offset as u32)),
+            operand => self.debugger.evaluate(operand),
+        };
+        let value = value.ok_or_else(|| format!("{} is uninitialized", expression))?;
+        Ok(json!({
+            "result": (value as i32).to_string(),
+            "variablesReference": 0,
+        }))
+    }
+
+    fn read_memory(&self, arguments: &Value) -> Result<Value, String> {
+        let reference = arguments["memoryReference"].as_str().unwrap_or("");
+        let address = parse_number(reference)
+            .ok_or_else(|| format!("Invalid memory reference: {}", reference))?;
+        let address = (address as i64 + arguments["offset"].as_i64().unwrap_or(0)) as u32;
+        let count = arguments["count"].as_u64().unwrap_or(0) as u32;
+        let bytes: Vec<u8> = (0..count)
+            .map(|i| self.debugger.read_byte(address.wrapping_add(i)))
+            .collect();
+        Ok(json!({
+            "address": format!("{:#x}", address),
+            "data": base64_encode(&bytes),
+        }))
+    }
+}
@@ -0,0 +1,469 @@
+use crate::trace::{MemoryAccess, MemoryAccessKind};
+use crate::{BasicMachine, Snapshot};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use p3_field::{PrimeField32, TwoAdicField};
+use valida_cpu::MachineWithCpuChip;
+use valida_elf::debug_info::load_executable_line_table;
+use valida_elf::{load_executable_file, DebugInfo, ElfLoadError, Program};
+use valida_machine::{
+    addr_of_word, index_of_byte, AdviceProvider, GlobalAdviceProvider, Machine, StoppingFlag, Word,
+};
+use valida_memory::MachineWithMemoryChip;
+use valida_opcodes::BYTES_PER_INSTR;
+use valida_program::MachineWithProgramChip;
+use valida_static_data::MachineWithStaticDataChip;
+
+/// Stop walking the frame pointer chain after this many frames, in case it is corrupt.
+const MAX_BACKTRACE_DEPTH: usize = 256;
+
+/// How many instructions to execute between checks for an interrupt while continuing.
+const INTERRUPT_CHECK_INTERVAL: usize = 4096;
+
+/// A value in a breakpoint condition.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Constant(u32),
+    Pc,
+    Fp,
+    /// The memory cell at `fp + offset`, written `[fp-8]`.
+    FrameCell(i32),
+    /// The memory cell at an absolute address, written `[0x1000]`.
+    Cell(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A condition such as `[fp-8] == 5`. Values are compared as signed integers, and a
+/// condition reading an uninitialized cell is false.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Condition {
+    pub lhs: Operand,
+    pub comparison: Comparison,
+    pub rhs: Operand,
+}
+
+#[derive(Clone, Debug)]
+pub struct Breakpoint {
+    pub pc: u32,
+    pub condition: Option<Condition>,
+}
+
+/// Stops execution when an instruction accesses a cell in `start..end`.
+#[derive(Clone, Debug)]
+pub struct Watchpoint {
+    pub start: u32,
+    pub end: u32,
+    /// The kind of access to stop on, or `None` to stop on both reads and writes.
+    pub kind: Option<MemoryAccessKind>,
+}
+
+/// Why execution stopped after a debugger command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// A single step finished.
+    Step,
+    /// Execution reached the breakpoint with this index.
+    Breakpoint(usize),
+    /// The watchpoint with this index saw a memory access.
+    Watchpoint(usize, MemoryAccess),
+    /// The program has stopped and cannot execute further.
+    Exited,
+    /// Reverse execution reached the first recorded instruction.
+    StartOfHistory,
+    /// The caller asked to stop while continuing.
+    Interrupted,
+}
+
+/// A call frame found by walking the frame pointer chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub pc: u32,
+    pub fp: u32,
+}
+
+/// A machine under the control of a debugger, together with its breakpoints and
+/// watchpoints. This is shared by the interactive REPL and the remote debugging servers.
+pub struct Debugger<F: PrimeField32 + TwoAdicField> {
+    pub machine: BasicMachine<F>,
+    pub advice: GlobalAdviceProvider,
+    pub debug_info: DebugInfo,
+    pub breakpoints: Vec<Breakpoint>,
+    pub watchpoints: Vec<Watchpoint>,
+    /// The initial frame pointer. Frames at or above it belong to the entry function.
+    pub stack_height: u32,
+    stopped: bool,
+    /// The advice position before each instruction executed so far, to rewind the advice
+    /// tape when stepping backwards.
+    advice_positions: Vec<usize>,
+}
+
+/// Parse a decimal or `0x`-prefixed hexadecimal number, which may be negative.
+pub fn parse_number(text: &str) -> Option<u32> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<u32>().ok()?,
+    };
+    Some(if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    })
+}
+
+impl Operand {
+    /// Parse `pc`, `fp`, a number, or a memory cell such as `[fp-8]` or `[0x1000]`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if text == "pc" {
+            return Some(Operand::Pc);
+        }
+        if text == "fp" {
+            return Some(Operand::Fp);
+        }
+        if let Some(inner) = text
+            .strip_prefix('[')
+            .and_then(|text| text.strip_suffix(']'))
+        {
+            let inner: String = inner.chars().filter(|c| !c.is_whitespace()).collect();
+            return match inner.strip_prefix("fp") {
+                Some("") => Some(Operand::FrameCell(0)),
+                Some(offset) => Some(Operand::FrameCell(parse_number(
+                    offset.strip_prefix('+').unwrap_or(offset),
+                )? as i32)),
+                None => Some(Operand::Cell(parse_number(&inner)?)),
+            };
+        }
+        Some(Operand::Constant(parse_number(text)?))
+    }
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Option<Self> {
+        // Two-character operators are listed first, so that `<=` is not read as `<`.
+        let comparisons = [
+            ("==", Comparison::Eq),
+            ("!=", Comparison::Ne),
+            ("<=", Comparison::Le),
+            (">=", Comparison::Ge),
+            ("<", Comparison::Lt),
+            (">", Comparison::Gt),
+        ];
+        let (lhs, rhs, comparison) = comparisons.iter().find_map(|(symbol, comparison)| {
+            text.split_once(symbol)
+                .map(|(lhs, rhs)| (lhs, rhs, *comparison))
+        })?;
+        Some(Condition {
+            lhs: Operand::parse(lhs)?,
+            comparison,
+            rhs: Operand::parse(rhs)?,
+        })
+    }
+}
+
+impl<F: PrimeField32 + TwoAdicField> Debugger<F> {
+    pub fn new(machine: BasicMachine<F>, advice: GlobalAdviceProvider, stack_height: u32) -> Self {
+        Self {
+            machine,
+            advice,
+            debug_info: DebugInfo::default(),
+            breakpoints: vec![],
+            watchpoints: vec![],
+            stack_height,
+            stopped: false,
+            advice_positions: vec![],
+        }
+    }
+
+    /// Load an executable into a fresh machine, ready to execute its first instruction.
+    pub fn load(
+        program_file: &[u8],
+        advice: GlobalAdviceProvider,
+        stack_height: u32,
+    ) -> Result<Self, ElfLoadError> {
+        let mut machine = BasicMachine::<F>::default();
+        let Program {
+            code,
+            data,
+            initial_program_counter,
+            symbols,
+        } = load_executable_file(program_file.to_vec())?;
+        machine.program_mut().set_program_rom(&code);
+        machine.static_data_mut().load(data);
+        machine.initialize_memory();
+        machine.cpu_mut().pc = initial_program_counter;
+        machine.cpu_mut().fp = stack_height;
+        machine.cpu_mut().save_register_state();
+
+        let mut debugger = Self::new(machine, advice, stack_height);
+        debugger.debug_info = DebugInfo {
+            symbols,
+            lines: load_executable_line_table(program_file),
+        };
+        Ok(debugger)
+    }
+
+    /// Whether the program has stopped, by executing `STOP` or exiting through a host call.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.machine.restore(snapshot);
+        self.advice.advance_to(snapshot.advice_position);
+        self.advice_positions.clear();
+        self.stopped = false;
+    }
+
+    /// Execute one instruction.
+    pub fn step(&mut self) -> StopReason {
+        if self.stopped {
+            return StopReason::Exited;
+        }
+        let clk = self.machine.cpu().clock;
+        self.advice_positions.push(self.advice.position());
+        if self.machine.step(&mut self.advice) == StoppingFlag::DidStop {
+            self.stopped = true;
+            return StopReason::Exited;
+        }
+        match self.watchpoint_hit(clk) {
+            Some((index, access)) => StopReason::Watchpoint(index, access),
+            None => StopReason::Step,
+        }
+    }
+
+    /// Execute until a breakpoint or watchpoint is hit or the program stops.
+    pub fn cont(&mut self) -> StopReason {
+        self.cont_until(|| false)
+    }
+
+    /// Like `cont`, but also stop when `interrupted` returns true. It is polled every few
+    /// thousand instructions, so that a remote client can interrupt a long-running guest.
+    pub fn cont_until<I: FnMut() -> bool>(&mut self, mut interrupted: I) -> StopReason {
+        let mut steps = 0;
+        loop {
+            match self.step() {
+                StopReason::Step => {}
+                reason => return reason,
+            }
+            if let Some(index) = self.breakpoint_hit(self.machine.cpu().pc) {
+                return StopReason::Breakpoint(index);
+            }
+            steps += 1;
+            if steps % INTERRUPT_CHECK_INTERVAL == 0 && interrupted() {
+                return StopReason::Interrupted;
+            }
+        }
+    }
+
+    /// Undo the last executed instruction.
+    pub fn step_back(&mut self) -> StopReason {
+        if !self.machine.step_back() {
+            return StopReason::StartOfHistory;
+        }
+        if let Some(position) = self.advice_positions.pop() {
+            self.advice.advance_to(position);
+        }
+        self.stopped = false;
+        StopReason::Step
+    }
+
+    /// Execute backwards until a breakpoint or watchpoint is hit or the first recorded
+    /// instruction is reached.
+    pub fn reverse_cont(&mut self) -> StopReason {
+        loop {
+            // Check the instruction about to be undone before its memory operations are
+            // discarded
+            let clk = self.machine.cpu().clock.wrapping_sub(1);
+            let watchpoint = self.watchpoint_hit(clk);
+            if self.step_back() == StopReason::StartOfHistory {
+                return StopReason::StartOfHistory;
+            }
+            if let Some((index, access)) = watchpoint {
+                return StopReason::Watchpoint(index, access);
+            }
+            if let Some(index) = self.breakpoint_hit(self.machine.cpu().pc) {
+                return StopReason::Breakpoint(index);
+            }
+        }
+    }
+
+    /// Set the pc and fp, e.g. on behalf of a debugger client.
+    pub fn set_registers(&mut self, pc: u32, fp: u32) {
+        let cpu = self.machine.cpu_mut();
+        cpu.pc = pc;
+        cpu.fp = fp;
+        // The last recorded registers are the ones the next instruction executes with.
+        if let Some(registers) = cpu.registers.last_mut() {
+            registers.pc = pc;
+            registers.fp = fp;
+        }
+    }
+
+    pub fn read_cell(&self, addr: u32) -> Option<u32> {
+        self.machine
+            .mem()
+            .cells
+            .get(&addr)
+            .map(|&value| value.into())
+    }
+
+    /// Read a byte of memory. Uninitialized memory reads as zero.
+    pub fn read_byte(&self, addr: u32) -> u8 {
+        self.machine
+            .mem()
+            .cells
+            .get(&addr_of_word(addr))
+            .map_or(0, |word| word[index_of_byte(addr)])
+    }
+
+    /// Write a byte of memory directly, without recording a memory operation.
+    pub fn write_byte(&mut self, addr: u32, value: u8) {
+        let cells = &mut self.machine.mem_mut().cells;
+        let word = cells.entry(addr_of_word(addr)).or_insert(Word([0; 4]));
+        word[index_of_byte(addr)] = value;
+    }
+
+    pub fn evaluate(&self, operand: Operand) -> Option<u32> {
+        let cpu = self.machine.cpu();
+        match operand {
+            Operand::Constant(value) => Some(value),
+            Operand::Pc => Some(cpu.pc),
+            Operand::Fp => Some(cpu.fp),
+            Operand::FrameCell(offset) => self.read_cell(cpu.fp.wrapping_add(offset as u32)),
+            Operand::Cell(addr) => self.read_cell(addr),
+        }
+    }
+
+    pub fn condition_holds(&self, condition: &Condition) -> bool {
+        let (Some(lhs), Some(rhs)) = (self.evaluate(condition.lhs), self.evaluate(condition.rhs))
+        else {
+            return false;
+        };
+        let (lhs, rhs) = (lhs as i32, rhs as i32);
+        match condition.comparison {
+            Comparison::Eq => lhs == rhs,
+            Comparison::Ne => lhs != rhs,
+            Comparison::Lt => lhs < rhs,
+            Comparison::Le => lhs <= rhs,
+            Comparison::Gt => lhs > rhs,
+            Comparison::Ge => lhs >= rhs,
+        }
+    }
+
+    /// The index of a breakpoint at `pc` whose condition, if any, holds.
+    pub fn breakpoint_hit(&self, pc: u32) -> Option<usize> {
+        self.breakpoints.iter().position(|breakpoint| {
+            breakpoint.pc == pc
+                && breakpoint
+                    .condition
+                    .as_ref()
+                    .map_or(true, |condition| self.condition_holds(condition))
+        })
+    }
+
+    /// The first watched memory access made by the instruction executed at `clk`.
+    pub fn watchpoint_hit(&self, clk: u32) -> Option<(usize, MemoryAccess)> {
+        let accesses = self.machine.memory_accesses(clk);
+        self.watchpoints
+            .iter()
+            .enumerate()
+            .find_map(|(index, watchpoint)| {
+                accesses
+                    .iter()
+                    .find(|access| {
+                        // Cells are 4 bytes wide, so a watched byte range may cover part of one.
+                        access.addr < watchpoint.end
+                            && watchpoint.start < access.addr.saturating_add(4)
+                            && watchpoint.kind.map_or(true, |kind| kind == access.kind)
+                    })
+                    .map(|access| (index, *access))
+            })
+    }
+
+    /// The function containing an instruction and its offset in instructions, e.g. `fib+3`.
+    pub fn function_name(&self, pc: u32) -> Option<String> {
+        let address = pc * BYTES_PER_INSTR;
+        self.debug_info.function_at(address).map(|symbol| {
+            format!(
+                "{}+{}",
+                symbol.name,
+                (address - symbol.address) / BYTES_PER_INSTR
+            )
+        })
+    }
+
+    /// Describe the source location of an instruction, e.g. `fib+3 at fib.c:12`.
+    pub fn location(&self, pc: u32) -> Option<String> {
+        let line = self
+            .debug_info
+            .line_at(pc * BYTES_PER_INSTR)
+            .map(|entry| format!("{}:{}", entry.file, entry.line));
+        match (self.function_name(pc), line) {
+            (Some(function), Some(line)) => Some(format!("{} at {}", function, line)),
+            (Some(location), None) | (None, Some(location)) => Some(location),
+            (None, None) => None,
+        }
+    }
+
+    /// Resolve a breakpoint location, which is a pc, a function name or `file:line`.
+    pub fn resolve_location(&self, location: &str) -> Result<Vec<u32>, String> {
+        if let Ok(pc) = location.parse::<u32>() {
+            return Ok(vec![pc]);
+        }
+        if let Some((file, line)) = location.rsplit_once(':') {
+            if let Ok(line) = line.parse::<u32>() {
+                let addresses = self.debug_info.line_addresses(file, line);
+                if addresses.is_empty() {
+                    return Err(format!("No code for line {}:{}", file, line));
+                }
+                return Ok(addresses
+                    .into_iter()
+                    .map(|address| address / BYTES_PER_INSTR)
+                    .collect());
+            }
+        }
+        match self.debug_info.symbol(location) {
+            Some(symbol) => Ok(vec![symbol.address / BYTES_PER_INSTR]),
+            None => Err(format!("No function named {}", location)),
+        }
+    }
+
+    /// The call stack, innermost frame first.
+    pub fn backtrace(&self) -> Vec<Frame> {
+        // Each call leaves the return address at 0(fp) and the distance back to the caller's
+        // frame at 8(fp) of the callee's frame, which is how `jalv` returns.
+        let mut frame = Frame {
+            pc: self.machine.cpu().pc,
+            fp: self.machine.cpu().fp,
+        };
+        let mut frames = vec![frame];
+        while frames.len() < MAX_BACKTRACE_DEPTH && frame.fp < self.stack_height {
+            match (self.read_cell(frame.fp), self.read_cell(frame.fp + 8)) {
+                (Some(return_address), Some(offset)) if offset != 0 => {
+                    frame = Frame {
+                        pc: return_address / BYTES_PER_INSTR,
+                        fp: frame.fp.wrapping_add(offset),
+                    };
+                    frames.push(frame);
+                }
+                _ => break,
+            }
+        }
+        frames
+    }
+}
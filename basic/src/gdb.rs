@@ -0,0 +1,483 @@
+use crate::debugger::{Breakpoint, Debugger, StopReason, Watchpoint};
+use crate::trace::MemoryAccessKind;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use p3_field::{PrimeField32, TwoAdicField};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use valida_cpu::MachineWithCpuChip;
+use valida_host_call::MachineWithHostCallChip;
+use valida_opcodes::BYTES_PER_INSTR;
+
+/// The register file presented to the client: the pc, as a byte address, and the fp.
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.valida.core">
+    <reg name="pc" bitsize="32" type="code_ptr" regnum="0"/>
+    <reg name="fp" bitsize="32" type="data_ptr" regnum="1"/>
+  </feature>
+</target>
+"#;
+
+const NUM_REGISTERS: usize = 2;
+
+/// The largest packet the client may send, as advertised in `qSupported`. Memory reads
+/// are limited to what fits in a reply of this size.
+const PACKET_SIZE: usize = 0x4000;
+
+/// Sent by the client to interrupt a running target.
+const INTERRUPT: u8 = 0x03;
+
+enum Response {
+    Reply(String),
+    /// Reply, and turn off acknowledgements afterwards.
+    ReplyNoAck(String),
+    ReplyAndClose(String),
+    Close,
+}
+
+/// A stub for the GDB remote serial protocol, which lets `gdb`, `lldb` and their front ends
+/// debug a guest program over TCP.
+///
+/// The pc is presented as a byte address, i.e. the instruction index times
+/// `BYTES_PER_INSTR`, so that it matches the addresses in the ELF symbol table.
+pub struct GdbServer<F: PrimeField32 + TwoAdicField> {
+    debugger: Debugger<F>,
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    no_ack: bool,
+}
+
+impl<F: PrimeField32 + TwoAdicField> GdbServer<F> {
+    pub fn new(debugger: Debugger<F>) -> Self {
+        Self { debugger }
+    }
+
+    pub fn debugger(&self) -> &Debugger<F> {
+        &self.debugger
+    }
+
+    pub fn into_debugger(self) -> Debugger<F> {
+        self.debugger
+    }
+
+    /// Accept a single client and serve it until it detaches, kills the target or
+    /// disconnects.
+    pub fn serve(&mut self, listener: &TcpListener) -> io::Result<()> {
+        let (stream, _) = listener.accept()?;
+        self.serve_connection(stream)
+    }
+
+    pub fn serve_connection(&mut self, stream: TcpStream) -> io::Result<()> {
+        let mut connection = Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            no_ack: false,
+        };
+        while let Some(packet) = connection.read_packet()? {
+            match self.handle_packet(&packet, &mut connection) {
+                Response::Reply(reply) => connection.write_packet(&reply)?,
+                Response::ReplyNoAck(reply) => {
+                    connection.write_packet(&reply)?;
+                    connection.no_ack = true;
+                }
+                Response::ReplyAndClose(reply) => {
+                    connection.write_packet(&reply)?;
+                    return Ok(());
+                }
+                Response::Close => return Ok(()),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_packet(&mut self, packet: &[u8], connection: &mut Connection) -> Response {
+        if packet == [INTERRUPT] {
+            return Response::Reply(String::from("S02"));
+        }
+        let packet = String::from_utf8_lossy(packet);
+        let reply = match packet.as_ref() {
+            "?" => self.stop_reply(StopReason::Step),
+            "qAttached" => String::from("1"),
+            "qC" => String::from("QC1"),
+            "qfThreadInfo" => String::from("m1"),
+            "qsThreadInfo" => String::from("l"),
+            "vCont?" => String::from("vCont;c;s"),
+            "QStartNoAckMode" => return Response::ReplyNoAck(String::from("OK")),
+            "g" => self.read_registers(),
+            "s" | "vCont;s" | "vCont;s:1" => {
+                let reason = self.debugger.step();
+                self.stop_reply(reason)
+            }
+            "c" | "vCont;c" | "vCont;c:1" => {
+                let reason = self.debugger.cont_until(|| connection.poll_interrupt());
+                self.stop_reply(reason)
+            }
+            "bs" => {
+                let reason = self.debugger.step_back();
+                self.stop_reply(reason)
+            }
+            "bc" => {
+                let reason = self.debugger.reverse_cont();
+                self.stop_reply(reason)
+            }
+            "k" => return Response::Close,
+            "D" => return Response::ReplyAndClose(String::from("OK")),
+            _ => {
+                if packet.starts_with("qSupported") {
+                    format!(
+                        "PacketSize={:x};QStartNoAckMode+;qXfer:features:read+;swbreak+;\
+                         ReverseStep+;ReverseContinue+",
+                        PACKET_SIZE
+                    )
+                } else if let Some(args) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+                    read_chunk(TARGET_XML, args).unwrap_or(String::from("E00"))
+                } else if let Some(register) = packet.strip_prefix("qRegisterInfo") {
+                    register_info(register).unwrap_or(String::from("E45"))
+                } else if packet.starts_with('H') {
+                    String::from("OK")
+                } else if let Some(registers) = packet.strip_prefix('G') {
+                    self.write_registers(registers)
+                } else if let Some(register) = packet.strip_prefix('p') {
+                    self.read_register(register)
+                } else if let Some(assignment) = packet.strip_prefix('P') {
+                    self.write_register(assignment)
+                } else if let Some(args) = packet.strip_prefix('m') {
+                    self.read_memory(args)
+                } else if let Some(args) = packet.strip_prefix('M') {
+                    self.write_memory(args)
+                } else if let Some(args) = packet.strip_prefix('Z') {
+                    self.insert_breakpoint(args)
+                } else if let Some(args) = packet.strip_prefix('z') {
+                    self.remove_breakpoint(args)
+                } else {
+                    // An empty reply tells the client the packet is not supported.
+                    String::new()
+                }
+            }
+        };
+        Response::Reply(reply)
+    }
+
+    fn stop_reply(&self, reason: StopReason) -> String {
+        if self.debugger.is_stopped() {
+            let exit_code = self.debugger.machine.host_call().exit_code.unwrap_or(0);
+            return format!("W{:02x}", exit_code & 0xff);
+        }
+        match reason {
+            StopReason::Breakpoint(_) => String::from("T05swbreak:;"),
+            StopReason::Watchpoint(index, access) => {
+                let kind = match self.debugger.watchpoints[index].kind {
+                    Some(MemoryAccessKind::Write) => "watch",
+                    Some(MemoryAccessKind::Read) => "rwatch",
+                    None => "awatch",
+                };
+                format!("T05{}:{:x};", kind, access.addr)
+            }
+            StopReason::StartOfHistory => String::from("T05replaylog:begin;"),
+            StopReason::Interrupted => String::from("S02"),
+            StopReason::Step | StopReason::Exited => String::from("S05"),
+        }
+    }
+
+    fn registers(&self) -> [u32; NUM_REGISTERS] {
+        let cpu = self.debugger.machine.cpu();
+        [cpu.pc * BYTES_PER_INSTR, cpu.fp]
+    }
+
+    fn set_register(&mut self, register: usize, value: u32) {
+        let [mut pc, mut fp] = self.registers();
+        match register {
+            0 => pc = value,
+            _ => fp = value,
+        }
+        self.debugger.set_registers(pc / BYTES_PER_INSTR, fp);
+    }
+
+    fn read_registers(&self) -> String {
+        self.registers()
+            .iter()
+            .map(|register| to_hex(&register.to_le_bytes()))
+            .collect()
+    }
+
+    fn write_registers(&mut self, registers: &str) -> String {
+        let Some(bytes) = from_hex(registers) else {
+            return String::from("E01");
+        };
+        if bytes.len() != NUM_REGISTERS * 4 {
+            return String::from("E01");
+        }
+        for (register, value) in bytes.chunks(4).enumerate() {
+            self.set_register(register, u32::from_le_bytes(value.try_into().unwrap()));
+        }
+        String::from("OK")
+    }
+
+    fn read_register(&self, register: &str) -> String {
+        match usize::from_str_radix(register, 16) {
+            Ok(register) if register < NUM_REGISTERS => {
+                to_hex(&self.registers()[register].to_le_bytes())
+            }
+            _ => String::from("E01"),
+        }
+    }
+
+    fn write_register(&mut self, assignment: &str) -> String {
+        let Some((register, value)) = assignment.split_once('=') else {
+            return String::from("E01");
+        };
+        let register = match usize::from_str_radix(register, 16) {
+            Ok(register) if register < NUM_REGISTERS => register,
+            _ => return String::from("E01"),
+        };
+        match from_hex(value).and_then(|bytes| <[u8; 4]>::try_from(bytes).ok()) {
+            Some(bytes) => {
+                self.set_register(register, u32::from_le_bytes(bytes));
+                String::from("OK")
+            }
+            None => String::from("E01"),
+        }
+    }
+
+    fn read_memory(&self, args: &str) -> String {
+        let Some((addr, len)) = parse_address_and_length(args) else {
+            return String::from("E01");
+        };
+        // Each byte takes two hex digits
+        if len as usize > PACKET_SIZE / 2 {
+            return String::from("E01");
+        }
+        let bytes: Vec<u8> = (0..len)
+            .map(|i| self.debugger.read_byte(addr.wrapping_add(i)))
+            .collect();
+        to_hex(&bytes)
+    }
+
+    fn write_memory(&mut self, args: &str) -> String {
+        let Some((range, data)) = args.split_once(':') else {
+            return String::from("E01");
+        };
+        let (Some((addr, len)), Some(bytes)) = (parse_address_and_length(range), from_hex(data))
+        else {
+            return String::from("E01");
+        };
+        if bytes.len() != len as usize {
+            return String::from("E01");
+        }
+        for (i, byte) in bytes.into_iter().enumerate() {
+            self.debugger.write_byte(addr.wrapping_add(i as u32), byte);
+        }
+        String::from("OK")
+    }
+
+    fn insert_breakpoint(&mut self, args: &str) -> String {
+        let Some((kind, addr, len)) = parse_breakpoint(args) else {
+            return String::from("E01");
+        };
+        match kind {
+            0 | 1 => self.debugger.breakpoints.push(Breakpoint {
+                pc: addr / BYTES_PER_INSTR,
+                condition: None,
+            }),
+            2..=4 => self.debugger.watchpoints.push(Watchpoint {
+                start: addr,
+                end: addr.saturating_add(len),
+                kind: watchpoint_kind(kind),
+            }),
+            _ => return String::new(),
+        }
+        String::from("OK")
+    }
+
+    fn remove_breakpoint(&mut self, args: &str) -> String {
+        let Some((kind, addr, len)) = parse_breakpoint(args) else {
+            return String::from("E01");
+        };
+        match kind {
+            0 | 1 => {
+                let pc = addr / BYTES_PER_INSTR;
+                if let Some(index) =
+                    self.debugger.breakpoints.iter().position(|breakpoint| {
+                        breakpoint.pc == pc && breakpoint.condition.is_none()
+                    })
+                {
+                    self.debugger.breakpoints.remove(index);
+                }
+            }
+            2..=4 => {
+                let end = addr.saturating_add(len);
+                let kind = watchpoint_kind(kind);
+                if let Some(index) = self.debugger.watchpoints.iter().position(|watchpoint| {
+                    watchpoint.start == addr && watchpoint.end == end && watchpoint.kind == kind
+                }) {
+                    self.debugger.watchpoints.remove(index);
+                }
+            }
+            _ => return String::new(),
+        }
+        String::from("OK")
+    }
+}
+
+impl Connection {
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = 0u8;
+        match self.reader.read(core::slice::from_mut(&mut byte))? {
+            0 => Ok(None),
+            _ => Ok(Some(byte)),
+        }
+    }
+
+    /// Read the next packet, acknowledging it unless acknowledgements are turned off.
+    /// Returns `None` when the client disconnects.
+    fn read_packet(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            let Some(byte) = self.read_byte()? else {
+                return Ok(None);
+            };
+            match byte {
+                INTERRUPT => return Ok(Some(Vec::from([INTERRUPT]))),
+                b'$' => {}
+                // Acknowledgements of our replies, and noise between packets
+                _ => continue,
+            }
+
+            let mut data = Vec::new();
+            let mut checksum = 0u8;
+            loop {
+                let Some(byte) = self.read_byte()? else {
+                    return Ok(None);
+                };
+                if byte == b'#' {
+                    break;
+                }
+                checksum = checksum.wrapping_add(byte);
+                if byte == b'}' {
+                    let Some(escaped) = self.read_byte()? else {
+                        return Ok(None);
+                    };
+                    checksum = checksum.wrapping_add(escaped);
+                    data.push(escaped ^ 0x20);
+                } else {
+                    data.push(byte);
+                }
+            }
+            let mut expected = [0u8; 2];
+            self.reader.read_exact(&mut expected)?;
+            let valid = core::str::from_utf8(&expected)
+                .ok()
+                .and_then(|expected| u8::from_str_radix(expected, 16).ok())
+                == Some(checksum);
+
+            if !self.no_ack {
+                self.writer.write_all(if valid { b"+" } else { b"-" })?;
+            }
+            if valid || self.no_ack {
+                return Ok(Some(data));
+            }
+        }
+    }
+
+    fn write_packet(&mut self, data: &str) -> io::Result<()> {
+        let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+        let packet = format!("${}#{:02x}", data, checksum);
+        loop {
+            self.writer.write_all(packet.as_bytes())?;
+            if self.no_ack {
+                return Ok(());
+            }
+            match self.read_byte()? {
+                Some(b'-') => continue,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Whether the client has sent an interrupt, without blocking.
+    fn poll_interrupt(&mut self) -> bool {
+        if self.reader.get_ref().set_nonblocking(true).is_err() {
+            return false;
+        }
+        let interrupted = match self.reader.fill_buf() {
+            Ok(buffer) => buffer.first() == Some(&INTERRUPT),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => false,
+            Err(_) => false,
+        };
+        if interrupted {
+            self.reader.consume(1);
+        }
+        let _ = self.reader.get_ref().set_nonblocking(false);
+        interrupted
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Parse `addr,length`, both in hex.
+fn parse_address_and_length(args: &str) -> Option<(u32, u32)> {
+    let (addr, len) = args.split_once(',')?;
+    Some((
+        u32::from_str_radix(addr, 16).ok()?,
+        u32::from_str_radix(len, 16).ok()?,
+    ))
+}
+
+/// Parse `type,addr,kind` of a `Z` or `z` packet. For watchpoints, the kind is the length
+/// of the watched range.
+fn parse_breakpoint(args: &str) -> Option<(u32, u32, u32)> {
+    let (kind, range) = args.split_once(',')?;
+    let (addr, len) = parse_address_and_length(range.split(';').next()?)?;
+    Some((kind.parse().ok()?, addr, len))
+}
+
+fn watchpoint_kind(breakpoint_type: u32) -> Option<MemoryAccessKind> {
+    match breakpoint_type {
+        2 => Some(MemoryAccessKind::Write),
+        3 => Some(MemoryAccessKind::Read),
+        _ => None,
+    }
+}
+
+/// Answer a `qXfer` read of `offset,length` with the requested part of `document`.
+fn read_chunk(document: &str, args: &str) -> Option<String> {
+    let (offset, len) = parse_address_and_length(args)?;
+    let (offset, len) = (offset as usize, len as usize);
+    if offset >= document.len() {
+        return Some(String::from("l"));
+    }
+    let end = (offset + len).min(document.len());
+    let marker = if end == document.len() { 'l' } else { 'm' };
+    Some(format!("{}{}", marker, &document[offset..end]))
+}
+
+/// Describe a register for `lldb`, which asks for them one at a time.
+fn register_info(register: &str) -> Option<String> {
+    let (name, generic) = match usize::from_str_radix(register, 16).ok()? {
+        0 => ("pc", "pc"),
+        1 => ("fp", "fp"),
+        _ => return None,
+    };
+    let offset = if name == "pc" { 0 } else { 4 };
+    Some(format!(
+        "name:{};bitsize:32;offset:{};encoding:uint;format:hex;set:General Purpose Registers;generic:{};",
+        name, offset, generic
+    ))
+}
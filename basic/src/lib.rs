@@ -15,6 +15,9 @@ use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::{Dimensions, Matrix, MatrixRowSlices};
 use p3_maybe_rayon::*;
 use p3_util::{log2_ceil_usize, log2_strict_usize};
+use valida_alu_u256::{
+    MachineWithU256Chip, U256AddInstruction, U256Chip, U256ModMulInstruction, U256MulInstruction,
+};
 use valida_alu_u32::{
     add::{Add32Chip, Add32Instruction, MachineWithAdd32Chip},
     bitwise::{
@@ -44,6 +47,10 @@ use valida_cpu::{
     ReadAdviceInstruction, StopInstruction, Store32Instruction, StoreU8Instruction,
 };
 use valida_cpu::{CpuChip, MachineWithCpuChip};
+use valida_curves::{
+    Ed25519AddInstruction, Ed25519Chip, Ed25519DoubleInstruction, MachineWithEd25519Chip,
+    MachineWithSecp256k1Chip, Secp256k1AddInstruction, Secp256k1Chip, Secp256k1DoubleInstruction,
+};
 use valida_machine::__internal::p3_challenger::{CanObserve, FieldChallenger};
 use valida_machine::__internal::{
     check_constraints, check_cumulative_sums, get_log_quotient_degree, quotient,
@@ -54,14 +61,27 @@ use valida_machine::{
     ValidaAirBuilder,
 };
 use valida_memory::{MachineWithMemoryChip, MemoryChip};
+use valida_host_call::{EcallInstruction, HostCallChip, MachineWithHostCallChip};
+use valida_keccak::{KeccakChip, KeccakfInstruction, MachineWithKeccakChip};
 use valida_output::{MachineWithOutputChip, OutputChip, WriteInstruction};
+use valida_poseidon2::{MachineWithPoseidon2Chip, Poseidon2Chip, Poseidon2Instruction};
 use valida_program::{MachineWithProgramChip, ProgramChip};
 use valida_range::{MachineWithRangeChip, RangeCheckerChip};
+use valida_sha256::{MachineWithSha256Chip, Sha256Chip, Sha256CompressInstruction};
 use valida_static_data::{MachineWithStaticDataChip, StaticDataChip};
 
 use p3_maybe_rayon::prelude::*;
 use valida_machine::StarkConfig;
 
+pub mod dap;
+pub mod debugger;
+pub mod gdb;
+pub mod profiler;
+pub mod snapshot;
+pub mod trace;
+
+pub use snapshot::Snapshot;
+
 #[derive(Default)]
 pub struct BasicMachine<F: PrimeField32 + TwoAdicField> {
     // Core instructions
@@ -101,6 +121,19 @@ pub struct BasicMachine<F: PrimeField32 + TwoAdicField> {
     // Input/output instructions
     read: ReadAdviceInstruction,
     write: WriteInstruction,
+    ecall: EcallInstruction,
+
+    // Precompiles
+    keccakf: KeccakfInstruction,
+    sha256compress: Sha256CompressInstruction,
+    poseidon2: Poseidon2Instruction,
+    secp256k1add: Secp256k1AddInstruction,
+    secp256k1double: Secp256k1DoubleInstruction,
+    ed25519add: Ed25519AddInstruction,
+    ed25519double: Ed25519DoubleInstruction,
+    u256add: U256AddInstruction,
+    u256mul: U256MulInstruction,
+    u256modmul: U256ModMulInstruction,
 
     // Chips
     cpu: CpuChip,
@@ -115,13 +148,20 @@ pub struct BasicMachine<F: PrimeField32 + TwoAdicField> {
     com_u32: Com32Chip,
     bitwise_u32: Bitwise32Chip,
     output: OutputChip,
+    host_call: HostCallChip,
+    keccak: KeccakChip,
+    sha256: Sha256Chip,
+    poseidon2: Poseidon2Chip,
+    secp256k1: Secp256k1Chip,
+    ed25519: Ed25519Chip,
+    u256: U256Chip,
     range: RangeCheckerChip<256>,
     static_data: StaticDataChip,
 
     _phantom_sc: PhantomData<fn() -> F>,
 }
 
-const NUM_CHIPS: usize = 14;
+const NUM_CHIPS: usize = 21;
 
 impl<F: PrimeField32 + TwoAdicField> Machine<F> for BasicMachine<F> {
     fn run<Adv>(&mut self, program: &ProgramROM<i32>, advice: &mut Adv)
@@ -138,7 +178,7 @@ impl<F: PrimeField32 + TwoAdicField> Machine<F> for BasicMachine<F> {
         }
 
         // Record padded STOP instructions
-        let n = self.cpu().clock.next_power_of_two() - self.cpu().clock;
+        let n = self.cpu().trace_height() as u32 - self.cpu().clock;
         for _ in 0..n {
             self.read_word(self.cpu().pc as usize);
         }
@@ -161,6 +201,13 @@ impl<F: PrimeField32 + TwoAdicField> Machine<F> for BasicMachine<F> {
             Box::new(self.com_u32()),
             Box::new(self.bitwise_u32()),
             Box::new(self.output()),
+            Box::new(self.host_call()),
+            Box::new(self.keccak()),
+            Box::new(self.sha256()),
+            Box::new(self.poseidon2()),
+            Box::new(self.secp256k1()),
+            Box::new(self.ed25519()),
+            Box::new(self.u256()),
             Box::new(self.range()),
             Box::new(self.static_data()),
         ];
@@ -178,6 +225,13 @@ impl<F: PrimeField32 + TwoAdicField> Machine<F> for BasicMachine<F> {
             get_log_quotient_degree::<Self, SC, _>(self, self.com_u32()),
             get_log_quotient_degree::<Self, SC, _>(self, self.bitwise_u32()),
             get_log_quotient_degree::<Self, SC, _>(self, self.output()),
+            get_log_quotient_degree::<Self, SC, _>(self, self.host_call()),
+            get_log_quotient_degree::<Self, SC, _>(self, self.keccak()),
+            get_log_quotient_degree::<Self, SC, _>(self, self.sha256()),
+            get_log_quotient_degree::<Self, SC, _>(self, self.poseidon2()),
+            get_log_quotient_degree::<Self, SC, _>(self, self.secp256k1()),
+            get_log_quotient_degree::<Self, SC, _>(self, self.ed25519()),
+            get_log_quotient_degree::<Self, SC, _>(self, self.u256()),
             get_log_quotient_degree::<Self, SC, _>(self, self.range()),
             get_log_quotient_degree::<Self, SC, _>(self, self.static_data()),
         ];
@@ -542,6 +596,167 @@ impl<F: PrimeField32 + TwoAdicField> Machine<F> for BasicMachine<F> {
         ));
         i += 1;
 
+        let chip = self.host_call();
+        #[cfg(debug_assertions)]
+        check_constraints::<Self, _, SC>(
+            self,
+            chip,
+            &main_traces[i],
+            &perm_traces[i],
+            &perm_challenges,
+        );
+        quotients.push(quotient(
+            self,
+            config,
+            chip,
+            log_degrees[i],
+            None::<RowMajorMatrix<SC::Val>>,
+            main_trace_ldes.remove(0),
+            perm_trace_ldes.remove(0),
+            cumulative_sums[i],
+            &perm_challenges,
+            alpha,
+        ));
+        i += 1;
+
+        let chip = self.keccak();
+        #[cfg(debug_assertions)]
+        check_constraints::<Self, _, SC>(
+            self,
+            chip,
+            &main_traces[i],
+            &perm_traces[i],
+            &perm_challenges,
+        );
+        quotients.push(quotient(
+            self,
+            config,
+            chip,
+            log_degrees[i],
+            None::<RowMajorMatrix<SC::Val>>,
+            main_trace_ldes.remove(0),
+            perm_trace_ldes.remove(0),
+            cumulative_sums[i],
+            &perm_challenges,
+            alpha,
+        ));
+        i += 1;
+
+        let chip = self.sha256();
+        #[cfg(debug_assertions)]
+        check_constraints::<Self, _, SC>(
+            self,
+            chip,
+            &main_traces[i],
+            &perm_traces[i],
+            &perm_challenges,
+        );
+        quotients.push(quotient(
+            self,
+            config,
+            chip,
+            log_degrees[i],
+            None::<RowMajorMatrix<SC::Val>>,
+            main_trace_ldes.remove(0),
+            perm_trace_ldes.remove(0),
+            cumulative_sums[i],
+            &perm_challenges,
+            alpha,
+        ));
+        i += 1;
+
+        let chip = self.poseidon2();
+        #[cfg(debug_assertions)]
+        check_constraints::<Self, _, SC>(
+            self,
+            chip,
+            &main_traces[i],
+            &perm_traces[i],
+            &perm_challenges,
+        );
+        quotients.push(quotient(
+            self,
+            config,
+            chip,
+            log_degrees[i],
+            None::<RowMajorMatrix<SC::Val>>,
+            main_trace_ldes.remove(0),
+            perm_trace_ldes.remove(0),
+            cumulative_sums[i],
+            &perm_challenges,
+            alpha,
+        ));
+        i += 1;
+
+        let chip = self.secp256k1();
+        #[cfg(debug_assertions)]
+        check_constraints::<Self, _, SC>(
+            self,
+            chip,
+            &main_traces[i],
+            &perm_traces[i],
+            &perm_challenges,
+        );
+        quotients.push(quotient(
+            self,
+            config,
+            chip,
+            log_degrees[i],
+            None::<RowMajorMatrix<SC::Val>>,
+            main_trace_ldes.remove(0),
+            perm_trace_ldes.remove(0),
+            cumulative_sums[i],
+            &perm_challenges,
+            alpha,
+        ));
+        i += 1;
+
+        let chip = self.ed25519();
+        #[cfg(debug_assertions)]
+        check_constraints::<Self, _, SC>(
+            self,
+            chip,
+            &main_traces[i],
+            &perm_traces[i],
+            &perm_challenges,
+        );
+        quotients.push(quotient(
+            self,
+            config,
+            chip,
+            log_degrees[i],
+            None::<RowMajorMatrix<SC::Val>>,
+            main_trace_ldes.remove(0),
+            perm_trace_ldes.remove(0),
+            cumulative_sums[i],
+            &perm_challenges,
+            alpha,
+        ));
+        i += 1;
+
+        let chip = self.u256();
+        #[cfg(debug_assertions)]
+        check_constraints::<Self, _, SC>(
+            self,
+            chip,
+            &main_traces[i],
+            &perm_traces[i],
+            &perm_challenges,
+        );
+        quotients.push(quotient(
+            self,
+            config,
+            chip,
+            log_degrees[i],
+            None::<RowMajorMatrix<SC::Val>>,
+            main_trace_ldes.remove(0),
+            perm_trace_ldes.remove(0),
+            cumulative_sums[i],
+            &perm_challenges,
+            alpha,
+        ));
+        i += 1;
+
         let chip = self.range();
         #[cfg(debug_assertions)]
         check_constraints::<Self, _, SC>(
@@ -691,6 +906,13 @@ impl<F: PrimeField32 + TwoAdicField> Machine<F> for BasicMachine<F> {
             Box::new(self.com_u32()),
             Box::new(self.bitwise_u32()),
             Box::new(self.output()),
+            Box::new(self.host_call()),
+            Box::new(self.keccak()),
+            Box::new(self.sha256()),
+            Box::new(self.poseidon2()),
+            Box::new(self.secp256k1()),
+            Box::new(self.ed25519()),
+            Box::new(self.u256()),
             Box::new(self.range()),
             Box::new(self.static_data()),
         ];
@@ -708,6 +930,13 @@ impl<F: PrimeField32 + TwoAdicField> Machine<F> for BasicMachine<F> {
             get_log_quotient_degree::<Self, SC, _>(self, self.com_u32()),
             get_log_quotient_degree::<Self, SC, _>(self, self.bitwise_u32()),
             get_log_quotient_degree::<Self, SC, _>(self, self.output()),
+            get_log_quotient_degree::<Self, SC, _>(self, self.host_call()),
+            get_log_quotient_degree::<Self, SC, _>(self, self.keccak()),
+            get_log_quotient_degree::<Self, SC, _>(self, self.sha256()),
+            get_log_quotient_degree::<Self, SC, _>(self, self.poseidon2()),
+            get_log_quotient_degree::<Self, SC, _>(self, self.secp256k1()),
+            get_log_quotient_degree::<Self, SC, _>(self, self.ed25519()),
+            get_log_quotient_degree::<Self, SC, _>(self, self.u256()),
             get_log_quotient_degree::<Self, SC, _>(self, self.range()),
             get_log_quotient_degree::<Self, SC, _>(self, self.static_data()),
         ];
@@ -1019,6 +1248,111 @@ impl<F: PrimeField32 + TwoAdicField> Machine<F> for BasicMachine<F> {
         .expect(&format!("Failed to verify constraints on chip {}", i));
         i += 1;
 
+        let chip = self.host_call();
+        verify_constraints::<Self, _, SC>(
+            self,
+            chip,
+            &proof.chip_proofs[i].opened_values,
+            proof.chip_proofs[i].cumulative_sum,
+            proof.chip_proofs[i].log_degree,
+            g_subgroups[i],
+            zeta,
+            alpha,
+            &perm_challenges,
+        )
+        .expect(&format!("Failed to verify constraints on chip {}", i));
+        i += 1;
+
+        let chip = self.keccak();
+        verify_constraints::<Self, _, SC>(
+            self,
+            chip,
+            &proof.chip_proofs[i].opened_values,
+            proof.chip_proofs[i].cumulative_sum,
+            proof.chip_proofs[i].log_degree,
+            g_subgroups[i],
+            zeta,
+            alpha,
+            &perm_challenges,
+        )
+        .expect(&format!("Failed to verify constraints on chip {}", i));
+        i += 1;
+
+        let chip = self.sha256();
+        verify_constraints::<Self, _, SC>(
+            self,
+            chip,
+            &proof.chip_proofs[i].opened_values,
+            proof.chip_proofs[i].cumulative_sum,
+            proof.chip_proofs[i].log_degree,
+            g_subgroups[i],
+            zeta,
+            alpha,
+            &perm_challenges,
+        )
+        .expect(&format!("Failed to verify constraints on chip {}", i));
+        i += 1;
+
+        let chip = self.poseidon2();
+        verify_constraints::<Self, _, SC>(
+            self,
+            chip,
+            &proof.chip_proofs[i].opened_values,
+            proof.chip_proofs[i].cumulative_sum,
+            proof.chip_proofs[i].log_degree,
+            g_subgroups[i],
+            zeta,
+            alpha,
+            &perm_challenges,
+        )
+        .expect(&format!("Failed to verify constraints on chip {}", i));
+        i += 1;
+
+        let chip = self.secp256k1();
+        verify_constraints::<Self, _, SC>(
+            self,
+            chip,
+            &proof.chip_proofs[i].opened_values,
+            proof.chip_proofs[i].cumulative_sum,
+            proof.chip_proofs[i].log_degree,
+            g_subgroups[i],
+            zeta,
+            alpha,
+            &perm_challenges,
+        )
+        .expect(&format!("Failed to verify constraints on chip {}", i));
+        i += 1;
+
+        let chip = self.ed25519();
+        verify_constraints::<Self, _, SC>(
+            self,
+            chip,
+            &proof.chip_proofs[i].opened_values,
+            proof.chip_proofs[i].cumulative_sum,
+            proof.chip_proofs[i].log_degree,
+            g_subgroups[i],
+            zeta,
+            alpha,
+            &perm_challenges,
+        )
+        .expect(&format!("Failed to verify constraints on chip {}", i));
+        i += 1;
+
+        let chip = self.u256();
+        verify_constraints::<Self, _, SC>(
+            self,
+            chip,
+            &proof.chip_proofs[i].opened_values,
+            proof.chip_proofs[i].cumulative_sum,
+            proof.chip_proofs[i].log_degree,
+            g_subgroups[i],
+            zeta,
+            alpha,
+            &perm_challenges,
+        )
+        .expect(&format!("Failed to verify constraints on chip {}", i));
+        i += 1;
+
         let chip = self.range();
         verify_constraints::<Self, _, SC>(
             self,
@@ -1174,12 +1508,47 @@ impl<F: PrimeField32 + TwoAdicField> Machine<F> for BasicMachine<F> {
             <WriteInstruction as Instruction<Self, F>>::OPCODE => {
                 WriteInstruction::execute_with_advice::<Adv>(self, ops, advice)
             }
+            <EcallInstruction as Instruction<Self, F>>::OPCODE => {
+                EcallInstruction::execute_with_advice::<Adv>(self, ops, advice)
+            }
+            <KeccakfInstruction as Instruction<Self, F>>::OPCODE => {
+                KeccakfInstruction::execute_with_advice::<Adv>(self, ops, advice)
+            }
+            <Sha256CompressInstruction as Instruction<Self, F>>::OPCODE => {
+                Sha256CompressInstruction::execute_with_advice::<Adv>(self, ops, advice)
+            }
+            <Poseidon2Instruction as Instruction<Self, F>>::OPCODE => {
+                Poseidon2Instruction::execute_with_advice::<Adv>(self, ops, advice)
+            }
+            <Secp256k1AddInstruction as Instruction<Self, F>>::OPCODE => {
+                Secp256k1AddInstruction::execute_with_advice::<Adv>(self, ops, advice)
+            }
+            <Secp256k1DoubleInstruction as Instruction<Self, F>>::OPCODE => {
+                Secp256k1DoubleInstruction::execute_with_advice::<Adv>(self, ops, advice)
+            }
+            <Ed25519AddInstruction as Instruction<Self, F>>::OPCODE => {
+                Ed25519AddInstruction::execute_with_advice::<Adv>(self, ops, advice)
+            }
+            <Ed25519DoubleInstruction as Instruction<Self, F>>::OPCODE => {
+                Ed25519DoubleInstruction::execute_with_advice::<Adv>(self, ops, advice)
+            }
+            <U256AddInstruction as Instruction<Self, F>>::OPCODE => {
+                U256AddInstruction::execute_with_advice::<Adv>(self, ops, advice)
+            }
+            <U256MulInstruction as Instruction<Self, F>>::OPCODE => {
+                U256MulInstruction::execute_with_advice::<Adv>(self, ops, advice)
+            }
+            <U256ModMulInstruction as Instruction<Self, F>>::OPCODE => {
+                U256ModMulInstruction::execute_with_advice::<Adv>(self, ops, advice)
+            }
             _ => panic!("Unrecognized opcode: {}, pc = {}", opcode, pc),
         };
         self.read_word(pc as usize);
 
-        // A STOP instruction signals the end of the program
-        if opcode == <StopInstruction as Instruction<Self, F>>::OPCODE {
+        // A STOP instruction or an exiting host call signals the end of the program
+        if opcode == <StopInstruction as Instruction<Self, F>>::OPCODE
+            || self.host_call.exit_code.is_some()
+        {
             StoppingFlag::DidStop
         } else {
             StoppingFlag::DidNotStop
@@ -1187,6 +1556,106 @@ impl<F: PrimeField32 + TwoAdicField> Machine<F> for BasicMachine<F> {
     }
 }
 
+impl<F: PrimeField32 + TwoAdicField> BasicMachine<F> {
+    /// Capture the architectural state of the machine. The advice provider is not owned by
+    /// the machine, so the caller supplies how many advice bytes have been consumed.
+    pub fn snapshot(&self, advice_position: usize) -> Snapshot {
+        Snapshot {
+            pc: self.cpu.pc,
+            fp: self.cpu.fp,
+            clock: self.cpu.clock,
+            cells: self
+                .mem
+                .cells
+                .iter()
+                .map(|(addr, value)| (*addr, value.0))
+                .collect(),
+            output: self.output.values.clone(),
+            advice_position,
+        }
+    }
+
+    /// Restore the architectural state from a snapshot. The program ROM and static data
+    /// are kept, and any execution trace recorded so far is discarded.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.cpu = CpuChip::default();
+        self.cpu.pc = snapshot.pc;
+        self.cpu.fp = snapshot.fp;
+        self.cpu.clock = snapshot.clock;
+        self.cpu.save_register_state();
+
+        self.mem = MemoryChip::new();
+        self.mem.static_data = self.static_data.get_cells();
+        self.mem.cells = snapshot.cells();
+
+        self.output = OutputChip::default();
+        self.output.values = snapshot.output.clone();
+        self.host_call.reset();
+
+        let program_rom = self.program.program_rom.clone();
+        self.program.set_program_rom(&program_rom);
+
+        self.add_u32 = Add32Chip::default();
+        self.sub_u32 = Sub32Chip::default();
+        self.mul_u32 = Mul32Chip::default();
+        self.div_u32 = Div32Chip::default();
+        self.shift_u32 = Shift32Chip::default();
+        self.lt_u32 = Lt32Chip::default();
+        self.com_u32 = Com32Chip::default();
+        self.bitwise_u32 = Bitwise32Chip::default();
+        self.keccak = KeccakChip::default();
+        self.sha256 = Sha256Chip::default();
+        self.poseidon2 = Poseidon2Chip::default();
+        self.secp256k1 = Secp256k1Chip::default();
+        self.ed25519 = Ed25519Chip::default();
+        self.u256 = U256Chip::default();
+        self.range = RangeCheckerChip::default();
+    }
+
+    /// Continue execution from the current state until the program stops. Unlike `run`,
+    /// this does not initialize memory, so it can be used after `restore`.
+    pub fn resume<Adv>(&mut self, advice: &mut Adv)
+    where
+        Adv: AdviceProvider,
+    {
+        loop {
+            let step_did_stop = self.step(advice);
+            if step_did_stop == StoppingFlag::DidStop {
+                break;
+            }
+        }
+    }
+
+    /// Undo the last executed instruction, restoring the registers, memory, output and
+    /// host call records from before it ran. Returns false if there is no recorded
+    /// instruction to undo, e.g. right after a snapshot was restored.
+    ///
+    /// The traces of the other chips are not rewound, so a machine that has stepped back
+    /// can be inspected and run further, but its execution cannot be proven. The advice
+    /// tape is not owned by the machine and has to be rewound by the caller.
+    pub fn step_back(&mut self) -> bool {
+        if self.cpu.instructions.is_empty() {
+            return false;
+        }
+        self.cpu.clock -= 1;
+        let clk = self.cpu.clock;
+        self.cpu.instructions.pop();
+        self.cpu.operations.pop();
+        self.cpu.registers.pop();
+        let registers = *self.cpu.registers.last().unwrap();
+        self.cpu.pc = registers.pc;
+        self.cpu.fp = registers.fp;
+
+        self.mem.undo(clk);
+        self.output.values.retain(|(value_clk, _)| *value_clk < clk);
+        self.host_call.calls.retain(|call| call.clk < clk);
+        // The machine stops as soon as the guest exits, so only the undone instruction
+        // can have set the exit code.
+        self.host_call.exit_code = None;
+        true
+    }
+}
+
 impl<F: PrimeField32 + TwoAdicField> MachineWithGeneralBus<F> for BasicMachine<F> {
     fn general_bus(&self) -> BusArgument {
         BusArgument::Global(0)
@@ -1330,6 +1799,76 @@ impl<F: PrimeField32 + TwoAdicField> MachineWithOutputChip<F> for BasicMachine<F
     }
 }
 
+impl<F: PrimeField32 + TwoAdicField> MachineWithHostCallChip<F> for BasicMachine<F> {
+    fn host_call(&self) -> &HostCallChip {
+        &self.host_call
+    }
+
+    fn host_call_mut(&mut self) -> &mut HostCallChip {
+        &mut self.host_call
+    }
+}
+
+impl<F: PrimeField32 + TwoAdicField> MachineWithKeccakChip<F> for BasicMachine<F> {
+    fn keccak(&self) -> &KeccakChip {
+        &self.keccak
+    }
+
+    fn keccak_mut(&mut self) -> &mut KeccakChip {
+        &mut self.keccak
+    }
+}
+
+impl<F: PrimeField32 + TwoAdicField> MachineWithSha256Chip<F> for BasicMachine<F> {
+    fn sha256(&self) -> &Sha256Chip {
+        &self.sha256
+    }
+
+    fn sha256_mut(&mut self) -> &mut Sha256Chip {
+        &mut self.sha256
+    }
+}
+
+impl<F: PrimeField32 + TwoAdicField> MachineWithPoseidon2Chip<F> for BasicMachine<F> {
+    fn poseidon2(&self) -> &Poseidon2Chip {
+        &self.poseidon2
+    }
+
+    fn poseidon2_mut(&mut self) -> &mut Poseidon2Chip {
+        &mut self.poseidon2
+    }
+}
+
+impl<F: PrimeField32 + TwoAdicField> MachineWithSecp256k1Chip<F> for BasicMachine<F> {
+    fn secp256k1(&self) -> &Secp256k1Chip {
+        &self.secp256k1
+    }
+
+    fn secp256k1_mut(&mut self) -> &mut Secp256k1Chip {
+        &mut self.secp256k1
+    }
+}
+
+impl<F: PrimeField32 + TwoAdicField> MachineWithEd25519Chip<F> for BasicMachine<F> {
+    fn ed25519(&self) -> &Ed25519Chip {
+        &self.ed25519
+    }
+
+    fn ed25519_mut(&mut self) -> &mut Ed25519Chip {
+        &mut self.ed25519
+    }
+}
+
+impl<F: PrimeField32 + TwoAdicField> MachineWithU256Chip<F> for BasicMachine<F> {
+    fn u256(&self) -> &U256Chip {
+        &self.u256
+    }
+
+    fn u256_mut(&mut self) -> &mut U256Chip {
+        &mut self.u256
+    }
+}
+
 impl<F: PrimeField32 + TwoAdicField> MachineWithRangeChip<F, 256> for BasicMachine<F> {
     fn range(&self) -> &RangeCheckerChip<256> {
         &self.range
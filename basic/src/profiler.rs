@@ -0,0 +1,242 @@
+use crate::trace::TraceStep;
+use crate::BasicMachine;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use p3_field::{PrimeField32, TwoAdicField};
+use std::io;
+use valida_elf::Symbol;
+use valida_opcodes::BYTES_PER_INSTR;
+
+/// Chip names, in the order their row counts are reported.
+pub const PROFILED_CHIPS: [&str; 18] = [
+    "cpu", "memory", "add_u32", "sub_u32", "mul_u32", "div_u32", "shift_u32", "lt_u32",
+    "com_u32", "bitwise_u32", "output", "host_call", "keccak", "sha256", "poseidon2",
+    "secp256k1", "ed25519", "u256",
+];
+
+const UNKNOWN_FUNCTION: &str = "[unknown]";
+
+/// Cycles and trace rows attributed to a function or call stack.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cost {
+    pub cycles: u64,
+    /// Trace rows created in each chip, indexed like `PROFILED_CHIPS`.
+    pub rows: [u64; PROFILED_CHIPS.len()],
+}
+
+impl Cost {
+    fn add(&mut self, other: &Cost) {
+        self.cycles += other.cycles;
+        for (a, b) in self.rows.iter_mut().zip(other.rows.iter()) {
+            *a += b;
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FunctionProfile {
+    pub name: String,
+    /// Cost of the instructions of this function itself.
+    pub self_cost: Cost,
+    /// Cycles spent in this function, including its callees.
+    pub total_cycles: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    /// Cost per call stack, outermost function first.
+    pub stacks: BTreeMap<Vec<String>, Cost>,
+}
+
+impl<F: PrimeField32 + TwoAdicField> BasicMachine<F> {
+    /// Attribute the execution so far to the functions in `symbols`.
+    pub fn profile(&self, symbols: &[Symbol]) -> Profile {
+        Profile::new(&self.execution_trace(), symbols)
+    }
+}
+
+impl Profile {
+    pub fn new(trace: &[TraceStep], symbols: &[Symbol]) -> Self {
+        let mut stacks: BTreeMap<Vec<String>, Cost> = BTreeMap::new();
+        let Some(first) = trace.first() else {
+            return Self { stacks };
+        };
+
+        // Each frame is the function being executed and the frame pointer it was entered
+        // with. Calls move fp down the stack and returns move it back up.
+        let mut call_stack: Vec<(String, u32)> = vec![(function_name(symbols, first.pc), first.fp)];
+        for (i, step) in trace.iter().enumerate() {
+            let names: Vec<String> = call_stack.iter().map(|(name, _)| name.clone()).collect();
+            stacks.entry(names).or_default().add(&step_cost(step));
+
+            let Some(next) = trace.get(i + 1) else {
+                break;
+            };
+            if step.opcode != valida_opcodes::JAL && step.opcode != valida_opcodes::JALV {
+                continue;
+            }
+            let callee = function_name(symbols, next.pc);
+            if next.fp < step.fp {
+                call_stack.push((callee, next.fp));
+            } else if next.fp > step.fp {
+                while call_stack.len() > 1 && call_stack.last().unwrap().1 < next.fp {
+                    call_stack.pop();
+                }
+            } else if callee != call_stack.last().unwrap().0 {
+                // A jump into another function without a new frame, i.e. a tail call.
+                call_stack.last_mut().unwrap().0 = callee;
+            }
+        }
+
+        Self { stacks }
+    }
+
+    /// Per-function costs, sorted by self cycles in decreasing order.
+    pub fn functions(&self) -> Vec<FunctionProfile> {
+        let mut functions: BTreeMap<&str, FunctionProfile> = BTreeMap::new();
+        for (stack, cost) in self.stacks.iter() {
+            let leaf = stack.last().unwrap();
+            functions
+                .entry(leaf)
+                .or_insert_with(|| FunctionProfile {
+                    name: leaf.clone(),
+                    ..Default::default()
+                })
+                .self_cost
+                .add(cost);
+
+            // Count a recursive function only once per stack.
+            let mut seen: Vec<&str> = vec![];
+            for name in stack.iter() {
+                if seen.contains(&name.as_str()) {
+                    continue;
+                }
+                seen.push(name);
+                functions
+                    .entry(name)
+                    .or_insert_with(|| FunctionProfile {
+                        name: name.clone(),
+                        ..Default::default()
+                    })
+                    .total_cycles += cost.cycles;
+            }
+        }
+        let mut functions: Vec<FunctionProfile> = functions.into_values().collect();
+        functions.sort_by(|a, b| b.self_cost.cycles.cmp(&a.self_cost.cycles));
+        functions
+    }
+
+    /// Write cycle counts in the folded stack format read by `flamegraph.pl` and `inferno`.
+    pub fn write_folded<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        for (stack, cost) in self.stacks.iter() {
+            writeln!(writer, "{} {}", stack.join(";"), cost.cycles)?;
+        }
+        Ok(())
+    }
+
+    /// Write a table of the `n` functions with the most self cycles, along with the trace
+    /// rows they created in each chip.
+    pub fn write_top<W: io::Write>(&self, writer: &mut W, n: usize) -> io::Result<()> {
+        let functions = self.functions();
+        let total_cycles: u64 = functions.iter().map(|f| f.self_cost.cycles).sum();
+        // Only show chips that received any rows.
+        let chips: Vec<usize> = (0..PROFILED_CHIPS.len())
+            .filter(|&chip| functions.iter().any(|f| f.self_cost.rows[chip] > 0))
+            .collect();
+        let name_width = functions
+            .iter()
+            .take(n)
+            .map(|f| f.name.len())
+            .fold("function".len(), usize::max);
+
+        let mut header = format!(
+            "{:<name_width$}  {:>12}  {:>7}  {:>12}",
+            "function", "self cycles", "self %", "total cycles"
+        );
+        for &chip in chips.iter() {
+            header += &format!("  {:>11}", PROFILED_CHIPS[chip]);
+        }
+        writeln!(writer, "{}", header)?;
+        for function in functions.iter().take(n) {
+            let percent = if total_cycles == 0 {
+                0.0
+            } else {
+                100.0 * function.self_cost.cycles as f64 / total_cycles as f64
+            };
+            let mut line = format!(
+                "{:<name_width$}  {:>12}  {:>6.2}%  {:>12}",
+                function.name, function.self_cost.cycles, percent, function.total_cycles
+            );
+            for &chip in chips.iter() {
+                line += &format!("  {:>11}", function.self_cost.rows[chip]);
+            }
+            writeln!(writer, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+fn function_name(symbols: &[Symbol], pc: u32) -> String {
+    let address = pc * BYTES_PER_INSTR;
+    symbols
+        .iter()
+        .find(|symbol| symbol.contains(address))
+        .map(|symbol| symbol.name.clone())
+        .unwrap_or_else(|| UNKNOWN_FUNCTION.to_string())
+}
+
+/// The rows a single instruction adds to each chip's trace. The program and range
+/// checker chips have fixed-size traces and are not counted.
+fn step_cost(step: &TraceStep) -> Cost {
+    let mut cost = Cost {
+        cycles: 1,
+        ..Default::default()
+    };
+    cost.rows[chip_index("cpu")] = 1;
+    cost.rows[chip_index("memory")] = step.memory.len() as u64;
+    if let Some(chip) = chip_for_opcode(step.opcode) {
+        cost.rows[chip] += 1;
+    }
+    if step.opcode == valida_opcodes::ECALL {
+        // Besides the call itself, the host call chip has a row for every memory operation
+        // the call made on top of the CPU's two reads and one write. A step that stopped
+        // before writing its result records fewer.
+        cost.rows[chip_index("host_call")] += step.memory.len().saturating_sub(3) as u64 + 1;
+    }
+    if step.opcode == valida_opcodes::KECCAKF {
+        // A permutation takes a row per round.
+        cost.rows[chip_index("keccak")] += valida_keccak::columns::NUM_ROUNDS as u64;
+    }
+    if step.opcode == valida_opcodes::SHA256COMPRESS {
+        cost.rows[chip_index("sha256")] += valida_sha256::columns::NUM_ROUNDS as u64;
+    }
+    cost
+}
+
+fn chip_for_opcode(opcode: u32) -> Option<usize> {
+    use valida_opcodes::*;
+    let name = match opcode {
+        ADD32 => "add_u32",
+        SUB32 => "sub_u32",
+        MUL32 | MULHS32 | MULHU32 => "mul_u32",
+        DIV32 | SDIV32 => "div_u32",
+        SHL32 | SHR32 | SRA32 => "shift_u32",
+        LT32 | LTE32 | SLT32 | SLE32 => "lt_u32",
+        NE32 | EQ32 => "com_u32",
+        AND32 | OR32 | XOR32 => "bitwise_u32",
+        WRITE => "output",
+        POSEIDON2 => "poseidon2",
+        SECP256K1ADD | SECP256K1DOUBLE => "secp256k1",
+        ED25519ADD | ED25519DOUBLE => "ed25519",
+        U256ADD | U256MUL | U256MODMUL => "u256",
+        _ => return None,
+    };
+    Some(chip_index(name))
+}
+
+fn chip_index(name: &str) -> usize {
+    PROFILED_CHIPS.iter().position(|chip| *chip == name).unwrap()
+}
@@ -0,0 +1,42 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use valida_machine::{Word, MEMORY_CELL_BYTES};
+
+/// The architectural state of a `BasicMachine` at a point in its execution.
+///
+/// A snapshot holds what is needed to continue executing a program: the registers, the
+/// contents of memory, the output written so far, and how much of the advice tape has
+/// been consumed. The execution trace recorded before the snapshot is not part of it, so
+/// a restored machine can be run and inspected but its execution cannot be proven.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    pub pc: u32,
+    pub fp: u32,
+    pub clock: u32,
+    /// Initialized memory cells, in the same (big-endian) byte order as `Word<u8>`.
+    pub cells: BTreeMap<u32, [u8; MEMORY_CELL_BYTES]>,
+    /// Output bytes written so far, with the clock cycle they were written in.
+    pub output: Vec<(u32, u8)>,
+    /// The number of advice bytes read so far.
+    pub advice_position: usize,
+}
+
+impl Snapshot {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(self, &mut bytes).expect("Snapshot serialization failed");
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ciborium::de::Error<std::io::Error>> {
+        ciborium::from_reader(bytes)
+    }
+
+    pub fn cells(&self) -> BTreeMap<u32, Word<u8>> {
+        self.cells
+            .iter()
+            .map(|(addr, value)| (*addr, Word(*value)))
+            .collect()
+    }
+}
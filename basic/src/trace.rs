@@ -0,0 +1,99 @@
+use crate::BasicMachine;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use p3_field::{PrimeField32, TwoAdicField};
+use serde::{Deserialize, Serialize};
+use std::io;
+use valida_machine::OPERAND_ELEMENTS;
+use valida_memory::Operation;
+
+/// One executed instruction, together with the register state it ran in and the memory
+/// operations it performed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TraceStep {
+    pub clk: u32,
+    pub pc: u32,
+    pub fp: u32,
+    pub opcode: u32,
+    pub operands: [i32; OPERAND_ELEMENTS],
+    /// The decoded instruction, as printed by `InstructionWord::to_string`.
+    pub instruction: String,
+    pub memory: Vec<MemoryAccess>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryAccessKind {
+    Read,
+    Write,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub kind: MemoryAccessKind,
+    pub addr: u32,
+    pub value: u32,
+}
+
+impl<F: PrimeField32 + TwoAdicField> BasicMachine<F> {
+    /// The instruction-level trace of everything executed so far.
+    pub fn execution_trace(&self) -> Vec<TraceStep> {
+        // After a snapshot is restored, the recorded instructions start at a later clock.
+        let first_clk = self.cpu.clock - self.cpu.instructions.len() as u32;
+        self.cpu
+            .instructions
+            .iter()
+            .zip(self.cpu.registers.iter())
+            .enumerate()
+            .map(|(i, (instruction, registers))| {
+                let clk = first_clk + i as u32;
+                TraceStep {
+                    clk,
+                    pc: registers.pc,
+                    fp: registers.fp,
+                    opcode: instruction.opcode,
+                    operands: instruction.operands.0,
+                    instruction: instruction.to_string(),
+                    memory: self.memory_accesses(clk),
+                }
+            })
+            .collect()
+    }
+
+    /// The memory reads and writes made by the instruction executed at `clk`.
+    pub fn memory_accesses(&self, clk: u32) -> Vec<MemoryAccess> {
+        self.mem
+            .operations
+            .get(&clk)
+            .map(|ops| {
+                ops.iter()
+                    .filter_map(|op| match op {
+                        Operation::Read(addr, value) | Operation::CoprocessorRead(addr, value) => {
+                            Some(MemoryAccess {
+                                kind: MemoryAccessKind::Read,
+                                addr: *addr,
+                                value: (*value).into(),
+                            })
+                        }
+                        Operation::Write(addr, value)
+                        | Operation::CoprocessorWrite(addr, value) => Some(MemoryAccess {
+                            kind: MemoryAccessKind::Write,
+                            addr: *addr,
+                            value: (*value).into(),
+                        }),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Write the execution trace as JSON Lines, one `TraceStep` per line.
+    pub fn write_execution_trace<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        for step in self.execution_trace() {
+            serde_json::to_writer(&mut *writer, &step)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
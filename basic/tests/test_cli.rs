@@ -0,0 +1,141 @@
+use p3_baby_bear::BabyBear;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use valida_assembler::assemble;
+use valida_basic::BasicMachine;
+use valida_cpu::MachineWithCpuChip;
+use valida_machine::{FixedAdviceProvider, Machine, ProgramROM};
+use valida_program::MachineWithProgramChip;
+
+/// A file in the scratch directory Cargo provides to integration tests.
+fn scratch_file(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join(name)
+}
+
+/// Assemble a program from `tests/programs/assembly` into the scratch directory.
+fn assemble_program(name: &str) -> (PathBuf, Vec<u8>) {
+    let asm = fs::read_to_string(format!("tests/programs/assembly/{}.val", name))
+        .expect("Failed to read asm");
+    let code = assemble(&asm).unwrap();
+    let path = scratch_file(&format!("{}.bin", name));
+    fs::write(&path, &code).unwrap();
+    (path, code)
+}
+
+#[test]
+fn run_restored_snapshot() {
+    let (program, code) = assemble_program("fibonacci");
+    let advice_file = scratch_file("fibonacci_advice.bin");
+    fs::write(&advice_file, [10]).unwrap();
+
+    // Save a snapshot in the middle of `fib`
+    let rom = ProgramROM::from_machine_code(&code);
+    let mut machine = BasicMachine::<BabyBear>::default();
+    machine.program_mut().set_program_rom(&rom);
+    machine.cpu_mut().fp = 16777216; // default stack height
+    machine.cpu_mut().save_register_state();
+    let mut advice = FixedAdviceProvider::new(vec![10]);
+    for _ in 0..50 {
+        machine.step(&mut advice);
+    }
+    let snapshot_file = scratch_file("fibonacci_snapshot.cbor");
+    fs::write(
+        &snapshot_file,
+        machine.snapshot(advice.position()).to_bytes(),
+    )
+    .unwrap();
+
+    let run = |output_name: &str, restore: Option<&Path>| {
+        let output_file = scratch_file(output_name);
+        let mut command = Command::new(env!("CARGO_BIN_EXE_valida"));
+        command
+            .arg("run")
+            .arg(&program)
+            .arg(&output_file)
+            .arg(&advice_file);
+        if let Some(snapshot_file) = restore {
+            command.arg("--restore").arg(snapshot_file);
+        }
+        assert!(command.status().unwrap().success());
+        fs::read(output_file).unwrap()
+    };
+    let output = run("fibonacci_output.bin", None);
+    assert_eq!(output, 55u32.to_le_bytes());
+    assert_eq!(
+        run("fibonacci_restored_output.bin", Some(&snapshot_file)),
+        output
+    );
+}
+
+#[test]
+fn run_repl_script() {
+    let (program, _) = assemble_program("fibonacci");
+    let advice_file = scratch_file("repl_advice.bin");
+    fs::write(&advice_file, [10]).unwrap();
+    let script_file = scratch_file("repl_script.txt");
+    let snapshot_file = scratch_file("repl_snapshot.cbor");
+    let script = format!(
+        "# Stop at the start of `fib`, whose frame is 28 bytes below `main`'s
+b 15
+r
+s 2
+x
+x/1dw fp+12
+set mem fp-4 0x1234
+x/2xh fp-4
+# Writes past the end of memory wrap around to its start
+set mem 0xfffffffe 0x11223344
+x/1xh 0
+set pc 20
+set fp 0x1000
+x
+disas 18 20
+save {snapshot}
+reset
+x
+restore {snapshot}
+x
+source {script}
+",
+        snapshot = snapshot_file.display(),
+        script = script_file.display(),
+    );
+    fs::write(&script_file, script).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_valida"))
+        .arg("interactive")
+        .arg(&program)
+        .arg("unused")
+        .arg(&advice_file)
+        .arg("--script")
+        .arg(&script_file)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(stdout.contains("Breakpoint 0 set at pc: 15"));
+    assert!(stdout.contains("Execution stopped at breakpoint 0, PC: 15"));
+    assert!(stdout.contains("  16 : ") && stdout.contains("  17 : "));
+    assert!(stdout.contains("FP: 16777188, PC: 17"));
+    // The argument to `fib`
+    assert!(stdout.contains("0xfffff0   : 10"));
+    assert!(stdout.contains("0xffffe0   : 4660"));
+    assert!(stdout.contains("0xffffe0   : 0x1234 0x0000"));
+    assert!(stdout.contains("0xfffffffe : 287454020"));
+    assert!(stdout.contains("0x0        : 0x1122"));
+    assert!(stdout.contains("PC: 20\n") && stdout.contains("FP: 0x1000\n"));
+    assert!(stdout.contains("  18 : ") && stdout.contains("  19 : "));
+    assert!(!stdout.contains("  20 : "));
+    assert!(stdout.contains("Saved snapshot at clock"));
+    assert!(stdout.contains(&format!("Reloaded {}", program.display())));
+    assert!(stdout.contains("FP: 16777216, PC: 0"));
+    assert!(stdout.contains("Restored snapshot at clock"));
+    assert_eq!(stdout.matches("FP: 4096, PC: 20").count(), 2);
+    assert!(stdout.contains(&format!(
+        "Command file {} is already being run",
+        script_file.display()
+    )));
+}
@@ -0,0 +1,224 @@
+use p3_baby_bear::BabyBear;
+use serde_json::{json, Value};
+use std::fs::read_to_string;
+use std::io::Cursor;
+use valida_assembler::assemble;
+use valida_basic::dap::{read_message, write_message, DapServer};
+use valida_basic::debugger::{Breakpoint, Debugger};
+use valida_cpu::MachineWithCpuChip;
+use valida_host_call::errors;
+use valida_machine::{FixedAdviceProvider, Word};
+use valida_memory::MachineWithMemoryChip;
+use valida_output::MachineWithOutputChip;
+
+/// Frame the requests as a client would send them, numbering them from 1.
+fn script(requests: &[(&str, Value)]) -> Vec<u8> {
+    let mut input = Vec::new();
+    for (seq, (command, arguments)) in requests.iter().enumerate() {
+        let request = json!({
+            "seq": seq + 1,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        });
+        write_message(&mut input, &request).unwrap();
+    }
+    input
+}
+
+#[test]
+fn dap_fibonacci() {
+    let asm = read_to_string("tests/programs/assembly/fibonacci.val").expect("Failed to read asm");
+    let code = assemble(&asm).unwrap();
+    let debugger = Debugger::<BabyBear>::load(
+        &code,
+        FixedAdviceProvider::new(vec![10]).into(),
+        16777216, // default stack height
+    )
+    .unwrap();
+
+    let requests = [
+        ("initialize", json!({ "adapterID": "valida" })),
+        ("launch", json!({})),
+        // `fib` starts after the 15 instructions of `main`
+        (
+            "setInstructionBreakpoints",
+            json!({ "breakpoints": [{ "instructionReference": "0x168" }] }),
+        ),
+        ("configurationDone", json!({})),
+        ("stackTrace", json!({ "threadId": 1 })),
+        ("variables", json!({ "variablesReference": 2 })),
+        ("evaluate", json!({ "expression": "[fp+12]", "frameId": 0 })),
+        ("next", json!({ "threadId": 1 })),
+        ("stackTrace", json!({ "threadId": 1 })),
+        ("stepBack", json!({ "threadId": 1 })),
+        ("stackTrace", json!({ "threadId": 1 })),
+        (
+            "readMemory",
+            json!({ "memoryReference": "0xffffe4", "offset": 12, "count": 4 }),
+        ),
+        ("setInstructionBreakpoints", json!({ "breakpoints": [] })),
+        ("continue", json!({ "threadId": 1 })),
+        ("disconnect", json!({})),
+    ];
+    let input = script(&requests);
+    let mut output = Vec::new();
+    let mut server = DapServer::new(debugger);
+    server.serve(&mut Cursor::new(input), &mut output).unwrap();
+
+    let mut messages = vec![];
+    let mut reader = Cursor::new(output);
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        messages.push(message);
+    }
+    let response = |seq: usize| {
+        messages
+            .iter()
+            .find(|message| message["type"] == "response" && message["request_seq"] == seq)
+            .unwrap_or_else(|| panic!("No response to request {}", seq))
+    };
+    // The events sent after the response to a request
+    let events = |seq: usize| -> Vec<&Value> {
+        messages
+            .iter()
+            .skip_while(|message| message["request_seq"] != seq)
+            .skip(1)
+            .take_while(|message| message["type"] == "event")
+            .collect()
+    };
+    let pcs = |seq: usize| -> Vec<String> {
+        response(seq)["body"]["stackFrames"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|frame| {
+                frame["instructionPointerReference"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    };
+    for seq in 1..=requests.len() {
+        assert_eq!(response(seq)["success"], true, "request {}", seq);
+    }
+
+    assert_eq!(response(1)["body"]["supportsStepBack"], true);
+    assert_eq!(events(2)[0]["event"], "initialized");
+    assert_eq!(response(3)["body"]["breakpoints"][0]["verified"], true);
+    assert_eq!(events(4)[0]["body"]["reason"], "breakpoint");
+
+    // `fib` was called from `main`, which resumes at its 6th instruction
+    assert_eq!(pcs(5), ["0x168", "0x90"]);
+    let locals = response(6)["body"]["variables"].as_array().unwrap();
+    let argument = locals.iter().find(|local| local["name"] == "12(fp)");
+    assert_eq!(argument.unwrap()["value"], "10");
+    assert_eq!(response(7)["body"]["result"], "10");
+
+    assert_eq!(events(8)[0]["body"]["reason"], "step");
+    assert_eq!(pcs(9)[0], "0x180");
+    assert_eq!(pcs(11)[0], "0x168");
+    // The argument 10, little-endian and base64 encoded
+    assert_eq!(response(12)["body"]["data"], "CgAAAA==");
+
+    let names: Vec<&Value> = events(14)
+        .into_iter()
+        .map(|event| &event["event"])
+        .collect();
+    assert_eq!(names, ["output", "exited", "terminated"]);
+    assert_eq!(events(14)[1]["body"]["exitCode"], 0);
+
+    let debugger = server.into_debugger();
+    let output = debugger.machine.output().bytes();
+    assert_eq!(u32::from_le_bytes(output.try_into().unwrap()), 55);
+}
+
+#[test]
+fn dap_guest_output() {
+    // write(1, "hi!\n", 4); write(2, "hi!\n", 4); write(3, "hi!\n", 4)
+    let asm = "main:
+\timm32\t-4(fp), 0, 0, 0, 4
+\timm32\t-12(fp), 0, 0, 15, 208
+\timm32\t-8(fp), 0, 0, 0, 4
+\timm32\t-48(fp), 10, 33, 105, 104
+\timm32\t-16(fp), 0, 0, 0, 1
+\tecall\t-20(fp), -4(fp), -16(fp)
+\timm32\t-16(fp), 0, 0, 0, 2
+\tecall\t-24(fp), -4(fp), -16(fp)
+\timm32\t-16(fp), 0, 0, 0, 3
+\tecall\t-28(fp), -4(fp), -16(fp)
+\tstop
+";
+    let debugger = Debugger::<BabyBear>::load(
+        &assemble(asm).unwrap(),
+        FixedAdviceProvider::empty().into(),
+        0x1000,
+    )
+    .unwrap();
+
+    let requests = [
+        ("initialize", json!({ "adapterID": "valida" })),
+        ("launch", json!({})),
+        ("configurationDone", json!({})),
+        ("disconnect", json!({})),
+    ];
+    let mut output = Vec::new();
+    let mut server = DapServer::new(debugger);
+    server
+        .serve(&mut Cursor::new(script(&requests)), &mut output)
+        .unwrap();
+
+    let mut events = vec![];
+    let mut reader = Cursor::new(output);
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        if message["type"] == "event" && message["event"] != "initialized" {
+            events.push(message);
+        }
+    }
+    let names: Vec<&Value> = events.iter().map(|event| &event["event"]).collect();
+    assert_eq!(names, ["output", "output", "exited", "terminated"]);
+    assert_eq!(events[0]["body"]["category"], "stdout");
+    assert_eq!(events[0]["body"]["output"], "hi!\n");
+    assert_eq!(events[1]["body"]["category"], "stderr");
+    assert_eq!(events[1]["body"]["output"], "hi!\n");
+
+    let debugger = server.into_debugger();
+    let cell = |addr: u32| debugger.read_cell(addr);
+    assert_eq!(cell(0x1000 - 20), Some(4));
+    assert_eq!(cell(0x1000 - 24), Some(4));
+    assert_eq!(cell(0x1000 - 28), Some(errors::EBADF));
+}
+
+#[test]
+fn dap_variables_with_corrupted_frame() {
+    let asm = read_to_string("tests/programs/assembly/fibonacci.val").expect("Failed to read asm");
+    let mut debugger = Debugger::<BabyBear>::load(
+        &assemble(&asm).unwrap(),
+        FixedAdviceProvider::new(vec![10]).into(),
+        16777216, // default stack height
+    )
+    .unwrap();
+    // Break at the start of `fib`, then corrupt the saved frame offset so that the caller's
+    // frame starts below `fib`'s
+    debugger.breakpoints.push(Breakpoint {
+        pc: 15,
+        condition: None,
+    });
+    debugger.cont();
+    let fp = debugger.machine.cpu().fp;
+    debugger
+        .machine
+        .mem_mut()
+        .cells
+        .insert(fp + 8, Word::from(-0x1000i32 as u32));
+
+    // The caller's locals
+    let requests = [("variables", json!({ "variablesReference": 4 }))];
+    let mut output = Vec::new();
+    DapServer::new(debugger)
+        .serve(&mut Cursor::new(script(&requests)), &mut output)
+        .unwrap();
+    let response = read_message(&mut Cursor::new(output)).unwrap().unwrap();
+    assert_eq!(response["success"], true);
+    assert_eq!(response["body"]["variables"], json!([]));
+}
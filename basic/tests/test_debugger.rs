@@ -0,0 +1,205 @@
+use p3_baby_bear::BabyBear;
+use std::fs;
+use valida_assembler::assemble;
+use valida_basic::debugger::{
+    parse_number, Breakpoint, Comparison, Condition, Debugger, Operand, StopReason, Watchpoint,
+};
+use valida_basic::trace::{MemoryAccess, MemoryAccessKind};
+use valida_cpu::MachineWithCpuChip;
+use valida_machine::FixedAdviceProvider;
+
+/// `main` in `src/main.c` calls `square` in `src/square.c`, at pc 5.
+fn lines_debugger() -> Debugger<BabyBear> {
+    let file = fs::read("../elf/tests/fixtures/lines.elf").expect("Failed to read ELF");
+    Debugger::load(&file, FixedAdviceProvider::empty().into(), 0x1000).unwrap()
+}
+
+/// The Fibonacci program, asked for the 10th number.
+fn fibonacci_debugger() -> Debugger<BabyBear> {
+    let asm =
+        fs::read_to_string("tests/programs/assembly/fibonacci.val").expect("Failed to read asm");
+    let code = assemble(&asm).unwrap();
+    Debugger::load(&code, FixedAdviceProvider::new(vec![10]).into(), 0x1000).unwrap()
+}
+
+#[test]
+fn parse_operands() {
+    assert_eq!(parse_number("12"), Some(12));
+    assert_eq!(parse_number("0x1f"), Some(0x1f));
+    assert_eq!(parse_number("-4"), Some(-4i32 as u32));
+    for text in ["", "-", "0x", "0x1g", "ten", "4294967296"] {
+        assert_eq!(parse_number(text), None, "{}", text);
+    }
+
+    assert_eq!(Operand::parse("pc"), Some(Operand::Pc));
+    assert_eq!(Operand::parse(" fp "), Some(Operand::Fp));
+    assert_eq!(Operand::parse("-1"), Some(Operand::Constant(u32::MAX)));
+    assert_eq!(Operand::parse("[fp]"), Some(Operand::FrameCell(0)));
+    assert_eq!(Operand::parse("[fp-8]"), Some(Operand::FrameCell(-8)));
+    assert_eq!(Operand::parse("[ fp + 0xc ]"), Some(Operand::FrameCell(12)));
+    assert_eq!(Operand::parse("[0x1000]"), Some(Operand::Cell(0x1000)));
+    for text in ["", "sp", "[fp-8", "fp-8]", "[]", "[pc]", "[fp*2]", "[fp-x]"] {
+        assert_eq!(Operand::parse(text), None, "{}", text);
+    }
+}
+
+#[test]
+fn parse_conditions() {
+    let condition = |lhs, comparison, rhs| {
+        Some(Condition {
+            lhs,
+            comparison,
+            rhs,
+        })
+    };
+    assert_eq!(
+        Condition::parse("[fp-8] == 5"),
+        condition(Operand::FrameCell(-8), Comparison::Eq, Operand::Constant(5))
+    );
+    assert_eq!(
+        Condition::parse("[fp]!=-1"),
+        condition(
+            Operand::FrameCell(0),
+            Comparison::Ne,
+            Operand::Constant(u32::MAX)
+        )
+    );
+    assert_eq!(
+        Condition::parse("pc <= 0x10"),
+        condition(Operand::Pc, Comparison::Le, Operand::Constant(16))
+    );
+    assert_eq!(
+        Condition::parse("fp >= [0x100]"),
+        condition(Operand::Fp, Comparison::Ge, Operand::Cell(0x100))
+    );
+    assert_eq!(
+        Condition::parse("1 < 2"),
+        condition(Operand::Constant(1), Comparison::Lt, Operand::Constant(2))
+    );
+    assert_eq!(
+        Condition::parse("[fp-4] > [fp-8]"),
+        condition(
+            Operand::FrameCell(-4),
+            Comparison::Gt,
+            Operand::FrameCell(-8)
+        )
+    );
+    for text in [
+        "",
+        "[fp-8]",
+        "[fp-8] = 5",
+        "[fp-8] =< 5",
+        "== 5",
+        "[fp-8] ==",
+        "x == 1",
+        "[fp-8 == 5",
+    ] {
+        assert_eq!(Condition::parse(text), None, "{}", text);
+    }
+}
+
+#[test]
+fn evaluate_conditions() {
+    let mut debugger = lines_debugger();
+    for (i, byte) in (-1i32).to_le_bytes().into_iter().enumerate() {
+        debugger.write_byte(0x1000 - 4 + i as u32, byte);
+    }
+    debugger.write_byte(0x100, 7);
+    let holds = |debugger: &Debugger<BabyBear>, text: &str| {
+        debugger.condition_holds(&Condition::parse(text).unwrap())
+    };
+
+    assert_eq!(debugger.evaluate(Operand::FrameCell(-4)), Some(u32::MAX));
+    assert_eq!(debugger.evaluate(Operand::Cell(0x100)), Some(7));
+    assert_eq!(debugger.evaluate(Operand::FrameCell(-8)), None);
+    // Values are compared as signed integers
+    assert!(holds(&debugger, "[fp-4] < 0"));
+    assert!(holds(&debugger, "[fp-4] == -1"));
+    assert!(holds(&debugger, "[0x100] >= 7"));
+    assert!(!holds(&debugger, "[0x100] > 7"));
+    assert!(holds(&debugger, "fp == 0x1000"));
+    // Conditions reading uninitialized memory are false either way
+    assert!(!holds(&debugger, "[fp-8] == 0"));
+    assert!(!holds(&debugger, "[fp-8] != 0"));
+    // Offsets that leave the address space wrap around
+    assert_eq!(debugger.evaluate(Operand::FrameCell(i32::MAX)), None);
+    assert_eq!(debugger.evaluate(Operand::FrameCell(i32::MIN)), None);
+
+    debugger.breakpoints = vec![
+        Breakpoint {
+            pc: 0,
+            condition: Condition::parse("[0x100] == 8"),
+        },
+        Breakpoint {
+            pc: 0,
+            condition: Condition::parse("[0x100] == 7"),
+        },
+        Breakpoint {
+            pc: 1,
+            condition: None,
+        },
+    ];
+    assert_eq!(debugger.breakpoint_hit(0), Some(1));
+    assert_eq!(debugger.breakpoint_hit(1), Some(2));
+    assert_eq!(debugger.breakpoint_hit(2), None);
+}
+
+#[test]
+fn stop_at_watchpoints() {
+    let mut debugger = fibonacci_debugger();
+    // `main` reads the advice into -8(fp), and its third instruction copies it to -16(fp).
+    // The second watchpoint covers a byte in the middle of -16(fp).
+    debugger.watchpoints = vec![
+        Watchpoint {
+            start: 0x1000 - 8,
+            end: 0x1000 - 4,
+            kind: Some(MemoryAccessKind::Read),
+        },
+        Watchpoint {
+            start: 0x1000 - 14,
+            end: 0x1000 - 13,
+            kind: None,
+        },
+    ];
+    let read = MemoryAccess {
+        kind: MemoryAccessKind::Read,
+        addr: 0x1000 - 8,
+        value: 10,
+    };
+    let write = MemoryAccess {
+        kind: MemoryAccessKind::Write,
+        addr: 0x1000 - 16,
+        value: 10,
+    };
+    assert_eq!(debugger.cont(), StopReason::Watchpoint(0, read));
+    assert_eq!(debugger.machine.cpu().pc, 3);
+
+    debugger.watchpoints.remove(0);
+    assert_eq!(debugger.step_back(), StopReason::Step);
+    assert_eq!(debugger.step(), StopReason::Watchpoint(0, write));
+    assert_eq!(debugger.step(), StopReason::Step);
+    // Running backwards stops at the same access
+    assert_eq!(debugger.reverse_cont(), StopReason::Watchpoint(0, write));
+    assert_eq!(debugger.machine.cpu().pc, 2);
+}
+
+#[test]
+fn resolve_location() {
+    let debugger = lines_debugger();
+    assert_eq!(debugger.resolve_location("3"), Ok(vec![3]));
+    assert_eq!(debugger.resolve_location("square"), Ok(vec![5]));
+    assert_eq!(debugger.resolve_location("main.c:4"), Ok(vec![1, 3]));
+    assert_eq!(debugger.resolve_location("src/square.c:3"), Ok(vec![7]));
+    assert_eq!(
+        debugger.resolve_location("main.c:7"),
+        Err(String::from("No code for line main.c:7"))
+    );
+    assert_eq!(
+        debugger.resolve_location("cube"),
+        Err(String::from("No function named cube"))
+    );
+    assert_eq!(
+        debugger.location(6),
+        Some(String::from("square+1 at src/square.c:2"))
+    );
+}
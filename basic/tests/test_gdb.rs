@@ -0,0 +1,108 @@
+use p3_baby_bear::BabyBear;
+use std::fs::read_to_string;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use valida_assembler::assemble;
+use valida_basic::debugger::Debugger;
+use valida_basic::gdb::GdbServer;
+use valida_machine::FixedAdviceProvider;
+use valida_opcodes::BYTES_PER_INSTR;
+use valida_output::MachineWithOutputChip;
+
+/// A minimal RSP client that acknowledges every reply.
+struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0u8];
+        self.stream.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+
+    fn request(&mut self, data: &str) -> String {
+        let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+        write!(self.stream, "${}#{:02x}", data, checksum).unwrap();
+        assert_eq!(self.read_byte(), b'+');
+
+        assert_eq!(self.read_byte(), b'$');
+        let mut reply = Vec::new();
+        loop {
+            match self.read_byte() {
+                b'#' => break,
+                byte => reply.push(byte),
+            }
+        }
+        let mut checksum = [0u8; 2];
+        self.stream.read_exact(&mut checksum).unwrap();
+        self.stream.write_all(b"+").unwrap();
+        String::from_utf8(reply).unwrap()
+    }
+}
+
+fn le_hex(value: u32) -> String {
+    value
+        .to_le_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[test]
+fn gdb_fibonacci() {
+    let asm = read_to_string("tests/programs/assembly/fibonacci.val").expect("Failed to read asm");
+    let code = assemble(&asm).unwrap();
+    let debugger = Debugger::<BabyBear>::load(
+        &code,
+        FixedAdviceProvider::new(vec![10]).into(),
+        16777216, // default stack height
+    )
+    .unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = thread::spawn(move || {
+        let mut server = GdbServer::new(debugger);
+        server.serve(&listener).unwrap();
+        server.into_debugger()
+    });
+    let mut client = Client {
+        stream: TcpStream::connect(("127.0.0.1", port)).unwrap(),
+    };
+
+    assert!(client
+        .request("qSupported:swbreak+")
+        .contains("qXfer:features:read+"));
+    assert_eq!(client.request("?"), "S05");
+    assert_eq!(client.request("g"), le_hex(0) + &le_hex(16777216));
+
+    // `fib` starts after the 15 instructions of `main`.
+    let fib = 15 * BYTES_PER_INSTR;
+    assert_eq!(client.request(&format!("Z0,{:x},4", fib)), "OK");
+    assert_eq!(client.request("c"), "T05swbreak:;");
+    assert_eq!(client.request("p0"), le_hex(fib));
+    assert_eq!(client.request("s"), "S05");
+    assert_eq!(client.request("p0"), le_hex(fib + BYTES_PER_INSTR));
+    assert_eq!(client.request("bs"), "S05");
+    assert_eq!(client.request("p0"), le_hex(fib));
+
+    // Memory is byte-addressed and little-endian
+    assert_eq!(client.request("M1000,4:78563412"), "OK");
+    assert_eq!(client.request("m1000,4"), "78563412");
+    assert_eq!(client.request("m1001,2"), "5634");
+    // Reads are limited to what fits in a packet
+    assert_eq!(client.request("m1000,2000").len(), 0x4000);
+    assert_eq!(client.request("m1000,2001"), "E01");
+    assert_eq!(client.request("m1000,ffffffff"), "E01");
+
+    assert_eq!(client.request(&format!("z0,{:x},4", fib)), "OK");
+    assert_eq!(client.request("c"), "W00");
+    assert_eq!(client.request("D"), "OK");
+
+    let debugger = server.join().unwrap();
+    assert_eq!(debugger.read_cell(0x1000), Some(0x12345678));
+    let output = debugger.machine.output().bytes();
+    assert_eq!(u32::from_le_bytes(output.try_into().unwrap()), 55);
+}
@@ -1,11 +1,24 @@
 use p3_baby_bear::BabyBear;
 use std::fs::read_to_string;
-use valida_assembler::assemble;
-use valida_basic::BasicMachine;
+use valida_assembler::{assemble, assemble_elf};
+use valida_basic::profiler::PROFILED_CHIPS;
+use valida_basic::trace::{MemoryAccessKind, TraceStep};
+use valida_basic::{BasicMachine, Snapshot};
 use valida_cpu::MachineWithCpuChip;
-use valida_machine::{FixedAdviceProvider, Machine, ProgramROM};
+use valida_elf::{load_executable_file, Program, Symbol};
+use valida_host_call::{
+    errors, DefaultHostCallHandler, HostCall, HostCallHandler, HostCallOutcome,
+    MachineWithHostCallChip, MemoryEffect,
+};
+use valida_machine::Word;
+use valida_memory::MachineWithMemoryChip;
+use valida_machine::{
+    decode_output, AdviceBuilder, DecodeError, FixedAdviceProvider, FrameReader, Machine, ProgramROM,
+    RecordingAdviceProvider,
+};
 use valida_output::MachineWithOutputChip;
 use valida_program::MachineWithProgramChip;
+use valida_static_data::MachineWithStaticDataChip;
 
 #[test]
 fn run_fibonacci() {
@@ -31,6 +44,359 @@ fn run_fibonacci() {
     assert_eq!(actual_result, expected_result);
 }
 
+#[test]
+fn run_static_data() {
+    let mut machine = BasicMachine::<BabyBear>::default();
+    let asm_path = "tests/programs/assembly/static_data.val";
+    let asm = read_to_string(asm_path).expect("Failed to read asm");
+    let Program { code, data, .. } = load_executable_file(assemble_elf(&asm).unwrap()).unwrap();
+    machine.program_mut().set_program_rom(&code);
+    machine.static_data_mut().load(data);
+    machine.cpu_mut().fp = 16777216; // default stack height
+    machine.cpu_mut().save_register_state();
+
+    machine.run(&code, &mut FixedAdviceProvider::empty());
+    let output = machine.output().bytes();
+    assert_eq!(u32::from_le_bytes(output.try_into().unwrap()), 55);
+}
+
+#[test]
+fn trace_fibonacci() {
+    let mut machine = BasicMachine::<BabyBear>::default();
+    let asm_path = "tests/programs/assembly/fibonacci.val";
+    let asm = read_to_string(asm_path).expect("Failed to read asm");
+    let rom = ProgramROM::from_machine_code(&assemble(&asm).unwrap());
+    machine.program_mut().set_program_rom(&rom);
+    machine.cpu_mut().fp = 16777216; // default stack height
+    machine.cpu_mut().save_register_state();
+    machine.run(&rom, &mut FixedAdviceProvider::new(vec![10]));
+
+    let trace = machine.execution_trace();
+    assert_eq!(trace.len() as u32, machine.cpu().clock);
+    for (i, step) in trace.iter().enumerate() {
+        assert_eq!(step.clk, i as u32);
+    }
+
+    // imm32 -4(fp), 0, 0, 0, 0
+    let first = &trace[0];
+    assert_eq!((first.pc, first.fp), (0, 16777216));
+    assert!(first.instruction.starts_with("IMM32"));
+    assert_eq!(first.memory.len(), 1);
+    assert_eq!(first.memory[0].kind, MemoryAccessKind::Write);
+    assert_eq!(first.memory[0].addr, 16777216 - 4);
+    assert_eq!(first.memory[0].value, 0);
+    assert!(trace.last().unwrap().instruction.starts_with("STOP"));
+
+    let mut jsonl = Vec::new();
+    machine.write_execution_trace(&mut jsonl).unwrap();
+    let parsed: Vec<TraceStep> = String::from_utf8(jsonl)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(parsed, trace);
+}
+
+#[test]
+fn profile_fibonacci() {
+    let mut machine = BasicMachine::<BabyBear>::default();
+    let asm_path = "tests/programs/assembly/fibonacci.val";
+    let asm = read_to_string(asm_path).expect("Failed to read asm");
+    let rom = ProgramROM::from_machine_code(&assemble(&asm).unwrap());
+    machine.program_mut().set_program_rom(&rom);
+    machine.cpu_mut().fp = 16777216; // default stack height
+    machine.cpu_mut().save_register_state();
+    machine.run(&rom, &mut FixedAdviceProvider::new(vec![10]));
+
+    // `fib` starts after the 15 instructions of `main`.
+    let symbols = vec![
+        Symbol {
+            name: "main".to_string(),
+            address: 0,
+            size: 15 * 24,
+        },
+        Symbol {
+            name: "fib".to_string(),
+            address: 15 * 24,
+            size: (rom.0.len() as u32 - 15) * 24,
+        },
+    ];
+    let profile = machine.profile(&symbols);
+    let cycles = machine.cpu().clock as u64;
+
+    let main_cycles = profile.stacks[&vec!["main".to_string()]].cycles;
+    let fib_cycles = profile.stacks[&vec!["main".to_string(), "fib".to_string()]].cycles;
+    assert_eq!(profile.stacks.len(), 2);
+    assert_eq!(main_cycles, 15);
+    assert_eq!(main_cycles + fib_cycles, cycles);
+
+    let functions = profile.functions();
+    assert_eq!(functions[0].name, "fib");
+    assert_eq!(functions[1].name, "main");
+    assert_eq!(functions[1].total_cycles, cycles);
+    let output_chip = PROFILED_CHIPS.iter().position(|c| *c == "output").unwrap();
+    assert_eq!(functions[1].self_cost.rows[output_chip], 4);
+    assert_eq!(functions[0].self_cost.rows[output_chip], 0);
+
+    let mut folded = Vec::new();
+    profile.write_folded(&mut folded).unwrap();
+    assert_eq!(
+        String::from_utf8(folded).unwrap(),
+        format!("main {}\nmain;fib {}\n", main_cycles, fib_cycles)
+    );
+}
+
+const HOST_CALL_PROGRAM: &str = "main:
+\timm32\t-4(fp), 0, 0, 0, 2
+\timm32\t-12(fp), 0, 0, 0, 0
+\tecall\t-8(fp), -4(fp), -12(fp)
+\timm32\t-24(fp), 0, 0, 1, 0
+\taddi\t-20(fp), -8(fp), 0
+\timm32\t-28(fp), 0, 0, 0, 3
+\tecall\t-32(fp), -28(fp), -24(fp)
+\timm32\t-36(fp), 0, 0, 0, 1
+\timm32\t-40(fp), 0, 0, 0, 7
+\tecall\t-44(fp), -36(fp), -40(fp)
+\tstop
+";
+
+#[test]
+fn run_host_calls() {
+    // input_len(); read(256, input_len()); exit(7)
+    let mut machine = BasicMachine::<BabyBear>::default();
+    let rom = ProgramROM::from_machine_code(&assemble(HOST_CALL_PROGRAM).unwrap());
+    machine.program_mut().set_program_rom(&rom);
+    machine.cpu_mut().fp = 0x1000;
+    machine.cpu_mut().save_register_state();
+    machine
+        .host_call_mut()
+        .set_handler(DefaultHostCallHandler::new(b"hello".to_vec()));
+    machine.run(&rom, &mut FixedAdviceProvider::empty());
+
+    let cell = |addr: u32| *machine.mem().cells.get(&addr).unwrap();
+    assert_eq!(cell(0x1000 - 8), Word([0, 0, 0, 5])); // input length
+    assert_eq!(cell(0x1000 - 32), Word([0, 0, 0, 5])); // bytes read
+    assert_eq!(cell(0x1000 - 44), Word([0, 0, 0, 7])); // exit code
+    assert_eq!(cell(256), Word([b'l', b'l', b'e', b'h']));
+    assert_eq!(cell(260), Word([0, 0, 0, b'o']));
+    assert_eq!(machine.host_call().exit_code, Some(7));
+    // The STOP instruction is never reached.
+    assert_eq!(machine.cpu().clock, 10);
+}
+
+struct DoublingHandler;
+
+impl HostCallHandler for DoublingHandler {
+    fn handle(&mut self, call: &mut HostCall) -> HostCallOutcome {
+        match call.number() {
+            1 => HostCallOutcome::Exit(call.arg(0)),
+            _ => HostCallOutcome::Return(call.number() * 2),
+        }
+    }
+}
+
+#[test]
+fn run_custom_host_call_handler() {
+    let mut machine = BasicMachine::<BabyBear>::default();
+    let rom = ProgramROM::from_machine_code(&assemble(HOST_CALL_PROGRAM).unwrap());
+    machine.program_mut().set_program_rom(&rom);
+    machine.cpu_mut().fp = 0x1000;
+    machine.cpu_mut().save_register_state();
+    machine.host_call_mut().set_handler(DoublingHandler);
+    machine.run(&rom, &mut FixedAdviceProvider::empty());
+
+    let cell = |addr: u32| *machine.mem().cells.get(&addr).unwrap();
+    assert_eq!(cell(0x1000 - 8), Word([0, 0, 0, 4]));
+    assert_eq!(cell(0x1000 - 32), Word([0, 0, 0, 6]));
+    assert_eq!(machine.host_call().exit_code, Some(7));
+    assert_eq!(machine.host_call().calls.len(), 3);
+}
+
+#[test]
+fn run_failing_host_calls() {
+    // unknown(); write(3, 0, 4); debug_log(0xfffffffe, 4)
+    let asm = "main:
+\timm32\t-4(fp), 0, 0, 0, 99
+\tecall\t-8(fp), -4(fp), -4(fp)
+\timm32\t-12(fp), 0, 0, 0, 4
+\timm32\t-24(fp), 0, 0, 0, 3
+\timm32\t-20(fp), 0, 0, 0, 0
+\timm32\t-16(fp), 0, 0, 0, 4
+\tecall\t-28(fp), -12(fp), -24(fp)
+\timm32\t-32(fp), 0, 0, 0, 0
+\timm32\t-40(fp), 255, 255, 255, 254
+\timm32\t-36(fp), 0, 0, 0, 4
+\tecall\t-44(fp), -32(fp), -40(fp)
+\tstop
+";
+    let mut machine = BasicMachine::<BabyBear>::default();
+    let rom = ProgramROM::from_machine_code(&assemble(asm).unwrap());
+    machine.program_mut().set_program_rom(&rom);
+    machine.cpu_mut().fp = 0x1000;
+    machine.cpu_mut().save_register_state();
+    machine.run(&rom, &mut FixedAdviceProvider::empty());
+
+    let cell = |addr: u32| *machine.mem().cells.get(&addr).unwrap();
+    assert_eq!(cell(0x1000 - 8), Word::from(errors::ENOSYS));
+    assert_eq!(cell(0x1000 - 28), Word::from(errors::EBADF));
+    // The bytes logged wrap around to the start of memory
+    assert_eq!(cell(0x1000 - 44), Word([0, 0, 0, 0]));
+    let effects = &machine.host_call().calls[2].effects;
+    assert!(matches!(
+        effects[..],
+        [
+            MemoryEffect::Read(_, _),
+            MemoryEffect::Read(0xfffffffc, _),
+            MemoryEffect::Read(0, _)
+        ]
+    ));
+    assert_eq!(machine.host_call().exit_code, None);
+}
+
+#[test]
+fn run_structured_io() {
+    let advice = AdviceBuilder::new().write(&(1u8, 2u8));
+    let len = advice.bytes().len();
+    assert_eq!(advice.bytes(), &[3, 0, 0, 0, 0x82, 0x01, 0x02]);
+
+    // Echo the advice tape to output, one byte at a time.
+    let mut asm = String::from("main:\n");
+    for _ in 0..len {
+        asm += "\tadvread\t-4\n\twrite\t0, -4, 0, 0, 1\n";
+    }
+    asm += "\tstop\n";
+
+    let mut machine = BasicMachine::<BabyBear>::default();
+    let rom = ProgramROM::from_machine_code(&assemble(&asm).unwrap());
+    machine.program_mut().set_program_rom(&rom);
+    machine.cpu_mut().fp = 0x1000;
+    machine.cpu_mut().save_register_state();
+    machine.run(&rom, &mut advice.build());
+
+    let output = machine.output().bytes();
+    assert_eq!(decode_output::<(u8, u8)>(&output), Ok((1, 2)));
+
+    let mut reader = FrameReader::new(&output);
+    assert_eq!(reader.read::<(u8, u8)>(), Ok((1, 2)));
+    assert!(reader.is_empty());
+    assert_eq!(
+        decode_output::<(u8, u8)>(&output[..len - 1]),
+        Err(DecodeError::Truncated {
+            expected: 3,
+            available: 2
+        })
+    );
+    assert!(matches!(
+        decode_output::<String>(&output),
+        Err(DecodeError::InvalidPayload(_))
+    ));
+}
+
+#[test]
+fn replay_recorded_advice() {
+    let asm_path = "tests/programs/assembly/fibonacci.val";
+    let asm = read_to_string(asm_path).expect("Failed to read asm");
+    let rom = ProgramROM::from_machine_code(&assemble(&asm).unwrap());
+    let run = |advice: &mut dyn FnMut(&mut BasicMachine<BabyBear>)| {
+        let mut machine = BasicMachine::<BabyBear>::default();
+        machine.program_mut().set_program_rom(&rom);
+        machine.cpu_mut().fp = 16777216; // default stack height
+        machine.cpu_mut().save_register_state();
+        advice(&mut machine);
+        machine.output().bytes()
+    };
+
+    // The program only reads one byte, so the rest of the tape is not recorded.
+    let mut recorder = RecordingAdviceProvider::new(FixedAdviceProvider::new(vec![25, 1, 2]));
+    let output = run(&mut |machine| machine.run(&rom, &mut recorder));
+    assert_eq!(recorder.recorded(), &[25]);
+
+    let recorded = recorder.recorded().to_vec();
+    let replayed = run(&mut |machine| {
+        machine.run(&rom, &mut FixedAdviceProvider::new(recorded.clone()))
+    });
+    assert_eq!(output, replayed);
+}
+
+#[test]
+fn snapshot_round_trip() {
+    let asm_path = "tests/programs/assembly/fibonacci.val";
+    let asm = read_to_string(asm_path).expect("Failed to read asm");
+    let rom = ProgramROM::from_machine_code(&assemble(&asm).unwrap());
+    let new_machine = || {
+        let mut machine = BasicMachine::<BabyBear>::default();
+        machine.program_mut().set_program_rom(&rom);
+        machine.cpu_mut().fp = 16777216; // default stack height
+        machine.cpu_mut().save_register_state();
+        machine
+    };
+
+    let mut uninterrupted = new_machine();
+    uninterrupted.run(&rom, &mut FixedAdviceProvider::new(vec![10]));
+    let clock = uninterrupted.cpu().clock;
+
+    // Before the advice is read, in the middle of `fib`, and after part of the output
+    // was written
+    for stop_clock in [1, clock / 2, clock - 4] {
+        let mut machine = new_machine();
+        let mut advice = FixedAdviceProvider::new(vec![10]);
+        for _ in 0..stop_clock {
+            machine.step(&mut advice);
+        }
+        let bytes = machine.snapshot(advice.position()).to_bytes();
+
+        let snapshot = Snapshot::from_bytes(&bytes).unwrap();
+        assert_eq!(snapshot.clock, stop_clock);
+        let mut restored = new_machine();
+        restored.restore(&snapshot);
+        let mut advice = FixedAdviceProvider::new(vec![10]);
+        advice.seek(snapshot.advice_position);
+        restored.resume(&mut advice);
+
+        assert_eq!(restored.output().bytes(), uninterrupted.output().bytes());
+        assert_eq!(restored.cpu().clock, clock);
+        assert_eq!(restored.cpu().pc, uninterrupted.cpu().pc);
+        assert_eq!(restored.mem().cells, uninterrupted.mem().cells);
+    }
+}
+
+#[test]
+fn step_back_fibonacci() {
+    let mut machine = BasicMachine::<BabyBear>::default();
+    let asm_path = "tests/programs/assembly/fibonacci.val";
+    let asm = read_to_string(asm_path).expect("Failed to read asm");
+    let rom = ProgramROM::from_machine_code(&assemble(&asm).unwrap());
+    machine.program_mut().set_program_rom(&rom);
+    machine.cpu_mut().fp = 16777216; // default stack height
+    machine.cpu_mut().save_register_state();
+
+    let mut advice = FixedAdviceProvider::new(vec![10]);
+    for _ in 0..50 {
+        machine.step(&mut advice);
+    }
+    let before = machine.snapshot(advice.position());
+
+    for _ in 0..40 {
+        machine.step(&mut advice);
+    }
+    for _ in 0..40 {
+        assert!(machine.step_back());
+    }
+    assert_eq!(machine.snapshot(advice.position()), before);
+    assert_eq!(machine.execution_trace().len(), 50);
+
+    // Executing again after stepping back reaches the same result
+    machine.resume(&mut advice);
+    let output = machine.output().bytes();
+    assert_eq!(u32::from_le_bytes(output.try_into().unwrap()), fibonacci(10));
+
+    while machine.step_back() {}
+    assert_eq!(machine.cpu().clock, 0);
+    assert_eq!(machine.cpu().pc, 0);
+    assert!(machine.mem().cells.is_empty());
+    assert!(machine.output().bytes().is_empty());
+}
+
 fn fibonacci(n: u8) -> u32 {
     let mut a = 0u32;
     let mut b = 1u32;
@@ -2,6 +2,9 @@ extern crate core;
 
 use p3_baby_bear::BabyBear;
 use p3_fri::{TwoAdicFriPcs, TwoAdicFriPcsConfig};
+use valida_alu_u256::{
+    MachineWithU256Chip, U256AddInstruction, U256ModMulInstruction, U256MulInstruction,
+};
 use valida_alu_u32::add::{Add32Instruction, MachineWithAdd32Chip};
 use valida_alu_u32::lt::{Lt32Instruction, Lte32Instruction, Sle32Instruction, Slt32Instruction};
 use valida_basic::BasicMachine;
@@ -9,21 +12,33 @@ use valida_cpu::{
     BeqInstruction, BneInstruction, Imm32Instruction, JalInstruction, JalvInstruction,
     LoadFpInstruction, MachineWithCpuChip, StopInstruction,
 };
+use valida_curves::{
+    Ed25519AddInstruction, Ed25519DoubleInstruction, MachineWithEd25519Chip,
+    MachineWithSecp256k1Chip, Secp256k1AddInstruction, Secp256k1DoubleInstruction,
+};
+use valida_machine::__internal::check_constraints;
 use valida_machine::{
-    FixedAdviceProvider, Instruction, InstructionWord, Machine, MachineProof, Operands, ProgramROM,
-    Word,
+    generate_permutation_trace, Chip, FixedAdviceProvider, Instruction, InstructionWord, Machine,
+    MachineProof, Operands, ProgramROM, Word,
 };
 
+use valida_host_call::{EcallInstruction, MachineWithHostCallChip};
+use valida_keccak::columns::{KECCAK_COL_MAP, NUM_ROUNDS};
+use valida_keccak::{keccak_f, KeccakfInstruction, MachineWithKeccakChip};
 use valida_memory::MachineWithMemoryChip;
 use valida_opcodes::BYTES_PER_INSTR;
+use valida_poseidon2::{MachineWithPoseidon2Chip, Poseidon2Instruction};
 use valida_program::MachineWithProgramChip;
+use valida_sha256::{compress, MachineWithSha256Chip, Sha256CompressInstruction};
 
 use p3_challenger::DuplexChallenger;
 use p3_dft::Radix2Bowers;
 use p3_field::extension::BinomialExtensionField;
-use p3_field::{Field, PrimeField32, TwoAdicField};
+use p3_field::{AbstractField, Field, PrimeField32, TwoAdicField};
 use p3_fri::FriConfig;
 use p3_keccak::Keccak256Hash;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
 use p3_mds::coset_mds::CosetMds;
 use p3_merkle_tree::FieldMerkleTreeMmcs;
 use p3_poseidon::Poseidon;
@@ -401,44 +416,398 @@ fn loadfp_program<Val: PrimeField32 + TwoAdicField>() -> Vec<InstructionWord<i32
     program
 }
 
-fn prove_program(program: Vec<InstructionWord<i32>>) -> BasicMachine<BabyBear> {
-    let mut machine = BasicMachine::<Val>::default();
+fn host_call_program<Val: PrimeField32 + TwoAdicField>() -> Vec<InstructionWord<i32>> {
+    let mut program = vec![];
+    // imm32 -4(fp), 0, 0, 0, 2       // INPUT_LEN
+    // imm32 -8(fp), 0, 0, 0, 0
+    // ecall -12(fp), -4(fp), -8(fp)
+    // imm32 -16(fp), 0, 0, 0, 1      // EXIT
+    // imm32 -20(fp), 0, 0, 0, 3
+    // ecall 4(fp), -16(fp), -20(fp)
+    // stop                           // not reached
+    program.extend([
+        InstructionWord {
+            opcode: <Imm32Instruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-4, 0, 0, 0, 2]),
+        },
+        InstructionWord {
+            opcode: <Imm32Instruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-8, 0, 0, 0, 0]),
+        },
+        InstructionWord {
+            opcode: <EcallInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-12, -4, -8, 0, 0]),
+        },
+        InstructionWord {
+            opcode: <Imm32Instruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-16, 0, 0, 0, 1]),
+        },
+        InstructionWord {
+            opcode: <Imm32Instruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-20, 0, 0, 0, 3]),
+        },
+        InstructionWord {
+            opcode: <EcallInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([4, -16, -20, 0, 0]),
+        },
+        InstructionWord {
+            opcode: <StopInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands::default(),
+        },
+    ]);
+
+    program
+}
+
+fn keccak_program<Val: PrimeField32 + TwoAdicField>() -> Vec<InstructionWord<i32>> {
+    let mut program = vec![];
+    // imm32 -4(fp), 0, 0, 1, 0       // the state is at 0x100
+    // keccakf -8(fp), -4(fp), -4(fp)
+    // keccakf -8(fp), -4(fp), -4(fp)
+    // stop
+    program.extend([
+        InstructionWord {
+            opcode: <Imm32Instruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-4, 0, 0, 1, 0]),
+        },
+        InstructionWord {
+            opcode: <KeccakfInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-8, -4, -4, 0, 0]),
+        },
+        InstructionWord {
+            opcode: <KeccakfInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-8, -4, -4, 0, 0]),
+        },
+        InstructionWord {
+            opcode: <StopInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands::default(),
+        },
+    ]);
+
+    program
+}
+
+/// The frame pointer the programs start with.
+const INITIAL_FP: u32 = 0x1000;
+
+/// `imm32` of `value` to the cell at `addr`, with the frame pointer at `INITIAL_FP`.
+fn imm32_at<Val: PrimeField32 + TwoAdicField>(addr: u32, value: u32) -> InstructionWord<i32> {
+    let [b0, b1, b2, b3] = value.to_be_bytes().map(|b| b as i32);
+    InstructionWord {
+        opcode: <Imm32Instruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+        operands: Operands([addr as i32 - INITIAL_FP as i32, b0, b1, b2, b3]),
+    }
+}
+
+/// The SHA-256 initial hash value
+const SHA256_IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+fn sha256_program<Val: PrimeField32 + TwoAdicField>() -> Vec<InstructionWord<i32>> {
+    let mut program = vec![];
+    // The state is at 0x100, and the block at 0x200 is the padded message "abc"
+    for (i, word) in SHA256_IV.into_iter().enumerate() {
+        program.push(imm32_at::<Val>(0x100 + 4 * i as u32, word));
+    }
+    program.push(imm32_at::<Val>(0x200, 0x61626380));
+    program.push(imm32_at::<Val>(0x200 + 4 * 15, 0x18));
+    // imm32 -4(fp), 0, 0, 1, 0
+    // imm32 -8(fp), 0, 0, 2, 0
+    // sha256compress -12(fp), -4(fp), -8(fp)
+    // stop
+    program.extend([
+        imm32_at::<Val>(INITIAL_FP - 4, 0x100),
+        imm32_at::<Val>(INITIAL_FP - 8, 0x200),
+        InstructionWord {
+            opcode: <Sha256CompressInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-12, -4, -8, 0, 0]),
+        },
+        InstructionWord {
+            opcode: <StopInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands::default(),
+        },
+    ]);
+
+    program
+}
+
+/// The permutation of the field elements 0..16, computed independently of the chip: the
+/// round constants were regenerated with the Grain LFSR, and the state multiplied by the
+/// dense matrices `circ(2 M4, M4, M4, M4)` and `1 + diag(V)` of the Poseidon2 paper.
+const POSEIDON2_EXPECTED: [u32; 16] = [
+    0x742261cf, 0x4abbe306, 0x7199c010, 0x1d9aa74c, 0x4bd9dcad, 0x714d0f63, 0x15eefddc, 0x0650774b,
+    0x0e9c54e7, 0x552f50c4, 0x406e196a, 0x20c900c6, 0x326d04b0, 0x396f5afc, 0x5784bebe, 0x4318c700,
+];
+
+fn poseidon2_program<Val: PrimeField32 + TwoAdicField>() -> Vec<InstructionWord<i32>> {
+    let mut program = vec![];
+    // The input at 0x100 is the field elements 0..16
+    for i in 0..16 {
+        program.push(imm32_at::<Val>(0x100 + 4 * i, i));
+    }
+    // imm32 -4(fp), 0, 0, 1, 0
+    // imm32 -8(fp), 0, 0, 2, 0
+    // poseidon2 -12(fp), -4(fp), -8(fp)
+    // stop
+    program.extend([
+        imm32_at::<Val>(INITIAL_FP - 4, 0x100),
+        imm32_at::<Val>(INITIAL_FP - 8, 0x200),
+        InstructionWord {
+            opcode: <Poseidon2Instruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-12, -4, -8, 0, 0]),
+        },
+        InstructionWord {
+            opcode: <StopInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands::default(),
+        },
+    ]);
+
+    program
+}
+
+/// A point of secp256k1 or ed25519, as the memory cells of its coordinates.
+type Point = [[u32; 8]; 2];
+
+// The generator of secp256k1 and its triple
+const SECP256K1_G: Point = [
+    [
+        0x16f81798, 0x59f2815b, 0x2dce28d9, 0x029bfcdb, 0xce870b07, 0x55a06295, 0xf9dcbbac,
+        0x79be667e,
+    ],
+    [
+        0xfb10d4b8, 0x9c47d08f, 0xa6855419, 0xfd17b448, 0x0e1108a8, 0x5da4fbfc, 0x26a3c465,
+        0x483ada77,
+    ],
+];
+const SECP256K1_3G: Point = [
+    [
+        0xbce036f9, 0x8601f113, 0x836f99b0, 0xb531c845, 0xf89d5229, 0x49344f85, 0x9258c310,
+        0xf9308a01,
+    ],
+    [
+        0x84b8e672, 0x6cb9fd75, 0x34c2231b, 0x6500a999, 0x2a37f356, 0x0fe337e6, 0x632de814,
+        0x388f7b0f,
+    ],
+];
+
+// The x coordinate of 2G, and the prime of the field of secp256k1
+const SECP256K1_2G_X: [u32; 8] = [
+    0x5c709ee5, 0xabac09b9, 0x8cef3ca7, 0x5c778e4b, 0x95c07cd8, 0x3045406e, 0x41ed7d6d, 0xc6047f94,
+];
+const SECP256K1_P: [u32; 8] = [
+    0xfffffc2f, 0xfffffffe, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff, 0xffffffff,
+];
+
+// The base point of ed25519 and its triple
+const ED25519_B: Point = [
+    [
+        0x8f25d51a, 0xc9562d60, 0x9525a7b2, 0x692cc760, 0xfdd6dc5c, 0xc0a4e231, 0xcd6e53fe,
+        0x216936d3,
+    ],
+    [
+        0x66666658, 0x66666666, 0x66666666, 0x66666666, 0x66666666, 0x66666666, 0x66666666,
+        0x66666666,
+    ],
+];
+const ED25519_3B: Point = [
+    [
+        0xd3f8e25c, 0xac62485f, 0x81624886, 0x63439819, 0x3edac83a, 0x1ff4ae74, 0x22928f49,
+        0x67ae9c4a,
+    ],
+    [
+        0x78f5b4d4, 0x02c36848, 0x67240304, 0x9f16ec17, 0x60269ef7, 0xa126a18e, 0x77ee69ab,
+        0x1267b1d1,
+    ],
+];
+
+/// A program that triples `point` by doubling it and then adding it.
+fn curve_program<Val: PrimeField32 + TwoAdicField>(
+    add_opcode: u32,
+    double_opcode: u32,
+    point: Point,
+) -> Vec<InstructionWord<i32>> {
+    let mut program = vec![];
+    // The point is at 0x200, and its double is written to 0x100
+    for (j, coord) in point.iter().enumerate() {
+        for (i, &cell) in coord.iter().enumerate() {
+            program.push(imm32_at::<Val>(0x200 + 32 * j as u32 + 4 * i as u32, cell));
+        }
+    }
+    // imm32 -4(fp), 0, 0, 1, 0
+    // imm32 -8(fp), 0, 0, 2, 0
+    // double -12(fp), -4(fp), -8(fp)
+    // add -12(fp), -4(fp), -8(fp)
+    // stop
+    program.extend([
+        imm32_at::<Val>(INITIAL_FP - 4, 0x100),
+        imm32_at::<Val>(INITIAL_FP - 8, 0x200),
+        InstructionWord {
+            opcode: double_opcode,
+            operands: Operands([-12, -4, -8, 0, 0]),
+        },
+        InstructionWord {
+            opcode: add_opcode,
+            operands: Operands([-12, -4, -8, 0, 0]),
+        },
+        InstructionWord {
+            opcode: <StopInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands::default(),
+        },
+    ]);
+
+    program
+}
+
+fn assert_point(machine: &BasicMachine<BabyBear>, addr: u32, point: Point) {
+    for (j, coord) in point.iter().enumerate() {
+        for (i, &cell) in coord.iter().enumerate() {
+            assert_eq!(
+                *machine
+                    .mem()
+                    .cells
+                    .get(&(addr + 32 * j as u32 + 4 * i as u32))
+                    .unwrap(),
+                Word::from(cell)
+            );
+        }
+    }
+}
+
+/// A program that adds, multiplies, and multiplies modulo the secp256k1 prime the x
+/// coordinates of 3G and 2G.
+fn u256_program<Val: PrimeField32 + TwoAdicField>() -> Vec<InstructionWord<i32>> {
+    let mut program = vec![];
+    // The first operand of each instruction is at 0x100, 0x140 and 0x180, and the second
+    // operand, followed by the modulus, is at 0x200
+    for addr in [0x100, 0x140, 0x180] {
+        for (i, &cell) in SECP256K1_3G[0].iter().enumerate() {
+            program.push(imm32_at::<Val>(addr + 4 * i as u32, cell));
+        }
+    }
+    for (j, value) in [SECP256K1_2G_X, SECP256K1_P].iter().enumerate() {
+        for (i, &cell) in value.iter().enumerate() {
+            program.push(imm32_at::<Val>(0x200 + 32 * j as u32 + 4 * i as u32, cell));
+        }
+    }
+    // imm32 -4(fp), 0, 0, 1, 0
+    // imm32 -8(fp), 0, 0, 2, 0
+    // imm32 -16(fp), 0, 0, 1, 64
+    // imm32 -20(fp), 0, 0, 1, 128
+    // u256add -12(fp), -4(fp), -8(fp)
+    // u256mul -24(fp), -16(fp), -8(fp)
+    // u256modmul -28(fp), -20(fp), -8(fp)
+    // stop
+    program.extend([
+        imm32_at::<Val>(INITIAL_FP - 4, 0x100),
+        imm32_at::<Val>(INITIAL_FP - 8, 0x200),
+        imm32_at::<Val>(INITIAL_FP - 16, 0x140),
+        imm32_at::<Val>(INITIAL_FP - 20, 0x180),
+        InstructionWord {
+            opcode: <U256AddInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-12, -4, -8, 0, 0]),
+        },
+        InstructionWord {
+            opcode: <U256MulInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-24, -16, -8, 0, 0]),
+        },
+        InstructionWord {
+            opcode: <U256ModMulInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-28, -20, -8, 0, 0]),
+        },
+        InstructionWord {
+            opcode: <StopInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands::default(),
+        },
+    ]);
+
+    program
+}
+
+/// A program that multiplies `2^256 - 1` by itself modulo the secp256k1 prime, and the x
+/// coordinate of 3G by itself modulo zero.
+fn u256_modmul_program<Val: PrimeField32 + TwoAdicField>() -> Vec<InstructionWord<i32>> {
+    let mut program = vec![];
+    // The first operands are at 0x100 and 0x140, and the second operands, each followed by
+    // its modulus, are at 0x200 and 0x240
+    let values = [
+        (0x100, [u32::MAX; 8]),
+        (0x200, [u32::MAX; 8]),
+        (0x220, SECP256K1_P),
+        (0x140, SECP256K1_3G[0]),
+        (0x240, SECP256K1_3G[0]),
+        (0x260, [0; 8]),
+    ];
+    for (addr, value) in values {
+        for (i, &cell) in value.iter().enumerate() {
+            program.push(imm32_at::<Val>(addr + 4 * i as u32, cell));
+        }
+    }
+    // imm32 -4(fp), 0, 0, 1, 0
+    // imm32 -8(fp), 0, 0, 2, 0
+    // imm32 -16(fp), 0, 0, 1, 64
+    // imm32 -20(fp), 0, 0, 2, 64
+    // u256modmul -12(fp), -4(fp), -8(fp)
+    // u256modmul -24(fp), -16(fp), -20(fp)
+    // stop
+    program.extend([
+        imm32_at::<Val>(INITIAL_FP - 4, 0x100),
+        imm32_at::<Val>(INITIAL_FP - 8, 0x200),
+        imm32_at::<Val>(INITIAL_FP - 16, 0x140),
+        imm32_at::<Val>(INITIAL_FP - 20, 0x240),
+        InstructionWord {
+            opcode: <U256ModMulInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-12, -4, -8, 0, 0]),
+        },
+        InstructionWord {
+            opcode: <U256ModMulInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands([-24, -16, -20, 0, 0]),
+        },
+        InstructionWord {
+            opcode: <StopInstruction as Instruction<BasicMachine<Val>, Val>>::OPCODE,
+            operands: Operands::default(),
+        },
+    ]);
+
+    program
+}
+
+type Challenge = BinomialExtensionField<BabyBear, 5>;
+type PackedChallenge = BinomialExtensionField<<BabyBear as Field>::Packing, 5>;
+type Mds16 = CosetMds<BabyBear, 16>;
+type Perm16 = Poseidon<BabyBear, Mds16, 16, 5>;
+type MyHash = SerializingHasher32<Keccak256Hash>;
+type MyCompress = CompressionFunctionFromHasher<BabyBear, MyHash, 2, 8>;
+type ValMmcs = FieldMerkleTreeMmcs<BabyBear, MyHash, MyCompress, 8>;
+type ChallengeMmcs = ExtensionMmcs<BabyBear, Challenge, ValMmcs>;
+type Dft = Radix2Bowers;
+type Challenger = DuplexChallenger<BabyBear, Perm16, 16>;
+type MyFriConfig =
+    TwoAdicFriPcsConfig<BabyBear, Challenge, Challenger, Dft, ValMmcs, ChallengeMmcs>;
+type Pcs = TwoAdicFriPcs<MyFriConfig>;
+type MyConfig = StarkConfigImpl<BabyBear, Challenge, PackedChallenge, Pcs, Challenger>;
+
+fn run_program(program: Vec<InstructionWord<i32>>) -> BasicMachine<BabyBear> {
+    let mut machine = BasicMachine::<BabyBear>::default();
     let rom = ProgramROM::new(program);
     machine.program_mut().set_program_rom(&rom);
-    machine.cpu_mut().fp = 0x1000;
+    machine.cpu_mut().fp = INITIAL_FP;
     machine.cpu_mut().save_register_state(); // TODO: Initial register state should be saved
                                              // automatically by the machine, not manually here
     machine.run(&rom, &mut FixedAdviceProvider::empty());
+    machine
+}
 
-    type Val = BabyBear;
-
-    type Challenge = BinomialExtensionField<Val, 5>;
-    type PackedChallenge = BinomialExtensionField<<Val as Field>::Packing, 5>;
+fn prove_program(program: Vec<InstructionWord<i32>>) -> BasicMachine<BabyBear> {
+    let machine = run_program(program);
 
-    type Mds16 = CosetMds<Val, 16>;
     let mds16 = Mds16::default();
-
-    type Perm16 = Poseidon<Val, Mds16, 16, 5>;
     let perm16 = Perm16::new_from_rng(4, 22, mds16, &mut thread_rng()); // TODO: Use deterministic RNG
-
-    type MyHash = SerializingHasher32<Keccak256Hash>;
     let hash = MyHash::new(Keccak256Hash {});
-
-    type MyCompress = CompressionFunctionFromHasher<Val, MyHash, 2, 8>;
     let compress = MyCompress::new(hash);
-
-    type ValMmcs = FieldMerkleTreeMmcs<Val, MyHash, MyCompress, 8>;
     let val_mmcs = ValMmcs::new(hash, compress);
-
-    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
     let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
-
-    type Dft = Radix2Bowers;
     let dft = Dft::default();
-
-    type Challenger = DuplexChallenger<Val, Perm16, 16>;
-
-    type MyFriConfig = TwoAdicFriPcsConfig<Val, Challenge, Challenger, Dft, ValMmcs, ChallengeMmcs>;
     let fri_config = FriConfig {
         log_blowup: 1,
         num_queries: 40,
@@ -446,9 +815,6 @@ fn prove_program(program: Vec<InstructionWord<i32>>) -> BasicMachine<BabyBear> {
         mmcs: challenge_mmcs,
     };
 
-    type Pcs = TwoAdicFriPcs<MyFriConfig>;
-    type MyConfig = StarkConfigImpl<Val, Challenge, PackedChallenge, Pcs, Challenger>;
-
     let pcs = Pcs::new(fri_config, dft, val_mmcs);
 
     let challenger = Challenger::new(perm16);
@@ -623,3 +989,261 @@ fn prove_loadfp() {
         Word([0, 0, 16, 3]) // fp(3) = 0x1003 = (0, 0, 16, 0)
     );
 }
+
+#[test]
+fn prove_host_calls() {
+    let program = host_call_program::<BabyBear>();
+
+    let machine = prove_program(program);
+    // Execution ends at the exiting host call, before the STOP instruction.
+    assert_eq!(machine.cpu().clock, 6);
+    assert_eq!(machine.host_call().calls.len(), 2);
+    assert_eq!(machine.host_call().exit_code, Some(3));
+    assert_eq!(
+        *machine.mem().cells.get(&(0x1000 - 12)).unwrap(),
+        Word([0, 0, 0, 0]) // no input
+    );
+    assert_eq!(
+        *machine.mem().cells.get(&(0x1000 + 4)).unwrap(),
+        Word([0, 0, 0, 3]) // exit code
+    );
+}
+
+#[test]
+fn prove_keccak() {
+    let program = keccak_program::<BabyBear>();
+
+    let machine = prove_program(program);
+    assert_eq!(machine.keccak().operations.len(), 2);
+
+    // Keccak-f[1600] applied twice to the zero state
+    let mut expected = [0u64; 25];
+    keccak_f(&mut expected);
+    keccak_f(&mut expected);
+    assert_eq!(expected[0], 0x2D5C954DF96ECB3C);
+    for (i, lane) in expected.iter().enumerate() {
+        let addr = 0x100 + 8 * i as u32;
+        assert_eq!(
+            *machine.mem().cells.get(&addr).unwrap(),
+            Word::from(*lane as u32)
+        );
+        assert_eq!(
+            *machine.mem().cells.get(&(addr + 4)).unwrap(),
+            Word::from((*lane >> 32) as u32)
+        );
+    }
+    assert_eq!(
+        *machine.mem().cells.get(&(0x1000 - 8)).unwrap(),
+        Word([0, 0, 1, 0]) // the output address
+    );
+}
+
+#[test]
+#[should_panic(expected = "constraints must evaluate to zero")]
+fn keccak_padding_is_not_real() {
+    let machine = run_program(keccak_program::<BabyBear>());
+    let chip = machine.keccak();
+    let challenges = vec![Challenge::from_canonical_u32(7); 3];
+    let check = |main: &RowMajorMatrix<BabyBear>| {
+        let perm =
+            generate_permutation_trace::<_, MyConfig>(&machine, chip, main, challenges.clone());
+        check_constraints::<_, _, MyConfig>(&machine, chip, main, &perm, &challenges);
+    };
+    let mut main = Chip::<BasicMachine<BabyBear>, MyConfig>::generate_trace(chip, &machine);
+    check(&main);
+
+    // The two permutations fill 48 of the 64 rows, so the padding ends with a permutation
+    // cut short. Marking it real would let it read memory without writing its output.
+    assert_eq!(main.height(), 64);
+    for row in 2 * NUM_ROUNDS..main.height() {
+        main.values[row * main.width + KECCAK_COL_MAP.is_real] = BabyBear::one();
+    }
+    main.values[2 * NUM_ROUNDS * main.width + KECCAK_COL_MAP.is_read] = BabyBear::one();
+    check(&main);
+}
+
+#[test]
+fn prove_sha256() {
+    let program = sha256_program::<BabyBear>();
+
+    let machine = prove_program(program);
+    assert_eq!(machine.sha256().operations.len(), 1);
+
+    // SHA-256("abc"), from FIPS 180-2
+    let expected = [
+        0xba7816bf, 0x8f01cfea, 0x414140de, 0x5dae2223, 0xb00361a3, 0x96177a9c, 0xb410ff61,
+        0xf20015ad,
+    ];
+    let mut block = [0; 16];
+    block[0] = 0x61626380;
+    block[15] = 0x18;
+    let mut state = SHA256_IV;
+    compress(&mut state, &block);
+    assert_eq!(state, expected);
+    for (i, word) in expected.into_iter().enumerate() {
+        assert_eq!(
+            *machine.mem().cells.get(&(0x100 + 4 * i as u32)).unwrap(),
+            Word::from(word)
+        );
+    }
+    assert_eq!(
+        *machine.mem().cells.get(&(0x1000 - 12)).unwrap(),
+        Word([0, 0, 1, 0]) // the state address
+    );
+}
+
+#[test]
+#[should_panic(expected = "constraints must evaluate to zero")]
+fn sha256_truncated_compression_is_not_real() {
+    let machine = run_program(sha256_program::<BabyBear>());
+    let chip = machine.sha256();
+    let challenges = vec![Challenge::from_canonical_u32(7); 3];
+    let check = |main: &RowMajorMatrix<BabyBear>| {
+        let perm =
+            generate_permutation_trace::<_, MyConfig>(&machine, chip, main, challenges.clone());
+        check_constraints::<_, _, MyConfig>(&machine, chip, main, &perm, &challenges);
+    };
+    let mut main = Chip::<BasicMachine<BabyBear>, MyConfig>::generate_trace(chip, &machine);
+    check(&main);
+
+    // Cutting the compression short keeps the reads of its first row, but drops the write
+    // of its last round.
+    assert_eq!(main.height(), 64);
+    main.values.truncate(32 * main.width);
+    check(&main);
+}
+
+#[test]
+fn prove_poseidon2() {
+    let program = poseidon2_program::<BabyBear>();
+
+    let machine = prove_program(program);
+    assert_eq!(machine.poseidon2().operations.len(), 1);
+
+    for (i, &x) in POSEIDON2_EXPECTED.iter().enumerate() {
+        assert_eq!(
+            *machine.mem().cells.get(&(0x200 + 4 * i as u32)).unwrap(),
+            Word::from(x)
+        );
+        // The input is left as it was
+        assert_eq!(
+            *machine.mem().cells.get(&(0x100 + 4 * i as u32)).unwrap(),
+            Word::from(i as u32)
+        );
+    }
+    assert_eq!(
+        *machine.mem().cells.get(&(0x1000 - 12)).unwrap(),
+        Word([0, 0, 2, 0]) // the output address
+    );
+}
+
+#[test]
+fn prove_secp256k1() {
+    let program = curve_program::<BabyBear>(
+        <Secp256k1AddInstruction as Instruction<BasicMachine<BabyBear>, BabyBear>>::OPCODE,
+        <Secp256k1DoubleInstruction as Instruction<BasicMachine<BabyBear>, BabyBear>>::OPCODE,
+        SECP256K1_G,
+    );
+
+    let machine = prove_program(program);
+    assert_eq!(machine.secp256k1().operations.len(), 2);
+    assert_point(&machine, 0x100, SECP256K1_3G);
+    assert_point(&machine, 0x200, SECP256K1_G);
+    assert_eq!(
+        *machine.mem().cells.get(&(0x1000 - 12)).unwrap(),
+        Word([0, 0, 1, 0]) // the result address
+    );
+}
+
+#[test]
+fn secp256k1_unreduced_point() {
+    // Neither the doubling nor the addition is defined, so both write (0, 0)
+    let program = curve_program::<BabyBear>(
+        <Secp256k1AddInstruction as Instruction<BasicMachine<BabyBear>, BabyBear>>::OPCODE,
+        <Secp256k1DoubleInstruction as Instruction<BasicMachine<BabyBear>, BabyBear>>::OPCODE,
+        [SECP256K1_P, SECP256K1_P],
+    );
+
+    let machine = run_program(program);
+    assert!(machine.secp256k1().operations.is_empty());
+    assert_point(&machine, 0x100, [[0; 8]; 2]);
+    assert_point(&machine, 0x200, [SECP256K1_P, SECP256K1_P]);
+}
+
+#[test]
+fn prove_ed25519() {
+    let program = curve_program::<BabyBear>(
+        <Ed25519AddInstruction as Instruction<BasicMachine<BabyBear>, BabyBear>>::OPCODE,
+        <Ed25519DoubleInstruction as Instruction<BasicMachine<BabyBear>, BabyBear>>::OPCODE,
+        ED25519_B,
+    );
+
+    let machine = prove_program(program);
+    assert_eq!(machine.ed25519().operations.len(), 2);
+    assert_point(&machine, 0x100, ED25519_3B);
+    assert_point(&machine, 0x200, ED25519_B);
+    assert_eq!(
+        *machine.mem().cells.get(&(0x1000 - 12)).unwrap(),
+        Word([0, 0, 1, 0]) // the result address
+    );
+}
+
+#[test]
+fn prove_u256() {
+    let program = u256_program::<BabyBear>();
+
+    let machine = prove_program(program);
+    assert_eq!(machine.u256().operations.len(), 3);
+
+    let sum = [
+        0x1950d5de, 0x31adfacd, 0x105ed658, 0x11a95691, 0x8e5dcf02, 0x79798ff4, 0xd446407d,
+        0xbf350995,
+    ];
+    let product = [
+        0xdf6edabd, 0x454c8604, 0x73fd8dcd, 0xf75d5474, 0xa4212c39, 0x212891a3, 0x9db2237d,
+        0x0ef7f63d,
+    ];
+    let modular_product = [
+        0xbe2eebd4, 0x8057023b, 0xd1479465, 0x5d134c75, 0x1fd6dfd3, 0x0b3f1e74, 0xbc580498,
+        0x6f546c0c,
+    ];
+    for (addr, expected) in [(0x100, sum), (0x140, product), (0x180, modular_product)] {
+        for (i, cell) in expected.into_iter().enumerate() {
+            assert_eq!(
+                *machine.mem().cells.get(&(addr + 4 * i as u32)).unwrap(),
+                Word::from(cell)
+            );
+        }
+    }
+    assert_eq!(
+        *machine.mem().cells.get(&(0x1000 - 12)).unwrap(),
+        Word([0, 0, 0, 1]) // the carry of the sum
+    );
+    assert_eq!(
+        *machine.mem().cells.get(&(0x1000 - 24)).unwrap(),
+        Word([0, 0, 1, 64]) // the address of the product
+    );
+    assert_eq!(
+        *machine.mem().cells.get(&(0x1000 - 28)).unwrap(),
+        Word([0, 0, 1, 128]) // the address of the modular product
+    );
+}
+
+#[test]
+fn prove_u256_modmul_unreduced() {
+    let program = u256_modmul_program::<BabyBear>();
+
+    let machine = prove_program(program);
+    assert_eq!(machine.u256().operations.len(), 2);
+
+    // 2^256 - 1 is 2^32 + 976 modulo the prime, whose square is below it
+    let square = [0x000e8900, 0x000007a0, 1, 0, 0, 0, 0, 0];
+    for (addr, expected) in [(0x100, square), (0x140, [0; 8])] {
+        for (i, cell) in expected.into_iter().enumerate() {
+            assert_eq!(
+                *machine.mem().cells.get(&(addr + 4 * i as u32)).unwrap(),
+                Word::from(cell)
+            );
+        }
+    }
+}
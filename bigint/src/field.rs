@@ -0,0 +1,207 @@
+use crate::uint::{div_rem_wide, U256};
+use crate::{Limbs, NUM_LIMBS};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::{Add, Mul, Sub};
+use p3_air::AirBuilder;
+use p3_field::{AbstractField, PrimeField32};
+
+/// The number of coefficients of the witness polynomial.
+pub const NUM_WITNESS_LIMBS: usize = 2 * NUM_LIMBS - 2;
+
+/// Added to the coefficients of the witness, which may be negative, before they are split
+/// into bytes. The coefficients are below 2^14 in absolute value.
+const WITNESS_OFFSET: i64 = 1 << 14;
+
+/// An operation of a field whose elements are 256-bit values.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FieldOperation {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// The columns of `a op b` modulo `m`, for 256-bit values split into bytes.
+///
+/// The operation is rearranged into an identity `lhs = rhs + quotient * m` between
+/// integers, for instance `result * b = a + quotient * m` for a division. Over the
+/// polynomials whose coefficients are the limbs, `lhs - rhs - quotient * m` must then be a
+/// multiple of `x - 2^8`, and the other factor is the witness. All the limbs are range
+/// checked, so none of the coefficients wrap around the native field.
+pub struct FieldOpCols<T> {
+    /// The result, which is reduced when the inputs are
+    pub result: Limbs<T>,
+    /// The multiple of the modulus taken away from the result
+    pub quotient: Limbs<T>,
+    /// The coefficients of the witness, offset and split into bytes
+    pub witness_low: [T; NUM_WITNESS_LIMBS],
+    pub witness_high: [T; NUM_WITNESS_LIMBS],
+}
+
+impl<F: PrimeField32> FieldOpCols<F> {
+    /// Fills in the columns for `a op b` modulo `m`, and returns the result. The inputs of
+    /// a subtraction or a division must be less than `m`, and for a division `m` must be
+    /// prime. Otherwise the quotient must fit in 256 bits, as it does when reducing any
+    /// value by adding zero to it.
+    pub fn populate(&mut self, a: U256, b: U256, m: U256, op: FieldOperation) -> U256 {
+        let result = match op {
+            FieldOperation::Add => {
+                let (low, high) = add_wide(a, b);
+                div_rem_wide(low, high, m).1
+            }
+            FieldOperation::Sub => a.sub_mod(b, m),
+            FieldOperation::Mul => a.mul_mod(b, m),
+            FieldOperation::Div => a.mul_mod(b.inv_mod(m), m),
+        };
+        let narrow = |x: U256| (x, U256::ZERO);
+        let (lhs, rhs) = match op {
+            FieldOperation::Add => (add_wide(a, b), narrow(result)),
+            FieldOperation::Sub => (add_wide(result, b), narrow(a)),
+            FieldOperation::Mul => (a.widening_mul(b), narrow(result)),
+            FieldOperation::Div => (result.widening_mul(b), narrow(a)),
+        };
+        let (low, high) = sub_wide(lhs, rhs);
+        let ((quotient, quotient_high), rem) = div_rem_wide(low, high, m);
+        debug_assert!(quotient_high.is_zero() && rem.is_zero());
+
+        let limbs = |x: U256| x.to_le_bytes().map(|byte| byte as i64);
+        let vanishing = vanishing_poly(
+            op,
+            &limbs(a),
+            &limbs(b),
+            &limbs(result),
+            &limbs(quotient),
+            &limbs(m),
+            0,
+        );
+        let mut carry = 0;
+        for (i, v) in vanishing.into_iter().take(NUM_WITNESS_LIMBS).enumerate() {
+            // v_i = w_{i - 1} - 2^8 w_i
+            debug_assert_eq!((carry - v) % 256, 0);
+            let w = (carry - v) / 256;
+            let offset = (w + WITNESS_OFFSET) as u32;
+            debug_assert!(offset < 1 << 16);
+            self.witness_low[i] = F::from_canonical_u32(offset & 0xFF);
+            self.witness_high[i] = F::from_canonical_u32(offset >> 8);
+            carry = w;
+        }
+
+        self.result = result.to_le_bytes().map(F::from_canonical_u8);
+        self.quotient = quotient.to_le_bytes().map(F::from_canonical_u8);
+        result
+    }
+}
+
+impl<V: Copy> FieldOpCols<V> {
+    /// Constrains the result to be `a op b` modulo `m`, given as limbs.
+    pub fn eval<AB: AirBuilder<Var = V>>(
+        &self,
+        builder: &mut AB,
+        a: &Limbs<AB::Expr>,
+        b: &Limbs<AB::Expr>,
+        m: &Limbs<AB::Expr>,
+        op: FieldOperation,
+    ) {
+        let result: Limbs<AB::Expr> = self.result.map(Into::into);
+        let quotient: Limbs<AB::Expr> = self.quotient.map(Into::into);
+        let vanishing = vanishing_poly(op, a, b, &result, &quotient, m, AB::Expr::zero());
+
+        let base = AB::Expr::from_canonical_u32(1 << 8);
+        let offset = AB::Expr::from_canonical_u32(WITNESS_OFFSET as u32);
+        let witness: Vec<AB::Expr> = (0..NUM_WITNESS_LIMBS)
+            .map(|i| {
+                let high: AB::Expr = self.witness_high[i].into();
+                let low: AB::Expr = self.witness_low[i].into();
+                high * base.clone() + low - offset.clone()
+            })
+            .collect();
+
+        for (i, v) in vanishing.into_iter().enumerate() {
+            // The coefficients of (x - 2^8) w(x)
+            let mut expected = AB::Expr::zero();
+            if i > 0 {
+                expected += witness[i - 1].clone();
+            }
+            if i < NUM_WITNESS_LIMBS {
+                expected -= witness[i].clone() * base.clone();
+            }
+            builder.assert_eq(v, expected);
+        }
+    }
+}
+
+impl<T: Copy> FieldOpCols<T> {
+    /// The columns that hold bytes.
+    pub fn range_checked(&self) -> impl Iterator<Item = T> + '_ {
+        self.result
+            .iter()
+            .chain(&self.quotient)
+            .chain(&self.witness_low)
+            .chain(&self.witness_high)
+            .copied()
+    }
+}
+
+/// The coefficients of `lhs - rhs - quotient * m`.
+fn vanishing_poly<E>(
+    op: FieldOperation,
+    a: &[E],
+    b: &[E],
+    result: &[E],
+    quotient: &[E],
+    m: &[E],
+    zero: E,
+) -> Vec<E>
+where
+    E: Clone + Add<Output = E> + Sub<Output = E> + Mul<Output = E>,
+{
+    let (lhs, rhs) = match op {
+        FieldOperation::Add => (poly_add(a, b), result.to_vec()),
+        FieldOperation::Sub => (poly_add(result, b), a.to_vec()),
+        FieldOperation::Mul => (poly_mul(a, b, zero.clone()), result.to_vec()),
+        FieldOperation::Div => (poly_mul(result, b, zero.clone()), a.to_vec()),
+    };
+    let mut vanishing = poly_mul(quotient, m, zero.clone())
+        .into_iter()
+        .map(|x| zero.clone() - x)
+        .collect::<Vec<_>>();
+    for (i, x) in lhs.into_iter().enumerate() {
+        vanishing[i] = vanishing[i].clone() + x;
+    }
+    for (i, x) in rhs.into_iter().enumerate() {
+        vanishing[i] = vanishing[i].clone() - x;
+    }
+    vanishing
+}
+
+fn poly_add<E: Clone + Add<Output = E>>(a: &[E], b: &[E]) -> Vec<E> {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| x.clone() + y.clone())
+        .collect()
+}
+
+fn poly_mul<E>(a: &[E], b: &[E], zero: E) -> Vec<E>
+where
+    E: Clone + Add<Output = E> + Mul<Output = E>,
+{
+    let mut product = vec![zero; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            product[i + j] = product[i + j].clone() + x.clone() * y.clone();
+        }
+    }
+    product
+}
+
+fn add_wide(a: U256, b: U256) -> (U256, U256) {
+    let (sum, carry) = a.overflowing_add(b);
+    (sum, U256::from_u64(carry as u64))
+}
+
+fn sub_wide((a_low, a_high): (U256, U256), (b_low, b_high): (U256, U256)) -> (U256, U256) {
+    let (low, borrow) = a_low.overflowing_sub(b_low);
+    let high = a_high.overflowing_sub(b_high).0;
+    (low, high.overflowing_sub(U256::from_u64(borrow as u64)).0)
+}
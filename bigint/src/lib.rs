@@ -0,0 +1,55 @@
+#![no_std]
+
+//! Arithmetic on 256-bit values for precompile chips. Values are split into byte limbs,
+//! least significant first, so that every limb can be range checked by the
+//! `RangeCheckerChip`. In memory, a value takes up 8 cells, least significant cell first.
+
+extern crate alloc;
+
+use p3_field::{AbstractField, PrimeField32};
+use valida_machine::Word;
+use valida_range::MachineWithRangeChip;
+
+pub mod field;
+pub mod lt;
+pub mod uint;
+
+pub use field::{FieldOpCols, FieldOperation};
+pub use lt::LtCols;
+pub use uint::U256;
+
+/// The number of byte limbs of a 256-bit value.
+pub const NUM_LIMBS: usize = 32;
+/// The number of memory cells taken up by a 256-bit value.
+pub const NUM_WORDS: usize = NUM_LIMBS / 4;
+
+/// A 256-bit value as bytes, least significant first.
+pub type Limbs<T> = [T; NUM_LIMBS];
+
+/// The limbs of memory cell `i`, in the most significant first order of `Word`.
+pub fn word<T: Copy>(limbs: &Limbs<T>, i: usize) -> Word<T> {
+    Word([
+        limbs[4 * i + 3],
+        limbs[4 * i + 2],
+        limbs[4 * i + 1],
+        limbs[4 * i],
+    ])
+}
+
+/// The limbs of a constant.
+pub fn constant_limbs<E: AbstractField>(value: U256) -> Limbs<E> {
+    value.to_le_bytes().map(E::from_canonical_u8)
+}
+
+/// Records values with the range checker, like `MachineWithRangeChip::range_check` does for
+/// the bytes of a word.
+pub fn range_check_values<M, F>(state: &mut M, values: impl IntoIterator<Item = F>)
+where
+    M: MachineWithRangeChip<F, 256>,
+    F: PrimeField32,
+{
+    let count = &mut state.range_mut().count;
+    for value in values {
+        *count.entry(value.as_canonical_u32()).or_insert(0) += 1;
+    }
+}
@@ -0,0 +1,57 @@
+use crate::uint::U256;
+use crate::{Limbs, NUM_LIMBS};
+use p3_air::AirBuilder;
+use p3_field::{AbstractField, PrimeField32};
+
+/// The columns showing that a value is less than a bound, such as the result of a field
+/// operation and its modulus. Above the flagged limb the two are equal, and at the
+/// flagged limb the value is smaller.
+pub struct LtCols<T> {
+    /// Marks the most significant limb where the value and the bound differ
+    pub flags: Limbs<T>,
+    /// The bound minus the value minus one at the flagged limb, which is range checked
+    pub diff: T,
+}
+
+impl<F: PrimeField32> LtCols<F> {
+    /// Fills in the columns for a value less than the bound.
+    pub fn populate(&mut self, value: U256, bound: U256) {
+        debug_assert!(value < bound);
+        let value = value.to_le_bytes();
+        let bound = bound.to_le_bytes();
+        let i = (0..NUM_LIMBS)
+            .rev()
+            .find(|&i| value[i] != bound[i])
+            .unwrap();
+        self.flags = core::array::from_fn(|j| F::from_bool(j == i));
+        self.diff = F::from_canonical_u8(bound[i] - value[i] - 1);
+    }
+}
+
+impl<V: Copy> LtCols<V> {
+    /// Constrains `value < bound`, given as limbs.
+    pub fn eval<AB: AirBuilder<Var = V>>(
+        &self,
+        builder: &mut AB,
+        value: &Limbs<AB::Expr>,
+        bound: &Limbs<AB::Expr>,
+    ) {
+        let flags: Limbs<AB::Expr> = self.flags.map(Into::into);
+        for flag in &flags {
+            builder.assert_bool(flag.clone());
+        }
+        builder.assert_one(flags.iter().cloned().sum::<AB::Expr>());
+
+        // Every limb above the flagged one is equal.
+        let mut below = AB::Expr::zero();
+        for i in 0..NUM_LIMBS {
+            builder.assert_zero(below.clone() * (value[i].clone() - bound[i].clone()));
+            below += flags[i].clone();
+        }
+
+        let diff: AB::Expr = (0..NUM_LIMBS)
+            .map(|i| flags[i].clone() * (bound[i].clone() - value[i].clone() - AB::Expr::one()))
+            .sum();
+        builder.assert_eq(self.diff, diff);
+    }
+}
@@ -0,0 +1,180 @@
+use core::cmp::Ordering;
+use valida_machine::Word;
+
+/// A 256-bit unsigned integer, as 64-bit words with the least significant first.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct U256(pub [u64; 4]);
+
+impl U256 {
+    pub const ZERO: Self = Self([0; 4]);
+    pub const ONE: Self = Self([1, 0, 0, 0]);
+
+    pub const fn from_u64(value: u64) -> Self {
+        Self([value, 0, 0, 0])
+    }
+
+    pub fn from_le_bytes(bytes: [u8; 32]) -> Self {
+        Self(core::array::from_fn(|i| {
+            u64::from_le_bytes(bytes[8 * i..8 * i + 8].try_into().unwrap())
+        }))
+    }
+
+    pub fn to_le_bytes(self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        for (chunk, word) in bytes.chunks_exact_mut(8).zip(self.0) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// The integer held by 8 memory cells, least significant cell first.
+    pub fn from_words(words: [Word<u8>; 8]) -> Self {
+        let mut bytes = [0; 32];
+        for (chunk, word) in bytes.chunks_exact_mut(4).zip(words) {
+            let word: u32 = word.into();
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        Self::from_le_bytes(bytes)
+    }
+
+    /// The memory cells holding the integer, least significant cell first.
+    pub fn to_words(self) -> [Word<u8>; 8] {
+        let bytes = self.to_le_bytes();
+        core::array::from_fn(|i| {
+            Word::from(u32::from_le_bytes(
+                bytes[4 * i..4 * i + 4].try_into().unwrap(),
+            ))
+        })
+    }
+
+    pub fn is_zero(self) -> bool {
+        self == Self::ZERO
+    }
+
+    fn bit(self, i: usize) -> bool {
+        (self.0[i / 64] >> (i % 64)) & 1 == 1
+    }
+
+    pub fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+        let mut sum = [0; 4];
+        let mut carry = false;
+        for (i, limb) in sum.iter_mut().enumerate() {
+            let (s, c1) = self.0[i].overflowing_add(rhs.0[i]);
+            let (s, c2) = s.overflowing_add(carry as u64);
+            *limb = s;
+            carry = c1 || c2;
+        }
+        (Self(sum), carry)
+    }
+
+    pub fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+        let mut diff = [0; 4];
+        let mut borrow = false;
+        for (i, limb) in diff.iter_mut().enumerate() {
+            let (d, b1) = self.0[i].overflowing_sub(rhs.0[i]);
+            let (d, b2) = d.overflowing_sub(borrow as u64);
+            *limb = d;
+            borrow = b1 || b2;
+        }
+        (Self(diff), borrow)
+    }
+
+    /// The full product, as its low and high halves.
+    pub fn widening_mul(self, rhs: Self) -> (Self, Self) {
+        let mut product = [0u64; 8];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let t = self.0[i] as u128 * rhs.0[j] as u128 + product[i + j] as u128 + carry;
+                product[i + j] = t as u64;
+                carry = t >> 64;
+            }
+            product[i + 4] = carry as u64;
+        }
+        (
+            Self(product[..4].try_into().unwrap()),
+            Self(product[4..].try_into().unwrap()),
+        )
+    }
+
+    /// `(self + rhs) mod m`, for `self` and `rhs` less than `m`.
+    pub fn add_mod(self, rhs: Self, m: Self) -> Self {
+        let (sum, carry) = self.overflowing_add(rhs);
+        if carry || sum >= m {
+            sum.overflowing_sub(m).0
+        } else {
+            sum
+        }
+    }
+
+    /// `(self - rhs) mod m`, for `self` and `rhs` less than `m`.
+    pub fn sub_mod(self, rhs: Self, m: Self) -> Self {
+        let (diff, borrow) = self.overflowing_sub(rhs);
+        if borrow {
+            diff.overflowing_add(m).0
+        } else {
+            diff
+        }
+    }
+
+    /// `(self * rhs) mod m`, for a nonzero `m`.
+    pub fn mul_mod(self, rhs: Self, m: Self) -> Self {
+        let (low, high) = self.widening_mul(rhs);
+        div_rem_wide(low, high, m).1
+    }
+
+    /// `self^exp mod m`, for a nonzero `m`.
+    pub fn pow_mod(self, exp: Self, m: Self) -> Self {
+        let mut result = div_rem_wide(Self::ONE, Self::ZERO, m).1;
+        for i in (0..256).rev() {
+            result = result.mul_mod(result, m);
+            if exp.bit(i) {
+                result = result.mul_mod(self, m);
+            }
+        }
+        result
+    }
+
+    /// The inverse of `self` modulo a prime `p`, which is zero for zero.
+    pub fn inv_mod(self, p: Self) -> Self {
+        self.pow_mod(p.overflowing_sub(Self::from_u64(2)).0, p)
+    }
+}
+
+impl Ord for U256 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Divides `high * 2^256 + low` by a nonzero `m`, and returns the low and high halves of
+/// the quotient along with the remainder.
+pub fn div_rem_wide(low: U256, high: U256, m: U256) -> ((U256, U256), U256) {
+    assert!(!m.is_zero(), "division by zero");
+    let mut quotient = [U256::ZERO; 2];
+    let mut rem = U256::ZERO;
+    for i in (0..512).rev() {
+        let bit = if i >= 256 {
+            high.bit(i - 256)
+        } else {
+            low.bit(i)
+        };
+        // Shift the next bit of the dividend into the remainder, which can overflow when
+        // the modulus takes up all 256 bits.
+        let overflow = rem.bit(255);
+        let (doubled, _) = rem.overflowing_add(rem);
+        rem = doubled;
+        rem.0[0] |= bit as u64;
+        if overflow || rem >= m {
+            rem = rem.overflowing_sub(m).0;
+            quotient[i / 256].0[(i % 256) / 64] |= 1 << (i % 64);
+        }
+    }
+    ((quotient[0], quotient[1]), rem)
+}
@@ -61,10 +61,10 @@ pub struct CpuChip {
     pub instructions: Vec<InstructionWord<i32>>,
 }
 
-#[derive(Default)]
+#[derive(Copy, Clone, Default, Debug)]
 pub struct Registers {
-    pc: u32,
-    fp: u32,
+    pub pc: u32,
+    pub fp: u32,
 }
 
 impl<M, SC> Chip<M, SC> for CpuChip
@@ -91,7 +91,7 @@ where
         let mut trace =
             RowMajorMatrix::new(rows.into_iter().flatten().collect::<Vec<_>>(), NUM_CPU_COLS);
 
-        Self::pad_to_power_of_two(&mut trace.values);
+        self.pad_to_power_of_two(&mut trace.values);
 
         trace
     }
@@ -314,17 +314,29 @@ impl CpuChip {
         }
     }
 
-    fn pad_to_power_of_two<F: PrimeField>(values: &mut Vec<F>) {
+    /// The height of the CPU trace. Execution can end without a STOP instruction (e.g. when
+    /// a host call exits), in which case at least one STOP row is added as padding.
+    pub fn trace_height(&self) -> usize {
+        match self.operations.last() {
+            Some(Operation::Stop) => self.operations.len().next_power_of_two(),
+            _ => (self.operations.len() + 1).next_power_of_two(),
+        }
+    }
+
+    fn pad_to_power_of_two<F: PrimeField>(&self, values: &mut Vec<F>) {
         let len = values.len();
         let n_real_rows = values.len() / NUM_CPU_COLS;
 
         debug_assert!(len > 0);
         let last_row = &values[len - NUM_CPU_COLS..];
-        let pc = last_row[CPU_COL_MAP.pc];
-        let fp = last_row[CPU_COL_MAP.fp];
         let clk = last_row[CPU_COL_MAP.clk];
 
-        values.resize(n_real_rows.next_power_of_two() * NUM_CPU_COLS, F::zero());
+        // Padding rows continue from the register state after the last instruction.
+        let registers = self.registers.last().unwrap();
+        let pc = F::from_canonical_u32(registers.pc);
+        let fp = F::from_canonical_u32(registers.fp);
+
+        values.resize(self.trace_height() * NUM_CPU_COLS, F::zero());
 
         // Interpret values as a slice of arrays of length `NUM_CPU_COLS`
         let rows = unsafe {
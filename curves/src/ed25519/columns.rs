@@ -0,0 +1,72 @@
+use crate::point::PointOpCols;
+use alloc::vec::Vec;
+use core::borrow::{Borrow, BorrowMut};
+use core::mem::{size_of, transmute};
+use valida_bigint::{FieldOpCols, LtCols};
+use valida_derive::AlignedBorrow;
+use valida_util::indices_arr;
+
+#[derive(AlignedBorrow)]
+pub struct Ed25519Cols<T> {
+    pub point: PointOpCols<T>,
+
+    /// The numerator of `x`, `p_x q_y + p_y q_x`
+    pub x1_y2: FieldOpCols<T>,
+    pub y1_x2: FieldOpCols<T>,
+    pub x_num: FieldOpCols<T>,
+    /// The numerator of `y`, `p_y q_y + p_x q_x`
+    pub y1_y2: FieldOpCols<T>,
+    pub x1_x2: FieldOpCols<T>,
+    pub y_num: FieldOpCols<T>,
+
+    /// The denominators, `1 + d p_x q_x p_y q_y` and `1 - d p_x q_x p_y q_y`
+    pub xy: FieldOpCols<T>,
+    pub d_xy: FieldOpCols<T>,
+    pub x_den: FieldOpCols<T>,
+    pub y_den: FieldOpCols<T>,
+
+    /// The coordinates of the result. The inverses of the denominators show that they
+    /// are nonzero.
+    pub x_den_inv: FieldOpCols<T>,
+    pub x: FieldOpCols<T>,
+    pub y_den_inv: FieldOpCols<T>,
+    pub y: FieldOpCols<T>,
+
+    /// The coordinates of the result are reduced
+    pub x_lt: LtCols<T>,
+    pub y_lt: LtCols<T>,
+}
+
+impl<T: Copy> Ed25519Cols<T> {
+    /// The columns that hold bytes.
+    pub fn range_checked(&self) -> Vec<T> {
+        let ops = [
+            &self.x1_y2,
+            &self.y1_x2,
+            &self.x_num,
+            &self.y1_y2,
+            &self.x1_x2,
+            &self.y_num,
+            &self.xy,
+            &self.d_xy,
+            &self.x_den,
+            &self.y_den,
+            &self.x_den_inv,
+            &self.x,
+            &self.y_den_inv,
+            &self.y,
+        ];
+        ops.into_iter()
+            .flat_map(|op| op.range_checked())
+            .chain([self.x_lt.diff, self.y_lt.diff])
+            .collect()
+    }
+}
+
+pub const NUM_ED25519_COLS: usize = size_of::<Ed25519Cols<u8>>();
+pub const ED25519_COL_MAP: Ed25519Cols<usize> = make_col_map();
+
+const fn make_col_map() -> Ed25519Cols<usize> {
+    let indices_arr = indices_arr::<NUM_ED25519_COLS>();
+    unsafe { transmute::<[usize; NUM_ED25519_COLS], Ed25519Cols<usize>>(indices_arr) }
+}
@@ -0,0 +1,197 @@
+use crate::point::{read_operation, write_result, AffinePoint, PointOperation};
+use alloc::vec::Vec;
+use columns::{Ed25519Cols, ED25519_COL_MAP, NUM_ED25519_COLS};
+use core::mem::transmute;
+use valida_bigint::{range_check_values, FieldOperation, U256};
+use valida_bus::{MachineWithGeneralBus, MachineWithMemBus, MachineWithRangeBus8};
+use valida_cpu::MachineWithCpuChip;
+use valida_machine::{instructions, Chip, Instruction, Interaction, Operands};
+use valida_opcodes::{ED25519ADD, ED25519DOUBLE};
+use valida_range::MachineWithRangeChip;
+
+use p3_field::{Field, PrimeField32};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_maybe_rayon::prelude::*;
+use valida_machine::StarkConfig;
+
+pub mod columns;
+pub mod stark;
+
+/// The modulus of the base field, `2^255 - 19`.
+pub const P: U256 = U256([
+    0xFFFF_FFFF_FFFF_FFED,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0x7FFF_FFFF_FFFF_FFFF,
+]);
+
+/// The coefficient `d = -121665 / 121666` of the curve.
+pub const D: U256 = U256([
+    0x75EB_4DCA_1359_78A3,
+    0x0070_0A4D_4141_D8AB,
+    0x8CC7_4079_7779_E898,
+    0x5203_6CEE_2B6F_FE73,
+]);
+
+/// Adds and doubles points of the twisted Edwards curve `-x^2 + y^2 = 1 + d x^2 y^2`, one
+/// operation per row. The addition law is complete, so a doubling is an addition of a
+/// point to itself.
+#[derive(Default)]
+pub struct Ed25519Chip {
+    pub operations: Vec<PointOperation>,
+}
+
+impl<M, SC> Chip<M, SC> for Ed25519Chip
+where
+    M: MachineWithGeneralBus<SC::Val> + MachineWithMemBus<SC::Val> + MachineWithRangeBus8<SC::Val>,
+    SC: StarkConfig,
+{
+    fn generate_trace(&self, _machine: &M) -> RowMajorMatrix<SC::Val> {
+        // The trace is padded with rows of no operation, whose points are zero.
+        let num_rows = self.operations.len().next_power_of_two();
+        let rows = (0..num_rows)
+            .into_par_iter()
+            .map(|i| self.op_to_row(self.operations.get(i)))
+            .collect::<Vec<_>>();
+        RowMajorMatrix::new(rows.into_iter().flatten().collect(), NUM_ED25519_COLS)
+    }
+
+    fn global_sends(&self, machine: &M) -> Vec<Interaction<SC::Val>> {
+        let result = [&ED25519_COL_MAP.x.result, &ED25519_COL_MAP.y.result];
+        ED25519_COL_MAP.point.global_sends::<M, SC>(
+            machine,
+            result,
+            ED25519_COL_MAP.range_checked(),
+        )
+    }
+
+    fn global_receives(&self, machine: &M) -> Vec<Interaction<SC::Val>> {
+        ED25519_COL_MAP
+            .point
+            .global_receives::<M, SC>(machine, ED25519ADD, ED25519DOUBLE)
+    }
+}
+
+impl Ed25519Chip {
+    fn op_to_row<F: PrimeField32>(&self, op: Option<&PointOperation>) -> [F; NUM_ED25519_COLS] {
+        let mut row = [F::zero(); NUM_ED25519_COLS];
+        let cols: &mut Ed25519Cols<F> = unsafe { transmute(&mut row) };
+        if let Some(op) = op {
+            cols.point.populate(op);
+        }
+        populate_field_ops(cols, op);
+        row
+    }
+}
+
+/// Fills in the field operations of a row, and returns the result.
+fn populate_field_ops<F: PrimeField32>(
+    cols: &mut Ed25519Cols<F>,
+    op: Option<&PointOperation>,
+) -> AffinePoint {
+    let (p, q) = op.map_or(Default::default(), |op| (op.p, op.q));
+
+    let x1_y2 = cols.x1_y2.populate(p.x, q.y, P, FieldOperation::Mul);
+    let y1_x2 = cols.y1_x2.populate(p.y, q.x, P, FieldOperation::Mul);
+    let x_num = cols.x_num.populate(x1_y2, y1_x2, P, FieldOperation::Add);
+    let y1_y2 = cols.y1_y2.populate(p.y, q.y, P, FieldOperation::Mul);
+    let x1_x2 = cols.x1_x2.populate(p.x, q.x, P, FieldOperation::Mul);
+    let y_num = cols.y_num.populate(y1_y2, x1_x2, P, FieldOperation::Add);
+
+    let xy = cols.xy.populate(x1_x2, y1_y2, P, FieldOperation::Mul);
+    let d_xy = cols.d_xy.populate(D, xy, P, FieldOperation::Mul);
+    let x_den = cols.x_den.populate(U256::ONE, d_xy, P, FieldOperation::Add);
+    let y_den = cols.y_den.populate(U256::ONE, d_xy, P, FieldOperation::Sub);
+
+    let is_real = U256::from_u64(op.is_some() as u64);
+    let x_den_inv = cols
+        .x_den_inv
+        .populate(is_real, x_den, P, FieldOperation::Div);
+    let x = cols.x.populate(x_num, x_den_inv, P, FieldOperation::Mul);
+    let y_den_inv = cols
+        .y_den_inv
+        .populate(is_real, y_den, P, FieldOperation::Div);
+    let y = cols.y.populate(y_num, y_den_inv, P, FieldOperation::Mul);
+
+    cols.x_lt.populate(x, P);
+    cols.y_lt.populate(y, P);
+    AffinePoint { x, y }
+}
+
+/// Whether the coordinates are reduced, and the denominators `1 ± d x1 x2 y1 y2` of the
+/// sum are nonzero, which they are for any points of the curve.
+fn is_defined(op: &PointOperation) -> bool {
+    let reduced = [op.p.x, op.p.y, op.q.x, op.q.y].iter().all(|c| *c < P);
+    let xy =
+        op.p.x
+            .mul_mod(op.q.x, P)
+            .mul_mod(op.p.y.mul_mod(op.q.y, P), P);
+    let d_xy = D.mul_mod(xy, P);
+    reduced && d_xy != U256::ONE && d_xy != U256::ZERO.sub_mod(U256::ONE, P)
+}
+
+pub trait MachineWithEd25519Chip<F: Field>: MachineWithCpuChip<F> {
+    fn ed25519(&self) -> &Ed25519Chip;
+    fn ed25519_mut(&mut self) -> &mut Ed25519Chip;
+}
+
+instructions!(Ed25519AddInstruction, Ed25519DoubleInstruction);
+
+impl<M, F> Instruction<M, F> for Ed25519AddInstruction
+where
+    M: MachineWithEd25519Chip<F> + MachineWithRangeChip<F, 256>,
+    F: PrimeField32,
+{
+    const OPCODE: u32 = ED25519ADD;
+
+    /// `ED25519ADD a(fp), b(fp), c(fp)` adds the point at the address in `c(fp)` to the
+    /// point at the address in `b(fp)`, which it overwrites, and writes the address in
+    /// `b(fp)` to `a(fp)`. A point takes up 16 cells, its `x` coordinate and then its `y`
+    /// coordinate, each 8 cells with the least significant first.
+    ///
+    /// The coordinates must be reduced. Points of the curve can always be added, but for
+    /// points off the curve a denominator of the sum can vanish. In either case `(0, 0)`,
+    /// which is not a point of the curve, is written in place of the sum, and the execution
+    /// cannot be proven.
+    fn execute(state: &mut M, ops: Operands<i32>) {
+        execute(state, <Self as Instruction<M, F>>::OPCODE, ops, false);
+    }
+}
+
+impl<M, F> Instruction<M, F> for Ed25519DoubleInstruction
+where
+    M: MachineWithEd25519Chip<F> + MachineWithRangeChip<F, 256>,
+    F: PrimeField32,
+{
+    const OPCODE: u32 = ED25519DOUBLE;
+
+    /// `ED25519DOUBLE a(fp), b(fp), c(fp)` doubles the point at the address in `c(fp)`, and
+    /// writes the result to the address in `b(fp)`, which may be the same. The address in
+    /// `b(fp)` is written to `a(fp)`. Points are laid out as for `ED25519ADD`, and the
+    /// same inputs fail in the same way.
+    fn execute(state: &mut M, ops: Operands<i32>) {
+        execute(state, <Self as Instruction<M, F>>::OPCODE, ops, true);
+    }
+}
+
+fn execute<M, F>(state: &mut M, opcode: u32, ops: Operands<i32>, is_double: bool)
+where
+    M: MachineWithEd25519Chip<F> + MachineWithRangeChip<F, 256>,
+    F: PrimeField32,
+{
+    let op = read_operation(state, opcode, ops, is_double);
+    if !is_defined(&op) {
+        // No row of the chip can hold the operation, so the instruction goes unmatched on
+        // the bus and the execution cannot be proven.
+        write_result(state, opcode, ops, &op, AffinePoint::default());
+        return;
+    }
+
+    let mut row = [F::zero(); NUM_ED25519_COLS];
+    let cols: &mut Ed25519Cols<F> = unsafe { transmute(&mut row) };
+    let result = populate_field_ops(cols, Some(&op));
+    range_check_values(state, cols.range_checked());
+
+    write_result(state, opcode, ops, &op, result);
+    state.ed25519_mut().operations.push(op);
+}
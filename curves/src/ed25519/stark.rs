@@ -0,0 +1,117 @@
+use super::columns::{Ed25519Cols, NUM_ED25519_COLS};
+use super::{Ed25519Chip, D, P};
+use core::borrow::Borrow;
+use valida_bigint::{constant_limbs, FieldOperation, Limbs, U256};
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, PrimeField};
+use p3_matrix::MatrixRowSlices;
+
+impl<F> BaseAir<F> for Ed25519Chip {
+    fn width(&self) -> usize {
+        NUM_ED25519_COLS
+    }
+}
+
+impl<F, AB> Air<AB> for Ed25519Chip
+where
+    F: PrimeField,
+    AB: AirBuilder<F = F>,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local: &Ed25519Cols<AB::Var> = main.row_slice(0).borrow();
+        let point = &local.point;
+        point.eval(builder);
+
+        let limbs = |x: &Limbs<AB::Var>| -> Limbs<AB::Expr> { x.map(Into::into) };
+        let p = constant_limbs::<AB::Expr>(P);
+        let one = constant_limbs::<AB::Expr>(U256::ONE);
+        let (p_x, p_y) = (limbs(&point.p_x), limbs(&point.p_y));
+        let (q_x, q_y) = (limbs(&point.q_x), limbs(&point.q_y));
+
+        local
+            .x1_y2
+            .eval(builder, &p_x, &q_y, &p, FieldOperation::Mul);
+        local
+            .y1_x2
+            .eval(builder, &p_y, &q_x, &p, FieldOperation::Mul);
+        local.x_num.eval(
+            builder,
+            &limbs(&local.x1_y2.result),
+            &limbs(&local.y1_x2.result),
+            &p,
+            FieldOperation::Add,
+        );
+        local
+            .y1_y2
+            .eval(builder, &p_y, &q_y, &p, FieldOperation::Mul);
+        local
+            .x1_x2
+            .eval(builder, &p_x, &q_x, &p, FieldOperation::Mul);
+        let (y1_y2, x1_x2) = (limbs(&local.y1_y2.result), limbs(&local.x1_x2.result));
+        local
+            .y_num
+            .eval(builder, &y1_y2, &x1_x2, &p, FieldOperation::Add);
+
+        local
+            .xy
+            .eval(builder, &x1_x2, &y1_y2, &p, FieldOperation::Mul);
+        local.d_xy.eval(
+            builder,
+            &constant_limbs(D),
+            &limbs(&local.xy.result),
+            &p,
+            FieldOperation::Mul,
+        );
+        let d_xy = limbs(&local.d_xy.result);
+        local
+            .x_den
+            .eval(builder, &one, &d_xy, &p, FieldOperation::Add);
+        local
+            .y_den
+            .eval(builder, &one, &d_xy, &p, FieldOperation::Sub);
+
+        // Padding rows take the inverses of the denominators to be zero.
+        let is_real = point.is_real::<AB>();
+        let real = core::array::from_fn(|i| {
+            if i == 0 {
+                is_real.clone()
+            } else {
+                AB::Expr::zero()
+            }
+        });
+        local.x_den_inv.eval(
+            builder,
+            &real,
+            &limbs(&local.x_den.result),
+            &p,
+            FieldOperation::Div,
+        );
+        local.x.eval(
+            builder,
+            &limbs(&local.x_num.result),
+            &limbs(&local.x_den_inv.result),
+            &p,
+            FieldOperation::Mul,
+        );
+        local.y_den_inv.eval(
+            builder,
+            &real,
+            &limbs(&local.y_den.result),
+            &p,
+            FieldOperation::Div,
+        );
+        local.y.eval(
+            builder,
+            &limbs(&local.y_num.result),
+            &limbs(&local.y_den_inv.result),
+            &p,
+            FieldOperation::Mul,
+        );
+
+        // The result is reduced.
+        local.x_lt.eval(builder, &limbs(&local.x.result), &p);
+        local.y_lt.eval(builder, &limbs(&local.y.result), &p);
+    }
+}
@@ -0,0 +1,19 @@
+#![no_std]
+
+//! Precompiles for the elliptic curves secp256k1 and ed25519. Each curve has a chip that
+//! adds and doubles points in affine coordinates, using the non-native field arithmetic of
+//! `valida-bigint`.
+
+extern crate alloc;
+
+pub mod ed25519;
+pub mod point;
+pub mod secp256k1;
+
+pub use ed25519::{
+    Ed25519AddInstruction, Ed25519Chip, Ed25519DoubleInstruction, MachineWithEd25519Chip,
+};
+pub use point::{AffinePoint, PointOperation};
+pub use secp256k1::{
+    MachineWithSecp256k1Chip, Secp256k1AddInstruction, Secp256k1Chip, Secp256k1DoubleInstruction,
+};
@@ -0,0 +1,287 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use valida_bigint::{word, Limbs, NUM_LIMBS, NUM_WORDS, U256};
+use valida_bus::{MachineWithGeneralBus, MachineWithMemBus, MachineWithRangeBus8};
+use valida_cpu::MachineWithCpuChip;
+use valida_machine::{Interaction, Operands, Word};
+
+use p3_air::{AirBuilder, VirtualPairCol};
+use p3_field::{AbstractField, Field, PrimeField32};
+use valida_machine::StarkConfig;
+
+/// A point in affine coordinates.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AffinePoint {
+    pub x: U256,
+    pub y: U256,
+}
+
+/// An addition or a doubling, as executed by a curve instruction.
+#[derive(Clone, Debug)]
+pub struct PointOperation {
+    pub clk: u32,
+    /// Address of the first point, which is overwritten by the result
+    pub p_addr: Word<u8>,
+    /// Address of the second point
+    pub q_addr: Word<u8>,
+    /// The points, where `p` is `q` for a doubling
+    pub p: AffinePoint,
+    pub q: AffinePoint,
+    pub is_double: bool,
+}
+
+/// The columns of a curve instruction, shared by the curve chips.
+pub struct PointOpCols<T> {
+    /// Whether the row is an addition or a doubling made by an instruction. Padding rows
+    /// are neither.
+    pub is_add: T,
+    pub is_double: T,
+
+    /// CPU clock of the instruction
+    pub clk: T,
+    /// Addresses of the points, as seen by the CPU
+    pub p_addr: Word<T>,
+    pub q_addr: Word<T>,
+    /// The same addresses, as field elements
+    pub p_ptr: T,
+    pub q_ptr: T,
+
+    /// The coordinates of the points
+    pub p_x: Limbs<T>,
+    pub p_y: Limbs<T>,
+    pub q_x: Limbs<T>,
+    pub q_y: Limbs<T>,
+}
+
+impl<F: PrimeField32> PointOpCols<F> {
+    pub fn populate(&mut self, op: &PointOperation) {
+        self.is_add = F::from_bool(!op.is_double);
+        self.is_double = F::from_bool(op.is_double);
+        self.clk = F::from_canonical_u32(op.clk);
+        self.p_addr = op.p_addr.transform(F::from_canonical_u8);
+        self.q_addr = op.q_addr.transform(F::from_canonical_u8);
+        self.p_ptr = F::from_canonical_u32(op.p_addr.into());
+        self.q_ptr = F::from_canonical_u32(op.q_addr.into());
+        self.p_x = op.p.x.to_le_bytes().map(F::from_canonical_u8);
+        self.p_y = op.p.y.to_le_bytes().map(F::from_canonical_u8);
+        self.q_x = op.q.x.to_le_bytes().map(F::from_canonical_u8);
+        self.q_y = op.q.y.to_le_bytes().map(F::from_canonical_u8);
+    }
+}
+
+impl<V: Copy> PointOpCols<V> {
+    pub fn eval<AB: AirBuilder<Var = V>>(&self, builder: &mut AB) {
+        let base = [1 << 24, 1 << 16, 1 << 8, 1].map(AB::Expr::from_canonical_u32);
+        let reduce = |word: Word<V>| -> AB::Expr {
+            word.into_iter()
+                .zip(base.iter())
+                .map(|(x, b)| b.clone() * x)
+                .sum()
+        };
+
+        builder.assert_bool(self.is_add);
+        builder.assert_bool(self.is_double);
+        builder.assert_bool(self.is_real::<AB>());
+        builder.assert_eq(self.p_ptr, reduce(self.p_addr));
+        builder.assert_eq(self.q_ptr, reduce(self.q_addr));
+
+        // A doubling reads a single point.
+        let p = self.p_x.into_iter().chain(self.p_y);
+        let q = self.q_x.into_iter().chain(self.q_y);
+        for (p_limb, q_limb) in p.zip(q) {
+            builder.when(self.is_double).assert_eq(p_limb, q_limb);
+        }
+    }
+
+    /// Whether the row is an instruction.
+    pub fn is_real<AB: AirBuilder<Var = V>>(&self) -> AB::Expr {
+        let is_add: AB::Expr = self.is_add.into();
+        is_add + self.is_double
+    }
+}
+
+impl PointOpCols<usize> {
+    /// The memory accesses of the instruction, which writes `result` over `p`, along with
+    /// the range checks of `range_checked`.
+    pub fn global_sends<M, SC>(
+        &self,
+        machine: &M,
+        result: [&Limbs<usize>; 2],
+        range_checked: Vec<usize>,
+    ) -> Vec<Interaction<SC::Val>>
+    where
+        M: MachineWithMemBus<SC::Val> + MachineWithRangeBus8<SC::Val>,
+        SC: StarkConfig,
+    {
+        let clk = VirtualPairCol::single_main(self.clk);
+        let is_static_initial = VirtualPairCol::constant(SC::Val::zero());
+        let is_real = VirtualPairCol::sum_main(vec![self.is_add, self.is_double]);
+        let memory = |is_read: bool,
+                      ptr: usize,
+                      coords: [&Limbs<usize>; 2],
+                      count: VirtualPairCol<SC::Val>| {
+            let mut sends = vec![];
+            for (j, limbs) in coords.into_iter().enumerate() {
+                for i in 0..NUM_WORDS {
+                    let addr = VirtualPairCol::new_main(
+                        vec![(ptr, SC::Val::one())],
+                        SC::Val::from_canonical_usize(NUM_LIMBS * j + 4 * i),
+                    );
+                    let mut fields = vec![
+                        VirtualPairCol::constant(SC::Val::from_bool(is_read)),
+                        clk.clone(),
+                        addr,
+                        is_static_initial.clone(),
+                    ];
+                    fields.extend(word(limbs, i).0.map(VirtualPairCol::single_main));
+                    sends.push(Interaction {
+                        fields,
+                        count: count.clone(),
+                        argument_index: machine.mem_bus(),
+                    });
+                }
+            }
+            sends
+        };
+
+        let mut sends = vec![];
+        sends.extend(memory(
+            true,
+            self.p_ptr,
+            [&self.p_x, &self.p_y],
+            VirtualPairCol::single_main(self.is_add),
+        ));
+        sends.extend(memory(
+            true,
+            self.q_ptr,
+            [&self.q_x, &self.q_y],
+            is_real.clone(),
+        ));
+        sends.extend(memory(false, self.p_ptr, result, is_real.clone()));
+        for col in range_checked {
+            sends.push(Interaction {
+                fields: vec![VirtualPairCol::single_main(col)],
+                count: is_real.clone(),
+                argument_index: machine.range_bus(),
+            });
+        }
+        sends
+    }
+
+    /// The instruction, with `add_opcode` or `double_opcode`.
+    pub fn global_receives<M, SC>(
+        &self,
+        machine: &M,
+        add_opcode: u32,
+        double_opcode: u32,
+    ) -> Vec<Interaction<SC::Val>>
+    where
+        M: MachineWithGeneralBus<SC::Val>,
+        SC: StarkConfig,
+    {
+        let opcode = VirtualPairCol::new_main(
+            vec![
+                (self.is_add, SC::Val::from_canonical_u32(add_opcode)),
+                (self.is_double, SC::Val::from_canonical_u32(double_opcode)),
+            ],
+            SC::Val::zero(),
+        );
+        let p_addr = self.p_addr.0.map(VirtualPairCol::single_main);
+        let q_addr = self.q_addr.0.map(VirtualPairCol::single_main);
+        let clk = VirtualPairCol::single_main(self.clk);
+
+        // The CPU writes the address of the result to the destination operand
+        let mut fields = vec![opcode];
+        fields.extend(p_addr.clone());
+        fields.extend(q_addr);
+        fields.extend(p_addr);
+        fields.push(clk);
+
+        let receive = Interaction {
+            fields,
+            count: VirtualPairCol::sum_main(vec![self.is_add, self.is_double]),
+            argument_index: machine.general_bus(),
+        };
+        vec![receive]
+    }
+}
+
+/// Reads the points of a curve instruction. The first point is at the address in `b(fp)`
+/// and the second at the address in `c(fp)`, and a doubling only reads the second.
+pub(crate) fn read_operation<M, F>(
+    state: &mut M,
+    opcode: u32,
+    ops: Operands<i32>,
+    is_double: bool,
+) -> PointOperation
+where
+    M: MachineWithCpuChip<F>,
+    F: Field,
+{
+    let clk = state.cpu().clock;
+    let pc = state.cpu().pc;
+    let fp = state.cpu().fp;
+    let read_addr_1 = (fp as i32 + ops.b()) as u32;
+    let read_addr_2 = (fp as i32 + ops.c()) as u32;
+
+    let p_addr = state
+        .mem_mut()
+        .read(clk, read_addr_1, true, pc, opcode, 0, "");
+    let q_addr = state
+        .mem_mut()
+        .read(clk, read_addr_2, true, pc, opcode, 1, "");
+
+    let p = (!is_double).then(|| read_point(state, clk, p_addr.into()));
+    let q = read_point(state, clk, q_addr.into());
+    let p = p.unwrap_or(q);
+    PointOperation {
+        clk,
+        p_addr,
+        q_addr,
+        p,
+        q,
+        is_double,
+    }
+}
+
+/// Writes the result of a curve instruction over the first point, and its address to
+/// `a(fp)`.
+pub(crate) fn write_result<M, F>(
+    state: &mut M,
+    opcode: u32,
+    ops: Operands<i32>,
+    op: &PointOperation,
+    result: AffinePoint,
+) where
+    M: MachineWithCpuChip<F>,
+    F: Field,
+{
+    let fp = state.cpu().fp;
+    let write_addr = (fp as i32 + ops.a()) as u32;
+    let base: u32 = op.p_addr.into();
+    let words = result.x.to_words().into_iter().chain(result.y.to_words());
+    for (i, word) in words.enumerate() {
+        state
+            .mem_mut()
+            .coprocessor_write(op.clk, base.wrapping_add(4 * i as u32), word);
+    }
+    state.mem_mut().write(op.clk, write_addr, op.p_addr, true);
+    state.cpu_mut().push_bus_op_with_memory(None, opcode, ops);
+}
+
+fn read_point<M, F>(state: &mut M, clk: u32, addr: u32) -> AffinePoint
+where
+    M: MachineWithCpuChip<F>,
+    F: Field,
+{
+    let mut coord = |offset: u32| {
+        U256::from_words(core::array::from_fn(|i| {
+            state
+                .mem_mut()
+                .coprocessor_read(clk, addr.wrapping_add(offset + 4 * i as u32))
+        }))
+    };
+    let x = coord(0);
+    let y = coord(NUM_LIMBS as u32);
+    AffinePoint { x, y }
+}
@@ -0,0 +1,70 @@
+use crate::point::PointOpCols;
+use alloc::vec::Vec;
+use core::borrow::{Borrow, BorrowMut};
+use core::mem::{size_of, transmute};
+use valida_bigint::{FieldOpCols, LtCols};
+use valida_derive::AlignedBorrow;
+use valida_util::indices_arr;
+
+#[derive(AlignedBorrow)]
+pub struct Secp256k1Cols<T> {
+    pub point: PointOpCols<T>,
+
+    /// The slope of an addition, `(q_y - p_y) / (q_x - p_x)`
+    pub dy: FieldOpCols<T>,
+    pub dx: FieldOpCols<T>,
+    /// The slope of a doubling, `3 p_x^2 / (2 p_y)`
+    pub x_sq: FieldOpCols<T>,
+    pub three_x_sq: FieldOpCols<T>,
+    pub two_y: FieldOpCols<T>,
+    /// The slope of the operation, which is one of the two. The inverse of the
+    /// denominator shows that it is nonzero.
+    pub den_inv: FieldOpCols<T>,
+    pub slope: FieldOpCols<T>,
+
+    /// `x = slope^2 - p_x - q_x`
+    pub slope_sq: FieldOpCols<T>,
+    pub x_partial: FieldOpCols<T>,
+    pub x: FieldOpCols<T>,
+    /// `y = slope (p_x - x) - p_y`
+    pub x_diff: FieldOpCols<T>,
+    pub y_partial: FieldOpCols<T>,
+    pub y: FieldOpCols<T>,
+
+    /// The coordinates of the result are reduced
+    pub x_lt: LtCols<T>,
+    pub y_lt: LtCols<T>,
+}
+
+impl<T: Copy> Secp256k1Cols<T> {
+    /// The columns that hold bytes.
+    pub fn range_checked(&self) -> Vec<T> {
+        let ops = [
+            &self.dy,
+            &self.dx,
+            &self.x_sq,
+            &self.three_x_sq,
+            &self.two_y,
+            &self.den_inv,
+            &self.slope,
+            &self.slope_sq,
+            &self.x_partial,
+            &self.x,
+            &self.x_diff,
+            &self.y_partial,
+            &self.y,
+        ];
+        ops.into_iter()
+            .flat_map(|op| op.range_checked())
+            .chain([self.x_lt.diff, self.y_lt.diff])
+            .collect()
+    }
+}
+
+pub const NUM_SECP256K1_COLS: usize = size_of::<Secp256k1Cols<u8>>();
+pub const SECP256K1_COL_MAP: Secp256k1Cols<usize> = make_col_map();
+
+const fn make_col_map() -> Secp256k1Cols<usize> {
+    let indices_arr = indices_arr::<NUM_SECP256K1_COLS>();
+    unsafe { transmute::<[usize; NUM_SECP256K1_COLS], Secp256k1Cols<usize>>(indices_arr) }
+}
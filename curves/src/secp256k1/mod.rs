@@ -0,0 +1,194 @@
+use crate::point::{read_operation, write_result, AffinePoint, PointOperation};
+use alloc::vec::Vec;
+use columns::{Secp256k1Cols, NUM_SECP256K1_COLS, SECP256K1_COL_MAP};
+use core::mem::transmute;
+use valida_bigint::{range_check_values, FieldOperation, U256};
+use valida_bus::{MachineWithGeneralBus, MachineWithMemBus, MachineWithRangeBus8};
+use valida_cpu::MachineWithCpuChip;
+use valida_machine::{instructions, Chip, Instruction, Interaction, Operands};
+use valida_opcodes::{SECP256K1ADD, SECP256K1DOUBLE};
+use valida_range::MachineWithRangeChip;
+
+use p3_field::{Field, PrimeField32};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_maybe_rayon::prelude::*;
+use valida_machine::StarkConfig;
+
+pub mod columns;
+pub mod stark;
+
+/// The modulus of the base field, `2^256 - 2^32 - 977`.
+pub const P: U256 = U256([
+    0xFFFF_FFFE_FFFF_FC2F,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0xFFFF_FFFF_FFFF_FFFF,
+]);
+
+/// Adds and doubles points of the curve `y^2 = x^3 + 7`, one operation per row.
+#[derive(Default)]
+pub struct Secp256k1Chip {
+    pub operations: Vec<PointOperation>,
+}
+
+impl<M, SC> Chip<M, SC> for Secp256k1Chip
+where
+    M: MachineWithGeneralBus<SC::Val> + MachineWithMemBus<SC::Val> + MachineWithRangeBus8<SC::Val>,
+    SC: StarkConfig,
+{
+    fn generate_trace(&self, _machine: &M) -> RowMajorMatrix<SC::Val> {
+        // The trace is padded with rows of no operation, whose points are zero.
+        let num_rows = self.operations.len().next_power_of_two();
+        let rows = (0..num_rows)
+            .into_par_iter()
+            .map(|i| self.op_to_row(self.operations.get(i)))
+            .collect::<Vec<_>>();
+        RowMajorMatrix::new(rows.into_iter().flatten().collect(), NUM_SECP256K1_COLS)
+    }
+
+    fn global_sends(&self, machine: &M) -> Vec<Interaction<SC::Val>> {
+        let result = [&SECP256K1_COL_MAP.x.result, &SECP256K1_COL_MAP.y.result];
+        SECP256K1_COL_MAP.point.global_sends::<M, SC>(
+            machine,
+            result,
+            SECP256K1_COL_MAP.range_checked(),
+        )
+    }
+
+    fn global_receives(&self, machine: &M) -> Vec<Interaction<SC::Val>> {
+        SECP256K1_COL_MAP
+            .point
+            .global_receives::<M, SC>(machine, SECP256K1ADD, SECP256K1DOUBLE)
+    }
+}
+
+impl Secp256k1Chip {
+    fn op_to_row<F: PrimeField32>(&self, op: Option<&PointOperation>) -> [F; NUM_SECP256K1_COLS] {
+        let mut row = [F::zero(); NUM_SECP256K1_COLS];
+        let cols: &mut Secp256k1Cols<F> = unsafe { transmute(&mut row) };
+        if let Some(op) = op {
+            cols.point.populate(op);
+        }
+        populate_field_ops(cols, op);
+        row
+    }
+}
+
+/// Fills in the field operations of a row, and returns the result.
+fn populate_field_ops<F: PrimeField32>(
+    cols: &mut Secp256k1Cols<F>,
+    op: Option<&PointOperation>,
+) -> AffinePoint {
+    let (p, q) = op.map_or(Default::default(), |op| (op.p, op.q));
+
+    let dy = cols.dy.populate(q.y, p.y, P, FieldOperation::Sub);
+    let dx = cols.dx.populate(q.x, p.x, P, FieldOperation::Sub);
+    let x_sq = cols.x_sq.populate(p.x, p.x, P, FieldOperation::Mul);
+    let three_x_sq = cols
+        .three_x_sq
+        .populate(x_sq, U256::from_u64(3), P, FieldOperation::Mul);
+    let two_y = cols.two_y.populate(p.y, p.y, P, FieldOperation::Add);
+    let (num, den) = match op {
+        Some(op) if op.is_double => (three_x_sq, two_y),
+        Some(_) => (dy, dx),
+        None => (U256::ZERO, U256::ZERO),
+    };
+    let is_real = U256::from_u64(op.is_some() as u64);
+    let den_inv = cols.den_inv.populate(is_real, den, P, FieldOperation::Div);
+    let slope = cols.slope.populate(num, den_inv, P, FieldOperation::Mul);
+
+    let slope_sq = cols.slope_sq.populate(slope, slope, P, FieldOperation::Mul);
+    let x_partial = cols
+        .x_partial
+        .populate(slope_sq, p.x, P, FieldOperation::Sub);
+    let x = cols.x.populate(x_partial, q.x, P, FieldOperation::Sub);
+    let x_diff = cols.x_diff.populate(p.x, x, P, FieldOperation::Sub);
+    let y_partial = cols
+        .y_partial
+        .populate(slope, x_diff, P, FieldOperation::Mul);
+    let y = cols.y.populate(y_partial, p.y, P, FieldOperation::Sub);
+
+    cols.x_lt.populate(x, P);
+    cols.y_lt.populate(y, P);
+    AffinePoint { x, y }
+}
+
+/// Whether the coordinates are reduced, and the slope of the line through the points
+/// exists. Adding a point to itself or to its negation has no slope, nor has doubling a
+/// point with `y = 0`.
+fn is_defined(op: &PointOperation) -> bool {
+    let reduced = [op.p.x, op.p.y, op.q.x, op.q.y].iter().all(|c| *c < P);
+    reduced
+        && if op.is_double {
+            !op.q.y.is_zero()
+        } else {
+            op.p.x != op.q.x
+        }
+}
+
+pub trait MachineWithSecp256k1Chip<F: Field>: MachineWithCpuChip<F> {
+    fn secp256k1(&self) -> &Secp256k1Chip;
+    fn secp256k1_mut(&mut self) -> &mut Secp256k1Chip;
+}
+
+instructions!(Secp256k1AddInstruction, Secp256k1DoubleInstruction);
+
+impl<M, F> Instruction<M, F> for Secp256k1AddInstruction
+where
+    M: MachineWithSecp256k1Chip<F> + MachineWithRangeChip<F, 256>,
+    F: PrimeField32,
+{
+    const OPCODE: u32 = SECP256K1ADD;
+
+    /// `SECP256K1ADD a(fp), b(fp), c(fp)` adds the point at the address in `c(fp)` to the
+    /// point at the address in `b(fp)`, which it overwrites, and writes the address in
+    /// `b(fp)` to `a(fp)`. A point takes up 16 cells, its `x` coordinate and then its `y`
+    /// coordinate, each 8 cells with the least significant first.
+    ///
+    /// The coordinates must be reduced, and the points must have different `x` coordinates,
+    /// so a point is doubled with `SECP256K1DOUBLE` rather than added to itself. Otherwise,
+    /// including for a point and its negation, `(0, 0)` is written in place of the sum, and
+    /// the execution cannot be proven.
+    fn execute(state: &mut M, ops: Operands<i32>) {
+        execute(state, <Self as Instruction<M, F>>::OPCODE, ops, false);
+    }
+}
+
+impl<M, F> Instruction<M, F> for Secp256k1DoubleInstruction
+where
+    M: MachineWithSecp256k1Chip<F> + MachineWithRangeChip<F, 256>,
+    F: PrimeField32,
+{
+    const OPCODE: u32 = SECP256K1DOUBLE;
+
+    /// `SECP256K1DOUBLE a(fp), b(fp), c(fp)` doubles the point at the address in `c(fp)`,
+    /// and writes the result to the address in `b(fp)`, which may be the same. The address
+    /// in `b(fp)` is written to `a(fp)`. Points are laid out as for `SECP256K1ADD`. The
+    /// coordinates must be reduced and `y` must be nonzero, or else `(0, 0)` is written
+    /// and the execution cannot be proven.
+    fn execute(state: &mut M, ops: Operands<i32>) {
+        execute(state, <Self as Instruction<M, F>>::OPCODE, ops, true);
+    }
+}
+
+fn execute<M, F>(state: &mut M, opcode: u32, ops: Operands<i32>, is_double: bool)
+where
+    M: MachineWithSecp256k1Chip<F> + MachineWithRangeChip<F, 256>,
+    F: PrimeField32,
+{
+    let op = read_operation(state, opcode, ops, is_double);
+    if !is_defined(&op) {
+        // No row of the chip can hold the operation, so the instruction goes unmatched on
+        // the bus and the execution cannot be proven.
+        write_result(state, opcode, ops, &op, AffinePoint::default());
+        return;
+    }
+
+    let mut row = [F::zero(); NUM_SECP256K1_COLS];
+    let cols: &mut Secp256k1Cols<F> = unsafe { transmute(&mut row) };
+    let result = populate_field_ops(cols, Some(&op));
+    range_check_values(state, cols.range_checked());
+
+    write_result(state, opcode, ops, &op, result);
+    state.secp256k1_mut().operations.push(op);
+}
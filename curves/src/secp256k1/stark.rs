@@ -0,0 +1,115 @@
+use super::columns::{Secp256k1Cols, NUM_SECP256K1_COLS};
+use super::{Secp256k1Chip, P};
+use core::borrow::Borrow;
+use valida_bigint::{constant_limbs, FieldOperation, Limbs, U256};
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, PrimeField};
+use p3_matrix::MatrixRowSlices;
+
+impl<F> BaseAir<F> for Secp256k1Chip {
+    fn width(&self) -> usize {
+        NUM_SECP256K1_COLS
+    }
+}
+
+impl<F, AB> Air<AB> for Secp256k1Chip
+where
+    F: PrimeField,
+    AB: AirBuilder<F = F>,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local: &Secp256k1Cols<AB::Var> = main.row_slice(0).borrow();
+        let point = &local.point;
+        point.eval(builder);
+
+        let limbs = |x: &Limbs<AB::Var>| -> Limbs<AB::Expr> { x.map(Into::into) };
+        let p = constant_limbs::<AB::Expr>(P);
+        let (p_x, p_y) = (limbs(&point.p_x), limbs(&point.p_y));
+        let (q_x, q_y) = (limbs(&point.q_x), limbs(&point.q_y));
+
+        local.dy.eval(builder, &q_y, &p_y, &p, FieldOperation::Sub);
+        local.dx.eval(builder, &q_x, &p_x, &p, FieldOperation::Sub);
+        local
+            .x_sq
+            .eval(builder, &p_x, &p_x, &p, FieldOperation::Mul);
+        local.three_x_sq.eval(
+            builder,
+            &limbs(&local.x_sq.result),
+            &constant_limbs(U256::from_u64(3)),
+            &p,
+            FieldOperation::Mul,
+        );
+        local
+            .two_y
+            .eval(builder, &p_y, &p_y, &p, FieldOperation::Add);
+
+        // The slope of an addition or a doubling, as selected by the row. Padding rows
+        // select neither, and take the inverse of their zero denominator to be zero.
+        let is_add: AB::Expr = point.is_add.into();
+        let is_double: AB::Expr = point.is_double.into();
+        let select = |add: &Limbs<AB::Var>, double: &Limbs<AB::Var>| -> Limbs<AB::Expr> {
+            core::array::from_fn(|i| is_add.clone() * add[i] + is_double.clone() * double[i])
+        };
+        let num = select(&local.dy.result, &local.three_x_sq.result);
+        let den = select(&local.dx.result, &local.two_y.result);
+        let is_real = point.is_real::<AB>();
+        let one = core::array::from_fn(|i| {
+            if i == 0 {
+                is_real.clone()
+            } else {
+                AB::Expr::zero()
+            }
+        });
+        local
+            .den_inv
+            .eval(builder, &one, &den, &p, FieldOperation::Div);
+        let den_inv = limbs(&local.den_inv.result);
+        local
+            .slope
+            .eval(builder, &num, &den_inv, &p, FieldOperation::Mul);
+
+        let slope = limbs(&local.slope.result);
+        local
+            .slope_sq
+            .eval(builder, &slope, &slope, &p, FieldOperation::Mul);
+        local.x_partial.eval(
+            builder,
+            &limbs(&local.slope_sq.result),
+            &p_x,
+            &p,
+            FieldOperation::Sub,
+        );
+        local.x.eval(
+            builder,
+            &limbs(&local.x_partial.result),
+            &q_x,
+            &p,
+            FieldOperation::Sub,
+        );
+
+        let x = limbs(&local.x.result);
+        local
+            .x_diff
+            .eval(builder, &p_x, &x, &p, FieldOperation::Sub);
+        local.y_partial.eval(
+            builder,
+            &slope,
+            &limbs(&local.x_diff.result),
+            &p,
+            FieldOperation::Mul,
+        );
+        local.y.eval(
+            builder,
+            &limbs(&local.y_partial.result),
+            &p_y,
+            &p,
+            FieldOperation::Sub,
+        );
+
+        // The result is reduced.
+        local.x_lt.eval(builder, &x, &p);
+        local.y_lt.eval(builder, &limbs(&local.y.result), &p);
+    }
+}
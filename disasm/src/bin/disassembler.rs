@@ -0,0 +1,48 @@
+use clap::{arg, command};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use valida_disasm::disassemble_executable;
+
+fn main() {
+    let matches = command!()
+        .arg(arg!(
+            -i --input <FILE> "The input executable or machine code file"
+        ))
+        .arg(arg!(
+            -o --output <FILE> "The assembly output file"
+        ))
+        .get_matches();
+
+    // Read machine code from input file, or from stdin if no file is specified
+    let mut machine_code = Vec::new();
+    if let Some(filepath) = matches.get_one::<String>("input") {
+        File::open(filepath)
+            .expect("Failed to open input file")
+            .read_to_end(&mut machine_code)
+            .expect("Failed to read from input file");
+    } else {
+        io::stdin()
+            .read_to_end(&mut machine_code)
+            .expect("Failed to read from stdin");
+    }
+
+    // Write assembly code to file, or stdout if no file is specified
+    let assembly_code = match disassemble_executable(&machine_code) {
+        Ok(assembly_code) => assembly_code,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            eprintln!("Failed to disassemble code");
+            std::process::exit(1);
+        }
+    };
+    if let Some(filepath) = matches.get_one::<String>("output") {
+        File::create(filepath)
+            .expect("Failed to open output file")
+            .write_all(assembly_code.as_bytes())
+            .expect("Failed to write to output file");
+    } else {
+        io::stdout()
+            .write_all(assembly_code.as_bytes())
+            .expect("Failed to write to stdout");
+    }
+}
@@ -0,0 +1,202 @@
+use core::fmt;
+use std::collections::{BTreeMap, BTreeSet};
+use valida_assembler::{OperandLayout, INSTRUCTIONS};
+use valida_elf::{load_executable_file, ElfLoadError, Symbol};
+use valida_machine::{InstructionWord, ProgramROM};
+use valida_opcodes::{BEQ, BNE, BYTES_PER_INSTR, JAL, WRITE};
+
+/// An instruction that has no assembly form.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisassemblyError {
+    /// The index of the instruction.
+    pub pc: u32,
+    pub message: String,
+}
+
+impl fmt::Display for DisassemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "instruction {}: {}", self.pc, self.message)
+    }
+}
+
+impl std::error::Error for DisassemblyError {}
+
+/// How an operand is written. Frame offsets and immediates assemble to the same value, so
+/// the distinction only makes the output easier to read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OperandKind {
+    FrameOffset,
+    Immediate,
+    /// The byte address of an instruction, written as a label where possible.
+    CodeAddress,
+}
+
+fn operand_kinds(opcode: u32, layout: OperandLayout) -> Vec<OperandKind> {
+    use OperandKind::*;
+    match (opcode, layout) {
+        (_, OperandLayout::Load | OperandLayout::Store) => vec![FrameOffset, FrameOffset],
+        (WRITE, OperandLayout::Full) => vec![Immediate; 5],
+        // imm32 a(fp), followed by the bytes of the value, most significant first
+        (_, OperandLayout::Full) => vec![FrameOffset, Immediate, Immediate, Immediate, Immediate],
+        (_, OperandLayout::Nullary) => vec![],
+        (JAL, _) => vec![FrameOffset, CodeAddress, Immediate],
+        (BEQ | BNE, OperandLayout::ImmediateC) => vec![CodeAddress, FrameOffset, Immediate],
+        (BEQ | BNE, _) => vec![CodeAddress, FrameOffset, FrameOffset],
+        (_, OperandLayout::Binary) => vec![FrameOffset, FrameOffset, FrameOffset],
+        (_, OperandLayout::ImmediateC) => vec![FrameOffset, FrameOffset, Immediate],
+        (_, OperandLayout::ImmediateB) => vec![FrameOffset, Immediate, FrameOffset],
+        (_, OperandLayout::Unary) => vec![FrameOffset],
+        // loadfp a(fp), b stores fp + b
+        (_, OperandLayout::Pair) => vec![FrameOffset, Immediate],
+    }
+}
+
+struct DecodedInstruction {
+    mnemonic: &'static str,
+    operands: Vec<(OperandKind, i32)>,
+}
+
+/// Pick the mnemonic whose operand layout matches the instruction's zero operands and
+/// immediate flags, e.g. `addi` rather than `add` when operand c is an immediate.
+fn decode(
+    pc: u32,
+    instruction: &InstructionWord<i32>,
+) -> Result<DecodedInstruction, DisassemblyError> {
+    let mut known_opcode = false;
+    for &(mnemonic, opcode, layout) in INSTRUCTIONS {
+        if opcode != instruction.opcode {
+            continue;
+        }
+        known_opcode = true;
+        if let Some(operands) = layout.extract(&instruction.operands.0) {
+            return Ok(DecodedInstruction {
+                mnemonic,
+                operands: operand_kinds(opcode, layout)
+                    .into_iter()
+                    .zip(operands)
+                    .collect(),
+            });
+        }
+    }
+    let message = match known_opcode {
+        true => format!(
+            "operands {:?} don't fit any assembly form of opcode {}",
+            instruction.operands.0, instruction.opcode
+        ),
+        false => format!("opcode {} has no assembly mnemonic", instruction.opcode),
+    };
+    Err(DisassemblyError { pc, message })
+}
+
+/// Whether a symbol can be used as a label, i.e. the assembler reads it as a label
+/// rather than a number.
+fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '.')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Disassemble a program into assembly that `valida_assembler::assemble` turns back into
+/// the same machine code.
+pub fn disassemble(rom: &ProgramROM<i32>) -> Result<String, DisassemblyError> {
+    disassemble_with_symbols(rom, &[])
+}
+
+/// Like `disassemble`, but name labels after the symbols where there are any.
+pub fn disassemble_with_symbols(
+    rom: &ProgramROM<i32>,
+    symbols: &[Symbol],
+) -> Result<String, DisassemblyError> {
+    let instructions = rom
+        .0
+        .iter()
+        .enumerate()
+        .map(|(pc, instruction)| decode(pc as u32, instruction))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Labels may also follow the last instruction
+    let num_instructions = instructions.len() as u32;
+    let target_pc = |address: i32| {
+        let address = address as u32;
+        (address.is_multiple_of(BYTES_PER_INSTR) && address / BYTES_PER_INSTR <= num_instructions)
+            .then_some(address / BYTES_PER_INSTR)
+    };
+
+    let mut labels = BTreeMap::new();
+    let mut names = BTreeSet::new();
+    for symbol in symbols {
+        if let Some(pc) = target_pc(symbol.address as i32) {
+            if is_label_name(&symbol.name)
+                && !labels.contains_key(&pc)
+                && names.insert(symbol.name.clone())
+            {
+                labels.insert(pc, symbol.name.clone());
+            }
+        }
+    }
+    // Synthesize labels for the branch and jump targets that have no symbol
+    for instruction in &instructions {
+        for &(kind, value) in &instruction.operands {
+            let Some(pc) = target_pc(value).filter(|_| kind == OperandKind::CodeAddress) else {
+                continue;
+            };
+            if labels.contains_key(&pc) {
+                continue;
+            }
+            let mut name = format!(".L{}", pc);
+            while names.contains(&name) {
+                name.push('_');
+            }
+            names.insert(name.clone());
+            labels.insert(pc, name);
+        }
+    }
+
+    let mut assembly = String::new();
+    for (pc, instruction) in instructions.iter().enumerate() {
+        if let Some(label) = labels.get(&(pc as u32)) {
+            assembly.push_str(&format!("{}:\n", label));
+        }
+        assembly.push('\t');
+        assembly.push_str(instruction.mnemonic);
+        let operands: Vec<String> = instruction
+            .operands
+            .iter()
+            .map(|&(kind, value)| match kind {
+                OperandKind::FrameOffset => format!("{}(fp)", value),
+                OperandKind::Immediate => value.to_string(),
+                OperandKind::CodeAddress => target_pc(value)
+                    .and_then(|pc| labels.get(&pc).cloned())
+                    .unwrap_or_else(|| value.to_string()),
+            })
+            .collect();
+        if !operands.is_empty() {
+            assembly.push('\t');
+            assembly.push_str(&operands.join(", "));
+        }
+        assembly.push('\n');
+    }
+    if let Some(label) = labels.get(&num_instructions) {
+        assembly.push_str(&format!("{}:\n", label));
+    }
+    Ok(assembly)
+}
+
+/// Disassemble the code of an ELF executable or flat binary, naming labels after its
+/// function symbols. Data and the entry point have no assembly form yet, so they are left
+/// out.
+pub fn disassemble_executable(file: &[u8]) -> Result<String, DisassemblyError> {
+    let program = load_executable_file(file.to_vec()).map_err(|error| match error {
+        ElfLoadError::UnknownOpcode { pc, opcode } => DisassemblyError {
+            pc,
+            message: format!("opcode {} has no assembly mnemonic", opcode),
+        },
+        error => DisassemblyError {
+            pc: 0,
+            message: error.to_string(),
+        },
+    })?;
+    disassemble_with_symbols(&program.code, &program.symbols)
+}
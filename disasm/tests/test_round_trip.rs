@@ -0,0 +1,71 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fs::read_to_string;
+use valida_assembler::{assemble, INSTRUCTIONS};
+use valida_disasm::{disassemble, disassemble_with_symbols};
+use valida_elf::Symbol;
+use valida_machine::ProgramROM;
+use valida_opcodes::BYTES_PER_INSTR;
+
+#[test]
+fn round_trip_fibonacci() {
+    let asm = read_to_string("../basic/tests/programs/assembly/fibonacci.val")
+        .expect("Failed to read asm");
+    let code = assemble(&asm).unwrap();
+    let rom = ProgramROM::from_machine_code(&code);
+
+    let text = disassemble(&rom).unwrap();
+    assert_eq!(assemble(&text).unwrap(), code);
+    // `fib` starts after the 15 instructions of `main`
+    assert!(text.contains("\tjal\t-28(fp), .L15, -28\n"), "{}", text);
+
+    let fib = Symbol {
+        name: "fib".to_string(),
+        address: 15 * BYTES_PER_INSTR,
+        size: 0,
+    };
+    let text = disassemble_with_symbols(&rom, &[fib]).unwrap();
+    assert_eq!(assemble(&text).unwrap(), code);
+    assert!(text.contains("fib:\n"), "{}", text);
+    assert!(text.contains("\tjal\t-28(fp), fib, -28\n"), "{}", text);
+}
+
+#[test]
+fn reports_unknown_opcode() {
+    let mut code = assemble("\tstop\n").unwrap();
+    code.extend([0xff; 24]);
+    let error = disassemble(&ProgramROM::from_machine_code(&code)).unwrap_err();
+    assert_eq!(error.pc, 1);
+}
+
+/// Machine code for random instructions with any mnemonic. Operands are random, but often
+/// small or the address of an instruction, so that labels are exercised.
+fn random_program(rng: &mut StdRng, len: usize) -> Vec<u8> {
+    let mut code = Vec::new();
+    for _ in 0..len {
+        let (_, opcode, layout) = INSTRUCTIONS[rng.gen_range(0..INSTRUCTIONS.len())];
+        let operands = (0..layout.num_operands())
+            .map(|_| match rng.gen_range(0..3) {
+                0 => rng.gen_range(-64..64),
+                1 => BYTES_PER_INSTR as i32 * rng.gen_range(0..=len as i32),
+                _ => rng.gen(),
+            })
+            .collect();
+        code.extend(opcode.to_le_bytes());
+        for operand in layout.arrange(operands) {
+            code.extend(operand.to_le_bytes());
+        }
+    }
+    code
+}
+
+#[test]
+fn round_trip_random_programs() {
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..200 {
+        let len = rng.gen_range(0..32);
+        let code = random_program(&mut rng, len);
+        let text = disassemble(&ProgramROM::from_machine_code(&code)).unwrap();
+        assert_eq!(assemble(&text).unwrap(), code, "{}", text);
+    }
+}
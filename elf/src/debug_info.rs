@@ -0,0 +1,141 @@
+use crate::{load_executable_symbols, Symbol};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use elf::endian::AnyEndian;
+use elf::ElfBytes;
+use gimli::{EndianSlice, RunTimeEndian, SectionId};
+
+/// A row of a DWARF line table: the instructions starting at `address` were generated
+/// from `line` of `file`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineEntry {
+    pub address: u32,
+    pub file: String,
+    pub line: u32,
+}
+
+/// The symbols and line tables of an executable, used to map program counters back to
+/// source locations. Both are empty for flat binaries and for ELF files without debug
+/// information.
+#[derive(Clone, Debug, Default)]
+pub struct DebugInfo {
+    pub symbols: Vec<Symbol>,
+    /// Line table rows, sorted by address.
+    pub lines: Vec<LineEntry>,
+}
+
+impl DebugInfo {
+    pub fn load(file: &[u8]) -> Self {
+        Self {
+            symbols: load_executable_symbols(file),
+            lines: load_executable_line_table(file),
+        }
+    }
+
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.name == name)
+    }
+
+    /// The function containing the instruction at `address`.
+    pub fn function_at(&self, address: u32) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.contains(address))
+    }
+
+    /// The source line the instruction at `address` was generated from.
+    pub fn line_at(&self, address: u32) -> Option<&LineEntry> {
+        let index = self.lines.partition_point(|entry| entry.address <= address);
+        index.checked_sub(1).map(|index| &self.lines[index])
+    }
+
+    /// The addresses where the code for a source line begins. `file` may be a path
+    /// suffix, such as the file name alone.
+    pub fn line_addresses(&self, file: &str, line: u32) -> Vec<u32> {
+        let suffix = format!("/{}", file);
+        let matches = |entry: &LineEntry| {
+            entry.line == line && (entry.file == file || entry.file.ends_with(&suffix))
+        };
+        let mut addresses = vec![];
+        for (i, entry) in self.lines.iter().enumerate() {
+            // Only the first row of a run of rows for the same line starts it.
+            if matches(entry) && (i == 0 || !matches(&self.lines[i - 1])) {
+                addresses.push(entry.address);
+            }
+        }
+        addresses
+    }
+}
+
+/// Read the DWARF line tables of an executable, sorted by address.
+pub fn load_executable_line_table(file: &[u8]) -> Vec<LineEntry> {
+    if file.len() >= 4 && file[0..4] == [0x7F, 0x45, 0x4C, 0x46] {
+        load_elf_line_table(file)
+    } else {
+        vec![]
+    }
+}
+
+pub fn load_elf_line_table(file: &[u8]) -> Vec<LineEntry> {
+    let file = ElfBytes::<AnyEndian>::minimal_parse(file).unwrap();
+    let endian = match file.ehdr.endianness {
+        AnyEndian::Little => RunTimeEndian::Little,
+        AnyEndian::Big => RunTimeEndian::Big,
+    };
+    let load_section = |id: SectionId| -> Result<EndianSlice<RunTimeEndian>, gimli::Error> {
+        let data = match file.section_header_by_name(id.name()) {
+            Ok(Some(header)) => match file.section_data(&header) {
+                Ok((data, None)) => data,
+                _ => &[],
+            },
+            _ => &[],
+        };
+        Ok(EndianSlice::new(data, endian))
+    };
+    // Malformed debug information is ignored rather than making the program unloadable.
+    let mut lines = read_line_table(load_section).unwrap_or_default();
+    lines.sort_by_key(|entry| entry.address);
+    lines
+}
+
+fn read_line_table<'a, F>(load_section: F) -> Result<Vec<LineEntry>, gimli::Error>
+where
+    F: FnMut(SectionId) -> Result<EndianSlice<'a, RunTimeEndian>, gimli::Error>,
+{
+    let dwarf = gimli::Dwarf::load(load_section)?;
+    let mut lines = vec![];
+    let mut units = dwarf.units();
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+        let Some(program) = unit.line_program.clone() else {
+            continue;
+        };
+        let mut rows = program.rows();
+        while let Some((header, row)) = rows.next_row()? {
+            if row.end_sequence() || !row.is_stmt() {
+                continue;
+            }
+            let (Some(file), Some(line)) = (row.file(header), row.line()) else {
+                continue;
+            };
+            let mut path = dwarf
+                .attr_string(&unit, file.path_name())?
+                .to_string_lossy()
+                .to_string();
+            if !path.starts_with('/') {
+                if let Some(directory) = file.directory(header) {
+                    let directory = dwarf.attr_string(&unit, directory)?.to_string_lossy();
+                    if !directory.is_empty() {
+                        path = format!("{}/{}", directory, path);
+                    }
+                }
+            }
+            lines.push(LineEntry {
+                address: row.address() as u32,
+                file: path,
+                line: line.get() as u32,
+            });
+        }
+    }
+    Ok(lines)
+}
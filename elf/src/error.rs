@@ -0,0 +1,71 @@
+use alloc::string::String;
+use core::fmt;
+
+/// Why an executable couldn't be loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ElfLoadError {
+    /// The file is not valid ELF.
+    Parse(String),
+    /// A section is compressed, which isn't supported.
+    CompressedSection(String),
+    /// A flat binary, or code in an executable, ends partway through an instruction.
+    PartialInstruction { length: u64 },
+    /// A segment or section of code doesn't start at an instruction boundary.
+    UnalignedText { section: String, address: u64 },
+    /// A segment or section extends past the 32-bit address space.
+    AddressOutOfRange { section: String, end: u64 },
+    /// Two segments or sections share addresses in the same address space.
+    OverlappingSections(String, String),
+    /// The entry point isn't an instruction boundary.
+    UnalignedEntry { address: u64 },
+    /// An instruction has an opcode that the machine doesn't implement.
+    UnknownOpcode { pc: u32, opcode: u32 },
+    /// The executable has no code to run.
+    NoText,
+}
+
+impl fmt::Display for ElfLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElfLoadError::Parse(message) => write!(f, "invalid ELF file: {}", message),
+            ElfLoadError::CompressedSection(section) => {
+                write!(f, "section `{}` is compressed", section)
+            }
+            ElfLoadError::PartialInstruction { length } => {
+                write!(
+                    f,
+                    "{} bytes of code is not a whole number of instructions",
+                    length
+                )
+            }
+            ElfLoadError::UnalignedText { section, address } => write!(
+                f,
+                "`{}` starts at {:#x}, which is not an instruction boundary",
+                section, address
+            ),
+            ElfLoadError::AddressOutOfRange { section, end } => write!(
+                f,
+                "`{}` ends at {:#x}, past the 32-bit address space",
+                section, end
+            ),
+            ElfLoadError::OverlappingSections(first, second) => {
+                write!(f, "`{}` and `{}` overlap", first, second)
+            }
+            ElfLoadError::UnalignedEntry { address } => write!(
+                f,
+                "the entry point {:#x} is not an instruction boundary",
+                address
+            ),
+            ElfLoadError::UnknownOpcode { pc, opcode } => {
+                write!(f, "instruction {} has unknown opcode {}", pc, opcode)
+            }
+            ElfLoadError::NoText => write!(f, "no code to run"),
+        }
+    }
+}
+
+impl From<elf::ParseError> for ElfLoadError {
+    fn from(error: elf::ParseError) -> Self {
+        ElfLoadError::Parse(alloc::format!("{}", error))
+    }
+}
@@ -3,100 +3,277 @@
 extern crate alloc;
 
 use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
 use elf::abi;
 use elf::endian::AnyEndian;
-use elf::section::SectionHeader;
 use elf::ElfBytes;
 use valida_machine::{ProgramROM, Word, INSTRUCTION_ELEMENTS};
+use valida_opcodes::Opcode;
+
+pub mod debug_info;
+mod error;
+
+pub use debug_info::{DebugInfo, LineEntry};
+pub use error::ElfLoadError;
+
+const ELF_MAGIC: [u8; 4] = [0x7F, b'E', b'L', b'F'];
+const INSTRUCTION_BYTES: usize = INSTRUCTION_ELEMENTS * 4;
 
 pub struct Program {
     pub code: ProgramROM<i32>,
     pub data: BTreeMap<u32, Word<u8>>,
     pub initial_program_counter: u32,
+    /// The function symbols, sorted by address. Flat binaries have none.
+    pub symbols: Vec<Symbol>,
+}
+
+/// A function symbol from an ELF symbol table. Addresses are in bytes, so the program
+/// counter of the first instruction is `address / BYTES_PER_INSTR`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub address: u32,
+    pub size: u32,
+}
+
+impl Symbol {
+    pub fn contains(&self, address: u32) -> bool {
+        self.address <= address && address < self.address + self.size.max(1)
+    }
+}
+
+/// Read the function symbols of an executable, sorted by address. Flat binaries have no
+/// symbol table, so an empty list is returned for them.
+pub fn load_executable_symbols(file: &[u8]) -> Vec<Symbol> {
+    if file.starts_with(&ELF_MAGIC) {
+        load_elf_symbols(file)
+    } else {
+        vec![]
+    }
+}
+
+/// Read the function symbols of an ELF file, or none if it isn't valid ELF.
+pub fn load_elf_symbols(file: &[u8]) -> Vec<Symbol> {
+    ElfBytes::<AnyEndian>::minimal_parse(file)
+        .map_err(ElfLoadError::from)
+        .and_then(|file| function_symbols(&file))
+        .unwrap_or_default()
 }
 
-pub fn load_executable_file(file: Vec<u8>) -> Program {
-    if file[0] == 0x7F && file[1] == 0x45 && file[2] == 0x4C && file[3] == 0x46 {
+/// The function symbols of a parsed ELF file, sorted by address.
+fn function_symbols(file: &ElfBytes<AnyEndian>) -> Result<Vec<Symbol>, ElfLoadError> {
+    let mut symbols: Vec<Symbol> = vec![];
+    if let Some((symbol_table, string_table)) = file.symbol_table()? {
+        for symbol in symbol_table.iter() {
+            if symbol.st_symtype() != abi::STT_FUNC || symbol.st_name == 0 {
+                continue;
+            }
+            symbols.push(Symbol {
+                name: string_table.get(symbol.st_name as usize)?.to_string(),
+                address: symbol.st_value as u32,
+                size: symbol.st_size as u32,
+            });
+        }
+    }
+    symbols.sort_by_key(|symbol| symbol.address);
+    Ok(symbols)
+}
+
+pub fn load_executable_file(file: Vec<u8>) -> Result<Program, ElfLoadError> {
+    if file.starts_with(&ELF_MAGIC) {
         load_elf_object_file(file)
     } else {
-        Program {
+        if !file.len().is_multiple_of(INSTRUCTION_BYTES) {
+            return Err(ElfLoadError::PartialInstruction {
+                length: file.len() as u64,
+            });
+        }
+        check_opcodes(&file, 0)?;
+        Ok(Program {
             code: ProgramROM::from_machine_code(file.as_slice()),
             data: BTreeMap::new(),
             initial_program_counter: 0,
+            symbols: vec![],
+        })
+    }
+}
+
+/// Check that every instruction of machine code starting at `first_pc` has an opcode the
+/// machine implements.
+fn check_opcodes(code: &[u8], first_pc: u32) -> Result<(), ElfLoadError> {
+    for (index, instruction) in code.chunks_exact(INSTRUCTION_BYTES).enumerate() {
+        let opcode = u32::from_le_bytes(instruction[..4].try_into().unwrap());
+        if Opcode::try_from(opcode).is_err() {
+            return Err(ElfLoadError::UnknownOpcode {
+                pc: first_pc + index as u32,
+                opcode,
+            });
         }
     }
+    Ok(())
 }
 
-pub fn load_elf_object_file(file: Vec<u8>) -> Program {
-    let file = ElfBytes::<AnyEndian>::minimal_parse(file.as_slice()).unwrap();
-    let mut data_sections: Vec<(SectionHeader, &[u8])> = vec![];
-    let mut bss_sections: Vec<SectionHeader> = vec![];
-    let mut text_sections: Vec<(SectionHeader, &[u8])> = vec![];
-    for section_header in file.section_headers().unwrap().iter() {
-        let is_data: bool = section_header.sh_type == abi::SHT_PROGBITS
-            && section_header.sh_flags == (abi::SHF_ALLOC | abi::SHF_WRITE).into();
-        let is_rodata: bool = section_header.sh_type == abi::SHT_PROGBITS
-            && (section_header.sh_flags == abi::SHF_ALLOC.into()
-                || section_header.sh_flags == 0x32); // TODO: what is 0x32?
-        let is_bss: bool = section_header.sh_type == abi::SHT_NOBITS
-            && section_header.sh_flags == (abi::SHF_ALLOC | abi::SHF_WRITE).into();
-        let is_text: bool = section_header.sh_type == abi::SHT_PROGBITS
-            && section_header.sh_flags == (abi::SHF_ALLOC | abi::SHF_EXECINSTR).into();
-        if is_data || is_rodata || is_text {
-            let section_data = file.section_data(&section_header).unwrap();
-            match section_data {
-                (section_data, None) => {
-                    if is_data || is_rodata {
-                        data_sections.push((section_header, section_data));
-                    } else if is_text {
-                        text_sections.push((section_header, section_data));
-                    }
-                }
-                _ => panic!("unsupported: compressed ELF section data"),
-            }
-        } else if is_bss {
-            bss_sections.push(section_header);
+/// Check that no two of the named address ranges overlap.
+fn check_overlaps(mut ranges: Vec<(String, u64, u64)>) -> Result<(), ElfLoadError> {
+    ranges.retain(|(_, start, end)| start < end);
+    ranges.sort_by_key(|&(_, start, _)| start);
+    for pair in ranges.windows(2) {
+        let [(first, _, end), (second, start, _)] = pair else {
+            unreachable!()
+        };
+        if start < end {
+            return Err(ElfLoadError::OverlappingSections(
+                first.clone(),
+                second.clone(),
+            ));
         }
     }
-    let initial_program_counter = text_sections
-        .iter()
-        .map(|(section_header, _)| section_header.sh_addr / ((INSTRUCTION_ELEMENTS * 4) as u64))
-        .reduce(|a, b| a.min(b))
-        .unwrap();
-    let code_size = text_sections
-        .iter()
-        .map(|(section_header, _)| section_header.sh_addr + section_header.sh_size)
-        .fold(0, |a, b| a.max(b));
-    let mut code: Vec<u8> = vec![0; code_size as usize];
-    for (section_header, section_data) in text_sections {
-        for i in 0..section_header.sh_size as usize {
-            code[i + section_header.sh_addr as usize] = section_data[i];
+    Ok(())
+}
+
+/// A part of an executable that is loaded into memory: a `PT_LOAD` segment, or an allocated
+/// section of a file without program headers.
+struct Region<'a> {
+    name: String,
+    address: u64,
+    /// The contents from the file, which may be shorter than the region. The rest of the
+    /// region, such as `.bss`, starts out zero.
+    bytes: &'a [u8],
+    size: u64,
+    executable: bool,
+}
+
+/// The `PT_LOAD` segments of an executable.
+fn load_segments<'a>(file: &ElfBytes<'a, AnyEndian>) -> Result<Vec<Region<'a>>, ElfLoadError> {
+    let mut regions = vec![];
+    let Some(segments) = file.segments() else {
+        return Ok(regions);
+    };
+    for (index, segment) in segments.iter().enumerate() {
+        if segment.p_type != abi::PT_LOAD {
+            continue;
         }
+        regions.push(Region {
+            name: format!("segment {}", index),
+            address: segment.p_vaddr,
+            bytes: file.segment_data(&segment)?,
+            size: segment.p_memsz.max(segment.p_filesz),
+            executable: segment.p_flags & abi::PF_X != 0,
+        });
+    }
+    Ok(regions)
+}
+
+/// The sections of an executable that occupy memory while it runs, for files without
+/// program headers.
+fn load_sections<'a>(file: &ElfBytes<'a, AnyEndian>) -> Result<Vec<Region<'a>>, ElfLoadError> {
+    let mut regions = vec![];
+    let (Some(section_headers), names) = file.section_headers_with_strtab()? else {
+        return Ok(regions);
+    };
+    for section_header in section_headers.iter() {
+        if section_header.sh_flags & abi::SHF_ALLOC as u64 == 0 {
+            continue;
+        }
+        let name = names
+            .as_ref()
+            .and_then(|names| names.get(section_header.sh_name as usize).ok())
+            .unwrap_or_default()
+            .to_string();
+        let bytes = match section_header.sh_type {
+            abi::SHT_NOBITS => &[][..],
+            abi::SHT_PROGBITS => match file.section_data(&section_header)? {
+                (section_data, None) => section_data,
+                _ => return Err(ElfLoadError::CompressedSection(name)),
+            },
+            _ => continue,
+        };
+        // Anything else is data, read-only or not, including the string literals that
+        // compilers put in SHF_MERGE | SHF_STRINGS sections
+        let executable = section_header.sh_flags & abi::SHF_EXECINSTR as u64 != 0;
+        regions.push(Region {
+            name,
+            address: section_header.sh_addr,
+            bytes,
+            size: section_header.sh_size,
+            executable,
+        });
+    }
+    Ok(regions)
+}
+
+/// Load an ELF executable's `PT_LOAD` segments, or its allocated sections if it has no
+/// program headers, and start execution at its entry point.
+pub fn load_elf_object_file(file: Vec<u8>) -> Result<Program, ElfLoadError> {
+    let file = ElfBytes::<AnyEndian>::minimal_parse(file.as_slice())?;
+    let mut regions = load_segments(&file)?;
+    if regions.is_empty() {
+        regions = load_sections(&file)?;
     }
+
+    let mut code: Vec<u8> = vec![];
     let mut data: BTreeMap<u32, Word<u8>> = BTreeMap::new();
-    for (section_header, section_data) in data_sections {
-        let mut section_data = Vec::from(section_data);
-        while section_data.len() % 4 != 0 {
-            section_data.push(0);
+    // Code and data are in separate address spaces, so only regions in the same space
+    // can overlap
+    let mut code_ranges = vec![];
+    let mut data_ranges = vec![];
+    for region in regions {
+        let start = region.address;
+        let end = start.saturating_add(region.size);
+        if end > 1 << 32 {
+            return Err(ElfLoadError::AddressOutOfRange {
+                section: region.name,
+                end,
+            });
+        }
+        if !region.executable {
+            let mut bytes = region.bytes.to_vec();
+            bytes.resize((region.size as usize).next_multiple_of(4), 0);
+            for (i, word) in bytes.chunks_exact(4).enumerate() {
+                data.insert(
+                    start as u32 + (i * 4) as u32,
+                    Word(word.try_into().unwrap()),
+                );
+            }
+            data_ranges.push((region.name, start, end));
+            continue;
+        }
+        if !start.is_multiple_of(INSTRUCTION_BYTES as u64) {
+            return Err(ElfLoadError::UnalignedText {
+                section: region.name,
+                address: start,
+            });
         }
-        for i in 0..(section_data.len() / 4) as usize {
-            data.insert(
-                <u64 as TryInto<u32>>::try_into(section_header.sh_addr).unwrap()
-                    + <usize as TryInto<u32>>::try_into(i * 4).unwrap(),
-                Word([
-                    section_data[i * 4],
-                    section_data[i * 4 + 1],
-                    section_data[i * 4 + 2],
-                    section_data[i * 4 + 3],
-                ]),
-            );
+        if !region.bytes.len().is_multiple_of(INSTRUCTION_BYTES) {
+            return Err(ElfLoadError::PartialInstruction {
+                length: region.bytes.len() as u64,
+            });
         }
+        check_opcodes(region.bytes, (start / INSTRUCTION_BYTES as u64) as u32)?;
+        let offset = start as usize;
+        if code.len() < offset + region.bytes.len() {
+            code.resize(offset + region.bytes.len(), 0);
+        }
+        code[offset..offset + region.bytes.len()].copy_from_slice(region.bytes);
+        code_ranges.push((region.name, start, end));
+    }
+    if code_ranges.is_empty() {
+        return Err(ElfLoadError::NoText);
     }
-    Program {
+    check_overlaps(code_ranges)?;
+    check_overlaps(data_ranges)?;
+
+    let entry = file.ehdr.e_entry;
+    if !entry.is_multiple_of(INSTRUCTION_BYTES as u64) {
+        return Err(ElfLoadError::UnalignedEntry { address: entry });
+    }
+    Ok(Program {
         code: ProgramROM::from_machine_code(code.as_slice()),
         data,
-        initial_program_counter: <u64 as TryInto<u32>>::try_into(initial_program_counter).unwrap(),
-    }
+        initial_program_counter: (entry / INSTRUCTION_BYTES as u64) as u32,
+        symbols: function_symbols(&file)?,
+    })
 }
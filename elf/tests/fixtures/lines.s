@@ -0,0 +1,54 @@
+# Valida machine code with DWARF line tables, for test_debug_info.rs. The instructions
+# are written out as words, since the Valida assembler does not emit debug information.
+# Rebuild lines.elf with:
+#
+#   llvm-mc -triple=i386-unknown-elf -filetype=obj -o lines.o lines.s
+#   ld -m elf_i386 -n -Ttext=0 -e main --build-id=none -o lines.elf lines.o
+
+	.file	1 "src/main.c"
+	.file	2 "src/square.c"
+	.text
+	.globl	main
+	.type	main, @function
+main:
+	.loc	1 3 0
+	.long	7, -16, 0, 0, 0, 3		# imm32	-16(fp), 0, 0, 0, 3
+	.loc	1 4 0
+	.long	3, -24, square, -24, 0, 0	# jal	-24(fp), square, -24
+	.loc	1 5 0
+	.long	100, -12, -20, 0, 0, 1		# addi	-12(fp), -20(fp), 0
+	.loc	1 4 0
+	.long	300, 0, -12, 0, 0, 1		# write	0, -12, 0, 0, 1
+	.loc	1 6 0
+	.long	8, 0, 0, 0, 0, 0		# stop
+	.size	main, . - main
+
+	.type	square, @function
+square:
+	.loc	2 2 0
+	.long	102, -4, 12, 12, 0, 0		# mul	-4(fp), 12(fp), 12(fp)
+	.loc	2 2 0
+	.long	100, 4, -4, 0, 0, 1		# addi	4(fp), -4(fp), 0
+	.loc	2 3 0
+	.long	4, -4, 0, 8, 0, 0		# jalv	-4(fp), 0(fp), 8(fp)
+	.size	square, . - square
+
+# A compile unit that points at the line table, which is all the debugger reads
+	.section	.debug_abbrev,"",@progbits
+	.uleb128	1		# abbreviation code
+	.uleb128	0x11		# DW_TAG_compile_unit
+	.byte	0			# DW_CHILDREN_no
+	.uleb128	0x10		# DW_AT_stmt_list
+	.uleb128	0x17		# DW_FORM_sec_offset
+	.byte	0, 0
+	.byte	0
+
+	.section	.debug_info,"",@progbits
+	.long	.Linfo_end - .Linfo_start	# unit length
+.Linfo_start:
+	.short	4			# DWARF version
+	.long	.debug_abbrev		# abbreviation table
+	.byte	4			# address size
+	.uleb128	1		# DW_TAG_compile_unit
+	.long	.debug_line		# DW_AT_stmt_list
+.Linfo_end:
@@ -0,0 +1,61 @@
+use valida_elf::{DebugInfo, LineEntry};
+
+/// `main` in `src/main.c` calls `square` in `src/square.c`. See `fixtures/lines.s`.
+const LINES_ELF: &[u8] = include_bytes!("fixtures/lines.elf");
+
+fn entry(address: u32, file: &str, line: u32) -> LineEntry {
+    LineEntry {
+        address,
+        file: file.to_string(),
+        line,
+    }
+}
+
+#[test]
+fn loads_line_table() {
+    let debug_info = DebugInfo::load(LINES_ELF);
+    assert_eq!(
+        debug_info.lines,
+        vec![
+            entry(0, "src/main.c", 3),
+            entry(24, "src/main.c", 4),
+            entry(48, "src/main.c", 5),
+            entry(72, "src/main.c", 4),
+            entry(96, "src/main.c", 6),
+            entry(120, "src/square.c", 2),
+            entry(144, "src/square.c", 2),
+            entry(168, "src/square.c", 3),
+        ]
+    );
+    assert_eq!(debug_info.symbol("square").unwrap().address, 120);
+    assert_eq!(debug_info.function_at(150).unwrap().name, "square");
+}
+
+#[test]
+fn line_at() {
+    let debug_info = DebugInfo::load(LINES_ELF);
+    assert_eq!(debug_info.line_at(0), Some(&entry(0, "src/main.c", 3)));
+    // An address inside an instruction belongs to the row before it
+    assert_eq!(debug_info.line_at(30), Some(&entry(24, "src/main.c", 4)));
+    assert_eq!(
+        debug_info.line_at(120),
+        Some(&entry(120, "src/square.c", 2))
+    );
+
+    let flat = DebugInfo::default();
+    assert_eq!(flat.line_at(0), None);
+}
+
+#[test]
+fn line_addresses() {
+    let debug_info = DebugInfo::load(LINES_ELF);
+    // A line whose code is split in two has two starts
+    assert_eq!(debug_info.line_addresses("src/main.c", 4), vec![24, 72]);
+    assert_eq!(debug_info.line_addresses("main.c", 4), vec![24, 72]);
+    // Consecutive rows for the same line start it once
+    assert_eq!(debug_info.line_addresses("square.c", 2), vec![120]);
+    // The file has to match whole path components
+    assert!(debug_info.line_addresses("ain.c", 4).is_empty());
+    assert!(debug_info.line_addresses("main.c", 7).is_empty());
+    assert!(debug_info.line_addresses("other.c", 3).is_empty());
+}
@@ -0,0 +1,167 @@
+use valida_assembler::{assemble_elf, DATA_START};
+use valida_elf::{load_executable_file, ElfLoadError};
+
+const PROGRAM: &str = r#"	.rodata
+message:
+	.asciz	"hi"
+	.data
+counter:
+	.word	7
+	.text
+	stop
+"#;
+
+/// The section headers that the assembler writes, and the fields of a header.
+const TEXT: usize = 1;
+const RODATA: usize = 2;
+const DATA: usize = 3;
+const FLAGS: usize = 8;
+const ADDRESS: usize = 12;
+const SIZE: usize = 20;
+
+/// Fields of a program header, and where the file header holds the entry point.
+const SEGMENT_ADDRESS: usize = 8;
+const MEMORY_SIZE: usize = 20;
+const ENTRY: usize = 24;
+
+/// Replace a field of a section header of an ELF file.
+fn patch(file: &[u8], section: usize, field: usize, value: u32) -> Vec<u8> {
+    let mut file = file.to_vec();
+    let headers = u32::from_le_bytes(file[32..36].try_into().unwrap()) as usize;
+    let offset = headers + 40 * section + field;
+    file[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    file
+}
+
+/// Replace a field of a program header of an ELF file.
+fn patch_segment(file: &[u8], segment: usize, field: usize, value: u32) -> Vec<u8> {
+    let mut file = file.to_vec();
+    let headers = u32::from_le_bytes(file[28..32].try_into().unwrap()) as usize;
+    let offset = headers + 32 * segment + field;
+    file[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    file
+}
+
+/// Drop the program headers of an ELF file, so that it is loaded by section.
+fn without_segments(mut file: Vec<u8>) -> Vec<u8> {
+    file[44..46].copy_from_slice(&0u16.to_le_bytes());
+    file
+}
+
+#[test]
+fn loads_merged_strings_as_data() {
+    // Compilers put string literals in SHF_ALLOC | SHF_MERGE | SHF_STRINGS sections
+    let file = without_segments(assemble_elf(PROGRAM).unwrap());
+    let file = patch(&file, RODATA, FLAGS, 0x32);
+    let program = load_executable_file(file).unwrap();
+    assert_eq!(program.data[&DATA_START].0, [0, 0, b'i', b'h']);
+    assert_eq!(program.data[&(DATA_START + 4)].0, [0, 0, 0, 7]);
+}
+
+#[test]
+fn rejects_invalid_executables() {
+    let error = |file: Vec<u8>| load_executable_file(file).err();
+
+    assert_eq!(
+        error(vec![0x7F]),
+        Some(ElfLoadError::PartialInstruction { length: 1 })
+    );
+    let mut unknown = vec![0; 24];
+    unknown[..4].copy_from_slice(&0xFFFFu32.to_le_bytes());
+    assert_eq!(
+        error(unknown),
+        Some(ElfLoadError::UnknownOpcode {
+            pc: 0,
+            opcode: 0xFFFF
+        })
+    );
+    assert!(matches!(
+        error(b"\x7FELF".to_vec()),
+        Some(ElfLoadError::Parse(_))
+    ));
+
+    let file = without_segments(assemble_elf(PROGRAM).unwrap());
+    assert_eq!(
+        error(patch(&file, TEXT, ADDRESS, 4)),
+        Some(ElfLoadError::UnalignedText {
+            section: ".text".to_string(),
+            address: 4
+        })
+    );
+    assert_eq!(
+        error(patch(&file, TEXT, SIZE, 20)),
+        Some(ElfLoadError::PartialInstruction { length: 20 })
+    );
+    assert_eq!(
+        error(patch(&file, DATA, ADDRESS, DATA_START)),
+        Some(ElfLoadError::OverlappingSections(
+            ".rodata".to_string(),
+            ".data".to_string()
+        ))
+    );
+    assert_eq!(
+        error(patch(&file, DATA, ADDRESS, u32::MAX - 1)),
+        Some(ElfLoadError::AddressOutOfRange {
+            section: ".data".to_string(),
+            end: u32::MAX as u64 + 3
+        })
+    );
+}
+
+#[test]
+fn loads_segments() {
+    let file = assemble_elf(
+        "	.globl	_start
+helper:
+	ret
+_start:
+	call	helper, -16
+	stop
+	.data
+counter:
+	.word	7
+	.bss
+buffer:
+	.zero	8
+",
+    )
+    .unwrap();
+    let program = load_executable_file(file.clone()).unwrap();
+    assert_eq!(program.code.0.len(), 4);
+    assert_eq!(program.initial_program_counter, 1);
+    let names: Vec<&str> = program.symbols.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["helper", "_start"]);
+    assert_eq!(program.symbols[1].address, 24);
+
+    // `.bss` starts out zero, so its words are part of the initial memory
+    assert_eq!(program.data[&DATA_START].0, [0, 0, 0, 7]);
+    assert_eq!(program.data[&(DATA_START + 4)].0, [0; 4]);
+    assert_eq!(program.data[&(DATA_START + 8)].0, [0; 4]);
+    assert_eq!(program.data.len(), 3);
+
+    // The section headers are ignored when there are segments
+    let program = load_executable_file(patch(&file, TEXT, ADDRESS, 4)).unwrap();
+    assert_eq!(program.initial_program_counter, 1);
+
+    let error = |file: Vec<u8>| load_executable_file(file).err();
+    let mut unaligned = file.clone();
+    unaligned[ENTRY..ENTRY + 4].copy_from_slice(&30u32.to_le_bytes());
+    assert_eq!(
+        error(unaligned),
+        Some(ElfLoadError::UnalignedEntry { address: 30 })
+    );
+    assert_eq!(
+        error(patch_segment(&file, 0, SEGMENT_ADDRESS, 4)),
+        Some(ElfLoadError::UnalignedText {
+            section: "segment 0".to_string(),
+            address: 4
+        })
+    );
+    assert_eq!(
+        error(patch_segment(&file, 1, MEMORY_SIZE, 8)),
+        Some(ElfLoadError::OverlappingSections(
+            "segment 1".to_string(),
+            "segment 2".to_string()
+        ))
+    );
+}
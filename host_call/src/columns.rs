@@ -0,0 +1,35 @@
+use core::borrow::{Borrow, BorrowMut};
+use core::mem::{size_of, transmute};
+use valida_derive::AlignedBorrow;
+use valida_machine::Word;
+use valida_util::indices_arr;
+
+#[derive(AlignedBorrow, Default)]
+pub struct HostCallCols<T> {
+    /// CPU clock of the `ECALL` instruction
+    pub clk: T,
+
+    /// Whether this row is the call itself, received from the CPU
+    pub is_call: T,
+    /// Whether this row is a memory read made by the call
+    pub is_read: T,
+    /// Whether this row is a memory write made by the call
+    pub is_write: T,
+
+    /// Call number, first argument and result, as seen by the CPU (call rows)
+    pub number: Word<T>,
+    pub arg: Word<T>,
+    pub result: Word<T>,
+
+    /// Address and value of a memory operation (read and write rows)
+    pub addr: T,
+    pub value: Word<T>,
+}
+
+pub const NUM_HOST_CALL_COLS: usize = size_of::<HostCallCols<u8>>();
+pub const HOST_CALL_COL_MAP: HostCallCols<usize> = make_col_map();
+
+const fn make_col_map() -> HostCallCols<usize> {
+    let indices_arr = indices_arr::<NUM_HOST_CALL_COLS>();
+    unsafe { transmute::<[usize; NUM_HOST_CALL_COLS], HostCallCols<usize>>(indices_arr) }
+}
@@ -0,0 +1,427 @@
+extern crate alloc;
+
+use crate::columns::{HostCallCols, HOST_CALL_COL_MAP, NUM_HOST_CALL_COLS};
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::transmute;
+use std::io::{stderr, stdout, Write};
+use valida_bus::{MachineWithGeneralBus, MachineWithMemBus};
+use valida_cpu::MachineWithCpuChip;
+use valida_machine::{
+    addr_of_word, index_of_byte, instructions, Chip, Instruction, Interaction, Operands, Word,
+};
+use valida_memory::MemoryChip;
+use valida_opcodes::ECALL;
+
+use p3_air::VirtualPairCol;
+use p3_field::{AbstractField, Field, PrimeField};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_maybe_rayon::prelude::*;
+use valida_machine::StarkConfig;
+use valida_util::pad_to_power_of_two;
+
+pub mod columns;
+pub mod stark;
+
+/// Call numbers understood by `DefaultHostCallHandler`.
+pub mod calls {
+    /// `debug_log(ptr, len)`: print `len` bytes at `ptr` to the host's stderr.
+    pub const DEBUG_LOG: u32 = 0;
+    /// `exit(code)`: stop execution with an exit code.
+    pub const EXIT: u32 = 1;
+    /// `input_len()`: the number of input bytes not read yet.
+    pub const INPUT_LEN: u32 = 2;
+    /// `read(ptr, len)`: copy up to `len` input bytes to `ptr`, returning how many were copied.
+    pub const READ: u32 = 3;
+    /// `write(fd, ptr, len)`: write `len` bytes at `ptr` to the host's stdout (fd 1) or
+    /// stderr (fd 2), returning `len`.
+    pub const WRITE: u32 = 4;
+}
+
+/// Results of failed calls to `DefaultHostCallHandler`: negated errno values, as in Linux
+/// system calls.
+pub mod errors {
+    /// The host could not write the output.
+    pub const EIO: u32 = -5i32 as u32;
+    /// `write` was passed a file descriptor other than stdout and stderr.
+    pub const EBADF: u32 = -9i32 as u32;
+    /// The call number is not recognized.
+    pub const ENOSYS: u32 = -38i32 as u32;
+}
+
+/// What the guest sees once a host call returns.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HostCallOutcome {
+    /// Write the value to the destination operand and continue.
+    Return(u32),
+    /// Write the exit code to the destination operand and stop execution.
+    Exit(u32),
+}
+
+/// Host-side implementation of the calls made with `ECALL`.
+pub trait HostCallHandler: Send + Sync {
+    fn handle(&mut self, call: &mut HostCall) -> HostCallOutcome;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MemoryEffect {
+    Read(u32, Word<u8>),
+    Write(u32, Word<u8>),
+}
+
+/// A host call as executed, with every memory operation the handler made.
+#[derive(Clone, Debug)]
+pub struct HostCallRecord {
+    pub clk: u32,
+    pub number: Word<u8>,
+    pub arg: Word<u8>,
+    pub result: Word<u8>,
+    pub effects: Vec<MemoryEffect>,
+}
+
+/// The guest state available to a `HostCallHandler`.
+///
+/// `ECALL a(fp), b(fp), c(fp)` reads the call number from `b(fp)` and passes the argument
+/// words at `c(fp)`, `c+4(fp)`, and so on; the result is written to `a(fp)`. All memory
+/// the handler touches goes through this struct, so that it is recorded in the trace.
+pub struct HostCall<'a> {
+    clk: u32,
+    number: u32,
+    first_arg: u32,
+    args_addr: u32,
+    mem: &'a mut MemoryChip,
+    effects: Vec<MemoryEffect>,
+}
+
+impl<'a> HostCall<'a> {
+    pub fn new(
+        clk: u32,
+        number: u32,
+        first_arg: u32,
+        args_addr: u32,
+        mem: &'a mut MemoryChip,
+    ) -> Self {
+        Self {
+            clk,
+            number,
+            first_arg,
+            args_addr,
+            mem,
+            effects: vec![],
+        }
+    }
+
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    /// The `i`-th argument word. The first one was already read by the CPU.
+    pub fn arg(&mut self, i: u32) -> u32 {
+        if i == 0 {
+            self.first_arg
+        } else {
+            self.read_word(self.args_addr.wrapping_add(i.wrapping_mul(4)))
+                .into()
+        }
+    }
+
+    pub fn read_word(&mut self, addr: u32) -> Word<u8> {
+        let value = self.mem.coprocessor_read(self.clk, addr);
+        self.effects.push(MemoryEffect::Read(addr, value));
+        value
+    }
+
+    pub fn write_word(&mut self, addr: u32, value: Word<u8>) {
+        self.mem.coprocessor_write(self.clk, addr, value);
+        self.effects.push(MemoryEffect::Write(addr, value));
+    }
+
+    /// Read bytes from memory. The addresses wrap around at the end of the address space.
+    pub fn read_bytes(&mut self, addr: u32, len: u32) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(len as usize);
+        let mut word_addr = None;
+        let mut word = Word::default();
+        for byte_addr in (0..len).map(|i| addr.wrapping_add(i)) {
+            if word_addr != Some(addr_of_word(byte_addr)) {
+                word_addr = Some(addr_of_word(byte_addr));
+                word = self.read_word(addr_of_word(byte_addr));
+            }
+            bytes.push(word[index_of_byte(byte_addr)]);
+        }
+        bytes
+    }
+
+    /// Write bytes to memory, preserving the other bytes of partially written words. As
+    /// with `read_bytes`, the addresses wrap around.
+    pub fn write_bytes(&mut self, addr: u32, bytes: &[u8]) {
+        let byte_addr = |i: usize| addr.wrapping_add(i as u32);
+        let mut i = 0;
+        while i < bytes.len() {
+            let word_addr = addr_of_word(byte_addr(i));
+            let mut word = self.read_word(word_addr);
+            while i < bytes.len() && addr_of_word(byte_addr(i)) == word_addr {
+                word[index_of_byte(byte_addr(i))] = bytes[i];
+                i += 1;
+            }
+            self.write_word(word_addr, word);
+        }
+    }
+
+    fn into_effects(self) -> Vec<MemoryEffect> {
+        self.effects
+    }
+}
+
+/// The handler used unless another one is installed: logging, exiting, reading from an
+/// input buffer, and writing to the host's stdout and stderr. Calls it does not support
+/// return one of the `errors` to the guest.
+#[derive(Default)]
+pub struct DefaultHostCallHandler {
+    pub input: Vec<u8>,
+    input_position: usize,
+}
+
+impl DefaultHostCallHandler {
+    pub fn new(input: Vec<u8>) -> Self {
+        Self {
+            input,
+            input_position: 0,
+        }
+    }
+}
+
+impl HostCallHandler for DefaultHostCallHandler {
+    fn handle(&mut self, call: &mut HostCall) -> HostCallOutcome {
+        match call.number() {
+            calls::DEBUG_LOG => {
+                let (ptr, len) = (call.arg(0), call.arg(1));
+                let bytes = call.read_bytes(ptr, len);
+                eprintln!("{}", String::from_utf8_lossy(&bytes));
+                HostCallOutcome::Return(0)
+            }
+            calls::EXIT => HostCallOutcome::Exit(call.arg(0)),
+            calls::INPUT_LEN => {
+                HostCallOutcome::Return((self.input.len() - self.input_position) as u32)
+            }
+            calls::READ => {
+                let (ptr, len) = (call.arg(0), call.arg(1));
+                let n = (len as usize).min(self.input.len() - self.input_position);
+                let end = self.input_position + n;
+                call.write_bytes(ptr, &self.input[self.input_position..end]);
+                self.input_position = end;
+                HostCallOutcome::Return(n as u32)
+            }
+            calls::WRITE => {
+                let (fd, ptr, len) = (call.arg(0), call.arg(1), call.arg(2));
+                if fd != 1 && fd != 2 {
+                    return HostCallOutcome::Return(errors::EBADF);
+                }
+                let bytes = call.read_bytes(ptr, len);
+                let written = match fd {
+                    1 => stdout().write_all(&bytes),
+                    _ => stderr().write_all(&bytes),
+                };
+                match written {
+                    Ok(()) => HostCallOutcome::Return(len),
+                    Err(_) => HostCallOutcome::Return(errors::EIO),
+                }
+            }
+            _ => HostCallOutcome::Return(errors::ENOSYS),
+        }
+    }
+}
+
+pub struct HostCallChip {
+    pub calls: Vec<HostCallRecord>,
+    /// Set once a host call has exited.
+    pub exit_code: Option<u32>,
+    handler: Option<Box<dyn HostCallHandler>>,
+}
+
+impl Default for HostCallChip {
+    fn default() -> Self {
+        Self {
+            calls: vec![],
+            exit_code: None,
+            handler: Some(Box::new(DefaultHostCallHandler::default())),
+        }
+    }
+}
+
+impl HostCallChip {
+    pub fn set_handler<H: HostCallHandler + 'static>(&mut self, handler: H) {
+        self.handler = Some(Box::new(handler));
+    }
+
+    /// Remove the recorded calls, keeping the handler.
+    pub fn reset(&mut self) {
+        self.calls.clear();
+        self.exit_code = None;
+    }
+}
+
+impl<M, SC> Chip<M, SC> for HostCallChip
+where
+    M: MachineWithGeneralBus<SC::Val> + MachineWithMemBus<SC::Val>,
+    SC: StarkConfig,
+{
+    fn generate_trace(&self, _machine: &M) -> RowMajorMatrix<SC::Val> {
+        let rows = self
+            .calls
+            .par_iter()
+            .map(|call| self.call_to_rows(call))
+            .collect::<Vec<_>>()
+            .concat();
+
+        let mut trace = RowMajorMatrix::new(
+            rows.into_iter().flatten().collect::<Vec<_>>(),
+            NUM_HOST_CALL_COLS,
+        );
+
+        pad_to_power_of_two::<NUM_HOST_CALL_COLS, SC::Val>(&mut trace.values);
+
+        trace
+    }
+
+    fn global_sends(&self, machine: &M) -> Vec<Interaction<SC::Val>> {
+        let is_read = VirtualPairCol::single_main(HOST_CALL_COL_MAP.is_read);
+        let clk = VirtualPairCol::single_main(HOST_CALL_COL_MAP.clk);
+        let addr = VirtualPairCol::single_main(HOST_CALL_COL_MAP.addr);
+        let is_static_initial = VirtualPairCol::constant(SC::Val::zero());
+        let value = HOST_CALL_COL_MAP.value.0.map(VirtualPairCol::single_main);
+
+        let mut fields = vec![is_read, clk, addr, is_static_initial];
+        fields.extend(value);
+
+        let send = Interaction {
+            fields,
+            count: VirtualPairCol::sum_main(vec![
+                HOST_CALL_COL_MAP.is_read,
+                HOST_CALL_COL_MAP.is_write,
+            ]),
+            argument_index: machine.mem_bus(),
+        };
+        vec![send]
+    }
+
+    fn global_receives(&self, machine: &M) -> Vec<Interaction<SC::Val>> {
+        let opcode = VirtualPairCol::constant(SC::Val::from_canonical_u32(ECALL));
+        let number = HOST_CALL_COL_MAP.number.0.map(VirtualPairCol::single_main);
+        let arg = HOST_CALL_COL_MAP.arg.0.map(VirtualPairCol::single_main);
+        let result = HOST_CALL_COL_MAP.result.0.map(VirtualPairCol::single_main);
+        let clk = VirtualPairCol::single_main(HOST_CALL_COL_MAP.clk);
+
+        let mut fields = vec![opcode];
+        fields.extend(number);
+        fields.extend(arg);
+        fields.extend(result);
+        fields.push(clk);
+
+        let receive = Interaction {
+            fields,
+            count: VirtualPairCol::single_main(HOST_CALL_COL_MAP.is_call),
+            argument_index: machine.general_bus(),
+        };
+        vec![receive]
+    }
+}
+
+impl HostCallChip {
+    fn call_to_rows<F: PrimeField>(&self, call: &HostCallRecord) -> Vec<[F; NUM_HOST_CALL_COLS]> {
+        let mut rows = Vec::with_capacity(call.effects.len() + 1);
+
+        let mut row = [F::zero(); NUM_HOST_CALL_COLS];
+        let cols: &mut HostCallCols<F> = unsafe { transmute(&mut row) };
+        cols.clk = F::from_canonical_u32(call.clk);
+        cols.is_call = F::one();
+        cols.number = call.number.transform(F::from_canonical_u8);
+        cols.arg = call.arg.transform(F::from_canonical_u8);
+        cols.result = call.result.transform(F::from_canonical_u8);
+        rows.push(row);
+
+        for effect in call.effects.iter() {
+            let mut row = [F::zero(); NUM_HOST_CALL_COLS];
+            let cols: &mut HostCallCols<F> = unsafe { transmute(&mut row) };
+            cols.clk = F::from_canonical_u32(call.clk);
+            let (addr, value) = match effect {
+                MemoryEffect::Read(addr, value) => {
+                    cols.is_read = F::one();
+                    (addr, value)
+                }
+                MemoryEffect::Write(addr, value) => {
+                    cols.is_write = F::one();
+                    (addr, value)
+                }
+            };
+            cols.addr = F::from_canonical_u32(*addr);
+            cols.value = value.transform(F::from_canonical_u8);
+            rows.push(row);
+        }
+
+        rows
+    }
+}
+
+pub trait MachineWithHostCallChip<F: Field>: MachineWithCpuChip<F> {
+    fn host_call(&self) -> &HostCallChip;
+    fn host_call_mut(&mut self) -> &mut HostCallChip;
+}
+
+instructions!(EcallInstruction);
+
+impl<M, F> Instruction<M, F> for EcallInstruction
+where
+    M: MachineWithHostCallChip<F>,
+    F: Field,
+{
+    const OPCODE: u32 = ECALL;
+
+    fn execute(state: &mut M, ops: Operands<i32>) {
+        let opcode = <Self as Instruction<M, F>>::OPCODE;
+        let clk = state.cpu().clock;
+        let pc = state.cpu().pc;
+        let fp = state.cpu().fp;
+        let number_addr = (fp as i32 + ops.b()) as u32;
+        let args_addr = (fp as i32 + ops.c()) as u32;
+        let write_addr = (fp as i32 + ops.a()) as u32;
+
+        let number = state
+            .mem_mut()
+            .read(clk, number_addr, true, pc, opcode, 0, "");
+        let arg = state
+            .mem_mut()
+            .read(clk, args_addr, true, pc, opcode, 1, "");
+
+        // The handler is taken out of the chip while it runs, since it needs to borrow
+        // the memory chip.
+        let mut handler = state
+            .host_call_mut()
+            .handler
+            .take()
+            .expect("host call handler is already running");
+        let mut call = HostCall::new(clk, number.into(), arg.into(), args_addr, state.mem_mut());
+        let outcome = handler.handle(&mut call);
+        let effects = call.into_effects();
+        state.host_call_mut().handler = Some(handler);
+
+        let result = match outcome {
+            HostCallOutcome::Return(value) => value,
+            HostCallOutcome::Exit(code) => {
+                state.host_call_mut().exit_code = Some(code);
+                code
+            }
+        };
+        let result = Word::from(result);
+        state.mem_mut().write(clk, write_addr, result, true);
+
+        state.host_call_mut().calls.push(HostCallRecord {
+            clk,
+            number,
+            arg,
+            result,
+            effects,
+        });
+        state.cpu_mut().push_bus_op_with_memory(None, opcode, ops);
+    }
+}
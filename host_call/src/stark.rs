@@ -0,0 +1,44 @@
+use crate::columns::{HostCallCols, NUM_HOST_CALL_COLS};
+use crate::HostCallChip;
+use core::borrow::Borrow;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::PrimeField;
+use p3_matrix::MatrixRowSlices;
+
+impl<F> BaseAir<F> for HostCallChip {
+    fn width(&self) -> usize {
+        NUM_HOST_CALL_COLS
+    }
+}
+
+impl<F, AB> Air<AB> for HostCallChip
+where
+    F: PrimeField,
+    AB: AirBuilder<F = F>,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local: &HostCallCols<AB::Var> = main.row_slice(0).borrow();
+        let next: &HostCallCols<AB::Var> = main.row_slice(1).borrow();
+
+        builder.assert_bool(local.is_call);
+        builder.assert_bool(local.is_read);
+        builder.assert_bool(local.is_write);
+        builder.assert_bool(local.is_call + local.is_read + local.is_write);
+
+        // Memory operations follow the call that made them, at the same clock cycle.
+        let next_is_mem = next.is_read + next.is_write;
+        builder
+            .when_first_row()
+            .assert_zero(local.is_read + local.is_write);
+        builder
+            .when_transition()
+            .when(next_is_mem.clone())
+            .assert_one(local.is_call + local.is_read + local.is_write);
+        builder
+            .when_transition()
+            .when(next_is_mem)
+            .assert_eq(next.clk, local.clk);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::round::R;
+use core::borrow::{Borrow, BorrowMut};
+use core::mem::{size_of, transmute};
+use valida_derive::AlignedBorrow;
+use valida_machine::Word;
+use valida_util::indices_arr;
+
+/// Keccak-f[1600] has 24 rounds, and each row of the trace computes one of them.
+pub const NUM_ROUNDS: usize = 24;
+/// Lanes are split into 16-bit limbs, which fit in the field.
+pub const BITS_PER_LIMB: usize = 16;
+pub const U64_LIMBS: usize = 64 / BITS_PER_LIMB;
+/// The 200-byte state occupies 50 memory cells.
+pub const STATE_WORDS: usize = 50;
+
+#[derive(AlignedBorrow)]
+pub struct KeccakCols<T> {
+    /// Which round this row computes
+    pub step_flags: [T; NUM_ROUNDS],
+
+    /// Whether this row belongs to a permutation made by an instruction, not padding
+    pub is_real: T,
+    /// Whether the state is read from memory (first round of a real permutation)
+    pub is_read: T,
+    /// Whether the state is written to memory (last round of a real permutation)
+    pub is_write: T,
+
+    /// CPU clock of the `KECCAKF` instruction
+    pub clk: T,
+    /// Addresses the state is read from and written to, as seen by the CPU
+    pub input_addr: Word<T>,
+    pub output_addr: Word<T>,
+    /// Address of the state read or written in this row
+    pub addr: T,
+    /// The state read or written in this row, as memory cells
+    pub state: [Word<T>; STATE_WORDS],
+
+    /// The input of the round, lanes indexed `[y][x]`
+    pub a: [[[T; U64_LIMBS]; 5]; 5],
+    /// The parity of each column of `A`, as bits
+    pub c: [[T; 64]; 5],
+    /// `C'[x, z] = xor(C[x, z], C[x - 1, z], C[x + 1, z - 1])`, as bits
+    pub c_prime: [[T; 64]; 5],
+    /// The state after theta, as bits
+    pub a_prime: [[[T; 64]; 5]; 5],
+    /// The state after rho, pi and chi
+    pub a_prime_prime: [[[T; U64_LIMBS]; 5]; 5],
+    /// Lane (0, 0) of `A''`, as bits, so that the round constant can be XORed in
+    pub a_prime_prime_0_0_bits: [T; 64],
+    /// Lane (0, 0) of the round's output
+    pub a_prime_prime_prime_0_0_limbs: [T; U64_LIMBS],
+}
+
+impl<T: Copy> KeccakCols<T> {
+    /// Bit `z` of lane `B[x, y]`. B is a rotation of `A'`, so this is a bit of `A'`.
+    pub fn b(&self, x: usize, y: usize, z: usize) -> T {
+        let source = (x + 3 * y) % 5;
+        let rotation = R[source][x] as usize;
+        self.a_prime[x][source][(z + 64 - rotation) % 64]
+    }
+
+    /// A limb of the round's output, which is `A''` except in lane (0, 0).
+    pub fn a_prime_prime_prime(&self, y: usize, x: usize, limb: usize) -> T {
+        if y == 0 && x == 0 {
+            self.a_prime_prime_prime_0_0_limbs[limb]
+        } else {
+            self.a_prime_prime[y][x][limb]
+        }
+    }
+}
+
+/// The memory cell holding bit `z` of lane `(x, y)`, and the index of its byte in the cell.
+/// Lanes are little-endian, like memory.
+pub fn state_byte(y: usize, x: usize, z: usize) -> (usize, usize) {
+    let word = 2 * (5 * y + x) + z / 32;
+    (word, 3 - (z % 32) / 8)
+}
+
+pub const NUM_KECCAK_COLS: usize = size_of::<KeccakCols<u8>>();
+pub const KECCAK_COL_MAP: KeccakCols<usize> = make_col_map();
+
+const fn make_col_map() -> KeccakCols<usize> {
+    let indices_arr = indices_arr::<NUM_KECCAK_COLS>();
+    unsafe { transmute::<[usize; NUM_KECCAK_COLS], KeccakCols<usize>>(indices_arr) }
+}
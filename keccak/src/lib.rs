@@ -0,0 +1,257 @@
+#![no_std]
+
+extern crate alloc;
+
+use crate::columns::{KeccakCols, KECCAK_COL_MAP, NUM_KECCAK_COLS, NUM_ROUNDS, STATE_WORDS};
+use crate::round::round;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::transmute;
+use valida_bus::{MachineWithGeneralBus, MachineWithMemBus};
+use valida_cpu::MachineWithCpuChip;
+use valida_machine::{instructions, Chip, Instruction, Interaction, Operands, Word};
+use valida_opcodes::KECCAKF;
+
+use p3_air::VirtualPairCol;
+use p3_field::{AbstractField, Field, PrimeField};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_maybe_rayon::prelude::*;
+use valida_machine::StarkConfig;
+
+pub mod columns;
+pub mod round;
+pub mod stark;
+
+pub use round::keccak_f;
+
+/// A Keccak-f[1600] permutation, as executed by `KECCAKF`.
+#[derive(Clone, Debug)]
+pub struct KeccakOperation {
+    pub clk: u32,
+    /// Address the state was read from
+    pub input_addr: Word<u8>,
+    /// Address the permuted state was written to
+    pub output_addr: Word<u8>,
+    /// The state before the permutation, where lane `A[x, y]` is `input[5 * y + x]`
+    pub input: [u64; 25],
+}
+
+#[derive(Default)]
+pub struct KeccakChip {
+    pub operations: Vec<KeccakOperation>,
+}
+
+impl<M, SC> Chip<M, SC> for KeccakChip
+where
+    M: MachineWithGeneralBus<SC::Val> + MachineWithMemBus<SC::Val>,
+    SC: StarkConfig,
+{
+    fn generate_trace(&self, _machine: &M) -> RowMajorMatrix<SC::Val> {
+        // The trace is padded with permutations of the zero state. The last one is cut
+        // short if the number of rows isn't a multiple of the number of rounds.
+        let num_rows = (self.operations.len() * NUM_ROUNDS).next_power_of_two();
+        let num_permutations = num_rows.div_ceil(NUM_ROUNDS);
+        let rows = (0..num_permutations)
+            .into_par_iter()
+            .map(|i| self.op_to_rows(self.operations.get(i)))
+            .collect::<Vec<_>>()
+            .concat();
+
+        let mut values = rows.into_iter().flatten().collect::<Vec<_>>();
+        values.truncate(num_rows * NUM_KECCAK_COLS);
+        RowMajorMatrix::new(values, NUM_KECCAK_COLS)
+    }
+
+    fn global_sends(&self, machine: &M) -> Vec<Interaction<SC::Val>> {
+        let is_read = VirtualPairCol::single_main(KECCAK_COL_MAP.is_read);
+        let clk = VirtualPairCol::single_main(KECCAK_COL_MAP.clk);
+        let is_static_initial = VirtualPairCol::constant(SC::Val::zero());
+
+        KECCAK_COL_MAP
+            .state
+            .iter()
+            .enumerate()
+            .map(|(i, word)| {
+                let addr = VirtualPairCol::new_main(
+                    vec![(KECCAK_COL_MAP.addr, SC::Val::one())],
+                    SC::Val::from_canonical_u32(4 * i as u32),
+                );
+                let mut fields = vec![
+                    is_read.clone(),
+                    clk.clone(),
+                    addr,
+                    is_static_initial.clone(),
+                ];
+                fields.extend(word.0.map(VirtualPairCol::single_main));
+
+                Interaction {
+                    fields,
+                    count: VirtualPairCol::sum_main(vec![
+                        KECCAK_COL_MAP.is_read,
+                        KECCAK_COL_MAP.is_write,
+                    ]),
+                    argument_index: machine.mem_bus(),
+                }
+            })
+            .collect()
+    }
+
+    fn global_receives(&self, machine: &M) -> Vec<Interaction<SC::Val>> {
+        let opcode = VirtualPairCol::constant(SC::Val::from_canonical_u32(KECCAKF));
+        let input_addr = KECCAK_COL_MAP.input_addr.0.map(VirtualPairCol::single_main);
+        let output_addr = KECCAK_COL_MAP
+            .output_addr
+            .0
+            .map(VirtualPairCol::single_main);
+        let clk = VirtualPairCol::single_main(KECCAK_COL_MAP.clk);
+
+        // The CPU writes the output address to the destination operand
+        let mut fields = vec![opcode];
+        fields.extend(input_addr);
+        fields.extend(output_addr.clone());
+        fields.extend(output_addr);
+        fields.push(clk);
+
+        let receive = Interaction {
+            fields,
+            count: VirtualPairCol::single_main(KECCAK_COL_MAP.is_read),
+            argument_index: machine.general_bus(),
+        };
+        vec![receive]
+    }
+}
+
+impl KeccakChip {
+    /// The rows of a permutation, one per round. Padding permutations have no operation.
+    fn op_to_rows<F: PrimeField>(&self, op: Option<&KeccakOperation>) -> Vec<[F; NUM_KECCAK_COLS]> {
+        let input = op.map_or([0; 25], |op| op.input);
+        let mut a: [[u64; 5]; 5] =
+            core::array::from_fn(|y| core::array::from_fn(|x| input[5 * y + x]));
+
+        let mut rows = vec![[F::zero(); NUM_KECCAK_COLS]; NUM_ROUNDS];
+        for (i, row) in rows.iter_mut().enumerate() {
+            let cols: &mut KeccakCols<F> = unsafe { transmute(row) };
+            let round = round(a, i);
+            cols.step_flags[i] = F::one();
+
+            for y in 0..5 {
+                for x in 0..5 {
+                    cols.a[y][x] = limbs(round.a[y][x]);
+                    cols.a_prime[y][x] = bits(round.a_prime[y][x]);
+                    cols.a_prime_prime[y][x] = limbs(round.a_prime_prime[y][x]);
+                }
+            }
+            for x in 0..5 {
+                cols.c[x] = bits(round.c[x]);
+                cols.c_prime[x] = bits(round.c_prime[x]);
+            }
+            cols.a_prime_prime_0_0_bits = bits(round.a_prime_prime[0][0]);
+            cols.a_prime_prime_prime_0_0_limbs = limbs(round.output[0][0]);
+
+            if let Some(op) = op {
+                cols.is_real = F::one();
+                cols.clk = F::from_canonical_u32(op.clk);
+                cols.input_addr = op.input_addr.transform(F::from_canonical_u8);
+                cols.output_addr = op.output_addr.transform(F::from_canonical_u8);
+                if i == 0 {
+                    cols.is_read = F::one();
+                    cols.addr = F::from_canonical_u32(op.input_addr.into());
+                    cols.state =
+                        state_words(round.a).map(|word| word.transform(F::from_canonical_u8));
+                }
+                if i == NUM_ROUNDS - 1 {
+                    cols.is_write = F::one();
+                    cols.addr = F::from_canonical_u32(op.output_addr.into());
+                    cols.state =
+                        state_words(round.output).map(|word| word.transform(F::from_canonical_u8));
+                }
+            }
+
+            a = round.output;
+        }
+        rows
+    }
+}
+
+fn bits<F: PrimeField>(lane: u64) -> [F; 64] {
+    core::array::from_fn(|z| F::from_canonical_u32(((lane >> z) & 1) as u32))
+}
+
+fn limbs<F: PrimeField>(lane: u64) -> [F; 4] {
+    core::array::from_fn(|limb| F::from_canonical_u32(((lane >> (16 * limb)) & 0xFFFF) as u32))
+}
+
+/// The memory cells of a state, low half of each lane first.
+fn state_words(lanes: [[u64; 5]; 5]) -> [Word<u8>; STATE_WORDS] {
+    core::array::from_fn(|i| {
+        let lane = lanes[i / 10][(i / 2) % 5];
+        Word::from((lane >> (32 * (i % 2))) as u32)
+    })
+}
+
+pub trait MachineWithKeccakChip<F: Field>: MachineWithCpuChip<F> {
+    fn keccak(&self) -> &KeccakChip;
+    fn keccak_mut(&mut self) -> &mut KeccakChip;
+}
+
+instructions!(KeccakfInstruction);
+
+impl<M, F> Instruction<M, F> for KeccakfInstruction
+where
+    M: MachineWithKeccakChip<F>,
+    F: Field,
+{
+    const OPCODE: u32 = KECCAKF;
+
+    /// `KECCAKF a(fp), b(fp), c(fp)` permutes the 200-byte state at the address in `b(fp)`
+    /// and writes the result to the address in `c(fp)`, which may be the same. The output
+    /// address is also written to `a(fp)`.
+    fn execute(state: &mut M, ops: Operands<i32>) {
+        let opcode = <Self as Instruction<M, F>>::OPCODE;
+        let clk = state.cpu().clock;
+        let pc = state.cpu().pc;
+        let fp = state.cpu().fp;
+        let read_addr_1 = (fp as i32 + ops.b()) as u32;
+        let read_addr_2 = (fp as i32 + ops.c()) as u32;
+        let write_addr = (fp as i32 + ops.a()) as u32;
+
+        let input_addr = state
+            .mem_mut()
+            .read(clk, read_addr_1, true, pc, opcode, 0, "");
+        let output_addr = state
+            .mem_mut()
+            .read(clk, read_addr_2, true, pc, opcode, 1, "");
+
+        let input_base: u32 = input_addr.into();
+        let mut words = [Word::default(); STATE_WORDS];
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = state
+                .mem_mut()
+                .coprocessor_read(clk, input_base.wrapping_add(4 * i as u32));
+        }
+        let input: [u64; 25] = core::array::from_fn(|i| {
+            let low: u32 = words[2 * i].into();
+            let high: u32 = words[2 * i + 1].into();
+            low as u64 | (high as u64) << 32
+        });
+
+        let mut output = input;
+        keccak_f(&mut output);
+        let lanes = core::array::from_fn(|y| core::array::from_fn(|x| output[5 * y + x]));
+        let output_base: u32 = output_addr.into();
+        for (i, word) in state_words(lanes).into_iter().enumerate() {
+            state
+                .mem_mut()
+                .coprocessor_write(clk, output_base.wrapping_add(4 * i as u32), word);
+        }
+        state.mem_mut().write(clk, write_addr, output_addr, true);
+
+        state.keccak_mut().operations.push(KeccakOperation {
+            clk,
+            input_addr,
+            output_addr,
+            input,
+        });
+        state.cpu_mut().push_bus_op_with_memory(None, opcode, ops);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::columns::NUM_ROUNDS;
+
+/// Round constants, XORed into lane (0, 0) at the end of each round.
+pub const RC: [u64; NUM_ROUNDS] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808A,
+    0x8000000080008000,
+    0x000000000000808B,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008A,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000A,
+    0x000000008000808B,
+    0x800000000000008B,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800A,
+    0x800000008000000A,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// Rotation offsets of the rho step, indexed `[x][y]`.
+pub const R: [[u32; 5]; 5] = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+];
+
+/// The intermediate values of one round of Keccak-f[1600]. Lanes are indexed `[y][x]`.
+pub struct Round {
+    pub a: [[u64; 5]; 5],
+    /// The parity of each column of `a`
+    pub c: [u64; 5],
+    /// `C'[x] = C[x] ^ C[x - 1] ^ ROT(C[x + 1], 1)`
+    pub c_prime: [u64; 5],
+    /// The state after theta, `A'[x, y] = A[x, y] ^ C[x] ^ C'[x]`
+    pub a_prime: [[u64; 5]; 5],
+    /// The state after rho, pi and chi
+    pub a_prime_prime: [[u64; 5]; 5],
+    /// The state after iota, which is the input of the next round
+    pub output: [[u64; 5]; 5],
+}
+
+/// Lane `B[x, y]` of the state after rho and pi, a rotation of a lane of `A'`.
+pub fn b(a_prime: &[[u64; 5]; 5], x: usize, y: usize) -> u64 {
+    let source = (x + 3 * y) % 5;
+    a_prime[x][source].rotate_left(R[source][x])
+}
+
+/// Compute a round of Keccak-f[1600] on the state `a`.
+pub fn round(a: [[u64; 5]; 5], round: usize) -> Round {
+    let c: [u64; 5] = core::array::from_fn(|x| (0..5).fold(0, |acc, y| acc ^ a[y][x]));
+    let c_prime: [u64; 5] =
+        core::array::from_fn(|x| c[x] ^ c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1));
+    let a_prime: [[u64; 5]; 5] =
+        core::array::from_fn(|y| core::array::from_fn(|x| a[y][x] ^ c[x] ^ c_prime[x]));
+    let a_prime_prime: [[u64; 5]; 5] = core::array::from_fn(|y| {
+        core::array::from_fn(|x| {
+            b(&a_prime, x, y) ^ (!b(&a_prime, (x + 1) % 5, y) & b(&a_prime, (x + 2) % 5, y))
+        })
+    });
+    let mut output = a_prime_prime;
+    output[0][0] ^= RC[round];
+    Round {
+        a,
+        c,
+        c_prime,
+        a_prime,
+        a_prime_prime,
+        output,
+    }
+}
+
+/// Apply Keccak-f[1600] to a state of 25 lanes, where lane `A[x, y]` is `state[5 * y + x]`.
+pub fn keccak_f(state: &mut [u64; 25]) {
+    let mut a: [[u64; 5]; 5] = core::array::from_fn(|y| core::array::from_fn(|x| state[5 * y + x]));
+    for i in 0..NUM_ROUNDS {
+        a = round(a, i).output;
+    }
+    for (i, lane) in state.iter_mut().enumerate() {
+        *lane = a[i / 5][i % 5];
+    }
+}
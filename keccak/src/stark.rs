@@ -0,0 +1,277 @@
+use crate::columns::{
+    state_byte, KeccakCols, BITS_PER_LIMB, NUM_KECCAK_COLS, NUM_ROUNDS, U64_LIMBS,
+};
+use crate::round::RC;
+use crate::KeccakChip;
+use alloc::vec;
+use core::borrow::Borrow;
+use valida_machine::Word;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, PrimeField};
+use p3_matrix::MatrixRowSlices;
+
+impl<F> BaseAir<F> for KeccakChip {
+    fn width(&self) -> usize {
+        NUM_KECCAK_COLS
+    }
+}
+
+impl<F, AB> Air<AB> for KeccakChip
+where
+    F: PrimeField,
+    AB: AirBuilder<F = F>,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local: &KeccakCols<AB::Var> = main.row_slice(0).borrow();
+        let next: &KeccakCols<AB::Var> = main.row_slice(1).borrow();
+
+        self.eval_round_flags(builder, local, next);
+        self.eval_memory(builder, local, next);
+        self.eval_round(builder, local, next);
+    }
+}
+
+impl KeccakChip {
+    fn eval_round_flags<AB: AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &KeccakCols<AB::Var>,
+        next: &KeccakCols<AB::Var>,
+    ) {
+        // The trace starts with the first round, and the rounds follow each other in turn.
+        builder.when_first_row().assert_one(local.step_flags[0]);
+        for i in 1..NUM_ROUNDS {
+            builder.when_first_row().assert_zero(local.step_flags[i]);
+        }
+        for i in 0..NUM_ROUNDS {
+            builder
+                .when_transition()
+                .assert_eq(next.step_flags[(i + 1) % NUM_ROUNDS], local.step_flags[i]);
+        }
+    }
+
+    fn eval_memory<AB: AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &KeccakCols<AB::Var>,
+        next: &KeccakCols<AB::Var>,
+    ) {
+        let base = [1 << 24, 1 << 16, 1 << 8, 1].map(AB::Expr::from_canonical_u32);
+        let first_step = local.step_flags[0];
+        let final_step = local.step_flags[NUM_ROUNDS - 1];
+        let not_final_step = AB::Expr::one() - final_step;
+
+        builder.assert_bool(local.is_real);
+        builder.assert_eq(local.is_read, local.is_real * first_step);
+        builder.assert_eq(local.is_write, local.is_real * final_step);
+        // A permutation cut short by the end of the trace never writes its output, so it
+        // can't be real.
+        builder
+            .when_last_row()
+            .assert_zero(local.is_real * not_final_step.clone());
+
+        // Every round of a permutation belongs to the same instruction.
+        let mut constant = vec![(local.is_real, next.is_real), (local.clk, next.clk)];
+        constant.extend(local.input_addr.into_iter().zip(next.input_addr));
+        constant.extend(local.output_addr.into_iter().zip(next.output_addr));
+        for (current, following) in constant {
+            builder
+                .when_transition()
+                .when(not_final_step.clone())
+                .assert_eq(following, current);
+        }
+        builder
+            .when(first_step)
+            .assert_eq(local.addr, reduce::<AB>(&base, local.input_addr));
+        builder
+            .when(final_step)
+            .assert_eq(local.addr, reduce::<AB>(&base, local.output_addr));
+
+        // The state read from memory is the input of the first round. Memory cells hold
+        // bytes, so the limbs built from them are in range.
+        for y in 0..5 {
+            for x in 0..5 {
+                for limb in 0..U64_LIMBS {
+                    let (word, low) = state_byte(y, x, limb * BITS_PER_LIMB);
+                    let value = local.state[word][low]
+                        + local.state[word][low - 1] * AB::Expr::from_canonical_u32(1 << 8);
+                    builder
+                        .when(local.is_read)
+                        .assert_eq(local.a[y][x][limb], value);
+                }
+            }
+        }
+
+        // The state written to memory is the output of the last round. Each byte is built
+        // from bits, so it is in range.
+        let output_bit = |y: usize, x: usize, z: usize| -> AB::Expr {
+            if y == 0 && x == 0 {
+                let rc_bit = (RC[NUM_ROUNDS - 1] >> z) & 1;
+                xor(
+                    local.a_prime_prime_0_0_bits[z].into(),
+                    AB::Expr::from_canonical_u32(rc_bit as u32),
+                )
+            } else {
+                chi_bit::<AB>(local, x, y, z)
+            }
+        };
+        for y in 0..5 {
+            for x in 0..5 {
+                for byte in 0..8 {
+                    let (word, index) = state_byte(y, x, byte * 8);
+                    let value = (byte * 8..(byte + 1) * 8)
+                        .rev()
+                        .fold(AB::Expr::zero(), |acc, z| {
+                            acc * AB::Expr::two() + output_bit(y, x, z)
+                        });
+                    builder
+                        .when(local.is_write)
+                        .assert_eq(local.state[word][index], value);
+                }
+            }
+        }
+    }
+
+    fn eval_round<AB: AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &KeccakCols<AB::Var>,
+        next: &KeccakCols<AB::Var>,
+    ) {
+        let final_step = local.step_flags[NUM_ROUNDS - 1];
+        let not_final_step = AB::Expr::one() - final_step;
+
+        // C'[x, z] = xor(C[x, z], C[x - 1, z], C[x + 1, z - 1]).
+        for x in 0..5 {
+            for z in 0..64 {
+                builder.assert_bool(local.c[x][z]);
+                let xor = xor3(
+                    local.c[x][z].into(),
+                    local.c[(x + 4) % 5][z].into(),
+                    local.c[(x + 1) % 5][(z + 63) % 64].into(),
+                );
+                builder.assert_eq(local.c_prime[x][z], xor);
+            }
+        }
+
+        // Check that the input limbs are consistent with A' and D.
+        // A[x, y, z] = xor(A'[x, y, z], D[x, y, z])
+        //            = xor(A'[x, y, z], C[x - 1, z], C[x + 1, z - 1])
+        //            = xor(A'[x, y, z], C[x, z], C'[x, z]).
+        // The last step is valid based on the identity checked above.
+        for y in 0..5 {
+            for x in 0..5 {
+                for limb in 0..U64_LIMBS {
+                    let bits = limb * BITS_PER_LIMB..(limb + 1) * BITS_PER_LIMB;
+                    let computed_limb = bits.rev().fold(AB::Expr::zero(), |acc, z| {
+                        builder.assert_bool(local.a_prime[y][x][z]);
+                        let bit = xor3(
+                            local.a_prime[y][x][z].into(),
+                            local.c[x][z].into(),
+                            local.c_prime[x][z].into(),
+                        );
+                        acc * AB::Expr::two() + bit
+                    });
+                    builder.assert_eq(computed_limb, local.a[y][x][limb]);
+                }
+            }
+        }
+
+        // xor_{i=0}^4 A'[x, i, z] = C'[x, z], so for each x, z,
+        // diff * (diff - 2) * (diff - 4) = 0, where
+        // diff = sum_{i=0}^4 A'[x, i, z] - C'[x, z]
+        for x in 0..5 {
+            for z in 0..64 {
+                let sum: AB::Expr = (0..5).map(|y| local.a_prime[y][x][z].into()).sum();
+                let diff = sum - local.c_prime[x][z];
+                let four = AB::Expr::from_canonical_u32(4);
+                builder
+                    .assert_zero(diff.clone() * (diff.clone() - AB::Expr::two()) * (diff - four));
+            }
+        }
+
+        // A''[x, y] = xor(B[x, y], andn(B[x + 1, y], B[x + 2, y])).
+        for y in 0..5 {
+            for x in 0..5 {
+                for limb in 0..U64_LIMBS {
+                    let bits = limb * BITS_PER_LIMB..(limb + 1) * BITS_PER_LIMB;
+                    let computed_limb = bits.rev().fold(AB::Expr::zero(), |acc, z| {
+                        acc * AB::Expr::two() + chi_bit::<AB>(local, x, y, z)
+                    });
+                    builder.assert_eq(computed_limb, local.a_prime_prime[y][x][limb]);
+                }
+            }
+        }
+
+        // A'''[0, 0] = A''[0, 0] XOR RC
+        for limb in 0..U64_LIMBS {
+            let bits = limb * BITS_PER_LIMB..(limb + 1) * BITS_PER_LIMB;
+            let computed_limb = bits.rev().fold(AB::Expr::zero(), |acc, z| {
+                builder.assert_bool(local.a_prime_prime_0_0_bits[z]);
+                acc * AB::Expr::two() + local.a_prime_prime_0_0_bits[z]
+            });
+            builder.assert_eq(computed_limb, local.a_prime_prime[0][0][limb]);
+        }
+        let xored_bit = |z: usize| {
+            let rc_bit = (0..NUM_ROUNDS)
+                .map(|round| {
+                    local.step_flags[round]
+                        * AB::Expr::from_canonical_u32(((RC[round] >> z) & 1) as u32)
+                })
+                .sum::<AB::Expr>();
+            xor(local.a_prime_prime_0_0_bits[z].into(), rc_bit)
+        };
+        for limb in 0..U64_LIMBS {
+            let bits = limb * BITS_PER_LIMB..(limb + 1) * BITS_PER_LIMB;
+            let computed_limb = bits.rev().fold(AB::Expr::zero(), |acc, z| {
+                acc * AB::Expr::two() + xored_bit(z)
+            });
+            builder.assert_eq(computed_limb, local.a_prime_prime_prime_0_0_limbs[limb]);
+        }
+
+        // The output of each round but the last is the input of the next.
+        for y in 0..5 {
+            for x in 0..5 {
+                for limb in 0..U64_LIMBS {
+                    builder
+                        .when_transition()
+                        .when(not_final_step.clone())
+                        .assert_eq(local.a_prime_prime_prime(y, x, limb), next.a[y][x][limb]);
+                }
+            }
+        }
+    }
+}
+
+/// Bit `z` of lane `A''[x, y] = xor(B[x, y], andn(B[x + 1, y], B[x + 2, y]))`.
+fn chi_bit<AB: AirBuilder>(local: &KeccakCols<AB::Var>, x: usize, y: usize, z: usize) -> AB::Expr {
+    let andn = andn(
+        local.b((x + 1) % 5, y, z).into(),
+        local.b((x + 2) % 5, y, z).into(),
+    );
+    xor(local.b(x, y, z).into(), andn)
+}
+
+/// XOR of two bits.
+fn xor<E: AbstractField>(a: E, b: E) -> E {
+    a.clone() + b.clone() - a * b * E::two()
+}
+
+fn xor3<E: AbstractField>(a: E, b: E, c: E) -> E {
+    xor(xor(a, b), c)
+}
+
+/// `!a & b` of two bits.
+fn andn<E: AbstractField>(a: E, b: E) -> E {
+    (E::one() - a) * b
+}
+
+fn reduce<AB: AirBuilder>(base: &[AB::Expr], input: Word<AB::Var>) -> AB::Expr {
+    input
+        .into_iter()
+        .enumerate()
+        .map(|(i, x)| base[i].clone() * x)
+        .sum()
+}
@@ -0,0 +1,48 @@
+use clap::{arg, command};
+use std::fs::File;
+use std::io::{self, Write};
+use valida_ld::link_elf;
+
+fn main() {
+    let matches = command!()
+        .arg(arg!(
+            -o --output <FILE> "The executable output file"
+        ))
+        .arg(arg!(
+            <OBJECTS> ... "The relocatable object files to link, made with `assembler --relocatable`"
+        ))
+        .get_matches();
+
+    let names: Vec<&String> = matches.get_many::<String>("OBJECTS").unwrap().collect();
+    let files: Vec<Vec<u8>> = names
+        .iter()
+        .map(|name| std::fs::read(name).expect("Failed to read input file"))
+        .collect();
+    let inputs: Vec<(&str, &[u8])> = names
+        .iter()
+        .zip(&files)
+        .map(|(name, file)| (name.as_str(), file.as_slice()))
+        .collect();
+
+    // Write the executable to file, or stdout if no file is specified
+    let executable = match link_elf(&inputs) {
+        Ok(executable) => executable,
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("error: {}", error);
+            }
+            eprintln!("Failed to link objects");
+            std::process::exit(1);
+        }
+    };
+    if let Some(filepath) = matches.get_one::<String>("output") {
+        File::create(filepath)
+            .expect("Failed to open output file")
+            .write_all(&executable)
+            .expect("Failed to write to output file");
+    } else {
+        io::stdout()
+            .write_all(&executable)
+            .expect("Failed to write to stdout");
+    }
+}
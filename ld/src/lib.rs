@@ -0,0 +1,330 @@
+use core::fmt;
+use elf::abi;
+use elf::endian::AnyEndian;
+use elf::ElfBytes;
+use std::collections::HashMap;
+use valida_assembler::{DataSection, Object, ObjectSymbol, RelocationKind, Section, DATA_START};
+use valida_opcodes::BYTES_PER_INSTR;
+
+/// A problem with an input object, or with how the objects fit together.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkError {
+    /// The name of the object the problem was found in.
+    pub object: String,
+    pub message: String,
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.object, self.message)
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+/// A symbol of an input object. Section symbols and the null symbol have no name, and
+/// symbols in sections that aren't linked have no section.
+struct InputSymbol {
+    name: String,
+    section: Option<Section>,
+    value: u32,
+    size: u32,
+    global: bool,
+}
+
+/// A relocation of an input object, which refers to a symbol by its index.
+struct InputRelocation {
+    section: Section,
+    offset: u32,
+    kind: RelocationKind,
+    symbol: usize,
+    addend: i32,
+}
+
+/// The parts of a relocatable object that are linked. Data sections hold their bytes in
+/// the order of their addresses.
+struct InputObject<'a> {
+    name: &'a str,
+    code: Vec<u8>,
+    /// The contents and alignment of each data section
+    sections: HashMap<Section, (Vec<u8>, u32)>,
+    symbols: Vec<InputSymbol>,
+    relocations: Vec<InputRelocation>,
+    /// The address that each section of the object is placed at
+    bases: HashMap<Section, u32>,
+}
+
+impl InputObject<'_> {
+    /// The address of a symbol defined in this object, once its sections are placed.
+    fn address(&self, symbol: &InputSymbol) -> Option<u32> {
+        match symbol.section? {
+            Section::Undefined => None,
+            Section::Absolute => Some(symbol.value),
+            section => self.bases.get(&section).map(|base| base + symbol.value),
+        }
+    }
+}
+
+fn read_object<'a>(name: &'a str, file: &[u8]) -> Result<InputObject<'a>, String> {
+    let file = ElfBytes::<AnyEndian>::minimal_parse(file).map_err(|error| error.to_string())?;
+    if file.ehdr.e_type != abi::ET_REL {
+        return Err("not a relocatable object".to_string());
+    }
+    let (Some(headers), Some(names)) = file
+        .section_headers_with_strtab()
+        .map_err(|error| error.to_string())?
+    else {
+        return Err("no section headers".to_string());
+    };
+
+    let mut object = InputObject {
+        name,
+        code: vec![],
+        sections: HashMap::new(),
+        symbols: vec![],
+        relocations: vec![],
+        bases: HashMap::new(),
+    };
+    // The section of each section header that is linked
+    let mut header_sections = HashMap::new();
+    for (index, header) in headers.iter().enumerate() {
+        let section_name = names
+            .get(header.sh_name as usize)
+            .map_err(|error| error.to_string())?;
+        let section = match section_name {
+            ".text" => Section::Text,
+            ".rodata" => Section::Rodata,
+            ".data" => Section::Data,
+            ".bss" => Section::Bss,
+            _ => continue,
+        };
+        header_sections.insert(index, section);
+        let alignment = header.sh_addralign.max(1) as u32;
+        if section == Section::Bss {
+            let bytes = vec![0; header.sh_size as usize];
+            object.sections.insert(section, (bytes, alignment));
+            continue;
+        }
+        let bytes = match file.section_data(&header) {
+            Ok((bytes, None)) => bytes.to_vec(),
+            Ok((_, Some(_))) => return Err(format!("`{}` is compressed", section_name)),
+            Err(error) => return Err(error.to_string()),
+        };
+        if section == Section::Text {
+            if bytes.len() % BYTES_PER_INSTR as usize != 0 {
+                return Err("`.text` is not a whole number of instructions".to_string());
+            }
+            object.code = bytes;
+            continue;
+        }
+        // Memory cells are read from the file most significant byte first, so the bytes
+        // of each word are reversed
+        let mut bytes = bytes;
+        bytes.resize(bytes.len().next_multiple_of(4), 0);
+        for word in bytes.chunks_exact_mut(4) {
+            word.reverse();
+        }
+        object.sections.insert(section, (bytes, alignment));
+    }
+
+    if let Some((symbols, strings)) = file.symbol_table().map_err(|error| error.to_string())? {
+        for symbol in symbols.iter() {
+            let section = match symbol.st_shndx {
+                abi::SHN_UNDEF => Some(Section::Undefined),
+                abi::SHN_ABS => Some(Section::Absolute),
+                index => header_sections.get(&(index as usize)).copied(),
+            };
+            let name = match symbol.st_symtype() {
+                abi::STT_SECTION => "",
+                _ => strings
+                    .get(symbol.st_name as usize)
+                    .map_err(|error| error.to_string())?,
+            };
+            object.symbols.push(InputSymbol {
+                name: name.to_string(),
+                section,
+                value: symbol.st_value as u32,
+                size: symbol.st_size as u32,
+                global: symbol.st_bind() != abi::STB_LOCAL,
+            });
+        }
+    }
+
+    for header in headers.iter() {
+        if header.sh_type == abi::SHT_REL {
+            return Err("relocations without addends are not supported".to_string());
+        }
+        if header.sh_type != abi::SHT_RELA {
+            continue;
+        }
+        let section = match header_sections.get(&(header.sh_info as usize)) {
+            Some(Section::Bss) | None => {
+                return Err(format!("relocations for section {}", header.sh_info));
+            }
+            Some(&section) => section,
+        };
+        let relocations = file
+            .section_data_as_relas(&header)
+            .map_err(|error| error.to_string())?;
+        for relocation in relocations {
+            let kind = RelocationKind::from_elf_type(relocation.r_type)
+                .ok_or_else(|| format!("unknown relocation type {}", relocation.r_type))?;
+            object.relocations.push(InputRelocation {
+                section,
+                offset: relocation.r_offset as u32,
+                kind,
+                symbol: relocation.r_sym as usize,
+                addend: relocation.r_addend as i32,
+            });
+        }
+    }
+    Ok(object)
+}
+
+/// Round an address up to a multiple of `alignment`, which is a power of two.
+fn align(address: u32, alignment: u32) -> u32 {
+    (address + alignment - 1) & !(alignment - 1)
+}
+
+/// Link relocatable objects, given by name and contents, into an executable. Code is placed
+/// at address 0 and data after `DATA_START`, as the assembler places them. Each section is
+/// the same section of every object in turn, and every relocation is filled in with the
+/// final address of the symbol it refers to.
+pub fn link(inputs: &[(&str, &[u8])]) -> Result<Object, Vec<LinkError>> {
+    let mut errors = Vec::new();
+    let mut objects = Vec::new();
+    for &(name, file) in inputs {
+        match read_object(name, file) {
+            Ok(object) => objects.push(object),
+            Err(message) => errors.push(LinkError {
+                object: name.to_string(),
+                message,
+            }),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut output = Object::default();
+    for object in &mut objects {
+        object.bases.insert(Section::Text, output.code.len() as u32);
+        output.code.extend(&object.code);
+    }
+    let mut address = DATA_START;
+    for section in [Section::Rodata, Section::Data, Section::Bss] {
+        let alignment = objects
+            .iter()
+            .filter_map(|object| object.sections.get(&section))
+            .map(|&(_, alignment)| alignment)
+            .fold(4, u32::max);
+        let start = align(address, alignment);
+        let mut bytes = Vec::new();
+        for object in &mut objects {
+            let Some((contents, alignment)) = object.sections.get(&section) else {
+                continue;
+            };
+            bytes.resize(align(bytes.len() as u32, *alignment) as usize, 0);
+            object.bases.insert(section, start + bytes.len() as u32);
+            bytes.extend(contents);
+        }
+        address = start + bytes.len() as u32;
+        let data = DataSection {
+            address: start,
+            bytes,
+        };
+        match section {
+            Section::Rodata => output.rodata = data,
+            Section::Data => output.data = data,
+            _ => output.bss = data,
+        }
+    }
+
+    let mut globals: HashMap<&str, u32> = HashMap::new();
+    for object in &objects {
+        for symbol in object.symbols.iter().filter(|symbol| symbol.global) {
+            let Some(address) = object.address(symbol) else {
+                continue;
+            };
+            if globals.insert(&symbol.name, address).is_some() {
+                errors.push(LinkError {
+                    object: object.name.to_string(),
+                    message: format!("duplicate symbol `{}`", symbol.name),
+                });
+            }
+        }
+    }
+
+    for object in &objects {
+        for relocation in &object.relocations {
+            let target = match object.symbols.get(relocation.symbol) {
+                Some(symbol) => object
+                    .address(symbol)
+                    .or_else(|| globals.get(symbol.name.as_str()).copied())
+                    .ok_or_else(|| format!("undefined symbol `{}`", symbol.name)),
+                None => Err(format!("no symbol {}", relocation.symbol)),
+            };
+            let value = match target {
+                Ok(address) => (address as i32).wrapping_add(relocation.addend),
+                Err(message) => {
+                    errors.push(LinkError {
+                        object: object.name.to_string(),
+                        message,
+                    });
+                    continue;
+                }
+            };
+            let bytes: Vec<u8> = match relocation.kind {
+                RelocationKind::Word => value.to_le_bytes().to_vec(),
+                // Each byte of the value is an operand of its own
+                RelocationKind::Imm32 => value
+                    .to_be_bytes()
+                    .iter()
+                    .flat_map(|&byte| (byte as i32).to_le_bytes())
+                    .collect(),
+            };
+            let (contents, start) = match relocation.section {
+                Section::Text => (&mut output.code, 0),
+                Section::Rodata => (&mut output.rodata.bytes, output.rodata.address),
+                _ => (&mut output.data.bytes, output.data.address),
+            };
+            let offset = (object.bases[&relocation.section] - start + relocation.offset) as usize;
+            match contents.get_mut(offset..offset + bytes.len()) {
+                Some(place) => place.copy_from_slice(&bytes),
+                None => errors.push(LinkError {
+                    object: object.name.to_string(),
+                    message: format!("relocation at {} is outside its section", relocation.offset),
+                }),
+            }
+        }
+    }
+
+    for object in &objects {
+        for symbol in &object.symbols {
+            let (Some(section), Some(address)) = (symbol.section, object.address(symbol)) else {
+                continue;
+            };
+            if symbol.name.is_empty() {
+                continue;
+            }
+            output.symbols.push(ObjectSymbol {
+                name: symbol.name.clone(),
+                section,
+                value: address,
+                size: symbol.size,
+                global: symbol.global,
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(output)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Link relocatable objects into a Valida ELF executable.
+pub fn link_elf(inputs: &[(&str, &[u8])]) -> Result<Vec<u8>, Vec<LinkError>> {
+    link(inputs).map(|object| object.to_elf())
+}
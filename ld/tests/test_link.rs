@@ -0,0 +1,93 @@
+use valida_assembler::{assemble_elf, assemble_object, assemble_relocatable, DATA_START};
+use valida_elf::load_executable_file;
+use valida_ld::{link, link_elf};
+
+const MAIN: &str = "	.globl	_start
+_start:
+	call	square, -16
+	li	-4(fp), table+4
+	j	done
+	.data
+pointers:
+	.word	square, table
+	.text
+done:
+	stop
+";
+
+const LIBRARY: &str = "	.globl	square, table
+square:
+	mul	-4(fp), 4(fp), 4(fp)
+	ret
+	.rodata
+table:
+	.word	1, 2, 3
+	.data
+next:
+	.word	next+4, 7
+";
+
+#[test]
+fn links_like_one_source() {
+    let main = assemble_relocatable(MAIN).unwrap();
+    let library = assemble_relocatable(LIBRARY).unwrap();
+    let linked = link(&[("main.o", &main), ("library.o", &library)]).unwrap();
+
+    let whole = assemble_object(&format!("{}{}", MAIN, LIBRARY)).unwrap();
+    assert_eq!(linked.code, whole.code);
+    assert_eq!(linked.rodata, whole.rodata);
+    assert_eq!(linked.data, whole.data);
+    assert_eq!(linked.bss, whole.bss);
+    let square = linked.symbols.iter().find(|symbol| symbol.name == "square");
+    assert_eq!(square.map(|symbol| symbol.value), Some(5 * 24));
+}
+
+#[test]
+fn writes_loadable_executable() {
+    let main = assemble_relocatable(MAIN).unwrap();
+    let library = assemble_relocatable(LIBRARY).unwrap();
+    let executable = link_elf(&[("main.o", &main), ("library.o", &library)]).unwrap();
+    let program = load_executable_file(executable).unwrap();
+    assert_eq!(program.code.0.len(), 7);
+
+    // `pointers` follows the 12 bytes of `table`, and holds the address of `square`
+    let pointers = DATA_START + 12;
+    assert_eq!(program.data[&pointers].0, [0, 0, 0, 5 * 24]);
+    assert_eq!(program.data[&(pointers + 4)].0, DATA_START.to_be_bytes());
+}
+
+#[test]
+fn reports_link_errors() {
+    let main = assemble_relocatable(MAIN).unwrap();
+    let library = assemble_relocatable(LIBRARY).unwrap();
+    let executable = assemble_elf(LIBRARY).unwrap();
+
+    let errors = link(&[("main.o", &main)]).unwrap_err();
+    let found: Vec<(&str, &str)> = errors
+        .iter()
+        .map(|error| (error.object.as_str(), error.message.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("main.o", "undefined symbol `square`"),
+            ("main.o", "undefined symbol `table`"),
+            ("main.o", "undefined symbol `square`"),
+            ("main.o", "undefined symbol `table`"),
+        ]
+    );
+
+    let errors = link(&[("a.o", &library), ("b.o", &library)]).unwrap_err();
+    let mut found: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    found.sort();
+    assert_eq!(
+        found,
+        [
+            "b.o: duplicate symbol `square`",
+            "b.o: duplicate symbol `table`"
+        ]
+    );
+
+    let errors = link(&[("a.out", &executable)]).unwrap_err();
+    assert_eq!(errors[0].to_string(), "a.out: not a relocatable object");
+}
@@ -41,6 +41,22 @@ impl FixedAdviceProvider {
         file.read_to_end(&mut advice).unwrap();
         Self { advice, index: 0 }
     }
+
+    /// The number of advice bytes consumed so far.
+    pub fn position(&self) -> usize {
+        self.index
+    }
+
+    /// Move the read position of the advice tape, e.g. when restoring a snapshot.
+    pub fn seek(&mut self, position: usize) {
+        self.index = position.min(self.advice.len());
+    }
+
+    /// Insert bytes at the read position, so that they are read next.
+    pub fn insert(&mut self, bytes: &[u8]) {
+        self.advice
+            .splice(self.index..self.index, bytes.iter().copied());
+    }
 }
 
 impl AdviceProvider for FixedAdviceProvider {
@@ -55,6 +71,45 @@ impl AdviceProvider for FixedAdviceProvider {
     }
 }
 
+/// Wraps an advice provider and keeps a copy of every byte handed out, so that the advice
+/// an execution consumed can be saved and replayed with a `FixedAdviceProvider`.
+pub struct RecordingAdviceProvider<A: AdviceProvider> {
+    inner: A,
+    recorded: Vec<u8>,
+}
+
+impl<A: AdviceProvider> RecordingAdviceProvider<A> {
+    pub fn new(inner: A) -> Self {
+        Self {
+            inner,
+            recorded: Vec::new(),
+        }
+    }
+
+    /// The bytes consumed so far.
+    pub fn recorded(&self) -> &[u8] {
+        &self.recorded
+    }
+
+    pub fn inner_mut(&mut self) -> &mut A {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+}
+
+impl<A: AdviceProvider> AdviceProvider for RecordingAdviceProvider<A> {
+    fn get_advice(&mut self) -> Option<u8> {
+        let advice = self.inner.get_advice();
+        if let Some(advice_byte) = advice {
+            self.recorded.push(advice_byte);
+        }
+        advice
+    }
+}
+
 #[cfg(feature = "std")]
 pub struct StdinAdviceProvider;
 
@@ -71,6 +126,9 @@ impl AdviceProvider for StdinAdviceProvider {
 
 pub struct GlobalAdviceProvider {
     provider: AdviceProviderType,
+    position: usize,
+    /// The bytes read from stdin so far, kept so that stdin can be rewound.
+    stdin_bytes: Vec<u8>,
 }
 impl GlobalAdviceProvider {
     pub fn new(file_name: &Option<String>) -> Self {
@@ -78,18 +136,83 @@ impl GlobalAdviceProvider {
             Some(file_name) => {
                 let mut file = File::open(file_name).unwrap();
                 let provider = AdviceProviderType::Fixed(FixedAdviceProvider::from_file(&mut file));
-                Self { provider }
+                Self {
+                    provider,
+                    position: 0,
+                    stdin_bytes: Vec::new(),
+                }
             }
             None => {
                 let provider = AdviceProviderType::Stdin(StdinAdviceProvider);
-                Self { provider }
+                Self {
+                    provider,
+                    position: 0,
+                    stdin_bytes: Vec::new(),
+                }
+            }
+        }
+    }
+
+    /// The number of advice bytes consumed so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Move to `position` bytes into the advice tape, either forwards or backwards.
+    pub fn advance_to(&mut self, position: usize) {
+        if let AdviceProviderType::Fixed(provider) = &mut self.provider {
+            provider.seek(position);
+            self.position = provider.position();
+            return;
+        }
+        self.position = position.min(self.stdin_bytes.len());
+        while self.position < position {
+            if self.get_advice().is_none() {
+                break;
+            }
+        }
+    }
+
+    /// Insert bytes into the advice tape at the current position, so that they are read
+    /// next, e.g. to feed advice interactively.
+    pub fn push(&mut self, bytes: &[u8]) {
+        match &mut self.provider {
+            AdviceProviderType::Fixed(provider) => provider.insert(bytes),
+            AdviceProviderType::Stdin(_) => {
+                // Bytes before the end of the buffer are replayed before reading stdin again
+                self.stdin_bytes
+                    .splice(self.position..self.position, bytes.iter().copied());
             }
         }
     }
 }
 
+impl From<FixedAdviceProvider> for GlobalAdviceProvider {
+    fn from(provider: FixedAdviceProvider) -> Self {
+        Self {
+            position: provider.position(),
+            provider: AdviceProviderType::Fixed(provider),
+            stdin_bytes: Vec::new(),
+        }
+    }
+}
+
 impl AdviceProvider for GlobalAdviceProvider {
     fn get_advice(&mut self) -> Option<u8> {
-        self.provider.get_advice()
+        let is_stdin = matches!(self.provider, AdviceProviderType::Stdin(_));
+        let advice = if is_stdin && self.position < self.stdin_bytes.len() {
+            // Replay bytes already read from stdin after rewinding
+            Some(self.stdin_bytes[self.position])
+        } else {
+            let advice = self.provider.get_advice();
+            if is_stdin {
+                self.stdin_bytes.extend(advice);
+            }
+            advice
+        };
+        if advice.is_some() {
+            self.position += 1;
+        }
+        advice
     }
 }
@@ -0,0 +1,120 @@
+//! Structured data exchanged between the host and a guest program.
+//!
+//! Values are serialized with CBOR and framed with their length: each frame is a
+//! little-endian `u32` byte count followed by that many bytes of CBOR. Input frames are
+//! placed on the advice tape, and the guest writes output frames one byte at a time with
+//! `WRITE`. See the "Input/output format" chapter of the book for the full description.
+
+use crate::FixedAdviceProvider;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// The number of bytes in a frame's length prefix.
+pub const FRAME_LENGTH_BYTES: usize = 4;
+
+/// Builds an advice tape out of serialized values.
+#[derive(Default, Clone, Debug)]
+pub struct AdviceBuilder {
+    bytes: Vec<u8>,
+}
+
+impl AdviceBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a frame holding the CBOR encoding of `value`.
+    pub fn write<T: Serialize + ?Sized>(mut self, value: &T) -> Self {
+        let mut payload = Vec::new();
+        ciborium::into_writer(value, &mut payload).expect("Advice serialization failed");
+        self.bytes
+            .extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        self.bytes.extend(payload);
+        self
+    }
+
+    /// The advice tape built so far, e.g. to save it as an advice file.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn build(self) -> FixedAdviceProvider {
+        FixedAdviceProvider::new(self.bytes)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// A frame's length prefix or payload extends past the end of the data.
+    Truncated { expected: usize, available: usize },
+    /// The frame payload is not a valid encoding of the requested type.
+    InvalidPayload(String),
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DecodeError::Truncated {
+                expected,
+                available,
+            } => {
+                write!(
+                    f,
+                    "Truncated frame: expected {} bytes, {} available",
+                    expected, available
+                )
+            }
+            DecodeError::InvalidPayload(err) => {
+                write!(f, "Invalid frame payload: {}", err)
+            }
+        }
+    }
+}
+
+/// Reads consecutive frames, e.g. from the bytes a program wrote to output.
+pub struct FrameReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> FrameReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The payload of the next frame.
+    pub fn next_frame(&mut self) -> Result<&'a [u8], DecodeError> {
+        let prefix = self.take(FRAME_LENGTH_BYTES)?;
+        let len = u32::from_le_bytes(prefix.try_into().unwrap()) as usize;
+        self.take(len)
+    }
+
+    /// Decode the next frame as a `T`.
+    pub fn read<T: DeserializeOwned>(&mut self) -> Result<T, DecodeError> {
+        let payload = self.next_frame()?;
+        ciborium::from_reader(payload).map_err(|err| DecodeError::InvalidPayload(err.to_string()))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() < len {
+            return Err(DecodeError::Truncated {
+                expected: len,
+                available: self.bytes.len(),
+            });
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+}
+
+/// Decode the first frame of a program's output.
+pub fn decode_output<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, DecodeError> {
+    FrameReader::new(bytes).read()
+}
@@ -12,6 +12,8 @@ mod core;
 mod debug_builder;
 mod error;
 mod folding_builder;
+#[cfg(feature = "std")]
+mod io;
 mod machine;
 mod program;
 mod proof;
@@ -24,6 +26,8 @@ pub use chip::*;
 pub use config::*;
 pub use core::*;
 pub use error::*;
+#[cfg(feature = "std")]
+pub use io::*;
 pub use machine::*;
 pub use program::*;
 pub use proof::*;
@@ -28,6 +28,10 @@ pub enum Operation {
     Read(u32, Word<u8>),
     Write(u32, Word<u8>),
     DummyRead(u32, Word<u8>),
+    /// Reads and writes made by a chip other than the CPU, e.g. to carry out a host call.
+    /// They are not assigned to a CPU memory channel.
+    CoprocessorRead(u32, Word<u8>),
+    CoprocessorWrite(u32, Word<u8>),
 }
 
 impl Operation {
@@ -36,6 +40,8 @@ impl Operation {
             Operation::Read(addr, _) => *addr,
             Operation::Write(addr, _) => *addr,
             Operation::DummyRead(addr, _) => *addr,
+            Operation::CoprocessorRead(addr, _) => *addr,
+            Operation::CoprocessorWrite(addr, _) => *addr,
         }
     }
     pub fn get_value(&self) -> Word<u8> {
@@ -43,6 +49,8 @@ impl Operation {
             Operation::Read(_, value) => *value,
             Operation::Write(_, value) => *value,
             Operation::DummyRead(_, value) => *value,
+            Operation::CoprocessorRead(_, value) => *value,
+            Operation::CoprocessorWrite(_, value) => *value,
         }
     }
 }
@@ -52,6 +60,9 @@ pub struct MemoryChip {
     pub cells: BTreeMap<u32, Word<u8>>,
     pub operations: BTreeMap<u32, Vec<Operation>>,
     pub static_data: BTreeMap<u32, Word<u8>>,
+    /// The value each logged write overwrote, as `(clk, address, previous value)` in the
+    /// order the writes were made. Used to step backwards in the debugger.
+    pub undo_log: Vec<(u32, u32, Option<Word<u8>>)>,
 }
 
 pub trait MachineWithMemoryChip<F: Field>: Machine<F> {
@@ -65,6 +76,7 @@ impl MemoryChip {
             cells: BTreeMap::new(),
             operations: BTreeMap::new(),
             static_data: BTreeMap::new(),
+            undo_log: Vec::new(),
         }
     }
 
@@ -125,10 +137,57 @@ impl MemoryChip {
                 .entry(clk)
                 .or_insert_with(Vec::new)
                 .push(Operation::Write(address, value));
+            self.log_overwrite(clk, address);
         }
         self.cells.insert(address, value.into());
     }
 
+    /// Read from a cell on behalf of a coprocessor. If the cell is empty, initialize it
+    /// with the default values.
+    pub fn coprocessor_read(&mut self, clk: u32, address: u32) -> Word<u8> {
+        let value = self
+            .cells
+            .get(&address)
+            .copied()
+            .unwrap_or_else(|| Word([0; MEMORY_CELL_BYTES]));
+        self.operations
+            .entry(clk)
+            .or_insert_with(Vec::new)
+            .push(Operation::CoprocessorRead(address, value));
+        value
+    }
+
+    /// Write to a cell on behalf of a coprocessor.
+    pub fn coprocessor_write(&mut self, clk: u32, address: u32, value: Word<u8>) {
+        self.operations
+            .entry(clk)
+            .or_insert_with(Vec::new)
+            .push(Operation::CoprocessorWrite(address, value));
+        self.log_overwrite(clk, address);
+        self.cells.insert(address, value);
+    }
+
+    fn log_overwrite(&mut self, clk: u32, address: u32) {
+        let previous = self.cells.get(&address).copied();
+        self.undo_log.push((clk, address, previous));
+    }
+
+    /// Undo the writes made at `clk`, which must be the latest clock cycle with memory
+    /// operations, and discard its operations. Used to step backwards in the debugger.
+    pub fn undo(&mut self, clk: u32) {
+        self.operations.remove(&clk);
+        while let Some(&(write_clk, address, previous)) = self.undo_log.last() {
+            if write_clk != clk {
+                break;
+            }
+            self.undo_log.pop();
+            match previous {
+                Some(value) => self.cells.insert(address, value),
+                None => self.cells.remove(&address),
+            };
+        }
+    }
+
     pub fn write_static(&mut self, address: u32, value: Word<u8>) {
         self.cells.insert(address, value.clone());
         self.static_data.insert(address, value);
@@ -243,12 +302,12 @@ impl MemoryChip {
         cols.is_static_initial = F::zero();
 
         match op {
-            Operation::Read(addr, value) => {
+            Operation::Read(addr, value) | Operation::CoprocessorRead(addr, value) => {
                 cols.addr = F::from_canonical_u32(addr);
                 cols.value = value.transform(F::from_canonical_u8);
                 cols.is_read = F::one();
             }
-            Operation::Write(addr, value) => {
+            Operation::Write(addr, value) | Operation::CoprocessorWrite(addr, value) => {
                 cols.addr = F::from_canonical_u32(addr);
                 cols.value = value.transform(F::from_canonical_u8);
                 cols.is_write = F::one();
@@ -42,6 +42,17 @@ pub enum Opcode {
     SUB = 201,
     MUL = 202,
     WRITE = 300,
+    ECALL = 400,
+    KECCAKF = 500,
+    SHA256COMPRESS = 501,
+    POSEIDON2 = 502,
+    SECP256K1ADD = 503,
+    SECP256K1DOUBLE = 504,
+    ED25519ADD = 505,
+    ED25519DOUBLE = 506,
+    U256ADD = 507,
+    U256MUL = 508,
+    U256MODMUL = 509,
 }
 
 macro_rules! declare_opcode {
@@ -97,3 +108,18 @@ declare_opcode!(MUL);
 
 /// OUTPUT
 declare_opcode!(WRITE);
+
+/// HOST CALLS
+declare_opcode!(ECALL);
+
+/// PRECOMPILES
+declare_opcode!(KECCAKF);
+declare_opcode!(SHA256COMPRESS);
+declare_opcode!(POSEIDON2);
+declare_opcode!(SECP256K1ADD);
+declare_opcode!(SECP256K1DOUBLE);
+declare_opcode!(ED25519ADD);
+declare_opcode!(ED25519DOUBLE);
+declare_opcode!(U256ADD);
+declare_opcode!(U256MUL);
+declare_opcode!(U256MODMUL);
@@ -0,0 +1,48 @@
+use crate::constants::{ROUNDS_F, ROUNDS_P, WIDTH};
+use core::borrow::{Borrow, BorrowMut};
+use core::mem::{size_of, transmute};
+use valida_derive::AlignedBorrow;
+use valida_machine::Word;
+use valida_util::indices_arr;
+
+/// Each row of the trace computes a whole permutation. The S-box `x^7` of every round is
+/// split into `x^3` and `x^7` columns, which keeps the constraints at degree 3.
+#[derive(AlignedBorrow)]
+pub struct Poseidon2Cols<T> {
+    /// Whether this row is a permutation made by an instruction, not padding
+    pub is_real: T,
+
+    /// CPU clock of the `POSEIDON2` instruction
+    pub clk: T,
+    /// Addresses of the input and the output, as seen by the CPU
+    pub input_addr: Word<T>,
+    pub output_addr: Word<T>,
+    /// The same addresses, as field elements
+    pub input_ptr: T,
+    pub output_ptr: T,
+
+    /// The state read from memory, one field element per cell
+    pub input: [Word<T>; WIDTH],
+
+    /// The cubes of the S-box inputs, and the S-box outputs, of the full rounds
+    pub full_cubes: [[T; WIDTH]; ROUNDS_F],
+    pub full_sboxes: [[T; WIDTH]; ROUNDS_F],
+    /// The same for the first element in the partial rounds
+    pub partial_cubes: [T; ROUNDS_P],
+    pub partial_sboxes: [T; ROUNDS_P],
+
+    /// The permuted state written to memory
+    pub output: [Word<T>; WIDTH],
+    /// Whether the top byte of each output element is that of the modulus, `0x78`
+    pub output_top_is_max: [T; WIDTH],
+    /// Otherwise, how far the top byte is below `0x77`, which is range checked
+    pub output_top_gap: [T; WIDTH],
+}
+
+pub const NUM_POSEIDON2_COLS: usize = size_of::<Poseidon2Cols<u8>>();
+pub const POSEIDON2_COL_MAP: Poseidon2Cols<usize> = make_col_map();
+
+const fn make_col_map() -> Poseidon2Cols<usize> {
+    let indices_arr = indices_arr::<NUM_POSEIDON2_COLS>();
+    unsafe { transmute::<[usize; NUM_POSEIDON2_COLS], Poseidon2Cols<usize>>(indices_arr) }
+}
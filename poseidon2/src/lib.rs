@@ -0,0 +1,268 @@
+#![no_std]
+
+extern crate alloc;
+
+use crate::columns::{Poseidon2Cols, NUM_POSEIDON2_COLS, POSEIDON2_COL_MAP};
+use crate::constants::{
+    EXTERNAL_FINAL_CONSTANTS, EXTERNAL_INITIAL_CONSTANTS, INTERNAL_CONSTANTS, ROUNDS_F, WIDTH,
+};
+use crate::permutation::{external_linear_layer, internal_linear_layer};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::transmute;
+use valida_bus::{MachineWithGeneralBus, MachineWithMemBus, MachineWithRangeBus8};
+use valida_cpu::MachineWithCpuChip;
+use valida_machine::{instructions, Chip, Instruction, Interaction, Operands, Word};
+use valida_opcodes::POSEIDON2;
+use valida_range::MachineWithRangeChip;
+
+use p3_air::VirtualPairCol;
+use p3_field::{AbstractField, Field, PrimeField32};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_maybe_rayon::prelude::*;
+use valida_machine::StarkConfig;
+
+pub mod columns;
+pub mod constants;
+pub mod permutation;
+pub mod stark;
+
+pub use permutation::permute;
+
+/// A permutation, as executed by `POSEIDON2`.
+#[derive(Clone, Debug)]
+pub struct Poseidon2Operation {
+    pub clk: u32,
+    /// Address of the input state
+    pub input_addr: Word<u8>,
+    /// Address of the output state, which may be the same as the input
+    pub output_addr: Word<u8>,
+    /// The memory cells of the input state
+    pub input: [Word<u8>; WIDTH],
+}
+
+#[derive(Default)]
+pub struct Poseidon2Chip {
+    pub operations: Vec<Poseidon2Operation>,
+}
+
+impl<M, SC> Chip<M, SC> for Poseidon2Chip
+where
+    M: MachineWithGeneralBus<SC::Val> + MachineWithMemBus<SC::Val> + MachineWithRangeBus8<SC::Val>,
+    SC: StarkConfig,
+{
+    fn generate_trace(&self, _machine: &M) -> RowMajorMatrix<SC::Val> {
+        // The trace is padded with permutations of a zero state.
+        let num_rows = self.operations.len().next_power_of_two();
+        let rows = (0..num_rows)
+            .into_par_iter()
+            .map(|i| self.op_to_row(self.operations.get(i)))
+            .collect::<Vec<_>>();
+        RowMajorMatrix::new(rows.into_iter().flatten().collect(), NUM_POSEIDON2_COLS)
+    }
+
+    fn global_sends(&self, machine: &M) -> Vec<Interaction<SC::Val>> {
+        let clk = VirtualPairCol::single_main(POSEIDON2_COL_MAP.clk);
+        let is_static_initial = VirtualPairCol::constant(SC::Val::zero());
+        let memory = |is_read: bool, ptr: usize, i: usize, word: &Word<usize>| {
+            let addr = VirtualPairCol::new_main(
+                vec![(ptr, SC::Val::one())],
+                SC::Val::from_canonical_u32(4 * i as u32),
+            );
+            let mut fields = vec![
+                VirtualPairCol::constant(SC::Val::from_bool(is_read)),
+                clk.clone(),
+                addr,
+                is_static_initial.clone(),
+            ];
+            fields.extend(word.0.map(VirtualPairCol::single_main));
+            Interaction {
+                fields,
+                count: VirtualPairCol::single_main(POSEIDON2_COL_MAP.is_real),
+                argument_index: machine.mem_bus(),
+            }
+        };
+
+        let mut sends = vec![];
+        for (i, word) in POSEIDON2_COL_MAP.input.iter().enumerate() {
+            sends.push(memory(true, POSEIDON2_COL_MAP.input_ptr, i, word));
+        }
+        for (i, word) in POSEIDON2_COL_MAP.output.iter().enumerate() {
+            sends.push(memory(false, POSEIDON2_COL_MAP.output_ptr, i, word));
+        }
+
+        // The bytes of the output, and the gaps below the top byte of the modulus, are range
+        // checked.
+        let output_bytes = POSEIDON2_COL_MAP.output.into_iter().flatten();
+        for col in output_bytes.chain(POSEIDON2_COL_MAP.output_top_gap) {
+            sends.push(Interaction {
+                fields: vec![VirtualPairCol::single_main(col)],
+                count: VirtualPairCol::single_main(POSEIDON2_COL_MAP.is_real),
+                argument_index: machine.range_bus(),
+            });
+        }
+        sends
+    }
+
+    fn global_receives(&self, machine: &M) -> Vec<Interaction<SC::Val>> {
+        let opcode = VirtualPairCol::constant(SC::Val::from_canonical_u32(POSEIDON2));
+        let input_addr = POSEIDON2_COL_MAP
+            .input_addr
+            .0
+            .map(VirtualPairCol::single_main);
+        let output_addr = POSEIDON2_COL_MAP
+            .output_addr
+            .0
+            .map(VirtualPairCol::single_main);
+        let clk = VirtualPairCol::single_main(POSEIDON2_COL_MAP.clk);
+
+        // The CPU writes the output address to the destination operand
+        let mut fields = vec![opcode];
+        fields.extend(input_addr);
+        fields.extend(output_addr.clone());
+        fields.extend(output_addr);
+        fields.push(clk);
+
+        let receive = Interaction {
+            fields,
+            count: VirtualPairCol::single_main(POSEIDON2_COL_MAP.is_real),
+            argument_index: machine.general_bus(),
+        };
+        vec![receive]
+    }
+}
+
+impl Poseidon2Chip {
+    /// The row of a permutation. Padding permutations have no operation.
+    fn op_to_row<F: PrimeField32>(
+        &self,
+        op: Option<&Poseidon2Operation>,
+    ) -> [F; NUM_POSEIDON2_COLS] {
+        let mut row = [F::zero(); NUM_POSEIDON2_COLS];
+        let cols: &mut Poseidon2Cols<F> = unsafe { transmute(&mut row) };
+
+        let input = op.map_or([Word::default(); WIDTH], |op| op.input);
+        cols.input = input.map(|word| word.transform(F::from_canonical_u8));
+
+        let mut state = input.map(|word| F::from_wrapped_u32(word.into()));
+        let mut full_round = |state: &mut [F; WIDTH], constants: &[u32; WIDTH], round: usize| {
+            for (i, x) in state.iter_mut().enumerate() {
+                let y = *x + F::from_canonical_u32(constants[i]);
+                cols.full_cubes[round][i] = y.cube();
+                *x = cols.full_cubes[round][i].square() * y;
+                cols.full_sboxes[round][i] = *x;
+            }
+            external_linear_layer(state);
+        };
+
+        external_linear_layer(&mut state);
+        for (round, constants) in EXTERNAL_INITIAL_CONSTANTS.iter().enumerate() {
+            full_round(&mut state, constants, round);
+        }
+        let mut partial_cubes = [F::zero(); INTERNAL_CONSTANTS.len()];
+        let mut partial_sboxes = [F::zero(); INTERNAL_CONSTANTS.len()];
+        for (round, &constant) in INTERNAL_CONSTANTS.iter().enumerate() {
+            let y = state[0] + F::from_canonical_u32(constant);
+            partial_cubes[round] = y.cube();
+            state[0] = partial_cubes[round].square() * y;
+            partial_sboxes[round] = state[0];
+            internal_linear_layer(&mut state);
+        }
+        for (round, constants) in EXTERNAL_FINAL_CONSTANTS.iter().enumerate() {
+            full_round(&mut state, constants, ROUNDS_F / 2 + round);
+        }
+        cols.partial_cubes = partial_cubes;
+        cols.partial_sboxes = partial_sboxes;
+        cols.output =
+            state.map(|x| Word::from(x.as_canonical_u32()).transform(F::from_canonical_u8));
+        for (i, x) in state.iter().enumerate() {
+            let x = x.as_canonical_u32();
+            cols.output_top_is_max[i] = F::from_bool(x >> 24 == 0x78);
+            cols.output_top_gap[i] = F::from_canonical_u32(top_byte_gap(x));
+        }
+
+        if let Some(op) = op {
+            cols.is_real = F::one();
+            cols.clk = F::from_canonical_u32(op.clk);
+            cols.input_addr = op.input_addr.transform(F::from_canonical_u8);
+            cols.output_addr = op.output_addr.transform(F::from_canonical_u8);
+            cols.input_ptr = F::from_canonical_u32(op.input_addr.into());
+            cols.output_ptr = F::from_canonical_u32(op.output_addr.into());
+        }
+        row
+    }
+}
+
+/// How far the top byte of a canonical BabyBear element is below `0x77`, or zero if it is
+/// `0x78`, the top byte of the modulus.
+fn top_byte_gap(x: u32) -> u32 {
+    0x77u32.saturating_sub(x >> 24)
+}
+
+pub trait MachineWithPoseidon2Chip<F: Field>: MachineWithCpuChip<F> {
+    fn poseidon2(&self) -> &Poseidon2Chip;
+    fn poseidon2_mut(&mut self) -> &mut Poseidon2Chip;
+}
+
+instructions!(Poseidon2Instruction);
+
+impl<M, F> Instruction<M, F> for Poseidon2Instruction
+where
+    M: MachineWithPoseidon2Chip<F> + MachineWithRangeChip<F, 256>,
+    F: PrimeField32,
+{
+    const OPCODE: u32 = POSEIDON2;
+
+    /// `POSEIDON2 a(fp), b(fp), c(fp)` applies the Poseidon2 permutation to the 16 field
+    /// elements at the address in `b(fp)`, and writes the result to the address in `c(fp)`,
+    /// which may be the same. The output address is also written to `a(fp)`. Like the
+    /// native field instructions, each memory cell holds one field element.
+    fn execute(state: &mut M, ops: Operands<i32>) {
+        let opcode = <Self as Instruction<M, F>>::OPCODE;
+        let clk = state.cpu().clock;
+        let pc = state.cpu().pc;
+        let fp = state.cpu().fp;
+        let read_addr_1 = (fp as i32 + ops.b()) as u32;
+        let read_addr_2 = (fp as i32 + ops.c()) as u32;
+        let write_addr = (fp as i32 + ops.a()) as u32;
+
+        let input_addr = state
+            .mem_mut()
+            .read(clk, read_addr_1, true, pc, opcode, 0, "");
+        let output_addr = state
+            .mem_mut()
+            .read(clk, read_addr_2, true, pc, opcode, 1, "");
+
+        let input_base: u32 = input_addr.into();
+        let output_base: u32 = output_addr.into();
+        let input: [Word<u8>; WIDTH] = core::array::from_fn(|i| {
+            state
+                .mem_mut()
+                .coprocessor_read(clk, input_base.wrapping_add(4 * i as u32))
+        });
+
+        let output = permute(input.map(|word| F::from_wrapped_u32(word.into())));
+        for (i, x) in output.into_iter().enumerate() {
+            let word = Word::from(x.as_canonical_u32());
+            state
+                .mem_mut()
+                .coprocessor_write(clk, output_base.wrapping_add(4 * i as u32), word);
+            state.range_check(word);
+        }
+        let range_count = &mut state.range_mut().count;
+        for x in output {
+            *range_count
+                .entry(top_byte_gap(x.as_canonical_u32()))
+                .or_insert(0) += 1;
+        }
+        state.mem_mut().write(clk, write_addr, output_addr, true);
+
+        state.poseidon2_mut().operations.push(Poseidon2Operation {
+            clk,
+            input_addr,
+            output_addr,
+            input,
+        });
+        state.cpu_mut().push_bus_op_with_memory(None, opcode, ops);
+    }
+}
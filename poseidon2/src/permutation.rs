@@ -0,0 +1,76 @@
+use crate::constants::{
+    EXTERNAL_FINAL_CONSTANTS, EXTERNAL_INITIAL_CONSTANTS, INTERNAL_CONSTANTS, INTERNAL_DIAGONAL,
+    WIDTH,
+};
+use p3_field::AbstractField;
+
+/// The Poseidon2 permutation of a state of 16 BabyBear elements.
+pub fn permute<E: AbstractField>(mut state: [E; WIDTH]) -> [E; WIDTH] {
+    external_linear_layer(&mut state);
+    for constants in EXTERNAL_INITIAL_CONSTANTS {
+        full_round(&mut state, &constants);
+    }
+    for constant in INTERNAL_CONSTANTS {
+        partial_round(&mut state, constant);
+    }
+    for constants in EXTERNAL_FINAL_CONSTANTS {
+        full_round(&mut state, &constants);
+    }
+    state
+}
+
+fn full_round<E: AbstractField>(state: &mut [E; WIDTH], constants: &[u32; WIDTH]) {
+    for (x, &c) in state.iter_mut().zip(constants) {
+        *x = sbox(x.clone() + E::from_canonical_u32(c));
+    }
+    external_linear_layer(state);
+}
+
+fn partial_round<E: AbstractField>(state: &mut [E; WIDTH], constant: u32) {
+    state[0] = sbox(state[0].clone() + E::from_canonical_u32(constant));
+    internal_linear_layer(state);
+}
+
+/// The S-box `x^7`.
+pub fn sbox<E: AbstractField>(x: E) -> E {
+    let cube = x.cube();
+    cube.square() * x
+}
+
+/// Multiplies the state by `circ(2 M4, M4, M4, M4)`, where `M4` is the 4x4 matrix of the
+/// Poseidon2 paper.
+pub fn external_linear_layer<E: AbstractField>(state: &mut [E; WIDTH]) {
+    for chunk in state.chunks_exact_mut(4) {
+        let mixed = apply_m4(chunk);
+        chunk.clone_from_slice(&mixed);
+    }
+    let sums: [E; 4] = core::array::from_fn(|k| state.iter().skip(k).step_by(4).cloned().sum());
+    for (i, x) in state.iter_mut().enumerate() {
+        *x = x.clone() + sums[i % 4].clone();
+    }
+}
+
+/// Multiplies the state by `1 + diag(V)`, where `1` is the matrix of ones.
+pub fn internal_linear_layer<E: AbstractField>(state: &mut [E; WIDTH]) {
+    let sum: E = state.iter().cloned().sum();
+    for (x, &v) in state.iter_mut().zip(&INTERNAL_DIAGONAL) {
+        *x = x.clone() * E::from_canonical_u32(v) + sum.clone();
+    }
+}
+
+/// Multiplies four elements by
+/// ```text
+/// [ 5 7 1 3 ]
+/// [ 4 6 1 1 ]
+/// [ 1 3 5 7 ]
+/// [ 1 1 4 6 ]
+/// ```
+fn apply_m4<E: AbstractField>(x: &[E]) -> [E; 4] {
+    const M4: [[u32; 4]; 4] = [[5, 7, 1, 3], [4, 6, 1, 1], [1, 3, 5, 7], [1, 1, 4, 6]];
+    M4.map(|row| {
+        row.iter()
+            .zip(x)
+            .map(|(&m, x_j)| x_j.clone() * E::from_canonical_u32(m))
+            .sum()
+    })
+}
@@ -0,0 +1,116 @@
+use crate::columns::{Poseidon2Cols, NUM_POSEIDON2_COLS};
+use crate::constants::{
+    EXTERNAL_FINAL_CONSTANTS, EXTERNAL_INITIAL_CONSTANTS, INTERNAL_CONSTANTS, ROUNDS_F, WIDTH,
+};
+use crate::permutation::{external_linear_layer, internal_linear_layer};
+use crate::Poseidon2Chip;
+use core::borrow::Borrow;
+use valida_machine::Word;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, PrimeField};
+use p3_matrix::MatrixRowSlices;
+
+impl<F> BaseAir<F> for Poseidon2Chip {
+    fn width(&self) -> usize {
+        NUM_POSEIDON2_COLS
+    }
+}
+
+impl<F, AB> Air<AB> for Poseidon2Chip
+where
+    F: PrimeField,
+    AB: AirBuilder<F = F>,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local: &Poseidon2Cols<AB::Var> = main.row_slice(0).borrow();
+
+        let base = [1 << 24, 1 << 16, 1 << 8, 1].map(AB::Expr::from_canonical_u32);
+
+        builder.assert_bool(local.is_real);
+        builder.assert_eq(local.input_ptr, reduce::<AB>(&base, local.input_addr));
+        builder.assert_eq(local.output_ptr, reduce::<AB>(&base, local.output_addr));
+
+        // Memory cells hold field elements as their canonical value.
+        let mut state: [AB::Expr; WIDTH] = local.input.map(|word| reduce::<AB>(&base, word));
+
+        external_linear_layer(&mut state);
+        for (round, constants) in EXTERNAL_INITIAL_CONSTANTS.iter().enumerate() {
+            eval_full_round(builder, &mut state, constants, local, round);
+        }
+        for (round, &constant) in INTERNAL_CONSTANTS.iter().enumerate() {
+            state[0] = eval_sbox(
+                builder,
+                state[0].clone() + AB::Expr::from_canonical_u32(constant),
+                local.partial_cubes[round],
+                local.partial_sboxes[round],
+            );
+            internal_linear_layer(&mut state);
+        }
+        for (round, constants) in EXTERNAL_FINAL_CONSTANTS.iter().enumerate() {
+            eval_full_round(builder, &mut state, constants, local, ROUNDS_F / 2 + round);
+        }
+
+        for (x, word) in state.into_iter().zip(local.output) {
+            builder.assert_eq(x, reduce::<AB>(&base, word));
+        }
+
+        // The output is canonical, i.e. below the BabyBear modulus `0x78000001`: either its
+        // top byte is below `0x78`, or it is exactly `0x78000000`.
+        for i in 0..WIDTH {
+            let word = local.output[i];
+            let is_max = local.output_top_is_max[i];
+            builder.assert_bool(is_max);
+            builder
+                .when(is_max)
+                .assert_eq(word[0], AB::Expr::from_canonical_u32(0x78));
+            builder
+                .when(is_max)
+                .assert_zero(word[1] + word[2] + word[3]);
+            builder.assert_eq(
+                local.output_top_gap[i],
+                (AB::Expr::one() - is_max) * (AB::Expr::from_canonical_u32(0x77) - word[0]),
+            );
+        }
+    }
+}
+
+fn eval_full_round<AB: AirBuilder>(
+    builder: &mut AB,
+    state: &mut [AB::Expr; WIDTH],
+    constants: &[u32; WIDTH],
+    local: &Poseidon2Cols<AB::Var>,
+    round: usize,
+) {
+    for (i, x) in state.iter_mut().enumerate() {
+        *x = eval_sbox(
+            builder,
+            x.clone() + AB::Expr::from_canonical_u32(constants[i]),
+            local.full_cubes[round][i],
+            local.full_sboxes[round][i],
+        );
+    }
+    external_linear_layer(state);
+}
+
+/// Constrains `cube` to be `x^3` and `sbox` to be `x^7`, and returns the latter.
+fn eval_sbox<AB: AirBuilder>(
+    builder: &mut AB,
+    x: AB::Expr,
+    cube: AB::Var,
+    sbox: AB::Var,
+) -> AB::Expr {
+    builder.assert_eq(cube, x.cube());
+    let cube: AB::Expr = cube.into();
+    builder.assert_eq(sbox, cube.square() * x);
+    sbox.into()
+}
+
+fn reduce<AB: AirBuilder>(base: &[AB::Expr], input: Word<AB::Var>) -> AB::Expr {
+    input
+        .into_iter()
+        .enumerate()
+        .map(|(i, x)| base[i].clone() * x)
+        .sum()
+}
@@ -0,0 +1,70 @@
+use core::borrow::{Borrow, BorrowMut};
+use core::mem::{size_of, transmute};
+use valida_derive::AlignedBorrow;
+use valida_machine::Word;
+use valida_util::indices_arr;
+
+/// The SHA-256 compression function has 64 rounds, and each row of the trace computes one
+/// of them.
+pub const NUM_ROUNDS: usize = 64;
+/// The state is 8 words, which occupy a memory cell each.
+pub const STATE_WORDS: usize = 8;
+/// A block of the message is 16 words.
+pub const BLOCK_WORDS: usize = 16;
+
+#[derive(AlignedBorrow)]
+pub struct Sha256Cols<T> {
+    /// Which round this row computes
+    pub step_flags: [T; NUM_ROUNDS],
+
+    /// Whether this row belongs to a compression made by an instruction, not padding
+    pub is_real: T,
+    /// Whether the state and block are read from memory (first round of a real compression)
+    pub is_read: T,
+    /// Whether the state is written to memory (last round of a real compression)
+    pub is_write: T,
+
+    /// CPU clock of the `SHA256COMPRESS` instruction
+    pub clk: T,
+    /// Addresses of the state and the block, as seen by the CPU
+    pub state_addr: Word<T>,
+    pub block_addr: Word<T>,
+    /// The same addresses, as field elements
+    pub state_ptr: T,
+    pub block_ptr: T,
+
+    /// The state at the start of the compression
+    pub initial_state: [Word<T>; STATE_WORDS],
+
+    /// The working variables `a..h` at the start of the round
+    pub vars: [Word<T>; 8],
+    /// The working variables as bits, least significant first
+    pub vars_bits: [[T; 32]; 8],
+
+    /// Words `i..i + 16` of the message schedule, where `i` is the round
+    pub w: [Word<T>; BLOCK_WORDS],
+    /// `w[1]` and `w[14]` as bits, for the small sigma functions
+    pub w_1_bits: [T; 32],
+    pub w_14_bits: [T; 32],
+
+    /// The new `a` and `e` of the round, and word `i + 16` of the message schedule. Each is
+    /// a sum of words with the carry out of each byte, least significant byte first.
+    pub new_a: Word<T>,
+    pub new_a_carry: [T; 4],
+    pub new_e: Word<T>,
+    pub new_e_carry: [T; 4],
+    pub w_16: Word<T>,
+    pub w_16_carry: [T; 4],
+
+    /// The state at the end of the compression, set in the last round
+    pub output: [Word<T>; STATE_WORDS],
+    pub output_carry: [[T; 4]; STATE_WORDS],
+}
+
+pub const NUM_SHA256_COLS: usize = size_of::<Sha256Cols<u8>>();
+pub const SHA256_COL_MAP: Sha256Cols<usize> = make_col_map();
+
+const fn make_col_map() -> Sha256Cols<usize> {
+    let indices_arr = indices_arr::<NUM_SHA256_COLS>();
+    unsafe { transmute::<[usize; NUM_SHA256_COLS], Sha256Cols<usize>>(indices_arr) }
+}
@@ -0,0 +1,91 @@
+use crate::columns::NUM_ROUNDS;
+
+/// Round constants, added to the working variables in each round.
+pub const K: [u32; NUM_ROUNDS] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+pub fn big_sigma_0(x: u32) -> u32 {
+    x.rotate_right(2) ^ x.rotate_right(13) ^ x.rotate_right(22)
+}
+
+pub fn big_sigma_1(x: u32) -> u32 {
+    x.rotate_right(6) ^ x.rotate_right(11) ^ x.rotate_right(25)
+}
+
+pub fn small_sigma_0(x: u32) -> u32 {
+    x.rotate_right(7) ^ x.rotate_right(18) ^ (x >> 3)
+}
+
+pub fn small_sigma_1(x: u32) -> u32 {
+    x.rotate_right(17) ^ x.rotate_right(19) ^ (x >> 10)
+}
+
+pub fn ch(e: u32, f: u32, g: u32) -> u32 {
+    (e & f) ^ (!e & g)
+}
+
+pub fn maj(a: u32, b: u32, c: u32) -> u32 {
+    (a & b) ^ (a & c) ^ (b & c)
+}
+
+/// The terms added to give the new `a` of a round, where `vars` are the working variables
+/// `a..h` at its start and `w` is its word of the message schedule.
+pub fn new_a_terms(vars: &[u32; 8], round: usize, w: u32) -> [u32; 7] {
+    let [a, b, c, _, e, f, g, h] = *vars;
+    [
+        h,
+        big_sigma_1(e),
+        ch(e, f, g),
+        K[round],
+        w,
+        big_sigma_0(a),
+        maj(a, b, c),
+    ]
+}
+
+/// The terms added to give the new `e` of a round.
+pub fn new_e_terms(vars: &[u32; 8], round: usize, w: u32) -> [u32; 6] {
+    let [_, _, _, d, e, f, g, h] = *vars;
+    [d, h, big_sigma_1(e), ch(e, f, g), K[round], w]
+}
+
+/// The terms added to give `W[i + 16]`, where `w` is `W[i..i + 16]`.
+pub fn schedule_terms(w: &[u32; 16]) -> [u32; 4] {
+    [small_sigma_1(w[14]), w[9], small_sigma_0(w[1]), w[0]]
+}
+
+/// The sum of words modulo 2^32.
+pub fn sum(terms: &[u32]) -> u32 {
+    terms.iter().fold(0, |acc, term| acc.wrapping_add(*term))
+}
+
+/// Compute a round of SHA-256 on the working variables `a..h`.
+pub fn round(vars: [u32; 8], round: usize, w: u32) -> [u32; 8] {
+    let [a, b, c, _, e, f, g, _] = vars;
+    let new_a = sum(&new_a_terms(&vars, round, w));
+    let new_e = sum(&new_e_terms(&vars, round, w));
+    [new_a, a, b, c, new_e, e, f, g]
+}
+
+/// Apply the SHA-256 compression function to `state` with a block of 16 words.
+pub fn compress(state: &mut [u32; 8], block: &[u32; 16]) {
+    let mut vars = *state;
+    let mut w = *block;
+    for i in 0..NUM_ROUNDS {
+        vars = round(vars, i, w[0]);
+        let w_16 = sum(&schedule_terms(&w));
+        w.rotate_left(1);
+        w[15] = w_16;
+    }
+    for (h, var) in state.iter_mut().zip(vars) {
+        *h = h.wrapping_add(var);
+    }
+}
@@ -0,0 +1,355 @@
+#![no_std]
+
+extern crate alloc;
+
+use crate::columns::{
+    Sha256Cols, BLOCK_WORDS, NUM_ROUNDS, NUM_SHA256_COLS, SHA256_COL_MAP, STATE_WORDS,
+};
+use crate::compress::{new_a_terms, new_e_terms, round, schedule_terms, sum};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::transmute;
+use valida_bus::{MachineWithGeneralBus, MachineWithMemBus, MachineWithRangeBus8};
+use valida_cpu::MachineWithCpuChip;
+use valida_machine::{instructions, Chip, Instruction, Interaction, Operands, Word};
+use valida_opcodes::SHA256COMPRESS;
+use valida_range::MachineWithRangeChip;
+
+use p3_air::VirtualPairCol;
+use p3_field::{AbstractField, Field, PrimeField};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_maybe_rayon::prelude::*;
+use valida_machine::StarkConfig;
+
+pub mod columns;
+pub mod compress;
+pub mod stark;
+
+pub use compress::compress;
+
+/// A SHA-256 compression, as executed by `SHA256COMPRESS`.
+#[derive(Clone, Debug)]
+pub struct Sha256Operation {
+    pub clk: u32,
+    /// Address of the state, which is read and then overwritten
+    pub state_addr: Word<u8>,
+    /// Address of the block
+    pub block_addr: Word<u8>,
+    /// The state before the compression
+    pub state: [u32; STATE_WORDS],
+    pub block: [u32; BLOCK_WORDS],
+}
+
+#[derive(Default)]
+pub struct Sha256Chip {
+    pub operations: Vec<Sha256Operation>,
+}
+
+impl<M, SC> Chip<M, SC> for Sha256Chip
+where
+    M: MachineWithGeneralBus<SC::Val> + MachineWithMemBus<SC::Val> + MachineWithRangeBus8<SC::Val>,
+    SC: StarkConfig,
+{
+    fn generate_trace(&self, _machine: &M) -> RowMajorMatrix<SC::Val> {
+        // The trace is padded with compressions of a zero state and block. The last one is
+        // cut short if the number of rows isn't a multiple of the number of rounds.
+        let num_rows = (self.operations.len() * NUM_ROUNDS).next_power_of_two();
+        let num_compressions = num_rows.div_ceil(NUM_ROUNDS);
+        let rows = (0..num_compressions)
+            .into_par_iter()
+            .map(|i| self.op_to_rows(self.operations.get(i)))
+            .collect::<Vec<_>>()
+            .concat();
+
+        let mut values = rows.into_iter().flatten().collect::<Vec<_>>();
+        values.truncate(num_rows * NUM_SHA256_COLS);
+        RowMajorMatrix::new(values, NUM_SHA256_COLS)
+    }
+
+    fn global_sends(&self, machine: &M) -> Vec<Interaction<SC::Val>> {
+        let clk = VirtualPairCol::single_main(SHA256_COL_MAP.clk);
+        let is_static_initial = VirtualPairCol::constant(SC::Val::zero());
+        let memory = |is_read: VirtualPairCol<SC::Val>,
+                      ptr: usize,
+                      i: usize,
+                      word: &Word<usize>,
+                      count: usize| {
+            let addr = VirtualPairCol::new_main(
+                vec![(ptr, SC::Val::one())],
+                SC::Val::from_canonical_u32(4 * i as u32),
+            );
+            let mut fields = vec![is_read, clk.clone(), addr, is_static_initial.clone()];
+            fields.extend(word.0.map(VirtualPairCol::single_main));
+            Interaction {
+                fields,
+                count: VirtualPairCol::single_main(count),
+                argument_index: machine.mem_bus(),
+            }
+        };
+        let range = |value: usize, count: usize| Interaction {
+            fields: vec![VirtualPairCol::single_main(value)],
+            count: VirtualPairCol::single_main(count),
+            argument_index: machine.range_bus(),
+        };
+
+        let read = VirtualPairCol::single_main(SHA256_COL_MAP.is_read);
+        let write = VirtualPairCol::constant(SC::Val::zero());
+        let mut sends = vec![];
+        for (i, word) in SHA256_COL_MAP.initial_state.iter().enumerate() {
+            sends.push(memory(
+                read.clone(),
+                SHA256_COL_MAP.state_ptr,
+                i,
+                word,
+                SHA256_COL_MAP.is_read,
+            ));
+        }
+        for (i, word) in SHA256_COL_MAP.w.iter().enumerate() {
+            sends.push(memory(
+                read.clone(),
+                SHA256_COL_MAP.block_ptr,
+                i,
+                word,
+                SHA256_COL_MAP.is_read,
+            ));
+        }
+        for (i, word) in SHA256_COL_MAP.output.iter().enumerate() {
+            sends.push(memory(
+                write.clone(),
+                SHA256_COL_MAP.state_ptr,
+                i,
+                word,
+                SHA256_COL_MAP.is_write,
+            ));
+        }
+
+        // The bytes and carries of every sum are range checked.
+        let sums = [
+            (SHA256_COL_MAP.new_a, SHA256_COL_MAP.new_a_carry),
+            (SHA256_COL_MAP.new_e, SHA256_COL_MAP.new_e_carry),
+            (SHA256_COL_MAP.w_16, SHA256_COL_MAP.w_16_carry),
+        ];
+        for (value, carry) in sums {
+            for col in value.into_iter().chain(carry) {
+                sends.push(range(col, SHA256_COL_MAP.is_real));
+            }
+        }
+        for (value, carry) in SHA256_COL_MAP
+            .output
+            .iter()
+            .zip(SHA256_COL_MAP.output_carry)
+        {
+            for col in value.into_iter().chain(carry) {
+                sends.push(range(col, SHA256_COL_MAP.is_write));
+            }
+        }
+        sends
+    }
+
+    fn global_receives(&self, machine: &M) -> Vec<Interaction<SC::Val>> {
+        let opcode = VirtualPairCol::constant(SC::Val::from_canonical_u32(SHA256COMPRESS));
+        let state_addr = SHA256_COL_MAP.state_addr.0.map(VirtualPairCol::single_main);
+        let block_addr = SHA256_COL_MAP.block_addr.0.map(VirtualPairCol::single_main);
+        let clk = VirtualPairCol::single_main(SHA256_COL_MAP.clk);
+
+        // The CPU writes the state address to the destination operand
+        let mut fields = vec![opcode];
+        fields.extend(state_addr.clone());
+        fields.extend(block_addr);
+        fields.extend(state_addr);
+        fields.push(clk);
+
+        let receive = Interaction {
+            fields,
+            count: VirtualPairCol::single_main(SHA256_COL_MAP.is_read),
+            argument_index: machine.general_bus(),
+        };
+        vec![receive]
+    }
+}
+
+impl Sha256Chip {
+    /// The rows of a compression, one per round. Padding compressions have no operation.
+    fn op_to_rows<F: PrimeField>(&self, op: Option<&Sha256Operation>) -> Vec<[F; NUM_SHA256_COLS]> {
+        let state = op.map_or([0; STATE_WORDS], |op| op.state);
+        let mut vars = state;
+        let mut w = op.map_or([0; BLOCK_WORDS], |op| op.block);
+
+        let mut rows = vec![[F::zero(); NUM_SHA256_COLS]; NUM_ROUNDS];
+        for (i, row) in rows.iter_mut().enumerate() {
+            let cols: &mut Sha256Cols<F> = unsafe { transmute(row) };
+            cols.step_flags[i] = F::one();
+
+            cols.initial_state = state.map(word);
+            cols.vars = vars.map(word);
+            cols.vars_bits = vars.map(bits);
+            cols.w = w.map(word);
+            cols.w_1_bits = bits(w[1]);
+            cols.w_14_bits = bits(w[14]);
+
+            (cols.new_a, cols.new_a_carry) = sum_cols(&new_a_terms(&vars, i, w[0]));
+            (cols.new_e, cols.new_e_carry) = sum_cols(&new_e_terms(&vars, i, w[0]));
+            (cols.w_16, cols.w_16_carry) = sum_cols(&schedule_terms(&w));
+
+            let w_16 = sum(&schedule_terms(&w));
+            vars = round(vars, i, w[0]);
+            w.rotate_left(1);
+            w[15] = w_16;
+
+            if i == NUM_ROUNDS - 1 {
+                for j in 0..STATE_WORDS {
+                    (cols.output[j], cols.output_carry[j]) = sum_cols(&[state[j], vars[j]]);
+                }
+            }
+
+            if let Some(op) = op {
+                cols.is_real = F::one();
+                cols.clk = F::from_canonical_u32(op.clk);
+                cols.state_addr = op.state_addr.transform(F::from_canonical_u8);
+                cols.block_addr = op.block_addr.transform(F::from_canonical_u8);
+                cols.state_ptr = F::from_canonical_u32(op.state_addr.into());
+                cols.block_ptr = F::from_canonical_u32(op.block_addr.into());
+                if i == 0 {
+                    cols.is_read = F::one();
+                }
+                if i == NUM_ROUNDS - 1 {
+                    cols.is_write = F::one();
+                }
+            }
+        }
+        rows
+    }
+}
+
+fn word<F: PrimeField>(value: u32) -> Word<F> {
+    Word::<u8>::from(value).transform(F::from_canonical_u8)
+}
+
+fn bits<F: PrimeField>(value: u32) -> [F; 32] {
+    core::array::from_fn(|i| F::from_canonical_u32((value >> i) & 1))
+}
+
+fn sum_cols<F: PrimeField>(terms: &[u32]) -> (Word<F>, [F; 4]) {
+    let (sum, carry) = add_bytes(terms);
+    (
+        sum.transform(F::from_canonical_u8),
+        carry.map(F::from_canonical_u8),
+    )
+}
+
+/// The sum of `terms` modulo 2^32, and the carry out of each byte of the sum, least
+/// significant byte first.
+fn add_bytes(terms: &[u32]) -> (Word<u8>, [u8; 4]) {
+    let mut sum = Word::default();
+    let mut carry = [0; 4];
+    let mut carry_in = 0;
+    for k in 0..4 {
+        let total = terms.iter().map(|t| (t >> (8 * k)) & 0xFF).sum::<u32>() + carry_in;
+        sum[3 - k] = total as u8;
+        carry_in = total >> 8;
+        carry[k] = carry_in as u8;
+    }
+    (sum, carry)
+}
+
+/// The sums that the trace of a compression range checks, and their carries, along with
+/// the new state.
+fn compress_with_sums(
+    state: [u32; STATE_WORDS],
+    block: [u32; BLOCK_WORDS],
+) -> ([u32; STATE_WORDS], Vec<Word<u8>>) {
+    let mut checked = vec![];
+    let mut check = |terms: &[u32]| {
+        let (sum, carry) = add_bytes(terms);
+        checked.push(sum);
+        checked.push(Word(carry));
+        let sum: u32 = sum.into();
+        sum
+    };
+
+    let mut vars = state;
+    let mut w = block;
+    for i in 0..NUM_ROUNDS {
+        check(&new_a_terms(&vars, i, w[0]));
+        check(&new_e_terms(&vars, i, w[0]));
+        let w_16 = check(&schedule_terms(&w));
+        vars = round(vars, i, w[0]);
+        w.rotate_left(1);
+        w[15] = w_16;
+    }
+    let output = core::array::from_fn(|j| check(&[state[j], vars[j]]));
+    (output, checked)
+}
+
+pub trait MachineWithSha256Chip<F: Field>: MachineWithCpuChip<F> {
+    fn sha256(&self) -> &Sha256Chip;
+    fn sha256_mut(&mut self) -> &mut Sha256Chip;
+}
+
+instructions!(Sha256CompressInstruction);
+
+impl<M, F> Instruction<M, F> for Sha256CompressInstruction
+where
+    M: MachineWithSha256Chip<F> + MachineWithRangeChip<F, 256>,
+    F: Field,
+{
+    const OPCODE: u32 = SHA256COMPRESS;
+
+    /// `SHA256COMPRESS a(fp), b(fp), c(fp)` compresses the 16-word block at the address in
+    /// `c(fp)` into the 8-word state at the address in `b(fp)`, which is overwritten. The
+    /// state address is also written to `a(fp)`. Words are the values of memory cells, so a
+    /// program hashing bytes loads them big-endian.
+    fn execute(state: &mut M, ops: Operands<i32>) {
+        let opcode = <Self as Instruction<M, F>>::OPCODE;
+        let clk = state.cpu().clock;
+        let pc = state.cpu().pc;
+        let fp = state.cpu().fp;
+        let read_addr_1 = (fp as i32 + ops.b()) as u32;
+        let read_addr_2 = (fp as i32 + ops.c()) as u32;
+        let write_addr = (fp as i32 + ops.a()) as u32;
+
+        let state_addr = state
+            .mem_mut()
+            .read(clk, read_addr_1, true, pc, opcode, 0, "");
+        let block_addr = state
+            .mem_mut()
+            .read(clk, read_addr_2, true, pc, opcode, 1, "");
+
+        let state_base: u32 = state_addr.into();
+        let block_base: u32 = block_addr.into();
+        let input: [u32; STATE_WORDS] = core::array::from_fn(|i| {
+            state
+                .mem_mut()
+                .coprocessor_read(clk, state_base.wrapping_add(4 * i as u32))
+                .into()
+        });
+        let block: [u32; BLOCK_WORDS] = core::array::from_fn(|i| {
+            state
+                .mem_mut()
+                .coprocessor_read(clk, block_base.wrapping_add(4 * i as u32))
+                .into()
+        });
+
+        let (output, checked) = compress_with_sums(input, block);
+        for (i, word) in output.into_iter().enumerate() {
+            state.mem_mut().coprocessor_write(
+                clk,
+                state_base.wrapping_add(4 * i as u32),
+                word.into(),
+            );
+        }
+        state.mem_mut().write(clk, write_addr, state_addr, true);
+        for word in checked {
+            state.range_check(word);
+        }
+
+        state.sha256_mut().operations.push(Sha256Operation {
+            clk,
+            state_addr,
+            block_addr,
+            state: input,
+            block,
+        });
+        state.cpu_mut().push_bus_op_with_memory(None, opcode, ops);
+    }
+}
@@ -0,0 +1,313 @@
+use crate::columns::{Sha256Cols, NUM_ROUNDS, NUM_SHA256_COLS, STATE_WORDS};
+use crate::compress::K;
+use crate::Sha256Chip;
+use alloc::vec;
+use core::borrow::Borrow;
+use valida_machine::Word;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, PrimeField};
+use p3_matrix::MatrixRowSlices;
+
+impl<F> BaseAir<F> for Sha256Chip {
+    fn width(&self) -> usize {
+        NUM_SHA256_COLS
+    }
+}
+
+impl<F, AB> Air<AB> for Sha256Chip
+where
+    F: PrimeField,
+    AB: AirBuilder<F = F>,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local: &Sha256Cols<AB::Var> = main.row_slice(0).borrow();
+        let next: &Sha256Cols<AB::Var> = main.row_slice(1).borrow();
+
+        self.eval_round_flags(builder, local, next);
+        self.eval_memory(builder, local, next);
+        self.eval_bits(builder, local);
+        self.eval_round(builder, local, next);
+    }
+}
+
+impl Sha256Chip {
+    fn eval_round_flags<AB: AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &Sha256Cols<AB::Var>,
+        next: &Sha256Cols<AB::Var>,
+    ) {
+        // The trace starts with the first round, and the rounds follow each other in turn.
+        builder.when_first_row().assert_one(local.step_flags[0]);
+        for i in 1..NUM_ROUNDS {
+            builder.when_first_row().assert_zero(local.step_flags[i]);
+        }
+        for i in 0..NUM_ROUNDS {
+            builder
+                .when_transition()
+                .assert_eq(next.step_flags[(i + 1) % NUM_ROUNDS], local.step_flags[i]);
+        }
+    }
+
+    fn eval_memory<AB: AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &Sha256Cols<AB::Var>,
+        next: &Sha256Cols<AB::Var>,
+    ) {
+        let base = [1 << 24, 1 << 16, 1 << 8, 1].map(AB::Expr::from_canonical_u32);
+        let first_step = local.step_flags[0];
+        let final_step = local.step_flags[NUM_ROUNDS - 1];
+        let not_final_step = AB::Expr::one() - final_step;
+
+        builder.assert_bool(local.is_real);
+        builder.assert_eq(local.is_read, local.is_real * first_step);
+        builder.assert_eq(local.is_write, local.is_real * final_step);
+        // A compression cut short by the end of the trace never writes its state, so it
+        // can't be real.
+        builder
+            .when_last_row()
+            .assert_zero(local.is_real * not_final_step.clone());
+
+        // Every round of a compression belongs to the same instruction.
+        let mut constant = vec![(local.is_real, next.is_real), (local.clk, next.clk)];
+        constant.extend(local.state_addr.into_iter().zip(next.state_addr));
+        constant.extend(local.block_addr.into_iter().zip(next.block_addr));
+        for j in 0..STATE_WORDS {
+            constant.extend(
+                local.initial_state[j]
+                    .into_iter()
+                    .zip(next.initial_state[j]),
+            );
+        }
+        for (current, following) in constant {
+            builder
+                .when_transition()
+                .when(not_final_step.clone())
+                .assert_eq(following, current);
+        }
+        builder.assert_eq(local.state_ptr, reduce::<AB>(&base, local.state_addr));
+        builder.assert_eq(local.block_ptr, reduce::<AB>(&base, local.block_addr));
+
+        // The working variables start out as the state read from memory. The block read
+        // from memory is the start of the message schedule, which is `w` in the first row.
+        for j in 0..STATE_WORDS {
+            for limb in 0..4 {
+                builder
+                    .when(first_step)
+                    .assert_eq(local.vars[j][limb], local.initial_state[j][limb]);
+            }
+        }
+
+        // The state written to memory is the sum of the state read and the working
+        // variables after the last round.
+        let final_vars = [
+            local.new_a,
+            local.vars[0],
+            local.vars[1],
+            local.vars[2],
+            local.new_e,
+            local.vars[4],
+            local.vars[5],
+            local.vars[6],
+        ];
+        for (j, var) in final_vars.into_iter().enumerate() {
+            let terms = [word::<AB>(local.initial_state[j]), word::<AB>(var)];
+            for constraint in sum::<AB>(&terms, local.output[j], local.output_carry[j]) {
+                builder.when(final_step).assert_zero(constraint);
+            }
+        }
+    }
+
+    fn eval_bits<AB: AirBuilder>(&self, builder: &mut AB, local: &Sha256Cols<AB::Var>) {
+        // The working variables and the words of the schedule that go through the sigma
+        // functions are decomposed into bits, which also range checks their bytes.
+        let mut decomposed = vec![];
+        for j in 0..8 {
+            decomposed.push((local.vars[j], local.vars_bits[j]));
+        }
+        decomposed.push((local.w[1], local.w_1_bits));
+        decomposed.push((local.w[14], local.w_14_bits));
+
+        for (value, bits) in decomposed {
+            for bit in bits {
+                builder.assert_bool(bit);
+            }
+            let computed = bytes(&bits.map(Into::<AB::Expr>::into));
+            for (limb, computed_limb) in computed.into_iter().enumerate() {
+                builder.assert_eq(computed_limb, value[limb]);
+            }
+        }
+    }
+
+    fn eval_round<AB: AirBuilder>(
+        &self,
+        builder: &mut AB,
+        local: &Sha256Cols<AB::Var>,
+        next: &Sha256Cols<AB::Var>,
+    ) {
+        let final_step = local.step_flags[NUM_ROUNDS - 1];
+        let not_final_step = AB::Expr::one() - final_step;
+
+        let [a, b, c, _, e, f, g, _]: [[AB::Expr; 32]; 8] =
+            local.vars_bits.map(|bits| bits.map(Into::into));
+        let w_1: [AB::Expr; 32] = local.w_1_bits.map(Into::into);
+        let w_14: [AB::Expr; 32] = local.w_14_bits.map(Into::into);
+
+        // Sigma_0(a) = ROTR^2(a) ^ ROTR^13(a) ^ ROTR^22(a)
+        let big_sigma_0 = xor3_bits(rotr(&a, 2), rotr(&a, 13), rotr(&a, 22));
+        // Sigma_1(e) = ROTR^6(e) ^ ROTR^11(e) ^ ROTR^25(e)
+        let big_sigma_1 = xor3_bits(rotr(&e, 6), rotr(&e, 11), rotr(&e, 25));
+        // sigma_0(x) = ROTR^7(x) ^ ROTR^18(x) ^ SHR^3(x)
+        let small_sigma_0 = xor3_bits(rotr(&w_1, 7), rotr(&w_1, 18), shr(&w_1, 3));
+        // sigma_1(x) = ROTR^17(x) ^ ROTR^19(x) ^ SHR^10(x)
+        let small_sigma_1 = xor3_bits(rotr(&w_14, 17), rotr(&w_14, 19), shr(&w_14, 10));
+        // Ch(e, f, g) = (e & f) ^ (!e & g), where at most one of the two is set
+        let ch: [AB::Expr; 32] = core::array::from_fn(|z| {
+            e[z].clone() * f[z].clone() + (AB::Expr::one() - e[z].clone()) * g[z].clone()
+        });
+        // Maj(a, b, c) = (a & b) ^ (a & c) ^ (b & c), which is set when at least two are
+        let maj: [AB::Expr; 32] = core::array::from_fn(|z| {
+            let (a_z, b_z, c_z) = (a[z].clone(), b[z].clone(), c[z].clone());
+            a_z.clone() * b_z.clone() + a_z.clone() * c_z.clone() + b_z.clone() * c_z.clone()
+                - a_z * b_z * c_z * AB::Expr::two()
+        });
+        let k: [AB::Expr; 4] = core::array::from_fn(|limb| {
+            (0..NUM_ROUNDS)
+                .map(|round| {
+                    let byte = (K[round] >> (8 * (3 - limb))) & 0xFF;
+                    local.step_flags[round] * AB::Expr::from_canonical_u32(byte)
+                })
+                .sum()
+        });
+
+        // new a = h + Sigma_1(e) + Ch(e, f, g) + K + W + Sigma_0(a) + Maj(a, b, c)
+        let new_a_terms = [
+            word::<AB>(local.vars[7]),
+            bytes(&big_sigma_1),
+            bytes(&ch),
+            k.clone(),
+            word::<AB>(local.w[0]),
+            bytes(&big_sigma_0),
+            bytes(&maj),
+        ];
+        for constraint in sum::<AB>(&new_a_terms, local.new_a, local.new_a_carry) {
+            builder.assert_zero(constraint);
+        }
+
+        // new e = d + h + Sigma_1(e) + Ch(e, f, g) + K + W
+        let new_e_terms = [
+            word::<AB>(local.vars[3]),
+            word::<AB>(local.vars[7]),
+            bytes(&big_sigma_1),
+            bytes(&ch),
+            k,
+            word::<AB>(local.w[0]),
+        ];
+        for constraint in sum::<AB>(&new_e_terms, local.new_e, local.new_e_carry) {
+            builder.assert_zero(constraint);
+        }
+
+        // W[i + 16] = sigma_1(W[i + 14]) + W[i + 9] + sigma_0(W[i + 1]) + W[i]
+        let w_16_terms = [
+            bytes(&small_sigma_1),
+            word::<AB>(local.w[9]),
+            bytes(&small_sigma_0),
+            word::<AB>(local.w[0]),
+        ];
+        for constraint in sum::<AB>(&w_16_terms, local.w_16, local.w_16_carry) {
+            builder.assert_zero(constraint);
+        }
+
+        // The working variables and the message schedule move on to the next round.
+        let next_vars = [
+            local.new_a,
+            local.vars[0],
+            local.vars[1],
+            local.vars[2],
+            local.new_e,
+            local.vars[4],
+            local.vars[5],
+            local.vars[6],
+        ];
+        let mut next_w = local.w[1..].to_vec();
+        next_w.push(local.w_16);
+        let shifted = next_vars
+            .into_iter()
+            .zip(next.vars)
+            .chain(next_w.into_iter().zip(next.w));
+        for (current, following) in shifted {
+            for limb in 0..4 {
+                builder
+                    .when_transition()
+                    .when(not_final_step.clone())
+                    .assert_eq(following[limb], current[limb]);
+            }
+        }
+    }
+}
+
+/// Expressions that are zero when `value` is the sum of the words `terms` modulo 2^32,
+/// given the carry out of each byte, least significant byte first.
+fn sum<AB: AirBuilder>(
+    terms: &[[AB::Expr; 4]],
+    value: Word<AB::Var>,
+    carry: [AB::Var; 4],
+) -> [AB::Expr; 4] {
+    let base = AB::Expr::from_canonical_u32(1 << 8);
+    core::array::from_fn(|k| {
+        let limb = 3 - k;
+        let mut total: AB::Expr = terms.iter().map(|term| term[limb].clone()).sum();
+        if k > 0 {
+            total = total + carry[k - 1];
+        }
+        total - value[limb] - carry[k] * base.clone()
+    })
+}
+
+fn word<AB: AirBuilder>(word: Word<AB::Var>) -> [AB::Expr; 4] {
+    word.0.map(Into::into)
+}
+
+/// The bytes of a 32-bit value given by its bits, most significant byte first like `Word`.
+fn bytes<E: AbstractField>(bits: &[E; 32]) -> [E; 4] {
+    core::array::from_fn(|limb| {
+        let low = 8 * (3 - limb);
+        (low..low + 8)
+            .rev()
+            .fold(E::zero(), |acc, z| acc * E::two() + bits[z].clone())
+    })
+}
+
+fn rotr<E: Clone>(bits: &[E; 32], n: usize) -> [E; 32] {
+    core::array::from_fn(|z| bits[(z + n) % 32].clone())
+}
+
+fn shr<E: AbstractField>(bits: &[E; 32], n: usize) -> [E; 32] {
+    core::array::from_fn(|z| {
+        if z + n < 32 {
+            bits[z + n].clone()
+        } else {
+            E::zero()
+        }
+    })
+}
+
+fn xor3_bits<E: AbstractField>(x: [E; 32], y: [E; 32], z: [E; 32]) -> [E; 32] {
+    core::array::from_fn(|i| xor(xor(x[i].clone(), y[i].clone()), z[i].clone()))
+}
+
+/// XOR of two bits.
+fn xor<E: AbstractField>(a: E, b: E) -> E {
+    a.clone() + b.clone() - a * b * E::two()
+}
+
+fn reduce<AB: AirBuilder>(base: &[AB::Expr], input: Word<AB::Var>) -> AB::Expr {
+    input
+        .into_iter()
+        .enumerate()
+        .map(|(i, x)| base[i].clone() * x)
+        .sum()
+}